@@ -0,0 +1,133 @@
+use num_traits::Float;
+
+use crate::mat3::Mat3;
+use crate::mat4::Mat4;
+use crate::quat::Quat;
+use crate::vec2::TVec2;
+use crate::vec3::TVec3;
+use crate::vec4::TVec4;
+
+/// Component wise comparison with an absolute tolerance.
+/// Any NaN component makes the comparison fail.
+pub trait ApproxEq<T> {
+    fn approx_eq(&self, other: &Self, eps: T) -> bool;
+}
+
+fn components_approx_eq<T: Float>(a: &[T], b: &[T], eps: T) -> bool {
+    a.len() == b.len() && a.iter().zip(b.iter()).all(|(x, y)| (*x - *y).abs() <= eps)
+}
+
+impl<T: Float> ApproxEq<T> for T {
+    fn approx_eq(&self, other: &Self, eps: T) -> bool {
+        (*self - *other).abs() <= eps
+    }
+}
+
+impl<T: Float + std::fmt::Debug> ApproxEq<T> for TVec2<T> {
+    fn approx_eq(&self, other: &Self, eps: T) -> bool {
+        components_approx_eq(self.as_slice(), other.as_slice(), eps)
+    }
+}
+
+impl<T: Float + std::fmt::Debug> ApproxEq<T> for TVec3<T> {
+    fn approx_eq(&self, other: &Self, eps: T) -> bool {
+        components_approx_eq(self.as_slice(), other.as_slice(), eps)
+    }
+}
+
+impl<T: Float + std::fmt::Debug> ApproxEq<T> for TVec4<T> {
+    fn approx_eq(&self, other: &Self, eps: T) -> bool {
+        components_approx_eq(self.as_slice(), other.as_slice(), eps)
+    }
+}
+
+impl<T: Float + std::fmt::Debug> ApproxEq<T> for Mat3<T> {
+    fn approx_eq(&self, other: &Self, eps: T) -> bool {
+        components_approx_eq(self.as_slice(), other.as_slice(), eps)
+    }
+}
+
+impl<T: Float + std::fmt::Debug> ApproxEq<T> for Mat4<T> {
+    fn approx_eq(&self, other: &Self, eps: T) -> bool {
+        components_approx_eq(self.as_slice(), other.as_slice(), eps)
+    }
+}
+
+impl<T: Float> ApproxEq<T> for Quat<T> {
+    fn approx_eq(&self, other: &Self, eps: T) -> bool {
+        components_approx_eq(
+            &[self.w, self.x, self.y, self.z],
+            &[other.w, other.x, other.y, other.z],
+            eps,
+        )
+    }
+}
+
+pub fn approx_eq<T, A: ApproxEq<T>>(a: &A, b: &A, eps: T) -> bool {
+    a.approx_eq(b, eps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_approx_eq_within_eps() {
+        assert!(approx_eq(&1.0f32, &1.0005f32, 1.0E-3));
+        assert!(approx_eq(
+            &TVec2::new(1f32, 2f32),
+            &TVec2::new(1.0001f32, 1.9999f32),
+            1.0E-3
+        ));
+        assert!(approx_eq(
+            &TVec3::new(1f32, 2f32, 3f32),
+            &TVec3::new(1f32, 2.0001f32, 3f32),
+            1.0E-3
+        ));
+        assert!(approx_eq(
+            &TVec4::new(1f64, 2f64, 3f64, 4f64),
+            &TVec4::new(1f64, 2f64, 3f64, 4.0000001f64),
+            1.0E-6
+        ));
+        assert!(approx_eq(
+            &crate::mat4::consts::identity::<f32>(),
+            &(crate::mat4::consts::identity::<f32>() * 1.00001f32),
+            1.0E-4
+        ));
+        assert!(approx_eq(
+            &crate::quat::consts::identity::<f32>(),
+            &crate::quat::consts::identity::<f32>(),
+            0f32
+        ));
+    }
+
+    #[test]
+    fn test_approx_eq_beyond_eps() {
+        assert!(!approx_eq(&1.0f32, &1.1f32, 1.0E-3));
+        assert!(!approx_eq(
+            &TVec3::new(1f32, 2f32, 3f32),
+            &TVec3::new(1f32, 2f32, 3.01f32),
+            1.0E-3
+        ));
+        assert!(!approx_eq(
+            &TVec4::new(1f32, 2f32, 3f32, 4f32),
+            &TVec4::new(1.5f32, 2f32, 3f32, 4f32),
+            1.0E-3
+        ));
+    }
+
+    #[test]
+    fn test_approx_eq_nan() {
+        assert!(!approx_eq(&f32::NAN, &f32::NAN, 1f32));
+        assert!(!approx_eq(
+            &TVec3::new(f32::NAN, 0f32, 0f32),
+            &TVec3::new(0f32, 0f32, 0f32),
+            1f32
+        ));
+        assert!(!approx_eq(
+            &TVec2::new(0f32, 0f32),
+            &TVec2::new(0f32, 0f32),
+            f32::NAN
+        ));
+    }
+}
@@ -1,6 +1,6 @@
 use super::colors::RGBAColor;
 use super::utility::saturate;
-use super::color_palette::basic;
+use super::color_palette::{basic, turbo_srgb};
 
 
 pub fn create_linear_colormap(start: RGBAColor, end: RGBAColor, num: u32) -> Vec<RGBAColor> {
@@ -51,6 +51,38 @@ impl ColorMap {
         }
     }
 
+    /// Palette of `num` colors, the control stops (position in [0, 1], color) are joined
+    /// by linear ramps. Entries before the first or after the last stop get the color of
+    /// that stop, no stops give an empty palette. The positions should be finite.
+    pub fn from_stops(stops: &[(f32, RGBAColor)], num: u32) -> Vec<RGBAColor> {
+        let index = |x: f32| (saturate(x) * (num.max(1) - 1) as f32).round() as usize;
+
+        let mut stops = stops.to_vec();
+        stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let (first, last) = match (stops.first(), stops.last()) {
+            (Some(&first), Some(&last)) => (first, last),
+            _ => return Vec::new(),
+        };
+
+        let mut colors = vec![first.1; index(first.0)];
+        stops.windows(2).for_each(|segment| {
+            let (x0, c0) = segment[0];
+            let (x1, c1) = segment[1];
+            //
+            // the segment's end is the start of the next one
+            let entries = index(x1) - index(x0);
+            colors.extend(
+                ColorMap::create_linear(c0, c1, entries as u32 + 1)
+                    .into_iter()
+                    .take(entries),
+            );
+        });
+        colors.resize(num as usize, last.1);
+
+        colors
+    }
+
     /// Creates a palette : Dark Blue -> Cyan -> Green
     pub fn pf1() -> Vec<RGBAColor> {
         std::iter::once(RGBAColor::from(basic::BLACK))
@@ -196,3 +228,120 @@ impl ColorMap {
         palette
     }
 }
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnknownPaletteError(pub String);
+
+impl std::fmt::Display for UnknownPaletteError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Unknown palette name: {}", self.0)
+    }
+}
+
+impl std::error::Error for UnknownPaletteError {}
+
+/// Names accepted by `palette_by_name`.
+pub const PALETTE_NAMES: [&str; 10] = [
+    "pf1",
+    "pf2",
+    "pf3",
+    "pf4",
+    "pf5",
+    "pf6",
+    "pf7",
+    "pf8",
+    "turbo",
+    "viridis-like",
+];
+
+/// Returns a 256 color palette, looked up by name (see `PALETTE_NAMES`).
+pub fn palette_by_name(name: &str) -> Result<Vec<RGBAColor>, UnknownPaletteError> {
+    match name {
+        "pf1" => Ok(ColorMap::pf1()),
+        "pf2" => Ok(ColorMap::pf2()),
+        "pf3" => Ok(ColorMap::pf3()),
+        "pf4" => Ok(ColorMap::pf4()),
+        "pf5" => Ok(ColorMap::pf5()),
+        "pf6" => Ok(ColorMap::pf6()),
+        "pf7" => Ok(ColorMap::pf7()),
+        "pf8" => Ok(ColorMap::pf8()),
+        "turbo" => Ok(turbo_srgb::BYTES
+            .iter()
+            .map(|&rgb| RGBAColor::from(rgb))
+            .collect()),
+        "viridis-like" => {
+            //
+            // purple -> teal -> yellow
+            let purple = RGBAColor::new(68, 1, 84);
+            let teal = RGBAColor::new(33, 145, 140);
+            let yellow = RGBAColor::new(253, 231, 37);
+
+            Ok(ColorMap::create_linear(purple, teal, 128)
+                .into_iter()
+                .chain(ColorMap::create_linear(teal, yellow, 128))
+                .collect())
+        }
+        _ => Err(UnknownPaletteError(name.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_palette_by_name() {
+        PALETTE_NAMES.iter().for_each(|name| {
+            let palette = palette_by_name(name).unwrap();
+            assert_eq!(palette.len(), 256, "palette {}", name);
+        });
+
+        assert_eq!(
+            palette_by_name("no-such-palette"),
+            Err(UnknownPaletteError("no-such-palette".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_palette_from_stops() {
+        let black = RGBAColor::new(0, 0, 0);
+        let white = RGBAColor::new(255, 255, 255);
+
+        //
+        // one step per entry, within the rounding of the ramp
+        let gray = ColorMap::from_stops(&[(1f32, white), (0f32, black)], 256);
+        assert_eq!(gray.len(), 256);
+        assert_eq!((gray[0], gray[255]), (black, white));
+        gray.iter().enumerate().for_each(|(i, c)| {
+            assert!(c.r == c.g && c.g == c.b);
+            assert!((c.r as i32 - i as i32).abs() <= 1, "{} {:?}", i, c);
+        });
+
+        //
+        // black -> red in the first half, red -> blue in the second
+        let red = RGBAColor::new(255, 0, 0);
+        let blue = RGBAColor::new(0, 0, 255);
+        let ramp = ColorMap::from_stops(&[(0f32, black), (0.5f32, red), (1f32, blue)], 256);
+        assert_eq!(ramp.len(), 256);
+        assert_eq!((ramp[0], ramp[128], ramp[255]), (black, red, blue));
+
+        ramp.windows(2).enumerate().for_each(|(i, pair)| {
+            let (a, b) = (pair[0], pair[1]);
+            assert_eq!((a.g, b.g), (0, 0));
+            if i < 128 {
+                assert!(b.r > a.r && b.b == 0);
+            } else {
+                assert!(b.r < a.r && b.b > a.b);
+            }
+        });
+        assert!((ramp[64].r as i32 - 128).abs() <= 2);
+        assert!((ramp[192].r as i32 - 128).abs() <= 2 && (ramp[192].b as i32 - 128).abs() <= 2);
+
+        //
+        // clamped before the first and after the last stop
+        let band = ColorMap::from_stops(&[(0.25f32, red), (0.75f32, blue)], 5);
+        assert_eq!((band[0], band[1], band[3], band[4]), (red, red, blue, blue));
+        assert!((band[2].r as i32 - 127).abs() <= 1 && (band[2].b as i32 - 127).abs() <= 1);
+        assert!(ColorMap::from_stops(&[], 16).is_empty());
+    }
+}
@@ -1,9 +1,11 @@
+pub mod approx;
 pub mod colors;
 pub mod rectangle;
 
 #[macro_use]
 pub mod utility;
 pub mod mat2x3;
+pub mod mat3;
 pub mod mat4;
 pub mod quat;
 pub mod ray;
@@ -17,4 +19,8 @@ pub mod color_palette;
 pub mod colormap;
 pub mod minmax;
 pub mod polynomial;
+pub mod prelude;
 pub mod projection;
+
+#[cfg(test)]
+mod test_rng;
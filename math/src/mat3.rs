@@ -0,0 +1,298 @@
+use super::mat4::Mat4;
+use super::vec3::TVec3;
+use num::Float;
+use num_traits::Num;
+
+/// A 3x3 matrix, stored in row major ordering.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[repr(C)]
+pub struct Mat3<T> {
+    pub a00: T,
+    pub a01: T,
+    pub a02: T,
+
+    pub a10: T,
+    pub a11: T,
+    pub a12: T,
+
+    pub a20: T,
+    pub a21: T,
+    pub a22: T,
+}
+
+impl<T> Mat3<T>
+where
+    T: Num + Copy + Clone + std::fmt::Debug,
+{
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(a00: T, a01: T, a02: T, a10: T, a11: T, a12: T, a20: T, a21: T, a22: T) -> Self {
+        Self {
+            a00,
+            a01,
+            a02,
+            a10,
+            a11,
+            a12,
+            a20,
+            a21,
+            a22,
+        }
+    }
+
+    /// The upper left 3x3 block of a 4x4 matrix (rotation and scaling, no translation).
+    pub fn from_mat4_upper_left(m: &Mat4<T>) -> Self {
+        Self {
+            a00: m.a00,
+            a01: m.a01,
+            a02: m.a02,
+
+            a10: m.a10,
+            a11: m.a11,
+            a12: m.a12,
+
+            a20: m.a20,
+            a21: m.a21,
+            a22: m.a22,
+        }
+    }
+
+    pub fn as_slice(&self) -> &[T] {
+        unsafe { std::slice::from_raw_parts(&self.a00 as *const _, 9) }
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        unsafe { std::slice::from_raw_parts_mut(&mut self.a00 as *mut _, 9) }
+    }
+
+    pub fn as_ptr(&self) -> *const T {
+        &self.a00 as *const _
+    }
+
+    pub fn as_mut_ptr(&mut self) -> *mut T {
+        &mut self.a00 as *mut _
+    }
+
+    pub fn transpose(&self) -> Self {
+        Self {
+            a00: self.a00,
+            a01: self.a10,
+            a02: self.a20,
+
+            a10: self.a01,
+            a11: self.a11,
+            a12: self.a21,
+
+            a20: self.a02,
+            a21: self.a12,
+            a22: self.a22,
+        }
+    }
+
+    pub fn det(&self) -> T {
+        self.a00 * (self.a11 * self.a22 - self.a12 * self.a21)
+            - self.a01 * (self.a10 * self.a22 - self.a12 * self.a20)
+            + self.a02 * (self.a10 * self.a21 - self.a11 * self.a20)
+    }
+
+    /// Transpose of the cofactor matrix.
+    pub fn adjoint(&self) -> Self {
+        Self {
+            a00: self.a11 * self.a22 - self.a12 * self.a21,
+            a01: self.a02 * self.a21 - self.a01 * self.a22,
+            a02: self.a01 * self.a12 - self.a02 * self.a11,
+
+            a10: self.a12 * self.a20 - self.a10 * self.a22,
+            a11: self.a00 * self.a22 - self.a02 * self.a20,
+            a12: self.a02 * self.a10 - self.a00 * self.a12,
+
+            a20: self.a10 * self.a21 - self.a11 * self.a20,
+            a21: self.a01 * self.a20 - self.a00 * self.a21,
+            a22: self.a00 * self.a11 - self.a01 * self.a10,
+        }
+    }
+}
+
+impl<T> Mat3<T>
+where
+    T: Float + std::fmt::Debug,
+{
+    pub fn inverse(&self) -> Self {
+        let d = self.det();
+        assert!(!d.is_zero(), "Matrix is not invertible");
+
+        let inv_det = d.recip();
+        let mut m = self.adjoint();
+        m.as_mut_slice().iter_mut().for_each(|e| *e = *e * inv_det);
+        m
+    }
+
+    /// Matrix that transforms normals for the object to world matrix `obj2world`
+    /// (inverse transpose of its upper left 3x3 block).
+    pub fn normal_matrix(obj2world: &Mat4<T>) -> Self {
+        Self::from_mat4_upper_left(obj2world).inverse().transpose()
+    }
+}
+
+pub mod consts {
+    use super::Mat3;
+    use num_traits::Num;
+
+    pub fn null<T>() -> Mat3<T>
+    where
+        T: Num + Copy + Clone + std::fmt::Debug,
+    {
+        Mat3 {
+            a00: T::zero(),
+            a01: T::zero(),
+            a02: T::zero(),
+
+            a10: T::zero(),
+            a11: T::zero(),
+            a12: T::zero(),
+
+            a20: T::zero(),
+            a21: T::zero(),
+            a22: T::zero(),
+        }
+    }
+
+    pub fn identity<T>() -> Mat3<T>
+    where
+        T: Num + Copy + Clone + std::fmt::Debug,
+    {
+        Mat3 {
+            a00: T::one(),
+            a11: T::one(),
+            a22: T::one(),
+            ..null()
+        }
+    }
+}
+
+impl<T> std::ops::Index<usize> for Mat3<T>
+where
+    T: Num + Copy + Clone + std::fmt::Debug,
+{
+    type Output = TVec3<T>;
+
+    fn index(&self, idx: usize) -> &Self::Output {
+        match idx {
+            0 => unsafe { &*(&self.a00 as *const _ as *const TVec3<T>) },
+            1 => unsafe { &*(&self.a10 as *const _ as *const TVec3<T>) },
+            2 => unsafe { &*(&self.a20 as *const _ as *const TVec3<T>) },
+            _ => panic!("Row index out of bounds!"),
+        }
+    }
+}
+
+impl<T> std::ops::IndexMut<usize> for Mat3<T>
+where
+    T: Num + Copy + Clone + std::fmt::Debug,
+{
+    fn index_mut(&mut self, idx: usize) -> &mut Self::Output {
+        match idx {
+            0 => unsafe { &mut *(&mut self.a00 as *mut _ as *mut TVec3<T>) },
+            1 => unsafe { &mut *(&mut self.a10 as *mut _ as *mut TVec3<T>) },
+            2 => unsafe { &mut *(&mut self.a20 as *mut _ as *mut TVec3<T>) },
+            _ => panic!("Row index out of bounds!"),
+        }
+    }
+}
+
+impl<T> std::ops::Mul for Mat3<T>
+where
+    T: Num + Copy + Clone + std::fmt::Debug + std::ops::AddAssign,
+{
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        let mut res = consts::null();
+
+        (0..3).for_each(|row| {
+            (0..3).for_each(|col| {
+                (0..3).for_each(|k| {
+                    res[row][col] += self[row][k] * rhs[k][col];
+                });
+            });
+        });
+
+        res
+    }
+}
+
+impl<T> std::ops::Mul<TVec3<T>> for Mat3<T>
+where
+    T: Num + Copy + Clone + std::fmt::Debug,
+{
+    type Output = TVec3<T>;
+
+    fn mul(self, rhs: TVec3<T>) -> Self::Output {
+        TVec3 {
+            x: self.a00 * rhs.x + self.a01 * rhs.y + self.a02 * rhs.z,
+            y: self.a10 * rhs.x + self.a11 * rhs.y + self.a12 * rhs.z,
+            z: self.a20 * rhs.x + self.a21 * rhs.y + self.a22 * rhs.z,
+        }
+    }
+}
+
+pub type Mat3F32 = Mat3<f32>;
+pub type Mat3F64 = Mat3<f64>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::approx::ApproxEq;
+    use crate::vec3::normalize;
+    use crate::vec4::TVec4;
+
+    fn scaled_rotation() -> Mat4<f32> {
+        //
+        // rotation about z by 30 degrees, followed by a non uniform scale and a translation
+        let (s, c) = 30f32.to_radians().sin_cos();
+        let rotation = Mat4::from([
+            c, -s, 0f32, 0f32, //
+            s, c, 0f32, 0f32, //
+            0f32, 0f32, 1f32, 0f32, //
+            0f32, 0f32, 0f32, 1f32,
+        ]);
+
+        Mat4::translate(TVec3::new(1f32, -2f32, 3f32))
+            * Mat4::non_uniform_scale(TVec3::new(3f32, 0.5f32, 2f32))
+            * rotation
+    }
+
+    #[test]
+    fn test_inverse() {
+        let m = Mat3::from_mat4_upper_left(&scaled_rotation());
+        let p = m * m.inverse();
+
+        assert!(p.approx_eq(&consts::identity(), 1.0e-5f32));
+        assert_eq!(m.transpose().transpose(), m);
+    }
+
+    #[test]
+    fn test_normal_matrix_matches_mat4_adjoint_transpose() {
+        let obj2world = scaled_rotation();
+        let normal2world = crate::mat4::adjoint(&obj2world).transpose();
+        let normal_matrix = Mat3::normal_matrix(&obj2world);
+
+        [
+            TVec3::new(1f32, 0f32, 0f32),
+            TVec3::new(1f32, 1f32, 0f32),
+            TVec3::new(-0.3f32, 0.2f32, 0.9f32),
+        ]
+        .iter()
+        .for_each(|&n| {
+            let expected = normalize((normal2world * TVec4::from_vec3(&n, 0f32)).xyz());
+            let computed = normalize(normal_matrix * n);
+            assert!(computed.approx_eq(&expected, 1.0e-5f32));
+        });
+
+        //
+        // the transformed normal stays perpendicular to transformed tangents
+        let n = TVec3::new(0f32, 0f32, 1f32);
+        let tangent = TVec3::new(1f32, 1f32, 0f32);
+        let t_world = crate::mat4::transform_vector(&obj2world, tangent);
+        assert!(crate::vec3::dot(normal_matrix * n, t_world).abs() < 1.0e-5f32);
+    }
+}
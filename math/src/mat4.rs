@@ -436,7 +436,7 @@ where
             x: self.a00 * rhs.x + self.a01 * rhs.y + self.a02 * rhs.z + self.a03 * rhs.w,
             y: self.a10 * rhs.x + self.a11 * rhs.y + self.a12 * rhs.z + self.a13 * rhs.w,
             z: self.a20 * rhs.x + self.a21 * rhs.y + self.a22 * rhs.z + self.a23 * rhs.w,
-            w: rhs.w,
+            w: self.a30 * rhs.x + self.a31 * rhs.y + self.a32 * rhs.z + self.a33 * rhs.w,
         }
     }
 }
@@ -452,6 +452,26 @@ where
     }
 }
 
+/// Transforms the point `p` (w = 1) by a projective transform, divides by the resulting w.
+/// Points that end up with w = 0 (at infinity) are returned without the divide.
+pub fn project_point<T>(m: &Mat4<T>, p: TVec3<T>) -> TVec3<T>
+where
+    T: Num + Copy + Clone + std::ops::Mul<Output = T> + std::ops::Add<Output = T>,
+{
+    let q = transform_point(m, p);
+    let w = m.a30 * p.x + m.a31 * p.y + m.a32 * p.z + m.a33;
+
+    if w.is_zero() {
+        q
+    } else {
+        TVec3 {
+            x: q.x / w,
+            y: q.y / w,
+            z: q.z / w,
+        }
+    }
+}
+
 pub fn transform_vector<T>(m: &Mat4<T>, p: TVec3<T>) -> TVec3<T>
 where
     T: Num + Copy + Clone + std::ops::Mul<Output = T> + std::ops::Add<Output = T>,
@@ -543,13 +563,13 @@ pub fn det<T: Float>(m: &Mat4<T>) -> T {
     let l1 = m.a22 * m.a33 - m.a23 * m.a32;
 
     let k2 = m.a00 * m.a12 - m.a02 * m.a10;
-    let l2 = m.a21 * m.a33 - m.a12 * m.a31;
+    let l2 = m.a21 * m.a33 - m.a23 * m.a31;
 
     let k3 = m.a00 * m.a13 - m.a03 * m.a10;
     let l3 = m.a21 * m.a32 - m.a22 * m.a31;
 
     let k4 = m.a01 * m.a12 - m.a02 * m.a11;
-    let l4 = m.a20 * m.a33 - m.a32 * m.a30;
+    let l4 = m.a20 * m.a33 - m.a23 * m.a30;
 
     let k5 = m.a01 * m.a13 - m.a03 * m.a11;
     let l5 = m.a20 * m.a32 - m.a22 * m.a30;
@@ -565,6 +585,9 @@ pub fn is_invertible<T: Float>(m: &Mat4<T>) -> bool {
 }
 
 pub fn adjoint<T: Float>(m: &Mat4<T>) -> Mat4<T> {
+    //
+    // 2x2 minors of the bottom two rows (m1 - m3, m7, m8, m11) and of the top two rows
+    // (m4 - m6, m9, m10, m12), every cofactor is expanded along the other two rows
     let m1 = m.a22 * m.a33 - m.a23 * m.a32;
     let m2 = m.a21 * m.a33 - m.a23 * m.a31;
     let m3 = m.a21 * m.a32 - m.a22 * m.a31;
@@ -573,14 +596,10 @@ pub fn adjoint<T: Float>(m: &Mat4<T>) -> Mat4<T> {
     let m6 = m.a01 * m.a12 - m.a02 * m.a11;
     let m7 = m.a20 * m.a33 - m.a23 * m.a30;
     let m8 = m.a20 * m.a32 - m.a22 * m.a30;
-    let m9 = m.a12 * m.a33 - m.a13 * m.a32;
-    let m10 = m.a10 * m.a33 - m.a13 * m.a30;
-    let m11 = m.a10 * m.a32 - m.a12 * m.a30;
-    let m12 = m.a00 * m.a13 - m.a03 * m.a10;
-    let m13 = m.a00 * m.a12 - m.a02 * m.a10;
-    let m14 = m.a20 * m.a31 - m.a21 * m.a30;
-    let m15 = m.a00 * m.a11 - m.a01 * m.a10;
-    let m16 = m.a20 * m.a31 - m.a21 * m.a30;
+    let m9 = m.a00 * m.a13 - m.a03 * m.a10;
+    let m10 = m.a00 * m.a12 - m.a02 * m.a10;
+    let m11 = m.a20 * m.a31 - m.a21 * m.a30;
+    let m12 = m.a00 * m.a11 - m.a01 * m.a10;
 
     Mat4 {
         a00: m.a11 * m1 - m.a12 * m2 + m.a13 * m3,
@@ -590,18 +609,18 @@ pub fn adjoint<T: Float>(m: &Mat4<T>) -> Mat4<T> {
 
         a10: -m.a10 * m1 + m.a12 * m7 - m.a13 * m8,
         a11: m.a00 * m1 - m.a02 * m7 + m.a03 * m8,
-        a12: -m.a00 * m9 + m.a02 * m10 - m.a03 * m11,
-        a13: m.a20 * m4 - m.a22 * m12 + m.a23 * m13,
+        a12: -m.a30 * m4 + m.a32 * m9 - m.a33 * m10,
+        a13: m.a20 * m4 - m.a22 * m9 + m.a23 * m10,
 
-        a20: m.a10 * m2 - m.a11 * m7 + m.a13 * m16,
-        a21: -m.a00 * m2 + m.a01 * m7 - m.a03 * m16,
-        a22: m.a30 * m5 - m.a31 * m12 + m.a33 * m15,
-        a23: -m.a20 * m5 + m.a21 * m12 - m.a23 * m15,
+        a20: m.a10 * m2 - m.a11 * m7 + m.a13 * m11,
+        a21: -m.a00 * m2 + m.a01 * m7 - m.a03 * m11,
+        a22: m.a30 * m5 - m.a31 * m9 + m.a33 * m12,
+        a23: -m.a20 * m5 + m.a21 * m9 - m.a23 * m12,
 
-        a30: -m.a10 * m3 + m.a11 * m8 - m.a12 * m14,
-        a31: m.a00 * m3 - m.a01 * m8 + m.a02 * m14,
-        a32: -m.a30 * m6 + m.a31 * m13 - m.a32 * m15,
-        a33: m.a20 * m6 - m.a21 * m13 + m.a22 * m15,
+        a30: -m.a10 * m3 + m.a11 * m8 - m.a12 * m11,
+        a31: m.a00 * m3 - m.a01 * m8 + m.a02 * m11,
+        a32: -m.a30 * m6 + m.a31 * m10 - m.a32 * m12,
+        a33: m.a20 * m6 - m.a21 * m10 + m.a22 * m12,
     }
 }
 
@@ -665,4 +684,94 @@ mod tests {
             Mat4::from([0, 4, 8, 12, 1, 5, 9, 13, 2, 6, 10, 14, 3, 7, 11, 15])
         );
     }
+
+    #[test]
+    fn test_matrix_times_inverse_is_identity() {
+        use crate::approx::approx_eq;
+        use std::iter::FromIterator;
+
+        //
+        // pseudo random matrices with entries in [-1, 1)
+        let mut rng = crate::test_rng::TestRng::new();
+
+        let identity = super::consts::identity::<f64>();
+        let mut tested = 0;
+        while tested < 100 {
+            let m = Mat4::from_iter((0..16).map(|_| rng.next_f64()));
+            if det(&m).abs() < 0.05f64 {
+                continue;
+            }
+
+            let inv = invert(&m);
+            assert!(approx_eq(&(m * inv), &identity, 1.0e-9), "{:?}", m);
+            assert!(approx_eq(&(inv * m), &identity, 1.0e-9), "{:?}", m);
+            tested += 1;
+        }
+
+        //
+        // the determinant of an upper triangular matrix is the product of the diagonal
+        let upper = Mat4::from([
+            2f64, 1f64, 3f64, 4f64, 0f64, 3f64, 5f64, 6f64, 0f64, 0f64, 4f64, 7f64, 0f64, 0f64,
+            0f64, 5f64,
+        ]);
+        assert_eq!(det(&upper), 120f64);
+        assert_eq!(det(&upper.transpose()), 120f64);
+    }
+
+    #[test]
+    fn test_projective_transforms_compute_w() {
+        //
+        // OpenGL style perspective projection, 90 degrees vertical fov, aspect 1,
+        // near 1, far 10, the camera looks down -z
+        let (n, f) = (1f32, 10f32);
+        let projection = Mat4::from([
+            [1f32, 0f32, 0f32, 0f32],
+            [0f32, 1f32, 0f32, 0f32],
+            [0f32, 0f32, (f + n) / (n - f), 2f32 * f * n / (n - f)],
+            [0f32, 0f32, -1f32, 0f32],
+        ]);
+
+        let clip = projection * TVec4::new(2f32, 1f32, -4f32, 1f32);
+        assert_eq!(clip.w, 4f32);
+        assert_eq!(clip.x, 2f32);
+        assert_eq!(clip.y, 1f32);
+
+        //
+        // the near and far planes end up at -1 and 1 after the divide
+        let ndc = project_point(&projection, TVec3::new(2f32, 1f32, -4f32));
+        assert_eq!(ndc.x, 0.5f32);
+        assert_eq!(ndc.y, 0.25f32);
+        assert!((project_point(&projection, TVec3::new(0f32, 0f32, -n)).z + 1f32).abs() < 1.0e-6);
+        assert!((project_point(&projection, TVec3::new(0f32, 0f32, -f)).z - 1f32).abs() < 1.0e-6);
+
+        //
+        // transform_point is affine, it ignores the bottom row
+        assert_eq!(
+            transform_point(&projection, TVec3::new(2f32, 1f32, -4f32)),
+            TVec3::new(clip.x, clip.y, clip.z)
+        );
+
+        //
+        // affine transforms keep w
+        let translation = Mat4::from([
+            1f32, 0f32, 0f32, 3f32, 0f32, 1f32, 0f32, 4f32, 0f32, 0f32, 1f32, 5f32, 0f32, 0f32,
+            0f32, 1f32,
+        ]);
+        assert_eq!(
+            translation * TVec4::new(1f32, 1f32, 1f32, 1f32),
+            TVec4::new(4f32, 5f32, 6f32, 1f32)
+        );
+        assert_eq!(
+            translation * TVec4::new(1f32, 1f32, 1f32, 0f32),
+            TVec4::new(1f32, 1f32, 1f32, 0f32)
+        );
+        assert_eq!(
+            transform_point(&translation, TVec3::new(1f32, 1f32, 1f32)),
+            TVec3::new(4f32, 5f32, 6f32)
+        );
+        assert_eq!(
+            project_point(&translation, TVec3::new(1f32, 1f32, 1f32)),
+            TVec3::new(4f32, 5f32, 6f32)
+        );
+    }
 }
@@ -1,3 +1,8 @@
+/// Minimum/maximum for the primitive numeric types, used by the component wise vector
+/// functions (`vec3::min`, `vec3::max`, `vec3::clamp`, ...).
+///
+/// For floating point types a NaN argument is ignored, the other argument is returned
+/// (same as `f32::min`/`f32::max`). So clamping a NaN with `min(max(x, lo), hi)` gives `lo`.
 pub trait MinMax {
     type Output;
     const MIN: Self::Output;
@@ -30,3 +35,53 @@ macro_rules! impl_minmax {
 }
 
 impl_minmax!(i8, u8, i16, u16, i32, u32, i64, u64, f32, f64, usize, isize);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vec3::{self, TVec3};
+
+    fn check_vec3_ops<T>(lo: T, mid: T, hi: T)
+    where
+        T: Copy + num_traits::Num + MinMax<Output = T> + std::fmt::Debug + PartialEq,
+    {
+        let a = TVec3::new(lo, mid, hi);
+        let b = TVec3::new(hi, mid, lo);
+
+        assert_eq!(vec3::min(a, b), TVec3::new(lo, mid, lo));
+        assert_eq!(vec3::max_sv(a, b), TVec3::new(hi, mid, hi));
+        assert_eq!(vec3::max(a, mid), TVec3::new(mid, mid, hi));
+        assert_eq!(
+            vec3::clamp(a, TVec3::new(mid, mid, mid), TVec3::new(mid, mid, mid)),
+            TVec3::new(mid, mid, mid)
+        );
+        assert_eq!(T::min(T::MIN, T::MAX), T::MIN);
+        assert_eq!(T::max(T::MIN, T::MAX), T::MAX);
+    }
+
+    #[test]
+    fn test_vec3_min_max_clamp_all_types() {
+        check_vec3_ops(-1f32, 0f32, 1f32);
+        check_vec3_ops(-1f64, 0f64, 1f64);
+        check_vec3_ops(-1i32, 0i32, 1i32);
+        check_vec3_ops(0u32, 1u32, 2u32);
+        check_vec3_ops(0u8, 1u8, u8::MAX);
+        check_vec3_ops(i32::MIN, 0i32, i32::MAX);
+    }
+
+    #[test]
+    fn test_nan_is_ignored() {
+        assert_eq!(<f32 as MinMax>::min(f32::NAN, 1f32), 1f32);
+        assert_eq!(<f32 as MinMax>::max(1f32, f32::NAN), 1f32);
+        assert_eq!(<f64 as MinMax>::min(2f64, f64::NAN), 2f64);
+        assert_eq!(<f64 as MinMax>::max(f64::NAN, 2f64), 2f64);
+        assert!(<f32 as MinMax>::min(f32::NAN, f32::NAN).is_nan());
+
+        let clamped = vec3::clamp(
+            TVec3::new(f64::NAN, 0.5f64, 2f64),
+            TVec3::new(0f64, 0f64, 0f64),
+            TVec3::new(1f64, 1f64, 1f64),
+        );
+        assert_eq!(clamped, TVec3::new(0f64, 0.5f64, 1f64));
+    }
+}
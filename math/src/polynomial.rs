@@ -17,8 +17,8 @@ pub fn poly_quadratic<T: Float + num::FromPrimitive>(a: T, b: T, c: T, roots: &m
 
     let delta = b * b - T::from_i32(4).unwrap() * a * c;
     if delta.is_zero() {
-        roots[0] = -b / T::from_i32(2).unwrap() * a;
-        roots[1] = -b / T::from_i32(2).unwrap() * a;
+        roots[0] = -b / (T::from_i32(2).unwrap() * a);
+        roots[1] = -b / (T::from_i32(2).unwrap() * a);
         return 2;
     }
 
@@ -242,3 +242,22 @@ pub fn poly_roots_quartic<T: Float + num::FromPrimitive>(
         return 0;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quadratic_double_root() {
+        let mut roots = [0f32; 2];
+        assert_eq!(poly_quadratic(1f32, -2f32, 1f32, &mut roots), 2);
+        assert_eq!(roots, [1f32, 1f32]);
+
+        //
+        // a != 1, the root is -b / (2a)
+        assert_eq!(poly_quadratic(2f32, -4f32, 2f32, &mut roots), 2);
+        assert_eq!(roots, [1f32, 1f32]);
+        assert_eq!(poly_quadratic(4f32, 4f32, 1f32, &mut roots), 2);
+        assert_eq!(roots, [-0.5f32, -0.5f32]);
+    }
+}
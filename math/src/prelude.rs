@@ -0,0 +1,64 @@
+//! Common types and functions, for `use math::prelude::*`.
+//!
+//! The vector functions are the 3D ones (`dot`, `cross`, `normalize`, ...), the ones with
+//! the same name for the other types are used through their modules, which are
+//! re-exported too (`vec2::dot`, `quat::normalize`, `mat4::invert`, ...).
+
+pub use crate::mat2x3::Mat2X3;
+pub use crate::mat3::{Mat3, Mat3F32, Mat3F64};
+pub use crate::mat4::{Mat4, Mat4F32, Mat4I32};
+pub use crate::quat::Quat;
+pub use crate::ray::TRay;
+pub use crate::vec2::{TVec2, Vec2F32, Vec2I32, Vec2U32};
+pub use crate::vec3::TVec3;
+pub use crate::vec4::{TVec4, Vec4F32, Vec4I32, Vec4U32};
+
+pub use crate::{mat3, mat4, quat, ray, vec2, vec3, vec4};
+
+pub use crate::vec3::{
+    angle_between, are_orthogonal, are_parallel, clamp, cross, dot, hsum, is_near_zero,
+    is_unit_length, length, length_squared, luminance, max, max_component, min, mix, normalize,
+    orthonormal_basis, reflect_unit_vector, refract,
+};
+
+pub use crate::mat4::{
+    adjoint, det, is_invertible, project_point, transform_point, transform_vector,
+};
+
+pub use crate::quat::{conjugate, to_rotation_matrix};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //
+    // typical use, everything comes from the prelude
+    fn reflect_off_rotated_plane(
+        rotation: Quat<f32>,
+        offset: TVec3<f32>,
+        v: TVec3<f32>,
+    ) -> (TVec3<f32>, TVec3<f32>) {
+        let xf = Mat4::translate(offset) * to_rotation_matrix(quat::normalize(rotation));
+        let normal = normalize(transform_vector(&xf, vec3::consts::unit_y()));
+        let tangent = normalize(cross(normal, vec3::consts::unit_z()));
+
+        let reflected = v - normal * (2_f32 * dot(v, normal));
+        let back = transform_point(&mat4::invert(&xf), transform_point(&xf, offset));
+        assert!(length(back - offset) < 1.0e-4);
+
+        (reflected, tangent)
+    }
+
+    #[test]
+    fn test_prelude_is_enough_for_typical_use() {
+        let (reflected, tangent) = reflect_off_rotated_plane(
+            quat::consts::identity(),
+            TVec3::new(1_f32, 2_f32, 3_f32),
+            TVec3::new(1_f32, -1_f32, 0_f32),
+        );
+
+        assert!(length(reflected - TVec3::new(1_f32, 1_f32, 0_f32)) < 1.0e-5);
+        assert!((length(tangent) - 1_f32).abs() < 1.0e-5);
+        assert!(dot(tangent, vec3::consts::unit_y()).abs() < 1.0e-5);
+    }
+}
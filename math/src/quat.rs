@@ -169,7 +169,7 @@ where
         self.w += rhs.w;
         self.x += rhs.x;
         self.y += rhs.y;
-        self.z += rhs.w;
+        self.z += rhs.z;
     }
 }
 
@@ -197,7 +197,7 @@ where
         self.w -= rhs.w;
         self.x -= rhs.x;
         self.y -= rhs.y;
-        self.z -= rhs.w;
+        self.z -= rhs.z;
     }
 }
 
@@ -331,7 +331,7 @@ where
     if len_squared.is_zero() {
         self::consts::identity()
     } else {
-        let k = len_squared.recip();
+        let k = len_squared.sqrt().recip();
         Quat {
             w: k * q.w,
             x: k * q.x,
@@ -349,6 +349,8 @@ where
     if len_squared.is_zero() {
         self::consts::identity()
     } else {
+        //
+        // conjugate / |q|^2, the conjugate is the inverse of a unit quat only
         let k = len_squared.recip();
         Quat {
             w: k * q.w,
@@ -373,3 +375,64 @@ pub fn is_unit_length<T: Float>(q: Quat<T>) -> bool {
 pub fn is_zero<T: Float>(q: Quat<T>) -> bool {
     length_squared(q).is_zero()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //
+    // quats with components in [-1, 1), reproducible
+    fn random_quats(n: usize) -> Vec<Quat<f32>> {
+        let mut rng = crate::test_rng::TestRng::new();
+
+        (0..n)
+            .map(|_| Quat {
+                w: rng.next_f32(),
+                x: rng.next_f32(),
+                y: rng.next_f32(),
+                z: rng.next_f32(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_assign_ops_match_the_binary_ops() {
+        let quats = random_quats(64);
+
+        quats.windows(2).for_each(|pair| {
+            let (a, b) = (pair[0], pair[1]);
+
+            let mut sum = a;
+            sum += b;
+            assert_eq!(sum, a + b);
+
+            let mut difference = a;
+            difference -= b;
+            assert_eq!(difference, a - b);
+        });
+    }
+
+    #[test]
+    fn test_normalize_and_invert_non_unit_quats() {
+        use crate::approx::approx_eq;
+        let identity = self::consts::identity::<f32>();
+
+        random_quats(64)
+            .into_iter()
+            .filter(|&q| length(q) > 0.1f32)
+            .zip([0.25f32, 1.5f32, 4f32].iter().cycle())
+            .for_each(|(q, &k)| {
+                let q = Quat {
+                    w: k * q.w,
+                    x: k * q.x,
+                    y: k * q.y,
+                    z: k * q.z,
+                };
+                assert!((length(normalize(q)) - 1f32).abs() < 1.0e-5, "{:?}", q);
+
+                let inv = invert(q);
+                assert!(approx_eq(&(q * inv), &identity, 1.0e-5), "{:?}", q);
+                assert!(approx_eq(&(inv * q), &identity, 1.0e-5), "{:?}", q);
+            });
+    }
+}
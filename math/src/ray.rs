@@ -1,9 +1,22 @@
 use crate::mat4::Mat4;
+use crate::vec3;
 use crate::vec3::TVec3;
 use crate::vec4::TVec4;
 use num::Float;
 use num_traits::Num;
 
+/// Rays through the points one pixel to the right (x) and one pixel down (y) of the
+/// point a camera ray goes through. Where they cross a surface, relative to where the ray
+/// itself does, gives the footprint of the pixel on the surface.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[repr(C)]
+pub struct TRayDifferentials<T> {
+    pub rx_origin: TVec3<T>,
+    pub rx_direction: TVec3<T>,
+    pub ry_origin: TVec3<T>,
+    pub ry_direction: TVec3<T>,
+}
+
 /// Ray in R3.
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 #[repr(C)]
@@ -11,6 +24,8 @@ pub struct TRay<T> {
     pub origin: TVec3<T>,
     pub direction: TVec3<T>,
     pub time: T,
+    /// Only for rays that come from a camera, through specular bounces.
+    pub differentials: Option<TRayDifferentials<T>>,
 }
 
 impl<T> TRay<T>
@@ -22,6 +37,14 @@ where
             origin,
             direction,
             time,
+            differentials: None,
+        }
+    }
+
+    pub fn with_differentials(self, differentials: TRayDifferentials<T>) -> TRay<T> {
+        TRay {
+            differentials: Some(differentials),
+            ..self
         }
     }
 
@@ -37,18 +60,191 @@ where
     {
         self.origin + t * self.direction
     }
+
+    /// Point at distance `d` along the ray, the direction does not need to be normalized.
+    pub fn point_at_distance(&self, d: T) -> TVec3<T>
+    where
+        T: Float,
+    {
+        self.origin + vec3::normalize(self.direction) * d
+    }
+
+    /// Same ray with the origin moved by `eps` along `normal`. Use a negative `eps`
+    /// (or flip the normal) for rays that continue below the surface.
+    pub fn offset_origin(&self, normal: TVec3<T>, eps: T) -> TRay<T> {
+        TRay {
+            origin: self.origin + normal * eps,
+            ..*self
+        }
+    }
+
+    /// Footprint of the pixel at the point `p` of a surface with normal `n`: the offsets
+    /// from `p` to the points where the differential rays cross the surface's tangent
+    /// plane, (dp/dx, dp/dy). None for rays without differentials or differential rays
+    /// parallel to the plane.
+    pub fn footprint(&self, p: TVec3<T>, n: TVec3<T>) -> Option<(TVec3<T>, TVec3<T>)>
+    where
+        T: Float + std::ops::Mul<TVec3<T>, Output = TVec3<T>>,
+    {
+        let d = self.differentials?;
+        let plane_hit = |origin: TVec3<T>, direction: TVec3<T>| {
+            let cos = vec3::dot(n, direction);
+            if cos == T::zero() {
+                None
+            } else {
+                Some(origin + (vec3::dot(n, p - origin) / cos) * direction - p)
+            }
+        };
+
+        Some((
+            plane_hit(d.rx_origin, d.rx_direction)?,
+            plane_hit(d.ry_origin, d.ry_direction)?,
+        ))
+    }
+
+    //
+    // differentials of the ray leaving `p` after a specular bounce, the differential rays
+    // leave from where they cross the tangent plane, with their directions bounced by
+    // `bounce` (the curvature of the surface is ignored)
+    fn bounced_differentials<F>(
+        &self,
+        p: TVec3<T>,
+        n: TVec3<T>,
+        bounce: F,
+    ) -> Option<TRayDifferentials<T>>
+    where
+        T: Float + std::ops::Mul<TVec3<T>, Output = TVec3<T>>,
+        F: Fn(TVec3<T>) -> Option<TVec3<T>>,
+    {
+        let d = self.differentials?;
+        let (dpdx, dpdy) = self.footprint(p, n)?;
+
+        Some(TRayDifferentials {
+            rx_origin: p + dpdx,
+            rx_direction: bounce(vec3::normalize(d.rx_direction))?,
+            ry_origin: p + dpdy,
+            ry_direction: bounce(vec3::normalize(d.ry_direction))?,
+        })
+    }
+
+    /// Mirror reflection of this ray about the unit normal `n` at point `p`.
+    pub fn reflect_ray(&self, p: TVec3<T>, n: TVec3<T>) -> TRay<T>
+    where
+        T: Float + std::ops::Mul<TVec3<T>, Output = TVec3<T>>,
+    {
+        let reflect = |v: TVec3<T>| Some(vec3::reflect_unit_vector(v, n));
+
+        TRay {
+            differentials: self.bounced_differentials(p, n, reflect),
+            ..TRay::new(
+                p,
+                vec3::reflect_unit_vector(vec3::normalize(self.direction), n),
+                self.time,
+            )
+        }
+    }
+
+    /// Refraction of this ray through the surface with unit normal `n` at point `p`.
+    /// None on total internal reflection.
+    pub fn refract_ray(&self, p: TVec3<T>, n: TVec3<T>, etai_over_etat: T) -> Option<TRay<T>>
+    where
+        T: Float + std::ops::Mul<TVec3<T>, Output = TVec3<T>>,
+    {
+        let refract = |v: TVec3<T>| vec3::refract(v, n, etai_over_etat);
+
+        refract(vec3::normalize(self.direction)).map(|direction| TRay {
+            differentials: self.bounced_differentials(p, n, refract),
+            ..TRay::new(p, direction, self.time)
+        })
+    }
 }
 
 pub fn transform<T>(mat: &Mat4<T>, ray: &TRay<T>) -> TRay<T>
 where
     T: Float + std::fmt::Debug,
 {
-    let origin = (*mat * TVec4::from_vec3(&ray.origin, T::one())).xyz();
-    let direction = (*mat * TVec4::from_vec3(&ray.direction, T::zero())).xyz();
+    let point = |p: TVec3<T>| (*mat * TVec4::from_vec3(&p, T::one())).xyz();
+    let vector = |v: TVec3<T>| (*mat * TVec4::from_vec3(&v, T::zero())).xyz();
 
     TRay {
-        origin,
-        direction,
+        origin: point(ray.origin),
+        direction: vector(ray.direction),
+        differentials: ray.differentials.map(|d| TRayDifferentials {
+            rx_origin: point(d.rx_origin),
+            rx_direction: vector(d.rx_direction),
+            ry_origin: point(d.ry_origin),
+            ry_direction: vector(d.ry_direction),
+        }),
         ..*ray
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::approx::approx_eq;
+
+    #[test]
+    fn test_point_at_distance() {
+        let r = TRay::new(
+            TVec3::new(1f32, 0f32, 0f32),
+            TVec3::new(0f32, 4f32, 0f32),
+            0f32,
+        );
+        assert!(approx_eq(
+            &r.point_at_distance(2f32),
+            &TVec3::new(1f32, 2f32, 0f32),
+            1.0E-6
+        ));
+    }
+
+    #[test]
+    fn test_offset_origin() {
+        let r = TRay::new(
+            TVec3::new(1f32, 2f32, 3f32),
+            TVec3::new(0f32, 0f32, -1f32),
+            0.5f32,
+        );
+        let n = vec3::normalize(TVec3::new(1f32, 1f32, 0f32));
+        let eps = 1.0E-3f32;
+        let offset = r.offset_origin(n, eps);
+
+        assert!(approx_eq(
+            &vec3::length(offset.origin - r.origin),
+            &eps,
+            1.0E-6
+        ));
+        assert!(approx_eq(&(offset.origin - r.origin), &(n * eps), 1.0E-6));
+        assert_eq!(offset.direction, r.direction);
+        assert_eq!(offset.time, r.time);
+    }
+
+    #[test]
+    fn test_reflect_refract_match_vec3_helpers() {
+        let r = TRay::new(
+            TVec3::new(0f32, 1f32, 0f32),
+            TVec3::new(2f32, -2f32, 0f32),
+            0.25f32,
+        );
+        let p = TVec3::new(1f32, 0f32, 0f32);
+        let n = TVec3::new(0f32, 1f32, 0f32);
+        let uv = vec3::normalize(r.direction);
+
+        let reflected = r.reflect_ray(p, n);
+        assert_eq!(reflected.origin, p);
+        assert_eq!(reflected.time, r.time);
+        assert!(approx_eq(
+            &reflected.direction,
+            &vec3::reflect_unit_vector(uv, n),
+            1.0E-6
+        ));
+
+        let refracted = r.refract_ray(p, n, 1f32 / 1.5f32).unwrap();
+        assert_eq!(refracted.origin, p);
+        assert!(approx_eq(
+            &refracted.direction,
+            &vec3::refract(uv, n, 1f32 / 1.5f32).unwrap(),
+            1.0E-6
+        ));
+    }
+}
@@ -0,0 +1,25 @@
+/// Reproducible pseudo random numbers for the tests, the math crate has no rng. A 32 bit
+/// LCG, the same stream on every run.
+pub(crate) struct TestRng {
+    state: u32,
+}
+
+impl TestRng {
+    pub(crate) fn new() -> TestRng {
+        TestRng { state: 0x2545_f491 }
+    }
+
+    /// Next number in [-1, 1).
+    pub(crate) fn next_f64(&mut self) -> f64 {
+        self.state = self
+            .state
+            .wrapping_mul(1_664_525)
+            .wrapping_add(1_013_904_223);
+        (self.state >> 8) as f64 / (1u32 << 23) as f64 - 1f64
+    }
+
+    /// Next number in [-1, 1), exactly the f64 one (24 bits of it).
+    pub(crate) fn next_f32(&mut self) -> f32 {
+        self.next_f64() as f32
+    }
+}
@@ -48,3 +48,192 @@ macro_rules! gen_multiply_vector_to_scalar_ops {
         }
     };
 }
+
+/// Generates the arithmetic operators of a vector type, component-wise over the listed
+/// fields: `Neg`, `Add`, `Sub`, `Mul`, `Div` (with another vector and, for `Mul` and
+/// `Div`, with a scalar), their assign versions, `Index`/`IndexMut` and scalar * vector
+/// for the primitive types. The type needs `new` taking the components in field order
+/// and `as_slice`/`as_mut_slice`.
+///
+/// ```text
+/// impl_vec_ops!(TVec3, 3, x, y, z);
+/// ```
+#[macro_export]
+macro_rules! impl_vec_ops {
+    ($vec:ident, $len:expr, $($field:ident),+) => {
+        impl<T> std::ops::Neg for $vec<T>
+        where
+            T: Copy + Clone + num_traits::Num + std::ops::Neg<Output = T> + std::fmt::Debug,
+        {
+            type Output = Self;
+
+            fn neg(self) -> Self::Output {
+                Self::new($(-self.$field),+)
+            }
+        }
+
+        $crate::impl_vec_ops!(@binary $vec, Add, add, AddAssign, add_assign, +, +=, $($field),+);
+        $crate::impl_vec_ops!(@binary $vec, Sub, sub, SubAssign, sub_assign, -, -=, $($field),+);
+        $crate::impl_vec_ops!(@binary $vec, Mul, mul, MulAssign, mul_assign, *, *=, $($field),+);
+        $crate::impl_vec_ops!(@binary $vec, Div, div, DivAssign, div_assign, /, /=, $($field),+);
+
+        impl<T> std::ops::Index<usize> for $vec<T>
+        where
+            T: Copy + Clone + num_traits::Num + std::fmt::Debug,
+        {
+            type Output = T;
+
+            fn index(&self, idx: usize) -> &Self::Output {
+                debug_assert!(idx < $len);
+                &self.as_slice()[idx]
+            }
+        }
+
+        impl<T> std::ops::IndexMut<usize> for $vec<T>
+        where
+            T: Copy + Clone + num_traits::Num + std::fmt::Debug,
+        {
+            fn index_mut(&mut self, idx: usize) -> &mut Self::Output {
+                debug_assert!(idx < $len);
+                &mut self.as_mut_slice()[idx]
+            }
+        }
+
+        $crate::gen_multiply_vector_to_scalar_ops!($vec<i8>, i8);
+        $crate::gen_multiply_vector_to_scalar_ops!($vec<u8>, u8);
+        $crate::gen_multiply_vector_to_scalar_ops!($vec<i16>, i16);
+        $crate::gen_multiply_vector_to_scalar_ops!($vec<u16>, u16);
+        $crate::gen_multiply_vector_to_scalar_ops!($vec<i32>, i32);
+        $crate::gen_multiply_vector_to_scalar_ops!($vec<u32>, u32);
+        $crate::gen_multiply_vector_to_scalar_ops!($vec<i64>, i64);
+        $crate::gen_multiply_vector_to_scalar_ops!($vec<u64>, u64);
+        $crate::gen_multiply_vector_to_scalar_ops!($vec<f32>, f32);
+        $crate::gen_multiply_vector_to_scalar_ops!($vec<f64>, f64);
+    };
+
+    //
+    // component-wise op with another vector and with a scalar, plus the assign versions
+    (@binary $vec:ident, $op:ident, $fn:ident, $op_assign:ident, $fn_assign:ident,
+     $sym:tt, $sym_assign:tt, $($field:ident),+) => {
+        impl<T> std::ops::$op for $vec<T>
+        where
+            T: Copy + Clone + num_traits::Num + std::fmt::Debug,
+        {
+            type Output = Self;
+
+            fn $fn(self, rhs: Self) -> Self::Output {
+                Self::new($(self.$field $sym rhs.$field),+)
+            }
+        }
+
+        impl<T> std::ops::$op<T> for $vec<T>
+        where
+            T: Copy + Clone + num_traits::Num + std::fmt::Debug,
+        {
+            type Output = Self;
+
+            fn $fn(self, k: T) -> Self::Output {
+                Self::new($(self.$field $sym k),+)
+            }
+        }
+
+        impl<T> std::ops::$op_assign for $vec<T>
+        where
+            T: Copy + Clone + num_traits::Num + std::ops::$op_assign + std::fmt::Debug,
+        {
+            fn $fn_assign(&mut self, rhs: Self) {
+                $(self.$field $sym_assign rhs.$field;)+
+            }
+        }
+
+        impl<T> std::ops::$op_assign<T> for $vec<T>
+        where
+            T: Copy + Clone + num_traits::Num + std::ops::$op_assign + std::fmt::Debug,
+        {
+            fn $fn_assign(&mut self, k: T) {
+                $(self.$field $sym_assign k;)+
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::vec2::TVec2;
+    use crate::vec3::TVec3;
+    use crate::vec4::TVec4;
+
+    //
+    // every operator against the same op done on the components one by one
+    macro_rules! check_vec_ops {
+        ($vec:ident, $a:expr, $b:expr, $k:expr) => {{
+            let (a, b, k) = ($a, $b, $k);
+            let zip = |f: &dyn Fn(_, _) -> _| {
+                let mut r = a;
+                r.iter_mut().zip(b.iter()).for_each(|(x, &y)| *x = f(*x, y));
+                $vec::from(r)
+            };
+            let scalar = |f: &dyn Fn(_, _) -> _| {
+                let mut r = a;
+                r.iter_mut().for_each(|x| *x = f(*x, k));
+                $vec::from(r)
+            };
+            let (va, vb) = ($vec::from(a), $vec::from(b));
+
+            assert_eq!(-va, scalar(&|x, _| -x));
+            assert_eq!(va + vb, zip(&|x, y| x + y));
+            assert_eq!(va - vb, zip(&|x, y| x - y));
+            assert_eq!(va * vb, zip(&|x, y| x * y));
+            assert_eq!(va / vb, zip(&|x, y| x / y));
+            assert_eq!(va * k, scalar(&|x, y| x * y));
+            assert_eq!(k * va, scalar(&|x, y| x * y));
+            assert_eq!(va / k, scalar(&|x, y| x / y));
+
+            let mut v = va;
+            v += vb;
+            assert_eq!(v, va + vb);
+            v -= vb;
+            v -= vb;
+            assert_eq!(v, va - vb);
+            let mut v = va;
+            v *= vb;
+            assert_eq!(v, va * vb);
+            let mut v = va;
+            v /= vb;
+            assert_eq!(v, va / vb);
+            let mut v = va;
+            v *= k;
+            assert_eq!(v, va * k);
+            let mut v = va;
+            v /= k;
+            assert_eq!(v, va / k);
+
+            let mut v = va;
+            (0..a.len()).for_each(|i| {
+                assert_eq!(va[i], a[i]);
+                v[i] = b[i];
+            });
+            assert_eq!(v, vb);
+        }};
+    }
+
+    #[test]
+    fn test_vec_ops_are_component_wise() {
+        check_vec_ops!(TVec2, [1f32, -2f32], [4f32, 0.5f32], 2f32);
+        check_vec_ops!(TVec2, [7i32, -9i32], [2i32, 4i32], 3i32);
+        check_vec_ops!(TVec3, [1f32, -2f32, 3f32], [4f32, 0.5f32, -8f32], 2f32);
+        check_vec_ops!(TVec3, [7i32, -9i32, 11i32], [2i32, 4i32, -3i32], 3i32);
+        check_vec_ops!(
+            TVec4,
+            [1f32, -2f32, 3f32, -4f32],
+            [4f32, 0.5f32, -8f32, 16f32],
+            2f32
+        );
+        check_vec_ops!(
+            TVec4,
+            [7i32, -9i32, 11i32, 13i32],
+            [2i32, 4i32, -3i32, 5i32],
+            3i32
+        );
+    }
+}
@@ -1,7 +1,5 @@
 use num_traits::{Float, Num};
-use std::ops::{
-    Add, AddAssign, Div, DivAssign, Index, IndexMut, Mul, MulAssign, Neg, Sub, SubAssign,
-};
+use std::ops::Neg;
 
 /// Two component vector in R2.
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
@@ -219,199 +217,7 @@ where
     }
 }
 
-///   Negation operator.
-impl<T> Neg for TVec2<T>
-where
-    T: Copy + Clone + std::fmt::Debug + Num + Neg<Output = T>,
-{
-    type Output = Self;
-
-    fn neg(self) -> Self::Output {
-        Self::new(-self.x, -self.y)
-    }
-}
-
-///   Self-assign addition operator.
-impl<T> AddAssign for TVec2<T>
-where
-    T: Copy + Clone + std::fmt::Debug + Num + AddAssign,
-{
-    fn add_assign(&mut self, rhs: Self) {
-        self.x += rhs.x;
-        self.y += rhs.y;
-    }
-}
-
-///   Addition operator.
-impl<T> Add for TVec2<T>
-where
-    T: Copy + Clone + std::fmt::Debug + Num + Add<Output = T>,
-{
-    type Output = Self;
-
-    fn add(self, rhs: Self) -> Self {
-        Self::new(self.x + rhs.x, self.y + rhs.y)
-    }
-}
-
-///   Substraction operation.
-impl<T> Sub for TVec2<T>
-where
-    T: Copy + Clone + std::fmt::Debug + Num + Sub<Output = T>,
-{
-    type Output = Self;
-
-    fn sub(self, rhs: Self) -> Self::Output {
-        Self::new(self.x - rhs.x, self.y - rhs.y)
-    }
-}
-
-///   Self-assign substraction.
-impl<T> SubAssign for TVec2<T>
-where
-    T: Copy + Clone + std::fmt::Debug + Num + SubAssign,
-{
-    fn sub_assign(&mut self, rhs: Self) {
-        self.x -= rhs.x;
-        self.y -= rhs.y;
-    }
-}
-
-///  Multiplication with scalar.
-impl<T> Mul<T> for TVec2<T>
-where
-    T: Copy + Clone + std::fmt::Debug + Num + Mul<Output = T>,
-{
-    type Output = Self;
-
-    fn mul(self, scalar: T) -> Self::Output {
-        Self::new(self.x * scalar, self.y * scalar)
-    }
-}
-
-///  Macro to generate scalar with TVec2 multiplication
-macro_rules! scalar_multiply_tvec2 {
-    ($stype:ty) => {
-        impl Mul<TVec2<$stype>> for $stype {
-            type Output = TVec2<$stype>;
-
-            fn mul(self, rhs: TVec2<$stype>) -> Self::Output {
-                rhs * self
-            }
-        }
-    };
-}
-
-scalar_multiply_tvec2!(i8);
-scalar_multiply_tvec2!(u8);
-scalar_multiply_tvec2!(i16);
-scalar_multiply_tvec2!(u16);
-scalar_multiply_tvec2!(i32);
-scalar_multiply_tvec2!(u32);
-scalar_multiply_tvec2!(i64);
-scalar_multiply_tvec2!(u64);
-scalar_multiply_tvec2!(f32);
-scalar_multiply_tvec2!(f64);
-
-///  Self-assign scalar multiplication.
-impl<T> MulAssign<T> for TVec2<T>
-where
-    T: Copy + Clone + std::fmt::Debug + Num + MulAssign,
-{
-    fn mul_assign(&mut self, scalar: T) {
-        self.x *= scalar;
-        self.y *= scalar;
-    }
-}
-
-///  Component-wise multiplication
-impl<T> Mul for TVec2<T>
-where
-    T: Copy + Clone + std::fmt::Debug + Num + Mul<Output = T>,
-{
-    type Output = TVec2<T>;
-
-    fn mul(self, rhs: Self) -> Self::Output {
-        Self::new(self.x * rhs.x, self.y * rhs.y)
-    }
-}
-
-///  Component-wise self-assign multiplication
-impl<T> MulAssign for TVec2<T>
-where
-    T: Copy + Clone + std::fmt::Debug + Num + MulAssign,
-{
-    fn mul_assign(&mut self, rhs: TVec2<T>) {
-        self.x *= rhs.x;
-        self.y *= rhs.y;
-    }
-}
-
-///  Division by scalar.
-impl<T> Div<T> for TVec2<T>
-where
-    T: Copy + Clone + std::fmt::Debug + Num + Div<Output = T>,
-{
-    type Output = Self;
-
-    fn div(self, scalar: T) -> Self::Output {
-        Self::new(self.x / scalar, self.y / scalar)
-    }
-}
-
-///  Component-wise division by another TVec2
-impl<T> Div for TVec2<T>
-where
-    T: Copy + Clone + std::fmt::Debug + Num + Div<Output = T>,
-{
-    type Output = Self;
-
-    fn div(self, rhs: Self) -> Self::Output {
-        Self::new(self.x / rhs.x, self.y / rhs.y)
-    }
-}
-
-///  Self-assign division by scalar.
-impl<T> DivAssign<T> for TVec2<T>
-where
-    T: Copy + Clone + std::fmt::Debug + Num + DivAssign,
-{
-    fn div_assign(&mut self, scalar: T) {
-        self.x /= scalar;
-        self.y /= scalar;
-    }
-}
-
-///  Self-assign division by another TVec2.
-impl<T> DivAssign for TVec2<T>
-where
-    T: Copy + Clone + std::fmt::Debug + Num + DivAssign,
-{
-    fn div_assign(&mut self, rhs: Self) {
-        self.x /= rhs.x;
-        self.y /= rhs.y;
-    }
-}
-
-impl<T> Index<usize> for TVec2<T>
-where
-    T: Copy + Clone + std::fmt::Debug + Num,
-{
-    type Output = T;
-
-    fn index(&self, idx: usize) -> &Self::Output {
-        &self.as_slice()[idx]
-    }
-}
-
-impl<T> IndexMut<usize> for TVec2<T>
-where
-    T: Copy + Clone + std::fmt::Debug + Num,
-{
-    fn index_mut(&mut self, idx: usize) -> &mut Self::Output {
-        &mut self.as_mut_slice()[idx]
-    }
-}
+impl_vec_ops!(TVec2, 2, x, y);
 
 ///   Normalizes the input vector.
 pub fn normalize<T>(a: TVec2<T>) -> TVec2<T>
@@ -466,6 +272,70 @@ where
     dot(a, b).is_zero()
 }
 
+/// Maps a point of the unit square [0, 1]^2 to the unit disk, with Shirley's concentric
+/// mapping. Areas are preserved, so uniformly distributed (or stratified) points stay that
+/// way on the disk.
+pub fn square_to_disk<T>(s: TVec2<T>) -> TVec2<T>
+where
+    T: Copy + Clone + std::fmt::Debug + Float,
+{
+    let one = T::one();
+    let two = one + one;
+    let sp = TVec2::new(two * s.x - one, two * s.y - one);
+
+    //
+    // radius and angle, the angle in units of pi / 4
+    let (r, phi) = if sp.x > -sp.y {
+        if sp.x > sp.y {
+            // sector 1
+            (sp.x, sp.y / sp.x)
+        } else {
+            // sector 2
+            (sp.y, two - sp.x / sp.y)
+        }
+    } else if sp.x < sp.y {
+        // sector 3
+        (-sp.x, two * two + sp.y / sp.x)
+    } else {
+        // sector 4
+        let phi = if !sp.y.is_zero() {
+            two * two + two - sp.x / sp.y
+        } else {
+            T::zero()
+        };
+
+        (-sp.y, phi)
+    };
+
+    let phi = phi * T::from(std::f64::consts::FRAC_PI_4).unwrap();
+    let (sin_phi, cos_phi) = phi.sin_cos();
+
+    TVec2::new(r * cos_phi, r * sin_phi)
+}
+
+/// Maps a point of the unit square [0, 1]^2 to a direction on the hemisphere around +z,
+/// distributed with a density proportional to cos^e(theta).
+pub fn square_to_cosine_power_hemisphere<T>(s: TVec2<T>, e: T) -> crate::vec3::TVec3<T>
+where
+    T: Copy + Clone + std::fmt::Debug + Float,
+{
+    let two_pi = T::from(2f64 * std::f64::consts::PI).unwrap();
+    let (sin_phi, cos_phi) = (two_pi * s.x).sin_cos();
+    let cos_theta = (T::one() - s.y).powf((T::one() + e).recip());
+    let sin_theta = (T::one() - cos_theta * cos_theta).sqrt();
+
+    crate::vec3::TVec3::new(sin_theta * cos_phi, sin_theta * sin_phi, cos_theta)
+}
+
+/// Maps a point of the unit square [0, 1]^2 to a cosine distributed direction on the
+/// hemisphere around +z.
+pub fn square_to_cosine_hemisphere<T>(s: TVec2<T>) -> crate::vec3::TVec3<T>
+where
+    T: Copy + Clone + std::fmt::Debug + Float,
+{
+    square_to_cosine_power_hemisphere(s, T::one())
+}
+
 pub type Vec2I8 = TVec2<i8>;
 pub type Vec2U8 = TVec2<u8>;
 pub type Vec2I16 = TVec2<i16>;
@@ -473,3 +343,63 @@ pub type Vec2U16 = TVec2<u16>;
 pub type Vec2I32 = TVec2<i32>;
 pub type Vec2U32 = TVec2<u32>;
 pub type Vec2F32 = TVec2<f32>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //
+    // centers of the cells of an n x n grid over the unit square
+    fn stratified(n: usize) -> Vec<Vec2F32> {
+        (0..n * n)
+            .map(|i| {
+                Vec2F32::new(
+                    ((i % n) as f32 + 0.5f32) / n as f32,
+                    ((i / n) as f32 + 0.5f32) / n as f32,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_square_to_disk_is_inside_and_area_preserving() {
+        let disk = stratified(100)
+            .into_iter()
+            .map(square_to_disk)
+            .collect::<Vec<_>>();
+
+        assert!(disk.iter().all(|p| p.square_len() <= 1f32 + 1.0e-5));
+        assert_eq!(
+            square_to_disk(Vec2F32::new(0.5, 0.5)),
+            Vec2F32::new(0.0, 0.0)
+        );
+        assert!((square_to_disk(Vec2F32::new(1.0, 0.5)).x - 1f32).abs() < 1.0e-6);
+
+        //
+        // equal areas of the disk get equal shares of the points
+        let fraction = |f: &dyn Fn(&Vec2F32) -> bool| {
+            disk.iter().filter(|p| f(p)).count() as f32 / disk.len() as f32
+        };
+        assert!((fraction(&|p| p.square_len() < 0.25) - 0.25).abs() < 0.01);
+        assert!((fraction(&|p| p.square_len() < 0.5) - 0.5).abs() < 0.01);
+        assert!((fraction(&|p| p.x > 0.0 && p.y > 0.0) - 0.25).abs() < 0.01);
+        assert!((fraction(&|p| p.y > p.x) - 0.5).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_square_to_cosine_hemisphere() {
+        let dirs = stratified(100)
+            .into_iter()
+            .map(square_to_cosine_hemisphere)
+            .collect::<Vec<_>>();
+
+        assert!(dirs
+            .iter()
+            .all(|d| { (crate::vec3::length(*d) - 1f32).abs() < 1.0e-5 && d.z >= 0f32 }));
+
+        //
+        // for a cosine distribution the average of cos(theta) is 2/3
+        let mean_cos = dirs.iter().map(|d| d.z).sum::<f32>() / dirs.len() as f32;
+        assert!((mean_cos - 2f32 / 3f32).abs() < 0.01);
+    }
+}
@@ -425,215 +425,7 @@ where
     }
 }
 
-impl<T> std::ops::Index<usize> for TVec3<T>
-where
-    T: Copy + Clone + Num + std::fmt::Debug,
-{
-    type Output = T;
-    fn index(&self, idx: usize) -> &Self::Output {
-        debug_assert!(idx < 3);
-        &self.as_slice()[idx]
-    }
-}
-
-impl<T> std::ops::IndexMut<usize> for TVec3<T>
-where
-    T: Copy + Clone + Num + std::fmt::Debug,
-{
-    fn index_mut(&mut self, idx: usize) -> &mut Self::Output {
-        debug_assert!(idx < 3);
-        &mut self.as_mut_slice()[idx]
-    }
-}
-
-impl<T> std::ops::Neg for TVec3<T>
-where
-    T: Copy + Clone + Num + std::ops::Neg<Output = T> + std::fmt::Debug,
-{
-    type Output = Self;
-    fn neg(self) -> Self::Output {
-        Self::new(-self.x, -self.y, -self.z)
-    }
-}
-
-impl<T> std::ops::AddAssign for TVec3<T>
-where
-    T: Copy + Clone + Num + std::ops::AddAssign + std::fmt::Debug,
-{
-    fn add_assign(&mut self, rhs: Self) {
-        self.x += rhs.x;
-        self.y += rhs.y;
-        self.z += rhs.z;
-    }
-}
-
-impl<T> std::ops::Add for TVec3<T>
-where
-    T: Copy + Clone + Num + std::ops::Add<Output = T> + std::fmt::Debug,
-{
-    type Output = Self;
-    fn add(self, rhs: Self) -> Self::Output {
-        Self {
-            x: self.x + rhs.x,
-            y: self.y + rhs.y,
-            z: self.z + rhs.z,
-        }
-    }
-}
-
-impl<T> std::ops::SubAssign for TVec3<T>
-where
-    T: Copy + Clone + Num + std::ops::SubAssign + std::fmt::Debug,
-{
-    fn sub_assign(&mut self, rhs: Self) {
-        self.x -= rhs.x;
-        self.y -= rhs.y;
-        self.z -= rhs.z;
-    }
-}
-
-impl<T> std::ops::Sub for TVec3<T>
-where
-    T: Copy + Clone + Num + std::ops::Sub<Output = T> + std::fmt::Debug,
-{
-    type Output = Self;
-    fn sub(self, rhs: Self) -> Self::Output {
-        Self {
-            x: self.x - rhs.x,
-            y: self.y - rhs.y,
-            z: self.z - rhs.z,
-        }
-    }
-}
-
-impl<T> std::ops::MulAssign<T> for TVec3<T>
-where
-    T: Copy + Clone + Num + std::ops::MulAssign + std::fmt::Debug,
-{
-    fn mul_assign(&mut self, k: T) {
-        self.x *= k;
-        self.y *= k;
-        self.z *= k;
-    }
-}
-
-/// Component-wise self assign multiplication with another TVec3.
-impl<T> std::ops::MulAssign for TVec3<T>
-where
-    T: Copy + Clone + Num + std::ops::MulAssign + std::fmt::Debug,
-{
-    fn mul_assign(&mut self, v: TVec3<T>) {
-        self.x *= v.x;
-        self.y *= v.y;
-        self.z *= v.z;
-    }
-}
-
-impl<T> std::ops::Mul<T> for TVec3<T>
-where
-    T: Copy + Clone + Num + std::ops::Mul<Output = T> + std::fmt::Debug,
-{
-    type Output = Self;
-
-    fn mul(self, k: T) -> Self::Output {
-        Self {
-            x: self.x * k,
-            y: self.y * k,
-            z: self.z * k,
-        }
-    }
-}
-
-///  Macro to generate scalar with TVec3 multiplication
-macro_rules! scalar_multiply_tvec3 {
-    ($stype:ty) => {
-        impl std::ops::Mul<TVec3<$stype>> for $stype {
-            type Output = TVec3<$stype>;
-
-            fn mul(self, rhs: TVec3<$stype>) -> Self::Output {
-                rhs * self
-            }
-        }
-    };
-}
-
-scalar_multiply_tvec3!(i8);
-scalar_multiply_tvec3!(u8);
-scalar_multiply_tvec3!(i16);
-scalar_multiply_tvec3!(u16);
-scalar_multiply_tvec3!(i32);
-scalar_multiply_tvec3!(u32);
-scalar_multiply_tvec3!(i64);
-scalar_multiply_tvec3!(u64);
-scalar_multiply_tvec3!(f32);
-scalar_multiply_tvec3!(f64);
-
-/// Component-wise multiplication with another TVec3.
-impl<T> std::ops::Mul for TVec3<T>
-where
-    T: Copy + Clone + Num + std::ops::Mul<Output = T> + std::fmt::Debug,
-{
-    type Output = Self;
-    fn mul(self, v: TVec3<T>) -> Self::Output {
-        Self {
-            x: self.x * v.x,
-            y: self.y * v.y,
-            z: self.z * v.z,
-        }
-    }
-}
-
-impl<T> std::ops::DivAssign<T> for TVec3<T>
-where
-    T: Copy + Clone + Num + std::ops::DivAssign + std::fmt::Debug,
-{
-    fn div_assign(&mut self, k: T) {
-        self.x /= k;
-        self.y /= k;
-        self.z /= k;
-    }
-}
-
-/// Component-wise self assign division with another TVec3
-impl<T> std::ops::DivAssign for TVec3<T>
-where
-    T: Copy + Clone + Num + std::ops::DivAssign + std::fmt::Debug,
-{
-    fn div_assign(&mut self, rhs: TVec3<T>) {
-        self.x /= rhs.x;
-        self.y /= rhs.y;
-        self.z /= rhs.z
-    }
-}
-
-impl<T> std::ops::Div<T> for TVec3<T>
-where
-    T: Copy + Clone + Num + std::ops::Div<Output = T> + std::fmt::Debug,
-{
-    type Output = Self;
-    fn div(self, k: T) -> Self::Output {
-        Self {
-            x: self.x / k,
-            y: self.y / k,
-            z: self.z / k,
-        }
-    }
-}
-
-/// Component-wise division with another TVec3
-impl<T> std::ops::Div for TVec3<T>
-where
-    T: Copy + Clone + Num + std::ops::Div<Output = T> + std::fmt::Debug,
-{
-    type Output = Self;
-    fn div(self, rhs: TVec3<T>) -> Self::Output {
-        Self {
-            x: self.x / rhs.x,
-            y: self.y / rhs.y,
-            z: self.z / rhs.z,
-        }
-    }
-}
+impl_vec_ops!(TVec3, 3, x, y, z);
 
 pub fn length_squared<T>(v: TVec3<T>) -> T
 where
@@ -752,8 +544,9 @@ where
     v - two * dot(v, n) * n
 }
 
-/// Refract vector uv along normal n, given the ratio of refraction indices
-pub fn refract<T>(uv: TVec3<T>, n: TVec3<T>, etai_over_etat: T) -> TVec3<T>
+/// Refract vector uv along normal n, given the ratio of refraction indices.
+/// Returns None on total internal reflection.
+pub fn refract<T>(uv: TVec3<T>, n: TVec3<T>, etai_over_etat: T) -> Option<TVec3<T>>
 where
     T: Copy
         + Clone
@@ -766,8 +559,13 @@ where
 {
     let cos_theta = dot(-uv, n);
     let r_out_parallel = etai_over_etat * (uv + cos_theta * n);
-    let r_out_perp = -(T::one() - length_squared(r_out_parallel)).sqrt() * n;
-    r_out_parallel + r_out_perp
+    let cos_theta_out_squared = T::one() - length_squared(r_out_parallel);
+
+    if cos_theta_out_squared < T::zero() {
+        return None;
+    }
+
+    Some(r_out_parallel - cos_theta_out_squared.sqrt() * n)
 }
 
 /// Test if two vectors are parallel using the cross product.
@@ -968,6 +766,52 @@ where
     }
 }
 
+/// Largest of the x, y, z components.
+pub fn max_component<T>(v: TVec3<T>) -> T
+where
+    T: Copy + Clone + Num + crate::minmax::MinMax<Output = T>,
+{
+    T::max(T::max(v.x, v.y), v.z)
+}
+
+/// Horizontal sum, x + y + z.
+pub fn hsum<T>(v: TVec3<T>) -> T
+where
+    T: Copy + Clone + Num,
+{
+    v.x + v.y + v.z
+}
+
+/// Relative luminance of a linear RGB color stored as (r, g, b), Rec. 709 weights.
+pub fn luminance<T>(v: TVec3<T>) -> T
+where
+    T: Float + std::fmt::Debug,
+{
+    let weights = TVec3::new(
+        T::from(0.2126).unwrap(),
+        T::from(0.7152).unwrap(),
+        T::from(0.0722).unwrap(),
+    );
+
+    dot(v, weights)
+}
+
+/// Tangent and bitangent completing the unit vector n to a right handed orthonormal
+/// basis (t, b, n). Branchless, from Duff et al., "Building an Orthonormal Basis, Revisited".
+pub fn orthonormal_basis<T>(n: TVec3<T>) -> (TVec3<T>, TVec3<T>)
+where
+    T: Float + std::fmt::Debug,
+{
+    let sign = n.z.signum();
+    let a = -T::one() / (sign + n.z);
+    let b = n.x * n.y * a;
+
+    (
+        TVec3::new(T::one() + sign * n.x * n.x * a, sign * b, -sign * n.x),
+        TVec3::new(b, sign + n.y * n.y * a, -n.y),
+    )
+}
+
 impl<T> std::fmt::Display for TVec3<T>
 where
     T: Copy + Clone + Num + std::fmt::Display,
@@ -976,3 +820,73 @@ where
         write!(f, "[{}, {}, {}]", self.x, self.y, self.z)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_refract_total_internal_reflection() {
+        let n = TVec3::new(0f32, 1f32, 0f32);
+
+        //
+        // glass to air, past the critical angle of ~41.8 degrees
+        let grazing = normalize(TVec3::new(1f32, -0.2f32, 0f32));
+        assert!(refract(grazing, n, 1.5f32).is_none());
+
+        let straight = TVec3::new(0f32, -1f32, 0f32);
+        let refracted = refract(straight, n, 1.5f32).unwrap();
+        assert!(length(refracted - straight) < 1.0E-6);
+        let refracted = refract(straight, n, 1f32 / 1.5f32).unwrap();
+        assert!(length(refracted - straight) < 1.0E-6);
+    }
+
+    #[test]
+    fn test_component_reductions() {
+        let v = TVec3::new(3f32, -1.5f32, 2f32);
+
+        assert_eq!(max_component(v), 3f32);
+        assert_eq!(hsum(v), 3.5f32);
+        assert_eq!(max_component(TVec3::new(4, 7, 1)), 7);
+        assert_eq!(hsum(TVec3::new(4, 7, 1)), 12);
+    }
+
+    #[test]
+    fn test_luminance() {
+        assert!((luminance(TVec3::new(1f32, 1f32, 1f32)) - 1f32).abs() < 1.0E-6);
+        assert!((luminance(TVec3::new(1f64, 0f64, 0f64)) - 0.2126f64).abs() < 1.0E-12);
+        assert!((luminance(TVec3::new(0f32, 1f32, 0f32)) - 0.7152f32).abs() < 1.0E-6);
+        assert!((luminance(TVec3::new(0f32, 0f32, 2f32)) - 0.1444f32).abs() < 1.0E-6);
+    }
+
+    #[test]
+    fn test_orthonormal_basis() {
+        //
+        // points on a fibonacci sphere plus directions right next to the poles
+        const COUNT: usize = 4096;
+        let golden_angle = std::f64::consts::PI * (3f64 - 5f64.sqrt());
+        let sphere = (0..COUNT).map(|i| {
+            let z = 1f64 - 2f64 * (i as f64 + 0.5f64) / COUNT as f64;
+            let r = (1f64 - z * z).sqrt();
+            let (sin_phi, cos_phi) = (golden_angle * i as f64).sin_cos();
+            TVec3::new(r * cos_phi, r * sin_phi, z)
+        });
+        let poles = [1f64, -1f64].iter().flat_map(|&z| {
+            [0f64, 1.0e-12f64, 1.0e-8f64, 1.0e-4f64]
+                .iter()
+                .map(move |&eps| normalize(TVec3::new(eps, -eps, z)))
+                .collect::<Vec<_>>()
+        });
+
+        sphere.chain(poles).for_each(|n| {
+            let (t, b) = orthonormal_basis(n);
+
+            assert!((length(t) - 1f64).abs() < 1.0e-9, "{:?}", n);
+            assert!((length(b) - 1f64).abs() < 1.0e-9, "{:?}", n);
+            assert!(dot(t, n).abs() < 1.0e-9, "{:?}", n);
+            assert!(dot(b, n).abs() < 1.0e-9, "{:?}", n);
+            assert!(dot(t, b).abs() < 1.0e-9, "{:?}", n);
+            assert!(length(cross(t, b) - n) < 1.0e-9, "{:?}", n);
+        });
+    }
+}
@@ -1,6 +1,5 @@
 use super::vec3::TVec3;
 use num_traits::{Float, Num};
-use std::ops::{Add, AddAssign, Index, IndexMut, Mul, MulAssign, Neg, Sub, SubAssign};
 
 /// Four component vector.
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
@@ -294,28 +293,6 @@ where
     }
 }
 
-/// Subscripting operator - non mutable
-impl<T> Index<usize> for TVec4<T>
-where
-    T: Copy + Clone + std::fmt::Debug + Num,
-{
-    type Output = T;
-
-    fn index(&self, idx: usize) -> &Self::Output {
-        &self.as_slice()[idx]
-    }
-}
-
-/// Subscripting operator - mutable
-impl<T> IndexMut<usize> for TVec4<T>
-where
-    T: Copy + Clone + std::fmt::Debug + Num,
-{
-    fn index_mut(&mut self, idx: usize) -> &mut Self::Output {
-        &mut self.as_mut_slice()[idx]
-    }
-}
-
 impl<T> std::iter::FromIterator<T> for TVec4<T>
 where
     T: Num + Copy + Clone + std::fmt::Debug,
@@ -344,125 +321,7 @@ where
     }
 }
 
-/// \brief  Negation operator.
-impl<T> Neg for TVec4<T>
-where
-    T: Copy + Clone + std::fmt::Debug + Num + Neg<Output = T>,
-{
-    type Output = Self;
-
-    fn neg(self) -> Self::Output {
-        Self::new(-self.x, -self.y, -self.z, -self.w)
-    }
-}
-
-/// Self-assignment +
-impl<T> AddAssign for TVec4<T>
-where
-    T: Copy + Clone + Num + AddAssign<T> + std::fmt::Debug,
-{
-    fn add_assign(&mut self, rhs: TVec4<T>) {
-        self.x += rhs.x;
-        self.y += rhs.y;
-        self.z += rhs.z;
-        self.w += rhs.w;
-    }
-}
-
-/// Binary +
-impl<T> Add for TVec4<T>
-where
-    T: Copy + Clone + Num + AddAssign + std::fmt::Debug,
-{
-    type Output = TVec4<T>;
-
-    fn add(self, rhs: TVec4<T>) -> Self::Output {
-        let mut res = self;
-        res += rhs;
-        res
-    }
-}
-
-/// Self-assign -
-impl<T> SubAssign for TVec4<T>
-where
-    T: Copy + Clone + Num + SubAssign<T> + std::fmt::Debug,
-{
-    fn sub_assign(&mut self, rhs: TVec4<T>) {
-        self.x -= rhs.x;
-        self.y -= rhs.y;
-        self.z -= rhs.z;
-        self.w -= rhs.w;
-    }
-}
-
-/// Binary -
-impl<T> Sub for TVec4<T>
-where
-    T: Copy + Clone + Num + SubAssign<T> + std::fmt::Debug,
-{
-    type Output = TVec4<T>;
-
-    fn sub(self, rhs: TVec4<T>) -> Self::Output {
-        let mut res = self;
-        res -= rhs;
-        res
-    }
-}
-
-/// Self-assign multiplication with scalar
-impl<T> MulAssign<T> for TVec4<T>
-where
-    T: Copy + Clone + Num + MulAssign<T> + std::fmt::Debug,
-{
-    fn mul_assign(&mut self, rhs: T) {
-        self.x *= rhs;
-        self.y *= rhs;
-        self.z *= rhs;
-        self.w *= rhs;
-    }
-}
-
-/// Self assign component-wise multiplication
-impl<T> MulAssign for TVec4<T>
-where
-    T: Copy + Clone + Num + MulAssign<T> + std::fmt::Debug,
-{
-    fn mul_assign(&mut self, rhs: TVec4<T>) {
-        self.x *= rhs.x;
-        self.y *= rhs.y;
-        self.z *= rhs.z;
-        self.w *= rhs.w;
-    }
-}
-
-/// Binary multiplication with scalar
-impl<T> Mul<T> for TVec4<T>
-where
-    T: Copy + Clone + Num + MulAssign<T> + std::fmt::Debug,
-{
-    type Output = TVec4<T>;
-
-    fn mul(self, rhs: T) -> Self::Output {
-        let mut res = self;
-        res *= rhs;
-        res
-    }
-}
-
-/// Binary component-wise multiplication
-impl<T> Mul<TVec4<T>> for TVec4<T>
-where
-    T: Copy + Clone + Num + MulAssign<T> + std::fmt::Debug,
-{
-    type Output = TVec4<T>;
-
-    fn mul(self, rhs: Self) -> Self::Output {
-        let mut res = self;
-        res *= rhs;
-        res
-    }
-}
+impl_vec_ops!(TVec4, 4, x, y, z, w);
 
 pub fn max<T>(a: TVec4<T>, b: TVec4<T>) -> TVec4<T>
 where
@@ -488,15 +347,6 @@ where
     }
 }
 
-gen_multiply_vector_to_scalar_ops!(TVec4<i8>, i8);
-gen_multiply_vector_to_scalar_ops!(TVec4<u8>, u8);
-gen_multiply_vector_to_scalar_ops!(TVec4<i16>, i16);
-gen_multiply_vector_to_scalar_ops!(TVec4<u16>, u16);
-gen_multiply_vector_to_scalar_ops!(TVec4<i32>, i32);
-gen_multiply_vector_to_scalar_ops!(TVec4<u32>, u32);
-gen_multiply_vector_to_scalar_ops!(TVec4<i64>, i64);
-gen_multiply_vector_to_scalar_ops!(TVec4<u64>, u64);
-
 pub type Vec4I8 = TVec4<i8>;
 pub type Vec4U8 = TVec4<u8>;
 pub type Vec4I16 = TVec4<i16>;
@@ -1,4 +1,11 @@
-use crate::{colors::RGBAColorF32, vec2::Vec2F32};
+use num_traits::Float;
+
+use crate::{
+    colors::RGBAColorF32,
+    vec2::{TVec2, Vec2F32},
+    vec3::TVec3,
+    vec4::TVec4,
+};
 
 #[derive(Copy, Debug, Clone)]
 #[repr(C)]
@@ -26,3 +33,51 @@ impl std::default::Default for VertexPTC {
         unsafe { std::mem::MaybeUninit::<Self>::zeroed().assume_init() }
     }
 }
+
+/// Mesh vertex with position, normal, texture coordinates, color and tangent, plus the
+/// index of its material in the PBR material buffer. Layout matches the GPU vertex buffer.
+#[derive(Copy, Debug, Clone)]
+#[repr(C)]
+pub struct VertexPNTCT<T> {
+    pub pos: TVec3<T>,
+    pub normal: TVec3<T>,
+    pub uv: TVec2<T>,
+    pub color: TVec4<T>,
+    pub tangent: TVec4<T>,
+    pub pbr_buf_id: u32,
+}
+
+impl<T> std::default::Default for VertexPNTCT<T>
+where
+    T: Float + std::fmt::Debug,
+{
+    fn default() -> Self {
+        VertexPNTCT {
+            pos: TVec3::new(T::zero(), T::zero(), T::zero()),
+            normal: TVec3::new(T::zero(), T::zero(), T::zero()),
+            uv: TVec2::new(T::zero(), T::zero()),
+            color: TVec4::new(T::zero(), T::zero(), T::zero(), T::one()),
+            tangent: TVec4::new(T::zero(), T::zero(), T::zero(), T::zero()),
+            pbr_buf_id: 0u32,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::mem::{offset_of, size_of};
+
+    #[test]
+    fn test_vertex_pntct_layout() {
+        type Vertex = VertexPNTCT<f32>;
+
+        assert_eq!(offset_of!(Vertex, pos), 0);
+        assert_eq!(offset_of!(Vertex, normal), 12);
+        assert_eq!(offset_of!(Vertex, uv), 24);
+        assert_eq!(offset_of!(Vertex, color), 32);
+        assert_eq!(offset_of!(Vertex, tangent), 48);
+        assert_eq!(offset_of!(Vertex, pbr_buf_id), 64);
+        assert_eq!(size_of::<Vertex>(), 68);
+    }
+}
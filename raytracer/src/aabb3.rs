@@ -1,6 +1,6 @@
 use math::vec3::TVec3;
 
-use crate::types::{Mat4, Ray, Real, Vec3, C_ZERO};
+use crate::types::{Mat4, Ray, Real, Vec3};
 
 #[derive(Copy, Clone, Debug)]
 pub struct Aabb {
@@ -49,9 +49,33 @@ impl Aabb {
         self.max = math::vec3::max_sv(self.max, p);
     }
 
+    /// True if `p` is inside the box or on its boundary.
+    pub fn contains(&self, p: Vec3) -> bool {
+        (0..3).all(|a| p[a] >= self.min[a] && p[a] <= self.max[a])
+    }
+
+    /// True if the boxes overlap, boxes that only touch count as intersecting.
+    pub fn intersects(&self, other: &Aabb) -> bool {
+        (0..3).all(|a| self.min[a] <= other.max[a] && other.min[a] <= self.max[a])
+    }
+
     pub fn center(&self) -> Vec3 {
         (self.min + self.max) * crate::types::C_HALF_ONE
     }
+
+    /// Grows any axis thinner than `min_extent`, so that flat boxes (planar geometry)
+    /// can still be hit by rays.
+    pub fn padded(&self, min_extent: Real) -> Aabb {
+        let mut bbox = *self;
+        for a in 0..3 {
+            if bbox.max[a] - bbox.min[a] < min_extent {
+                let delta = min_extent * crate::types::C_HALF_ONE;
+                bbox.min[a] -= delta;
+                bbox.max[a] += delta;
+            }
+        }
+        bbox
+    }
 }
 pub fn merge_aabbs(a: &Aabb, b: &Aabb) -> Aabb {
     let min = Vec3::new(
@@ -78,20 +102,78 @@ impl Default for Aabb {
     }
 }
 
+/// Box around the 8 corners of `aabb` transformed by `mat` (affine).
 pub fn transform(mat: &Mat4, aabb: &Aabb) -> Aabb {
-    let xmin = mat.column(0) * aabb.min.x;
-    let xmax = mat.column(0) * aabb.max.x;
+    use math::mat4::transform_point;
 
-    let ymin = mat.column(1) * aabb.min.y;
-    let ymax = mat.column(1) * aabb.max.y;
+    let corner = |i: usize| {
+        Vec3::new(
+            if i & 1 != 0 { aabb.max.x } else { aabb.min.x },
+            if i & 2 != 0 { aabb.max.y } else { aabb.min.y },
+            if i & 4 != 0 { aabb.max.z } else { aabb.min.z },
+        )
+    };
+
+    let first = transform_point(mat, corner(0));
+    (1..8)
+        .map(|i| transform_point(mat, corner(i)))
+        .fold(Aabb::new(first, first), |bbox, p| {
+            merge_aabbs(&bbox, &Aabb::new(p, p))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::C_ZERO;
+
+    fn unit_box_at(x: Real) -> Aabb {
+        Aabb::new(
+            Vec3::new(x, C_ZERO, C_ZERO),
+            Vec3::new(x + 1 as Real, 1 as Real, 1 as Real),
+        )
+    }
+
+    #[test]
+    fn test_intersects() {
+        let a = unit_box_at(C_ZERO);
+
+        assert!(a.intersects(&a));
+        assert!(a.intersects(&unit_box_at(0.5)));
+        assert!(unit_box_at(0.5).intersects(&a));
+        //
+        // touching faces
+        assert!(a.intersects(&unit_box_at(1 as Real)));
+        assert!(unit_box_at(-1 as Real).intersects(&a));
+        assert!(!a.intersects(&unit_box_at(1.001)));
+        assert!(!unit_box_at(-1.001).intersects(&a));
+
+        //
+        // overlapping on x only
+        let above = Aabb::new(
+            Vec3::new(0.5, 2 as Real, C_ZERO),
+            Vec3::new(1.5, 3 as Real, 1 as Real),
+        );
+        assert!(!a.intersects(&above));
+
+        //
+        // one inside the other
+        let inner = Aabb::new(Vec3::broadcast(0.25), Vec3::broadcast(0.75));
+        assert!(a.intersects(&inner) && inner.intersects(&a));
+    }
 
-    let zmin = mat.column(2) * aabb.min.z;
-    let zmax = mat.column(2) * aabb.max.z;
+    #[test]
+    fn test_contains() {
+        let a = unit_box_at(C_ZERO);
 
-    use math::vec4::{max, min};
+        assert!(a.contains(Vec3::broadcast(0.5)));
+        assert!(a.contains(a.min));
+        assert!(a.contains(a.max));
+        assert!(a.contains(Vec3::new(1 as Real, 0.5, 0.5)));
+        assert!(a.contains(Vec3::new(0.5, C_ZERO, 1 as Real)));
 
-    Aabb {
-        min: (min(xmin, xmax) + min(ymin, ymax) + min(zmin, zmax) + mat.column(3)).xyz(),
-        max: (max(xmin, xmax) + max(ymin, ymax) + max(zmin, zmax) + mat.column(3)).xyz(),
+        assert!(!a.contains(Vec3::new(1.001, 0.5, 0.5)));
+        assert!(!a.contains(Vec3::new(0.5, -0.001, 0.5)));
+        assert!(!a.contains(Vec3::broadcast(2 as Real)));
     }
 }
@@ -0,0 +1,260 @@
+use crate::{
+    sampling::{SampleStrategy, SamplerBase},
+    types::{Real, Vec2, C_ONE, C_PI, C_TWO, C_ZERO},
+};
+
+/// Shape of the lens opening, as a grayscale mask over the [-1, 1]^2 square around the lens
+/// center. Lens samples are distributed like the mask values, so out of focus highlights
+/// take the shape of the mask (hexagons for a six blade diaphragm, cat-eyes, ...).
+///
+/// The samples of the unit square are warped to the mask by inverting the distribution
+/// of its rows and of the texels in a row, stratified samples stay stratified over the
+/// opening.
+pub struct ApertureMask {
+    width: u32,
+    height: u32,
+    /// Row major, top row first, values in [0, 1].
+    texels: Vec<Real>,
+    /// Distribution of the rows, the (normalized) sum of the rows up to and including
+    /// each one.
+    rows_cdf: Vec<Real>,
+    /// Distribution of the texels of every row, like `rows_cdf`, row major.
+    texels_cdf: Vec<Real>,
+}
+
+impl ApertureMask {
+    fn new(width: u32, height: u32, texels: Vec<Real>) -> ApertureMask {
+        let prefix_sums = |values: &[Real]| {
+            values
+                .iter()
+                .scan(C_ZERO, |sum, &v| {
+                    *sum += v;
+                    Some(*sum)
+                })
+                .collect::<Vec<_>>()
+        };
+        let normalized = |mut cdf: Vec<Real>| {
+            let total = cdf.last().copied().unwrap_or(C_ZERO);
+            if total > C_ZERO {
+                cdf.iter_mut().for_each(|c| *c /= total);
+            }
+            cdf
+        };
+
+        let row_sums = texels
+            .chunks(width as usize)
+            .map(|row| row.iter().sum::<Real>())
+            .collect::<Vec<_>>();
+        let texels_cdf = texels
+            .chunks(width as usize)
+            .flat_map(|row| normalized(prefix_sums(row)))
+            .collect();
+
+        ApertureMask {
+            width,
+            height,
+            texels,
+            rows_cdf: normalized(prefix_sums(&row_sums)),
+            texels_cdf,
+        }
+    }
+
+    /// Mask from a grayscale image, the image covers the [-1, 1]^2 square.
+    pub fn from_image(img: &image::GrayImage) -> ApertureMask {
+        Self::new(
+            img.width(),
+            img.height(),
+            img.pixels()
+                .map(|px| px.0[0] as Real / 255 as Real)
+                .collect(),
+        )
+    }
+
+    /// Loads the mask from an image file, color images are converted to grayscale.
+    pub fn load<P: AsRef<std::path::Path>>(p: P) -> Result<ApertureMask, String> {
+        image::open(p.as_ref())
+            .map(|img| Self::from_image(&img.to_luma8()))
+            .map_err(|e| {
+                format!(
+                    "Failed to load aperture mask {}: {}",
+                    p.as_ref().display(),
+                    e
+                )
+            })
+    }
+
+    /// `resolution` x `resolution` mask, the texels are 1 where `inside` is true for the
+    /// texel center.
+    pub fn from_fn<F: Fn(Vec2) -> bool>(resolution: u32, inside: F) -> ApertureMask {
+        let texels = (0..resolution * resolution)
+            .map(|i| {
+                let (x, y) = (i % resolution, i / resolution);
+                let p = Vec2::new(
+                    (x as Real + 0.5 as Real) / resolution as Real * C_TWO - C_ONE,
+                    C_ONE - (y as Real + 0.5 as Real) / resolution as Real * C_TWO,
+                );
+
+                if inside(p) {
+                    C_ONE
+                } else {
+                    C_ZERO
+                }
+            })
+            .collect();
+
+        Self::new(resolution, resolution, texels)
+    }
+
+    /// The round opening, same as sampling the unit disk.
+    pub fn disk(resolution: u32) -> ApertureMask {
+        Self::from_fn(resolution, |p| p.square_len() <= C_ONE)
+    }
+
+    /// Regular polygon inscribed in the unit circle, the opening of a diaphragm with
+    /// `blades` straight blades. A vertex points up.
+    pub fn polygon(blades: u32, resolution: u32) -> ApertureMask {
+        Self::from_fn(resolution, |p| inside_polygon(blades, p))
+    }
+
+    /// Mask value at a point of the [-1, 1]^2 square.
+    pub fn value(&self, p: Vec2) -> Real {
+        let to_texel =
+            |x: Real, size: u32| (((x + C_ONE) * 0.5 as Real * size as Real) as u32).min(size - 1);
+
+        let x = to_texel(p.x, self.width);
+        let y = to_texel(-p.y, self.height);
+        self.texels[(y * self.width + x) as usize]
+    }
+
+    /// Point on the lens, in the [-1, 1]^2 square, distributed like the mask. Made from the
+    /// next sample of the unit square of `smp`, the lens center for an empty mask.
+    pub fn sample<S: SampleStrategy>(&self, smp: &mut SamplerBase<S>) -> Vec2 {
+        let u = smp.sample_unit_square();
+        if self.rows_cdf.last().map_or(true, |&total| total <= C_ZERO) {
+            return Vec2::broadcast(C_ZERO);
+        }
+
+        let (y, fy) = invert_cdf(&self.rows_cdf, u.y);
+        let row = y * self.width as usize;
+        let (x, fx) = invert_cdf(&self.texels_cdf[row..row + self.width as usize], u.x);
+
+        Vec2::new(
+            (x as Real + fx) / self.width as Real * C_TWO - C_ONE,
+            C_ONE - (y as Real + fy) / self.height as Real * C_TWO,
+        )
+    }
+}
+
+//
+// the entry of the cdf u falls in and where in the entry's interval, in [0, 1]
+fn invert_cdf(cdf: &[Real], u: Real) -> (usize, Real) {
+    let idx = cdf.partition_point(|&c| c <= u).min(cdf.len() - 1);
+    let start = if idx == 0 { C_ZERO } else { cdf[idx - 1] };
+    let width = cdf[idx] - start;
+
+    let offset = if width > C_ZERO {
+        ((u - start) / width).clamp(C_ZERO, C_ONE)
+    } else {
+        0.5 as Real
+    };
+    (idx, offset)
+}
+
+//
+// whether p is inside the regular polygon with n sides, inscribed in the unit circle and
+// with a vertex on +y
+fn inside_polygon(n: u32, p: Vec2) -> bool {
+    let half_angle = C_PI / n as Real;
+
+    (0..n).all(|k| {
+        //
+        // the edges' normals are halfway between the vertices
+        let angle = C_PI * 0.5 as Real + (2 * k + 1) as Real * half_angle;
+        p.x * angle.cos() + p.y * angle.sin() <= half_angle.cos()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sampling::{MultiJitteredSamplingStrategy, SamplerBase};
+
+    //
+    // (mean squared radius, fraction in the +x +y quadrant, fraction in the upper half)
+    fn moments(points: &[Vec2]) -> (Real, Real, Real) {
+        let n = points.len() as Real;
+        (
+            points.iter().map(|p| p.square_len()).sum::<Real>() / n,
+            points
+                .iter()
+                .filter(|p| p.x > C_ZERO && p.y > C_ZERO)
+                .count() as Real
+                / n,
+            points.iter().filter(|p| p.y > C_ZERO).count() as Real / n,
+        )
+    }
+
+    #[test]
+    fn test_hexagon_samples_stay_in_the_hexagon_and_disk_matches_the_unit_disk() {
+        const N: usize = 20_000;
+
+        let mut smp = SamplerBase::<MultiJitteredSamplingStrategy>::new(64, None);
+        let hexagon = ApertureMask::polygon(6, 256);
+        let samples = (0..N).map(|_| hexagon.sample(&mut smp)).collect::<Vec<_>>();
+
+        //
+        // a texel of slack around the edges
+        let slack = 1 as Real + C_TWO / 256 as Real;
+        assert!(samples.iter().all(|&p| inside_polygon(6, p / slack)));
+        //
+        // some get close to the top vertex, where the disk goes but the flat sides don't
+        assert!(samples.iter().any(|p| p.y > 0.95 as Real));
+        assert!(samples.iter().all(|p| p.x.abs() <= 0.87 as Real * slack));
+
+        //
+        // the disk mask against the sampler's unit disk
+        let disk = ApertureMask::disk(256);
+        let masked = (0..N).map(|_| disk.sample(&mut smp)).collect::<Vec<_>>();
+        let reference = (0..N).map(|_| smp.sample_unit_disk()).collect::<Vec<_>>();
+
+        assert!(masked.iter().all(|p| p.square_len() <= slack * slack));
+        let (r2, quadrant, upper) = moments(&masked);
+        let (ref_r2, ref_quadrant, ref_upper) = moments(&reference);
+        assert!((r2 - ref_r2).abs() < 0.02 as Real, "{} {}", r2, ref_r2);
+        assert!((quadrant - ref_quadrant).abs() < 0.02 as Real);
+        assert!((upper - ref_upper).abs() < 0.02 as Real);
+    }
+    #[test]
+    fn test_mask_samples_follow_the_stratification_of_the_sampler() {
+        //
+        // the left half is open, the right half lets half as much light through
+        let mask = ApertureMask::from_fn(64, |p| p.x < C_ZERO);
+        let half = ApertureMask::new(2, 1, vec![C_ONE, 0.5 as Real]);
+
+        //
+        // one sample per 1/n strip of the square: one per strip of the open half
+        const N: usize = 64;
+        let mut smp = SamplerBase::<MultiJitteredSamplingStrategy>::new(N as i32, None);
+        let mut strips = (0..N)
+            .map(|_| {
+                let p = mask.sample(&mut smp);
+                assert!(p.x < C_ZERO);
+                ((p.x + C_ONE) * N as Real) as usize
+            })
+            .collect::<Vec<_>>();
+        strips.sort_unstable();
+        assert_eq!(strips, (0..N).collect::<Vec<_>>());
+
+        let right = (0..N * 16)
+            .filter(|_| half.sample(&mut smp).x > C_ZERO)
+            .count() as Real
+            / (N * 16) as Real;
+        assert!(
+            (right - C_ONE / 3 as Real).abs() < 0.01 as Real,
+            "{}",
+            right
+        );
+
+        assert!(ApertureMask::load("no/such/mask.png").is_err());
+    }
+}
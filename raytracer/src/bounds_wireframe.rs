@@ -0,0 +1,166 @@
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    aabb3::Aabb,
+    block::Block,
+    bvh::BvhNode,
+    diffuse_light::DiffuseLight,
+    hittable::Hittable,
+    hittable_list::HittableList,
+    material::Material,
+    types::{Point, Real, C_HALF_ONE},
+};
+
+/// Boxes drawn as a wireframe over the scene, to look at how the objects are partitioned.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BoundsOverlay {
+    /// The bounding boxes of the scene's objects.
+    Objects,
+    /// The nodes of the scene's BVHs, the ones nested in other BVHs included, down to the
+    /// given depth (0 draws only the root boxes). Nothing for scenes without a BVH.
+    BvhNodes(u32),
+}
+
+/// Edge thickness, relative to the diagonal of all the boxes drawn.
+const EDGE_THICKNESS: Real = 0.002 as Real;
+
+/// The 12 edges of the box, as (start, end) corners. Edges along x come first, then the
+/// ones along y and z, every edge goes from the min to the max side of its axis.
+pub fn aabb_edges(bbox: &Aabb) -> [(Point, Point); 12] {
+    let corner = |x: bool, y: bool, z: bool| {
+        Point::new(
+            if x { bbox.max.x } else { bbox.min.x },
+            if y { bbox.max.y } else { bbox.min.y },
+            if z { bbox.max.z } else { bbox.min.z },
+        )
+    };
+
+    let mut edges = [(bbox.min, bbox.min); 12];
+    (0..3).for_each(|axis| {
+        (0..4).for_each(|i| {
+            //
+            // the other two axes pick the min or max side
+            let (a, b) = (i & 1 != 0, i & 2 != 0);
+            edges[axis * 4 + i] = match axis {
+                0 => (corner(false, a, b), corner(true, a, b)),
+                1 => (corner(a, false, b), corner(a, true, b)),
+                _ => (corner(a, b, false), corner(a, b, true)),
+            };
+        })
+    });
+    edges
+}
+
+/// Thin blocks along the edges of the boxes, `thickness` wide on the axes across the edge.
+pub fn edge_blocks(
+    boxes: &[Aabb],
+    thickness: Real,
+    mtl: Arc<dyn Material>,
+) -> Vec<Arc<dyn Hittable>> {
+    let half = Point::broadcast(thickness * C_HALF_ONE);
+
+    boxes
+        .iter()
+        .flat_map(|bbox| aabb_edges(bbox).to_vec())
+        .map(|(start, end)| {
+            Arc::new(Block::new(start - half, end + half, mtl.clone())) as Arc<dyn Hittable>
+        })
+        .collect()
+}
+
+/// Emissive wireframe of the boxes picked by `overlay`, for the objects of `world`.
+/// None if there are no boxes to draw.
+pub fn bounds_wireframe(
+    world: &HittableList,
+    overlay: BoundsOverlay,
+    time0: Real,
+    time1: Real,
+) -> Option<Arc<dyn Hittable>> {
+    let boxes = match overlay {
+        BoundsOverlay::Objects => world
+            .iter()
+            .filter_map(|obj| obj.bounding_box(time0, time1))
+            .collect::<Vec<_>>(),
+        BoundsOverlay::BvhNodes(max_depth) => {
+            let mut boxes = Vec::new();
+            world.node_boxes(max_depth, &mut boxes);
+            boxes
+        }
+    };
+
+    let extent = boxes
+        .iter()
+        .copied()
+        .reduce(|a, b| crate::aabb3::merge_aabbs(&a, &b))?;
+    let thickness = math::vec3::length(extent.max - extent.min) * EDGE_THICKNESS;
+
+    let mtl: Arc<dyn Material> = Arc::new(DiffuseLight::from((4 as Real, 2 as Real, 0.2 as Real)));
+    let mut edges = edge_blocks(&boxes, thickness, mtl);
+    Some(BvhNode::new(&mut edges, time0, time1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lambertian::Lambertian;
+
+    #[test]
+    fn test_single_box_has_12_edges_between_its_corners() {
+        let bbox = Aabb::new(
+            (-1 as Real, 2 as Real, 3 as Real),
+            (4 as Real, 5 as Real, 9 as Real),
+        );
+        let edges = aabb_edges(&bbox);
+
+        let is_corner = |p: Point| (0..3).all(|a| p[a] == bbox.min[a] || p[a] == bbox.max[a]);
+        edges.iter().enumerate().for_each(|(i, &(start, end))| {
+            assert!(is_corner(start) && is_corner(end));
+
+            //
+            // min to max along the edge's axis, the same on the other two
+            let axis = i / 4;
+            (0..3).for_each(|a| {
+                if a == axis {
+                    assert_eq!((start[a], end[a]), (bbox.min[a], bbox.max[a]));
+                } else {
+                    assert_eq!(start[a], end[a]);
+                }
+            });
+        });
+
+        //
+        // all different, every corner starts or ends 3 of them
+        (0..12).for_each(|i| {
+            (i + 1..12).for_each(|j| assert_ne!(edges[i], edges[j]));
+        });
+        (0..8).for_each(|c| {
+            let corner = Point::new(
+                if c & 1 != 0 { bbox.max.x } else { bbox.min.x },
+                if c & 2 != 0 { bbox.max.y } else { bbox.min.y },
+                if c & 4 != 0 { bbox.max.z } else { bbox.min.z },
+            );
+            let touching = edges
+                .iter()
+                .filter(|&&(start, end)| start == corner || end == corner)
+                .count();
+            assert_eq!(touching, 3);
+        });
+
+        //
+        // the blocks cover the edges and are as thin as asked across them
+        let mtl = Arc::new(Lambertian::new((0.5, 0.5, 0.5)));
+        let blocks = edge_blocks(&[bbox], 0.1 as Real, mtl);
+        assert_eq!(blocks.len(), 12);
+        blocks
+            .iter()
+            .zip(edges.iter())
+            .for_each(|(block, &(start, end))| {
+                let block_box = block.bounding_box(0 as Real, 1 as Real).unwrap();
+                assert!(block_box.contains(start) && block_box.contains(end));
+                let size = block_box.max - block_box.min;
+                assert_eq!((0..3).filter(|&a| size[a] < 0.11 as Real).count(), 2);
+            });
+    }
+}
@@ -2,18 +2,59 @@ use std::{cmp::Ordering, sync::Arc};
 
 use crate::{
     aabb3::Aabb,
-    hittable::Hittable,
-    types::{random_int, Real},
+    hittable::{HitRecord, HitStats, Hittable, PrimitiveId},
+    hittable_list::HittableList,
+    types::{random_int, Point, Ray, Real, C_PI},
 };
 
+//
+// children of an inner node are nodes, the ones of the bottom nodes are the objects
+enum BvhChild {
+    Node(Arc<BvhNode>),
+    Object(Arc<dyn Hittable>),
+}
+
+impl BvhChild {
+    fn hittable(&self) -> &dyn Hittable {
+        match self {
+            BvhChild::Node(node) => node.as_ref(),
+            BvhChild::Object(obj) => obj.as_ref(),
+        }
+    }
+}
+
 pub struct BvhNode {
-    left: Arc<dyn Hittable>,
-    right: Arc<dyn Hittable>,
+    left: BvhChild,
+    right: BvhChild,
     bbox: Aabb,
+    /// Bounding spheres of the children, for the ones where it is tighter than the box.
+    left_sphere: Option<(Point, Real)>,
+    right_sphere: Option<(Point, Real)>,
 }
 
 impl BvhNode {
+    /// Builds a BVH over the objects. Objects without a bounding box (e.g. infinite planes)
+    /// can't go in the hierarchy, they are kept in a list next to it and tested separately.
     pub fn new(l: &mut [Arc<dyn Hittable>], time0: Real, time1: Real) -> Arc<dyn Hittable> {
+        let (mut bounded, unbounded): (Vec<_>, Vec<_>) = l
+            .iter()
+            .cloned()
+            .partition(|obj| obj.bounding_box(time0, time1).is_some());
+
+        if unbounded.is_empty() {
+            return Self::build(l, time0, time1);
+        }
+
+        let mut world = HittableList::new();
+        if !bounded.is_empty() {
+            world.add(Self::build(&mut bounded, time0, time1));
+        }
+        unbounded.into_iter().for_each(|obj| world.add(obj));
+
+        Arc::new(world)
+    }
+
+    fn build(l: &mut [Arc<dyn Hittable>], time0: Real, time1: Real) -> Arc<BvhNode> {
         let cmp_axis = random_int(0, 2);
         let cmp_fn = match cmp_axis {
             0 => box_x_compare,
@@ -23,71 +64,406 @@ impl BvhNode {
         };
 
         let (left, right) = if l.len() == 1 {
-            (l[0].clone(), l[0].clone())
+            (
+                BvhChild::Object(l[0].clone()),
+                BvhChild::Object(l[0].clone()),
+            )
         } else if l.len() == 2 {
             if cmp_fn(&l[0], &l[1]) == Ordering::Less {
-                (l[0].clone(), l[1].clone())
+                (
+                    BvhChild::Object(l[0].clone()),
+                    BvhChild::Object(l[1].clone()),
+                )
             } else {
-                (l[1].clone(), l[0].clone())
+                (
+                    BvhChild::Object(l[1].clone()),
+                    BvhChild::Object(l[0].clone()),
+                )
             }
         } else {
             l.sort_by(cmp_fn);
             let mid = l.len() / 2;
 
-            let left = Self::new(&mut l[..mid], time0, time1);
-            let right = Self::new(&mut l[mid..], time0, time1);
+            let left = Self::build(&mut l[..mid], time0, time1);
+            let right = Self::build(&mut l[mid..], time0, time1);
 
-            (left as Arc<dyn Hittable>, right as Arc<dyn Hittable>)
+            (BvhChild::Node(left), BvhChild::Node(right))
         };
 
-        let bbox_left = left.bounding_box(time0, time1);
-        let bbox_right = right.bounding_box(time0, time1);
+        let bbox_left = left.hittable().bounding_box(time0, time1);
+        let bbox_right = right.hittable().bounding_box(time0, time1);
 
         if bbox_left.is_none() || bbox_right.is_none() {
             panic!("No bounding box in bvh_node constructor");
         }
 
+        let bbox_left = bbox_left.unwrap();
+        let bbox_right = bbox_right.unwrap();
+
         Arc::new(Self {
+            left_sphere: tighter_bounding_sphere(left.hittable(), &bbox_left),
+            right_sphere: tighter_bounding_sphere(right.hittable(), &bbox_right),
             left,
             right,
-            bbox: crate::aabb3::merge_aabbs(&bbox_left.unwrap(), &bbox_right.unwrap()),
+            bbox: crate::aabb3::merge_aabbs(&bbox_left, &bbox_right),
         })
     }
-}
 
-impl Hittable for BvhNode {
-    fn hit(
+    //
+    // hit_child tests a child for the [t_min, t_max] interval passed to it
+    fn closest_hit<F>(
         &self,
-        r: &crate::types::Ray,
-        t_min: crate::types::Real,
-        t_max: crate::types::Real,
-    ) -> Option<crate::hittable::HitRecord> {
+        r: &Ray,
+        t_min: Real,
+        t_max: Real,
+        mut hit_child: F,
+    ) -> Option<HitRecord>
+    where
+        F: FnMut(&dyn Hittable, Real, Real) -> Option<HitRecord>,
+    {
         if !self.bbox.hit(r, t_min, t_max) {
             return None;
         }
 
-        let hit_left = self.left.hit(r, t_min, t_max);
-        let hit_right = self.right.hit(
-            r,
-            t_min,
-            if let Some(hl) = hit_left.as_ref() {
-                hl.t
-            } else {
-                t_max
-            },
-        );
+        let hit_left = if misses_sphere(self.left_sphere, r, t_min, t_max) {
+            None
+        } else {
+            hit_child(self.left.hittable(), t_min, t_max)
+        };
+
+        let t_max = if let Some(hl) = hit_left.as_ref() {
+            hl.t
+        } else {
+            t_max
+        };
+        let hit_right = if misses_sphere(self.right_sphere, r, t_min, t_max) {
+            None
+        } else {
+            hit_child(self.right.hittable(), t_min, t_max)
+        };
 
         hit_left
             .into_iter()
             .chain(hit_right.into_iter())
             .reduce(|a, b| if a.t < b.t { a } else { b })
     }
+}
+
+impl Hittable for BvhNode {
+    fn hit(&self, r: &Ray, t_min: Real, t_max: Real) -> Option<HitRecord> {
+        self.hit_counted(r, t_min, t_max, &mut HitStats::default())
+    }
+
+    fn hit_counted(
+        &self,
+        r: &Ray,
+        t_min: Real,
+        t_max: Real,
+        stats: &mut HitStats,
+    ) -> Option<HitRecord> {
+        stats.node_visits += 1;
+        self.closest_hit(r, t_min, t_max, |child, t0, t1| {
+            child.hit_counted(r, t0, t1, stats)
+        })
+    }
+
+    fn hit_skipping(
+        &self,
+        r: &Ray,
+        t_min: Real,
+        t_max: Real,
+        skip: Option<PrimitiveId>,
+    ) -> Option<HitRecord> {
+        self.closest_hit(r, t_min, t_max, |child, t0, t1| {
+            child.hit_skipping(r, t0, t1, skip)
+        })
+    }
+
+    fn name(&self) -> Option<&str> {
+        Some("bvh")
+    }
+
+    fn node_boxes(&self, depth: u32, boxes: &mut Vec<Aabb>) {
+        boxes.push(self.bbox);
+        if depth == 0 {
+            return;
+        }
+
+        match (&self.left, &self.right) {
+            //
+            // a single object is both children of its node
+            (BvhChild::Object(left), BvhChild::Object(right)) if Arc::ptr_eq(left, right) => {
+                left.node_boxes(depth - 1, boxes)
+            }
+            (left, right) => {
+                left.hittable().node_boxes(depth - 1, boxes);
+                right.hittable().node_boxes(depth - 1, boxes);
+            }
+        }
+    }
 
     fn bounding_box(&self, _time0: crate::types::Real, _time1: crate::types::Real) -> Option<Aabb> {
         Some(self.bbox)
     }
 }
 
+#[derive(Copy, Clone, Debug)]
+struct FlatBvhNode {
+    bbox: Aabb,
+    /// Inner nodes: index of the second child, the first one is the next node.
+    /// Bottom nodes: index of the first object.
+    offset: u32,
+    /// Number of objects of a bottom node, 0 for inner nodes.
+    objects: u32,
+}
+
+/// The `BvhNode` tree flattened into an array, nodes in depth first order, with the objects
+/// of the bottom nodes stored contiguously. Traversal walks the array with a small stack
+/// instead of going from node to node through trait objects. Hits are the same as the
+/// ones of the tree.
+pub struct FlatBvh {
+    nodes: Vec<FlatBvhNode>,
+    objects: Vec<Arc<dyn Hittable>>,
+}
+
+impl FlatBvh {
+    /// Deepest tree that can be traversed, the builder splits the objects in halves.
+    const MAX_DEPTH: usize = 64;
+
+    /// Builds a BVH over the objects and flattens it. Like for the tree, objects without a
+    /// bounding box are kept in a list next to it.
+    pub fn build_world(l: &mut [Arc<dyn Hittable>], time0: Real, time1: Real) -> Arc<dyn Hittable> {
+        let (mut bounded, unbounded): (Vec<_>, Vec<_>) = l
+            .iter()
+            .cloned()
+            .partition(|obj| obj.bounding_box(time0, time1).is_some());
+
+        if unbounded.is_empty() && !bounded.is_empty() {
+            return Arc::new(Self::from_tree(&BvhNode::build(&mut bounded, time0, time1)));
+        }
+
+        let mut world = HittableList::new();
+        if !bounded.is_empty() {
+            world.add(Arc::new(Self::from_tree(&BvhNode::build(
+                &mut bounded,
+                time0,
+                time1,
+            ))));
+        }
+        unbounded.into_iter().for_each(|obj| world.add(obj));
+
+        Arc::new(world)
+    }
+
+    fn from_tree(root: &BvhNode) -> FlatBvh {
+        let mut flat = FlatBvh {
+            nodes: Vec::new(),
+            objects: Vec::new(),
+        };
+        flat.flatten(root);
+        flat
+    }
+
+    fn flatten(&mut self, node: &BvhNode) {
+        match (&node.left, &node.right) {
+            (BvhChild::Object(left), BvhChild::Object(right)) => {
+                let offset = self.objects.len() as u32;
+                self.objects.push(left.clone());
+                //
+                // a single object is both children of its node
+                if !Arc::ptr_eq(left, right) {
+                    self.objects.push(right.clone());
+                }
+
+                self.nodes.push(FlatBvhNode {
+                    bbox: node.bbox,
+                    offset,
+                    objects: self.objects.len() as u32 - offset,
+                });
+            }
+
+            (left, right) => {
+                let idx = self.nodes.len();
+                self.nodes.push(FlatBvhNode {
+                    bbox: node.bbox,
+                    offset: 0,
+                    objects: 0,
+                });
+
+                [left, right].iter().enumerate().for_each(|(i, child)| {
+                    if i == 1 {
+                        self.nodes[idx].offset = self.nodes.len() as u32;
+                    }
+
+                    match child {
+                        BvhChild::Node(child) => self.flatten(child),
+                        BvhChild::Object(_) => {
+                            panic!("Objects and nodes as children of a BVH node")
+                        }
+                    }
+                });
+            }
+        }
+    }
+
+    //
+    // hit_object tests an object for the [t_min, t_max] interval passed to it,
+    // visit_node is called for every node whose box is tested
+    fn closest_hit<F, V>(
+        &self,
+        r: &Ray,
+        t_min: Real,
+        t_max: Real,
+        mut hit_object: F,
+        mut visit_node: V,
+    ) -> Option<HitRecord>
+    where
+        F: FnMut(&dyn Hittable, Real, Real) -> Option<HitRecord>,
+        V: FnMut(),
+    {
+        let mut stack = [0u32; Self::MAX_DEPTH];
+        let mut stack_size = 1;
+        let mut closest: Option<HitRecord> = None;
+
+        while stack_size > 0 {
+            stack_size -= 1;
+            let idx = stack[stack_size] as usize;
+            let node = &self.nodes[idx];
+            let t_closest = closest.as_ref().map_or(t_max, |hit| hit.t);
+
+            visit_node();
+            if !node.bbox.hit(r, t_min, t_closest) {
+                continue;
+            }
+
+            if node.objects > 0 {
+                let first = node.offset as usize;
+                self.objects[first..first + node.objects as usize]
+                    .iter()
+                    .for_each(|obj| {
+                        let t_closest = closest.as_ref().map_or(t_max, |hit| hit.t);
+                        if let Some(hit) = hit_object(obj.as_ref(), t_min, t_closest) {
+                            closest = Some(hit);
+                        }
+                    });
+            } else {
+                //
+                // the first child is popped first
+                stack[stack_size] = node.offset;
+                stack[stack_size + 1] = idx as u32 + 1;
+                stack_size += 2;
+            }
+        }
+
+        closest
+    }
+}
+
+impl Hittable for FlatBvh {
+    fn hit(&self, r: &Ray, t_min: Real, t_max: Real) -> Option<HitRecord> {
+        self.hit_counted(r, t_min, t_max, &mut HitStats::default())
+    }
+
+    fn hit_counted(
+        &self,
+        r: &Ray,
+        t_min: Real,
+        t_max: Real,
+        stats: &mut HitStats,
+    ) -> Option<HitRecord> {
+        let visits = std::cell::Cell::new(0);
+        let hit = self.closest_hit(
+            r,
+            t_min,
+            t_max,
+            |obj, t0, t1| obj.hit_counted(r, t0, t1, stats),
+            || visits.set(visits.get() + 1),
+        );
+        stats.node_visits += visits.get();
+        hit
+    }
+
+    fn hit_skipping(
+        &self,
+        r: &Ray,
+        t_min: Real,
+        t_max: Real,
+        skip: Option<PrimitiveId>,
+    ) -> Option<HitRecord> {
+        self.closest_hit(
+            r,
+            t_min,
+            t_max,
+            |obj, t0, t1| obj.hit_skipping(r, t0, t1, skip),
+            || {},
+        )
+    }
+
+    fn name(&self) -> Option<&str> {
+        Some("flat bvh")
+    }
+
+    fn node_boxes(&self, depth: u32, boxes: &mut Vec<Aabb>) {
+        //
+        // (node, depth below the root), in the order of the tree
+        let mut stack = vec![(0usize, 0u32)];
+        while let Some((idx, node_depth)) = stack.pop() {
+            let node = &self.nodes[idx];
+            boxes.push(node.bbox);
+            if node_depth == depth {
+                continue;
+            }
+
+            if node.objects > 0 {
+                let first = node.offset as usize;
+                self.objects[first..first + node.objects as usize]
+                    .iter()
+                    .for_each(|obj| obj.node_boxes(depth - node_depth - 1, boxes));
+            } else {
+                stack.push((node.offset as usize, node_depth + 1));
+                stack.push((idx + 1, node_depth + 1));
+            }
+        }
+    }
+
+    fn bounding_box(&self, _time0: Real, _time1: Real) -> Option<Aabb> {
+        Some(self.nodes[0].bbox)
+    }
+}
+
+//
+// Only worth testing against the bounding sphere if it encloses less volume than the box.
+fn tighter_bounding_sphere(obj: &dyn Hittable, bbox: &Aabb) -> Option<(Point, Real)> {
+    obj.bounding_sphere().filter(|&(_, radius)| {
+        let extent = bbox.max - bbox.min;
+        let sphere_volume = 4 as Real / 3 as Real * C_PI * radius * radius * radius;
+        sphere_volume < extent.x * extent.y * extent.z
+    })
+}
+
+//
+// Cheap reject, true if the ray can't hit anything inside the sphere in [t_min, t_max].
+fn misses_sphere(sphere: Option<(Point, Real)>, r: &Ray, t_min: Real, t_max: Real) -> bool {
+    use math::vec3::{dot, length_squared};
+
+    sphere.map_or(false, |(center, radius)| {
+        let oc = r.origin - center;
+        let a = length_squared(r.direction);
+        let half_b = dot(oc, r.direction);
+        let c = length_squared(oc) - radius * radius;
+
+        let discriminant = half_b * half_b - a * c;
+        if discriminant < 0 as Real {
+            return true;
+        }
+
+        let sqrtd = discriminant.sqrt();
+        let t0 = (-half_b - sqrtd) / a;
+        let t1 = (-half_b + sqrtd) / a;
+        t1 < t_min || t0 > t_max
+    })
+}
+
 fn box_compare(a: &Arc<dyn Hittable>, b: &Arc<dyn Hittable>, axis: usize) -> Ordering {
     let box_a = a
         .bounding_box(0f32, 0f32)
@@ -110,3 +486,256 @@ fn box_y_compare(a: &Arc<dyn Hittable>, b: &Arc<dyn Hittable>) -> Ordering {
 fn box_z_compare(a: &Arc<dyn Hittable>, b: &Arc<dyn Hittable>) -> Ordering {
     box_compare(a, b, 2)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lambertian::Lambertian;
+    use crate::objects::plane::Plane;
+    use crate::objects::sphere::Sphere;
+    use crate::types::Vec3;
+
+    #[test]
+    fn test_bounding_sphere_rejects_rays_through_box_corners() {
+        let mtl = Arc::new(Lambertian::new((0.5 as Real, 0.5 as Real, 0.5 as Real)));
+        let unit_sphere = Sphere::new(Point::broadcast(0 as Real), 1 as Real, mtl.clone());
+
+        let (center, radius) = unit_sphere.bounding_sphere().unwrap();
+        assert_eq!(center, Point::broadcast(0 as Real));
+        assert_eq!(radius, 1 as Real);
+        let bbox = unit_sphere.bounding_box(0 as Real, 1 as Real).unwrap();
+        assert!(radius < math::vec3::length(bbox.max - bbox.center()));
+
+        let mut objects: Vec<Arc<dyn Hittable>> = vec![
+            Arc::new(unit_sphere),
+            Arc::new(Sphere::new(
+                Point::new(5 as Real, 0 as Real, 0 as Real),
+                1 as Real,
+                mtl,
+            )),
+        ];
+        let bvh = BvhNode::new(&mut objects, 0 as Real, 1 as Real);
+
+        //
+        // rays along -z through the corners of the unit sphere's box: they hit the box, miss the sphere
+        let mut stats = HitStats::default();
+        let mut box_hits = 0;
+        [(-0.9, -0.9), (0.9, -0.9), (-0.9, 0.9), (0.9, 0.9)]
+            .iter()
+            .for_each(|&(x, y)| {
+                let r = Ray::new(
+                    Point::new(x, y, 5 as Real),
+                    Vec3::new(0 as Real, 0 as Real, -1 as Real),
+                    0 as Real,
+                );
+                if bbox.hit(&r, 0.001 as Real, Real::MAX) {
+                    box_hits += 1;
+                }
+                assert!(bvh
+                    .hit_counted(&r, 0.001 as Real, Real::MAX, &mut stats)
+                    .is_none());
+            });
+
+        assert_eq!(box_hits, 4);
+        assert_eq!(stats.primitive_tests, 0);
+
+        //
+        // a ray through the middle still gets tested and hits
+        let r = Ray::new(
+            Point::new(0 as Real, 0 as Real, 5 as Real),
+            Vec3::new(0 as Real, 0 as Real, -1 as Real),
+            0 as Real,
+        );
+        let hit = bvh.hit_counted(&r, 0.001 as Real, Real::MAX, &mut stats);
+        assert!((hit.unwrap().t - 4 as Real).abs() < 1.0e-5);
+        assert_eq!(stats.primitive_tests, 1);
+    }
+
+    #[test]
+    fn test_unbounded_objects_are_kept_next_to_the_bvh() {
+        let mtl = Arc::new(Lambertian::new((0.5 as Real, 0.5 as Real, 0.5 as Real)));
+        let up = Vec3::new(0 as Real, 1 as Real, 0 as Real);
+
+        let mut objects: Vec<Arc<dyn Hittable>> = vec![
+            Arc::new(Sphere::new(
+                Point::new(0 as Real, 1 as Real, 0 as Real),
+                1 as Real,
+                mtl.clone(),
+            )),
+            Arc::new(Plane::new(Point::broadcast(0 as Real), up, mtl.clone())),
+            Arc::new(Sphere::new(
+                Point::new(5 as Real, 1 as Real, 0 as Real),
+                1 as Real,
+                mtl,
+            )),
+        ];
+        let world = BvhNode::new(&mut objects, 0 as Real, 1 as Real);
+        assert!(world.bounding_box(0 as Real, 1 as Real).is_none());
+
+        let down = |x: Real| Ray::new(Point::new(x, 5 as Real, 0 as Real), -up, 0 as Real);
+
+        //
+        // top of the spheres, the plane in between them
+        [
+            (0 as Real, 3 as Real),
+            (5 as Real, 3 as Real),
+            (2.5 as Real, 5 as Real),
+        ]
+        .iter()
+        .for_each(|&(x, t)| {
+            let hit = world.hit(&down(x), 0.001 as Real, Real::MAX).unwrap();
+            assert!((hit.t - t).abs() < 1.0e-5);
+        });
+    }
+
+    fn random_spheres(count: usize) -> Vec<Arc<dyn Hittable>> {
+        use crate::types::random_real_range;
+
+        let mtl = Arc::new(Lambertian::new((0.5 as Real, 0.5 as Real, 0.5 as Real)));
+        (0..count)
+            .map(|_| {
+                let center = Point::new(
+                    random_real_range(0 as Real, 10 as Real),
+                    random_real_range(0 as Real, 10 as Real),
+                    random_real_range(0 as Real, 10 as Real),
+                );
+                Arc::new(Sphere::new(
+                    center,
+                    random_real_range(0.1, 0.3),
+                    mtl.clone(),
+                )) as Arc<dyn Hittable>
+            })
+            .collect()
+    }
+
+    fn random_ray() -> Ray {
+        use crate::types::{random_real_range, random_unit_vector};
+
+        let center = Point::broadcast(5 as Real);
+        let origin = center + random_unit_vector() * 20 as Real;
+        let target = center + random_unit_vector() * random_real_range(0 as Real, 5 as Real);
+        Ray::new(origin, target - origin, 0 as Real)
+    }
+
+    #[test]
+    fn test_flat_bvh_hits_like_the_tree() {
+        let mut objects = random_spheres(1000);
+        let tree = BvhNode::build(&mut objects, 0 as Real, 1 as Real);
+        let flat = FlatBvh::from_tree(&tree);
+
+        //
+        // every object once, in contiguous runs of at most 2
+        assert_eq!(flat.objects.len(), 1000);
+        assert!(flat.nodes.iter().all(|node| node.objects <= 2));
+        let tree_box = tree.bounding_box(0 as Real, 1 as Real).unwrap();
+        let flat_box = flat.bounding_box(0 as Real, 1 as Real).unwrap();
+        assert_eq!((tree_box.min, tree_box.max), (flat_box.min, flat_box.max));
+
+        let mut hits = 0;
+        (0..2000).for_each(|_| {
+            let r = random_ray();
+            match (
+                tree.hit(&r, 0.001 as Real, Real::MAX),
+                flat.hit(&r, 0.001 as Real, Real::MAX),
+            ) {
+                (Some(a), Some(b)) => {
+                    hits += 1;
+                    assert_eq!(a.t, b.t);
+                    assert_eq!(a.p, b.p);
+                    assert_eq!(a.normal, b.normal);
+                }
+                (a, b) => assert_eq!(a.is_some(), b.is_some()),
+            }
+        });
+        assert!(hits > 500);
+
+        //
+        // unbounded objects are kept next to it
+        let up = Vec3::new(0 as Real, 1 as Real, 0 as Real);
+        let mut with_plane = random_spheres(10);
+        with_plane.push(Arc::new(Plane::new(
+            Point::new(0 as Real, -1 as Real, 0 as Real),
+            up,
+            Arc::new(Lambertian::new((0.5 as Real, 0.5 as Real, 0.5 as Real))),
+        )));
+        let world = FlatBvh::build_world(&mut with_plane, 0 as Real, 1 as Real);
+        assert!(world.bounding_box(0 as Real, 1 as Real).is_none());
+        let down = Ray::new(Point::new(-5 as Real, 5 as Real, 0 as Real), -up, 0 as Real);
+        let hit = world.hit(&down, 0.001 as Real, Real::MAX).unwrap();
+        assert!((hit.t - 6 as Real).abs() < 1.0e-5);
+    }
+
+    #[test]
+    fn test_node_boxes_walk_the_built_hierarchy() {
+        let mut objects = random_spheres(100);
+        let tree = BvhNode::build(&mut objects, 0 as Real, 1 as Real);
+        let flat = FlatBvh::from_tree(&tree);
+
+        let boxes = |obj: &dyn Hittable, depth: u32| {
+            let mut boxes = Vec::new();
+            obj.node_boxes(depth, &mut boxes);
+            boxes
+                .iter()
+                .map(|bbox| (bbox.min, bbox.max))
+                .collect::<Vec<_>>()
+        };
+
+        //
+        // the root, its children, down to every node of the tree
+        assert_eq!(boxes(tree.as_ref(), 0).len(), 1);
+        assert_eq!(boxes(tree.as_ref(), 1).len(), 3);
+        assert_eq!(boxes(tree.as_ref(), 64).len(), flat.nodes.len());
+        [0, 1, 3, 64].iter().for_each(|&depth| {
+            assert_eq!(boxes(tree.as_ref(), depth), boxes(&flat, depth));
+        });
+
+        //
+        // a BVH among the objects of another one continues below its bottom node, lists
+        // pass the depth through
+        let nested: Arc<dyn Hittable> = Arc::new(FlatBvh::from_tree(&tree));
+        let mut outer_objects = random_spheres(1);
+        outer_objects.push(nested.clone());
+        let outer = BvhNode::build(&mut outer_objects, 0 as Real, 1 as Real);
+        assert_eq!(boxes(outer.as_ref(), 0).len(), 1);
+        assert_eq!(
+            boxes(outer.as_ref(), 1),
+            [boxes(outer.as_ref(), 0), boxes(nested.as_ref(), 0)].concat()
+        );
+        assert_eq!(
+            boxes(outer.as_ref(), 65).len(),
+            1 + boxes(nested.as_ref(), 64).len()
+        );
+
+        let mut world = HittableList::new();
+        world.add(tree.clone());
+        world.add(random_spheres(1).remove(0));
+        assert_eq!(boxes(&world, 2), boxes(tree.as_ref(), 2));
+    }
+
+    //
+    // cargo test --release -p raytracer flat_bvh_is_faster -- --ignored
+    #[test]
+    #[ignore]
+    fn test_flat_bvh_is_faster_than_the_tree() {
+        let mut objects = random_spheres(1000);
+        let tree = BvhNode::build(&mut objects, 0 as Real, 1 as Real);
+        let flat = FlatBvh::from_tree(&tree);
+        let rays = (0..200_000).map(|_| random_ray()).collect::<Vec<_>>();
+
+        let time = |bvh: &dyn Hittable| {
+            let started = std::time::Instant::now();
+            let hits = rays
+                .iter()
+                .filter(|r| bvh.hit(r, 0.001 as Real, Real::MAX).is_some())
+                .count();
+            (started.elapsed(), hits)
+        };
+
+        let (tree_time, tree_hits) = time(tree.as_ref());
+        let (flat_time, flat_hits) = time(&flat);
+        println!("tree {:?}, flat {:?}", tree_time, flat_time);
+
+        assert_eq!(tree_hits, flat_hits);
+        assert!(flat_time < tree_time);
+    }
+}
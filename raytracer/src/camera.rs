@@ -1,27 +1,35 @@
 use std::sync::Arc;
 
 use crate::{
-    hittable::Hittable,
+    aperture::ApertureMask,
+    hittable::{HitRecord, HitStats, Hittable, PrimitiveId},
     hittable_list::HittableList,
+    integrator::Integrator,
     material::ScatterRecord,
     pdf::{HittablePdf, MixturePdf, Pdf},
+    render_stats::RenderStats,
     sampling::{SampleStrategy, SamplerBase},
     types::{
-        random_real, Color, Point, Ray, Real, Vec2, Vec3, C_HALF_ONE, C_INFINITY, C_ONE, C_PI,
-        C_TWO, C_ZERO,
+        random_real, Color, Point, Ray, RayDifferentials, Real, SeededRandom, Vec2, Vec3,
+        C_HALF_ONE, C_INFINITY, C_ONE, C_PI, C_TWO, C_ZERO,
     },
     RaytracerParams,
 };
 
-#[derive(Copy, Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[derive(Copy, Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
 pub enum Projection {
+    #[default]
     Perspective,
     Orthographic,
     FishEye,
     SphericalPanoramic,
+    /// Horizontal angle spans [-lambda_max, +lambda_max], the vertical coordinate is the
+    /// height on a unit cylinder (vertical field of view at the center). Straight
+    /// vertical lines stay straight, with less distortion than the fisheye.
+    Cylindrical,
 }
 
-#[derive(Copy, Clone)]
+#[derive(Clone)]
 pub struct Camera {
     origin: Point,
     lower_left_corner: Point,
@@ -31,8 +39,14 @@ pub struct Camera {
     v: Vec3,
     w: Vec3,
     lens_radius: Real,
+    focus_dist: Real,
     time0: Real,
     time1: Real,
+    aspect_ratio: Real,
+    /// Radial distortion coefficients (k1, k2) of the perspective projection.
+    lens_distortion: [Real; 2],
+    /// Shape of the lens opening, the uniform disk if None.
+    aperture_mask: Option<Arc<ApertureMask>>,
 }
 
 impl Camera {
@@ -71,44 +85,182 @@ impl Camera {
             horizontal: focus_dist * u * half_width * 2 as Real,
             vertical: focus_dist * v * half_height * 2 as Real,
             lens_radius: aperture * 0.5 as Real,
+            focus_dist,
             u,
             v,
             w,
             time0,
             time1,
+            aspect_ratio,
+            lens_distortion: [C_ZERO, C_ZERO],
+            aperture_mask: None,
         }
     }
 
+    /// Radial lens distortion for the perspective projection, the image plane point at
+    /// distance r from the center (half the image height is 1) is moved to
+    /// r * (1 + k1 * r^2 + k2 * r^4). Positive coefficients give barrel distortion,
+    /// negative ones pincushion.
+    pub fn with_lens_distortion(self, k1: Real, k2: Real) -> Camera {
+        Camera {
+            lens_distortion: [k1, k2],
+            ..self
+        }
+    }
+
+    /// Lens samples drawn from `mask` instead of the uniform disk, for shaped bokeh.
+    pub fn with_aperture_mask(self, aperture_mask: Option<Arc<ApertureMask>>) -> Camera {
+        Camera {
+            aperture_mask,
+            ..self
+        }
+    }
+
+    //
+    // offset of a lens sample from the lens center
+    fn lens_offset<S: SampleStrategy>(&self, smp: &mut SamplerBase<S>) -> Vec3 {
+        let rd = self.lens_radius
+            * match self.aperture_mask.as_ref() {
+                Some(mask) => mask.sample(smp),
+                None => smp.sample_unit_disk(),
+            };
+
+        self.u * rd.x + self.v * rd.y
+    }
+
+    //
+    // image plane coordinates in [0, 1] after the radial distortion
+    fn distort(&self, s: Real, t: Real) -> (Real, Real) {
+        let [k1, k2] = self.lens_distortion;
+        if k1 == C_ZERO && k2 == C_ZERO {
+            return (s, t);
+        }
+
+        let x = (C_TWO * s - C_ONE) * self.aspect_ratio;
+        let y = C_TWO * t - C_ONE;
+        let r2 = x * x + y * y;
+        let scale = C_ONE + k1 * r2 + k2 * r2 * r2;
+
+        (
+            C_HALF_ONE + (s - C_HALF_ONE) * scale,
+            C_HALF_ONE + (t - C_HALF_ONE) * scale,
+        )
+    }
+
+    /// Distance from the lens to the plane in focus.
+    pub fn focus_dist(&self) -> Real {
+        self.focus_dist
+    }
+
+    /// Center of the lens.
+    pub fn origin(&self) -> Point {
+        self.origin
+    }
+
+    /// Unit vector pointing to the right of the view.
+    pub fn u(&self) -> Vec3 {
+        self.u
+    }
+
+    /// Distance to the object in the center of the view, None if the ray from `lookfrom`
+    /// towards `lookat` misses everything in `world`.
+    pub fn autofocus_distance(
+        lookfrom: Point,
+        lookat: Point,
+        world: &dyn Hittable,
+    ) -> Option<Real> {
+        let r = Ray::new(lookfrom, lookat - lookfrom, C_ZERO);
+
+        world
+            .hit(&r, 0.001 as Real, C_INFINITY)
+            .map(|hit| hit.t * math::vec3::length(r.direction))
+    }
+
+    /// Random time inside the shutter interval.
+    fn random_time(&self) -> Real {
+        self.time0 + random_real() * (self.time1 - self.time0)
+    }
+
     pub fn get_ray_perspective<S: SampleStrategy>(
         &self,
         s: Real,
         t: Real,
         smp: &mut SamplerBase<S>,
     ) -> Ray {
-        let rd = self.lens_radius * smp.sample_unit_disk();
-        let offset = self.u * rd.x + self.v * rd.y;
+        let offset = self.lens_offset(smp);
 
         Ray::new(
             self.origin + offset,
-            self.lower_left_corner + s * self.horizontal + t * self.vertical - self.origin - offset,
-            random_real(),
+            self.perspective_direction(s, t, offset),
+            self.random_time(),
         )
     }
 
+    //
+    // from the lens point at `offset` to the image plane point (s, t)
+    fn perspective_direction(&self, s: Real, t: Real, offset: Vec3) -> Vec3 {
+        let (s, t) = self.distort(s, t);
+        self.lower_left_corner + s * self.horizontal + t * self.vertical - self.origin - offset
+    }
+
+    /// Same as `get_ray_perspective`, with the differentials for the image plane steps
+    /// `ds`, `dt` from one pixel to the next one in x and y. The differential rays leave
+    /// from the same lens point.
+    pub fn get_ray_perspective_differential<S: SampleStrategy>(
+        &self,
+        s: Real,
+        t: Real,
+        (ds, dt): (Real, Real),
+        smp: &mut SamplerBase<S>,
+    ) -> Ray {
+        let offset = self.lens_offset(smp);
+        let origin = self.origin + offset;
+
+        Ray::new(
+            origin,
+            self.perspective_direction(s, t, offset),
+            self.random_time(),
+        )
+        .with_differentials(RayDifferentials {
+            rx_origin: origin,
+            rx_direction: self.perspective_direction(s + ds, t, offset),
+            ry_origin: origin,
+            ry_direction: self.perspective_direction(s, t + dt, offset),
+        })
+    }
+
     pub fn get_ray_ortho<S: SampleStrategy>(
         &self,
         s: Real,
         t: Real,
         smp: &mut SamplerBase<S>,
     ) -> Ray {
-        let rd = self.lens_radius * smp.sample_unit_disk();
-        let offset = self.u * rd.x + self.v * rd.y;
+        let offset = self.lens_offset(smp);
 
-        Ray {
-            origin: self.lower_left_corner + s * self.horizontal + t * self.vertical + offset,
-            direction: -self.w,
-            time: random_real(),
-        }
+        Ray::new(
+            self.lower_left_corner + s * self.horizontal + t * self.vertical + offset,
+            -self.w,
+            self.random_time(),
+        )
+    }
+
+    /// Same as `get_ray_ortho`, with the differentials for the image plane steps `ds`, `dt`
+    /// from one pixel to the next one in x and y.
+    pub fn get_ray_ortho_differential<S: SampleStrategy>(
+        &self,
+        s: Real,
+        t: Real,
+        (ds, dt): (Real, Real),
+        smp: &mut SamplerBase<S>,
+    ) -> Ray {
+        let r = self.get_ray_ortho(s, t, smp);
+
+        r.with_differentials(RayDifferentials {
+            rx_origin: r.origin + ds * self.horizontal,
+            rx_direction: r.direction,
+            ry_origin: r.origin + dt * self.vertical,
+            ry_direction: r.direction,
+        })
     }
 
     fn get_ray_fisheye<S: SampleStrategy>(
@@ -136,12 +288,11 @@ impl Camera {
                 y: sin_alpha,
             } = pn / r;
 
-            Some(Ray {
-                origin: self.origin,
-                direction: sin_psi * cos_alpha * self.u + sin_psi * sin_alpha * self.v
-                    - cos_psi * self.w,
-                time: random_real(),
-            })
+            Some(Ray::new(
+                self.origin,
+                sin_psi * cos_alpha * self.u + sin_psi * sin_alpha * self.v - cos_psi * self.w,
+                self.random_time(),
+            ))
         } else {
             None
         }
@@ -174,15 +325,99 @@ impl Camera {
         let (sin_theta, cos_theta) = theta.sin_cos();
         let (sin_phi, cos_phi) = phi.sin_cos();
 
-        Ray {
-            origin: self.origin,
-            direction: sin_theta * sin_phi * self.u
-                + cos_theta * self.v
-                + sin_theta * cos_phi * self.w,
-            time: random_real(),
+        Ray::new(
+            self.origin,
+            sin_theta * sin_phi * self.u + cos_theta * self.v + sin_theta * cos_phi * self.w,
+            self.random_time(),
+        )
+    }
+
+    fn get_ray_cylindrical<S: SampleStrategy>(
+        &self,
+        params: &RaytracerParams,
+        x: Real,
+        y: Real,
+        _smp: &mut SamplerBase<S>,
+    ) -> Option<Ray> {
+        use crate::types::degrees_to_radians;
+
+        //
+        // transform sampled point to [-1, +1]x[-1, +1]
+        let pn = Vec2 {
+            x: C_TWO * x - C_ONE,
+            y: C_TWO * y - C_ONE,
+        };
+
+        //
+        // azimuth past +/- pi would wrap around and see the same directions twice
+        let phi = pn.x * params.lambda_max;
+        if phi.abs() > C_PI {
+            return None;
+        }
+
+        let half_height = (degrees_to_radians(params.vertical_fov) * C_HALF_ONE).tan();
+        let (sin_phi, cos_phi) = phi.sin_cos();
+
+        Some(Ray::new(
+            self.origin,
+            sin_phi * self.u + pn.y * half_height * self.v - cos_phi * self.w,
+            self.random_time(),
+        ))
+    }
+
+    /// Ray through the image plane point (u, v) for the projection of `params`, None if
+    /// the point is outside of the projection's image (fisheye out of its disk, cylindrical
+    /// wrapping past +/- pi). Perspective and orthographic rays carry the differentials of
+    /// one pixel step of the image size in `params`.
+    pub fn get_ray<S: SampleStrategy>(
+        &self,
+        params: &RaytracerParams,
+        u: Real,
+        v: Real,
+        smp: &mut SamplerBase<S>,
+    ) -> Option<Ray> {
+        //
+        // image plane steps to the next pixel in x and y
+        let pixel_step = (
+            1 as Real / (params.image_width - 1) as Real,
+            -1 as Real / (params.image_height - 1) as Real,
+        );
+
+        match params.projection {
+            Projection::Perspective => {
+                Some(self.get_ray_perspective_differential(u, v, pixel_step, smp))
+            }
+            Projection::Orthographic => {
+                Some(self.get_ray_ortho_differential(u, v, pixel_step, smp))
+            }
+            Projection::FishEye => self.get_ray_fisheye(params, u, v, smp),
+            Projection::SphericalPanoramic => {
+                Some(self.get_ray_spherical_panoramic(params, u, v, smp))
+            }
+            Projection::Cylindrical => self.get_ray_cylindrical(params, u, v, smp),
         }
     }
 
+    //
+    // seeded renders draw every pixel from its own stream, the same whichever worker
+    // traces it and whatever it traced before
+    fn start_pixel<S: SampleStrategy>(
+        &self,
+        x: i32,
+        y: i32,
+        params: &RaytracerParams,
+        s: &mut SamplerBase<S>,
+    ) -> SeededRandom {
+        let pixel_seed = params
+            .seed
+            .map(|seed| crate::types::pixel_seed(x, y, self.time0, seed));
+        let seeded = crate::types::seed_random(pixel_seed);
+        if pixel_seed.is_some() {
+            s.restart();
+        }
+        seeded
+    }
+
     pub fn raytrace_pixel<S: SampleStrategy>(
         &self,
         x: i32,
@@ -190,121 +425,927 @@ impl Camera {
         params: &RaytracerParams,
         world: &Arc<HittableList>,
         lights: &Arc<HittableList>,
+        integrator: &dyn Integrator,
         s: &mut SamplerBase<S>,
     ) -> Color {
+        let _seeded = self.start_pixel(x, y, params, s);
+
         (0..params.samples_per_pixel).fold(Color::broadcast(0 as Real), |color, _| -> Color {
             let off = s.sample_unit_square();
             let u = (x as Real + off.x) / (params.image_width - 1) as Real;
             let v = 1 as Real - (y as Real + off.y) / (params.image_height - 1) as Real;
 
-            match params.projection {
-                Projection::Perspective => {
-                    let r = self.get_ray_perspective(u, v, s);
-                    color
-                        + Self::ray_color(
-                            &r,
-                            params.background.into(),
-                            &world,
-                            lights.clone(),
-                            params.max_ray_depth,
-                        )
-                }
-                Projection::Orthographic => {
-                    let r = self.get_ray_ortho(u, v, s);
-                    color
-                        + Self::ray_color(
-                            &r,
-                            params.background.into(),
-                            &world,
-                            lights.clone(),
-                            params.max_ray_depth,
-                        )
-                }
-                Projection::FishEye => {
-                    if let Some(ray) = self.get_ray_fisheye(params, u, v, s) {
-                        color
-                            + Self::ray_color(
-                                &ray,
-                                params.background.into(),
-                                &world,
-                                lights.clone(),
-                                params.max_ray_depth,
-                            )
-                    } else {
-                        color
-                    }
-                }
-
-                Projection::SphericalPanoramic => {
-                    let r = self.get_ray_spherical_panoramic(params, u, v, s);
-                    color
-                        + Self::ray_color(
-                            &r,
-                            params.background.into(),
-                            &world,
-                            lights.clone(),
-                            params.max_ray_depth,
-                        )
-                }
+            match self.get_ray(params, u, v, s) {
+                Some(r) => color + Self::finite_channels(integrator.radiance(&r, world, lights)),
+                None => color,
             }
         })
     }
 
-    fn ray_color(
+    //
+    // a NaN or infinite channel of a sample (a degenerate pdf somewhere on its path) adds
+    // nothing to the pixel, the other channels and samples still count
+    fn finite_channels(c: Color) -> Color {
+        //
+        // the sum is finite only when every channel is, the usual sample costs one check
+        if math::vec3::hsum(Vec3::new(c.r, c.g, c.b)).is_finite() {
+            return c;
+        }
+
+        let finite = |x: Real| if x.is_finite() { x } else { C_ZERO };
+        Color {
+            r: finite(c.r),
+            g: finite(c.g),
+            b: finite(c.b),
+            ..c
+        }
+    }
+
+    /// BVH work done by the primary ray through the center of pixel (x, y).
+    pub fn primary_hit_stats<S: SampleStrategy>(
+        &self,
+        x: i32,
+        y: i32,
+        params: &RaytracerParams,
+        world: &HittableList,
+        s: &mut SamplerBase<S>,
+    ) -> HitStats {
+        let u = (x as Real + C_HALF_ONE) / (params.image_width - 1) as Real;
+        let v = 1 as Real - (y as Real + C_HALF_ONE) / (params.image_height - 1) as Real;
+        let _seeded = self.start_pixel(x, y, params, s);
+
+        let mut stats = HitStats::default();
+        if let Some(r) = self.get_ray(params, u, v, s) {
+            world.hit_counted(&r, 0.001 as Real, C_INFINITY, &mut stats);
+        }
+
+        stats
+    }
+
+    fn channel_mask(channel: usize) -> Color {
+        match channel {
+            0 => Color::new(C_ONE, C_ZERO, C_ZERO),
+            1 => Color::new(C_ZERO, C_ONE, C_ZERO),
+            _ => Color::new(C_ZERO, C_ZERO, C_ONE),
+        }
+    }
+
+    pub(crate) fn clamp_pdf(pdf_val: Real) -> Real {
+        if pdf_val.abs() < 1.0E-5 {
+            if pdf_val.is_sign_positive() {
+                1.0E-4
+            } else {
+                -1.0E-4
+            }
+        } else {
+            pdf_val
+        }
+    }
+
+    /// Pdf the direction scattered at `p` is drawn from: the material's `pdf` mixed with
+    /// the pdf of the lights. Lights turning their back to the hit point can't light it,
+    /// with no light left only the material is sampled.
+    pub(crate) fn light_mixture_pdf(
+        lights: &Arc<HittableList>,
+        p: Point,
+        pdf: Arc<dyn Pdf>,
+    ) -> Arc<dyn Pdf> {
+        if lights
+            .objects()
+            .iter()
+            .all(|light| light.faces(p) == Some(false))
+        {
+            return pdf;
+        }
+
+        Arc::new(MixturePdf::new(
+            Arc::new(HittablePdf {
+                obj: lights.clone(),
+                origin: p,
+            }),
+            pdf,
+        ))
+    }
+
+    pub(crate) fn ray_color(
         r: &Ray,
-        background: Color,
+        opts: &TraceOptions,
         world: &HittableList,
-        lights: Arc<dyn Hittable>,
+        lights: &Arc<HittableList>,
         depth: i32,
+        channel: Option<usize>,
+        skip: Option<PrimitiveId>,
     ) -> Color {
         if depth <= 0 {
+            opts.record_path_end(depth);
             return Color::broadcast(C_ZERO);
         }
 
-        if let Some(rec) = world.hit(r, 0.001 as Real, C_INFINITY) {
+        opts.record_ray();
+
+        //
+        // skip is the primitive the ray starts on, so it can't hit it again
+        // because of rounding errors, even past the epsilon
+        if let Some(rec) = world.hit_skipping(r, 0.001 as Real, C_INFINITY, skip) {
+            let rec = HitRecord {
+                walk_steps: opts.walk_steps,
+                ..rec
+            };
+            let scatter = match channel {
+                Some(c) => rec.mtl.scatter_channel(r, &rec, c),
+                None if opts.per_channel && rec.mtl.is_dispersive() => {
+                    //
+                    // split the path, each channel continues on its own and only
+                    // contributes to its own component of the final color
+                    return (0..3).fold(Color::broadcast(C_ZERO), |color, c| {
+                        color
+                            + Self::channel_mask(c)
+                                * Self::ray_color(r, opts, world, lights, depth, Some(c), skip)
+                    });
+                }
+                None => rec.mtl.scatter(r, &rec),
+            };
+
             let emitted = rec.mtl.emitted(r, &rec, rec.u, rec.v, rec.p);
-            if let Some(scatter) = rec.mtl.scatter(r, &rec) {
+            if let Some(scatter) = scatter {
                 return match scatter {
                     ScatterRecord::SpecularRec { ray, attenuation } => {
-                        attenuation * Self::ray_color(&ray, background, world, lights, depth - 1)
+                        let opts = &TraceOptions {
+                            walk_steps: 0,
+                            ..*opts
+                        };
+                        let skip = rec.skip_for(ray.direction);
+                        attenuation
+                            * Self::ray_color(&ray, opts, world, lights, depth - 1, channel, skip)
+                    }
+                    ScatterRecord::WalkRec {
+                        ray,
+                        attenuation,
+                        walk_steps,
+                    } => {
+                        let opts = &TraceOptions {
+                            walk_steps,
+                            ..*opts
+                        };
+                        let skip = rec.skip_for(ray.direction);
+                        attenuation
+                            * Self::ray_color(&ray, opts, world, lights, depth - 1, channel, skip)
                     }
                     ScatterRecord::PdfRec { pdf, attenuation } => {
-                        let light_pdf = HittablePdf {
-                            obj: lights.clone(),
-                            origin: rec.p,
+                        let opts = &TraceOptions {
+                            after_diffuse: true,
+                            walk_steps: 0,
+                            ..*opts
+                        };
+                        let mixed_pdf = Self::light_mixture_pdf(lights, rec.p, pdf.clone());
+                        let facing_lights = || {
+                            lights
+                                .objects()
+                                .iter()
+                                .filter(|light| light.faces(rec.p) != Some(false))
                         };
 
-                        let mixed_pdf = MixturePdf::new(Arc::new(light_pdf), pdf);
-                        let scattered_ray = Ray::new(rec.p, mixed_pdf.generate(), r.time);
-                        let pdf_val = mixed_pdf.value(scattered_ray.direction);
-                        let pdf_val = if pdf_val.abs() < 1.0E-5 {
-                            if pdf_val.is_sign_positive() {
-                                1.0E-4
-                            } else {
-                                -1.0E-4
-                            }
+                        let facing_count = if opts.first_hit_light_samples > 0 {
+                            facing_lights().count()
                         } else {
-                            pdf_val
+                            0
                         };
+                        if facing_count > 0 {
+                            //
+                            // sample every light explicitly, the directions are weighted by the
+                            // pdf of the full mixture so the MIS weighting stays the same.
+                            // Only done at the first diffuse hit (the split paths go on with
+                            // no explicit samples), splitting again at every bounce would
+                            // grow the paths exponentially with the depth.
+                            let split_opts = TraceOptions {
+                                first_hit_light_samples: 0,
+                                ..*opts
+                            };
+
+                            let total_samples = facing_count as i32 * opts.first_hit_light_samples;
+                            let radiance = facing_lights()
+                                .flat_map(|light| {
+                                    (0..opts.first_hit_light_samples).map(move |_| light.clone())
+                                })
+                                .fold(Color::broadcast(C_ZERO), |color, light| {
+                                    let single_light_pdf = MixturePdf::new(
+                                        Arc::new(HittablePdf {
+                                            obj: light,
+                                            origin: rec.p,
+                                        }),
+                                        pdf.clone(),
+                                    );
+
+                                    let scattered_ray =
+                                        Ray::new(rec.p, single_light_pdf.generate(), r.time);
+                                    let pdf_val =
+                                        Self::clamp_pdf(mixed_pdf.value(scattered_ray.direction));
+
+                                    color
+                                        + rec.mtl.scattering(r, &rec, &scattered_ray, attenuation)
+                                            * Self::ray_color(
+                                                &scattered_ray,
+                                                &split_opts,
+                                                world,
+                                                lights,
+                                                depth - 1,
+                                                channel,
+                                                rec.skip_for(scattered_ray.direction),
+                                            )
+                                            / pdf_val
+                                });
+
+                            return emitted + radiance * (C_ONE / total_samples as Real);
+                        }
+
+                        let scattered_ray = Ray::new(rec.p, mixed_pdf.generate(), r.time);
+                        let pdf_val = Self::clamp_pdf(mixed_pdf.value(scattered_ray.direction));
 
                         emitted
-                            + attenuation
-                                * rec.mtl.scattering_pdf(r, &rec, &scattered_ray)
+                            + rec.mtl.scattering(r, &rec, &scattered_ray, attenuation)
                                 * Self::ray_color(
                                     &scattered_ray,
-                                    background,
+                                    opts,
                                     world,
                                     lights,
                                     depth - 1,
+                                    channel,
+                                    rec.skip_for(scattered_ray.direction),
                                 )
                                 / pdf_val
                     }
                 };
             } else {
+                opts.record_path_end(depth);
                 return emitted;
             }
         } else {
-            return background;
+            opts.record_path_end(depth);
+
+            //
+            // only diffusely scattered paths pick up the ambient term, the background seen
+            // by the camera, in mirrors or through glass stays as configured
+            return opts.miss_color();
         }
     }
 }
+
+/// Per frame settings, constant along a path except for `after_diffuse` and `walk_steps`.
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct TraceOptions<'a> {
+    pub(crate) background: Color,
+    pub(crate) ambient: Color,
+    /// True once the path has scattered off a diffuse (`PdfRec`) surface.
+    pub(crate) after_diffuse: bool,
+    /// Steps of the random walk the ray is on, see `ScatterRecord::WalkRec`.
+    pub(crate) walk_steps: u32,
+    pub(crate) max_depth: i32,
+    pub(crate) per_channel: bool,
+    pub(crate) first_hit_light_samples: i32,
+    pub(crate) stats: Option<&'a RenderStats>,
+}
+
+impl<'a> TraceOptions<'a> {
+    pub(crate) fn new(params: &RaytracerParams, stats: Option<&'a RenderStats>) -> Self {
+        Self {
+            background: params.background.into(),
+            ambient: params.ambient.into(),
+            after_diffuse: false,
+            walk_steps: 0,
+            max_depth: params.max_ray_depth,
+            per_channel: params.per_channel_tracing,
+            first_hit_light_samples: params.first_hit_light_samples,
+            stats,
+        }
+    }
+
+    /// Color of a ray that leaves the scene.
+    pub(crate) fn miss_color(&self) -> Color {
+        if self.after_diffuse {
+            self.background + self.ambient
+        } else {
+            self.background
+        }
+    }
+
+    pub(crate) fn record_ray(&self) {
+        if let Some(stats) = self.stats {
+            stats.record_ray();
+        }
+    }
+
+    pub(crate) fn record_path_end(&self, depth: i32) {
+        if let Some(stats) = self.stats {
+            stats.record_path(self.max_depth - depth);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        dielectric::Dielectric,
+        diffuse_light::DiffuseLight,
+        flip_face::FlipFace,
+        lambertian::Lambertian,
+        metal::Metal,
+        rectangles::{XZRect, YZRect},
+    };
+
+    fn trace_options(per_channel: bool, first_hit_light_samples: i32) -> TraceOptions<'static> {
+        TraceOptions {
+            background: Color::broadcast(C_ZERO),
+            ambient: Color::broadcast(C_ZERO),
+            after_diffuse: false,
+            walk_steps: 0,
+            max_depth: 8,
+            per_channel,
+            first_hit_light_samples,
+            stats: None,
+        }
+    }
+
+    //
+    // Glass plane at y = 0 with a strong dispersion, a thin light strip below
+    // it that only the refracted red channel reaches.
+    fn dispersive_scene() -> HittableList {
+        let mut world = HittableList::new();
+        world.add(Arc::new(XZRect {
+            x0: -10 as Real,
+            x1: 10 as Real,
+            z0: -10 as Real,
+            z1: 10 as Real,
+            k: 0 as Real,
+            mtl: Arc::new(Dielectric::dispersive([1.3, 1.5, 1.8])),
+        }));
+        world.add(Arc::new(XZRect {
+            x0: 1.6,
+            x1: 1.7,
+            z0: -1 as Real,
+            z1: 1 as Real,
+            k: -1 as Real,
+            mtl: Arc::new(DiffuseLight::from((1 as Real, 1 as Real, 1 as Real))),
+        }));
+        world
+    }
+
+    fn average_color(per_channel: bool) -> Color {
+        let world = dispersive_scene();
+        let lights = Arc::new(HittableList::new());
+        let opts = trace_options(per_channel, 0);
+        let r = Ray::new(
+            Point::new(C_ZERO, C_ONE, C_ZERO),
+            Vec3::new(C_ONE, -C_ONE, C_ZERO),
+            C_ZERO,
+        );
+
+        const SAMPLES: i32 = 256;
+        (0..SAMPLES).fold(Color::broadcast(C_ZERO), |color, _| {
+            color + Camera::ray_color(&r, &opts, &world, &lights, opts.max_depth, None, None)
+        }) * (1 as Real / SAMPLES as Real)
+    }
+
+    #[test]
+    fn test_per_channel_tracing_separates_dispersed_channels() {
+        let c = average_color(true);
+        assert!(c.r > 0.5 as Real);
+        assert_eq!(c.g, C_ZERO);
+        assert_eq!(c.b, C_ZERO);
+    }
+
+    #[test]
+    fn test_single_path_misses_dispersed_channels() {
+        let c = average_color(false);
+        assert_eq!(c.r, C_ZERO);
+        assert_eq!(c.g, C_ZERO);
+        assert_eq!(c.b, C_ZERO);
+    }
+
+    //
+    // Diffuse floor lit by a bright and a dim small ceiling light.
+    fn two_lights_scene() -> (HittableList, Arc<HittableList>) {
+        let light_rect = |x: Real, intensity: Real| XZRect {
+            x0: x - 0.25 as Real,
+            x1: x + 0.25 as Real,
+            z0: -0.25 as Real,
+            z1: 0.25 as Real,
+            k: 2 as Real,
+            mtl: Arc::new(DiffuseLight::from((intensity, intensity, intensity))),
+        };
+
+        let mut world = HittableList::new();
+        world.add(Arc::new(XZRect {
+            x0: -5 as Real,
+            x1: 5 as Real,
+            z0: -5 as Real,
+            z1: 5 as Real,
+            k: 0 as Real,
+            mtl: Arc::new(Lambertian::new((0.8 as Real, 0.8 as Real, 0.8 as Real))),
+        }));
+
+        let mut lights = HittableList::new();
+        [(-1 as Real, 16 as Real), (1 as Real, 0.1 as Real)]
+            .iter()
+            .for_each(|&(x, intensity)| {
+                let light = Arc::new(FlipFace {
+                    obj: Arc::new(light_rect(x, intensity)),
+                });
+                world.add(light.clone());
+                lights.add(light);
+            });
+
+        (world, Arc::new(lights))
+    }
+
+    fn estimator_variance(first_hit_light_samples: i32, paths_per_estimate: i32) -> Real {
+        let (world, lights) = two_lights_scene();
+        let opts = trace_options(false, first_hit_light_samples);
+        let r = Ray::new(
+            Point::new(C_ZERO, 1 as Real, C_ZERO),
+            Vec3::new(C_ZERO, -1 as Real, C_ZERO),
+            C_ZERO,
+        );
+
+        const ESTIMATES: usize = 4000;
+        let estimates = (0..ESTIMATES)
+            .map(|_| {
+                (0..paths_per_estimate)
+                    .map(|_| {
+                        Camera::ray_color(&r, &opts, &world, &lights, opts.max_depth, None, None).r
+                    })
+                    .sum::<Real>()
+                    / paths_per_estimate as Real
+            })
+            .collect::<Vec<_>>();
+
+        let mean = estimates.iter().sum::<Real>() / ESTIMATES as Real;
+        estimates
+            .iter()
+            .map(|e| (e - mean) * (e - mean))
+            .sum::<Real>()
+            / ESTIMATES as Real
+    }
+
+    #[test]
+    fn test_per_light_sampling_reduces_variance() {
+        //
+        // one sample per light means two paths per estimate, so give the random
+        // light selection two paths as well
+        let per_light = estimator_variance(1, 1);
+        let random_light = estimator_variance(0, 2);
+        assert!(per_light < random_light * 0.85 as Real);
+    }
+
+    //
+    // Floor in the shadow of a plate, the only light is off to the side. A mirror
+    // high up on the left reflects the sky.
+    fn shadowed_floor_color(ambient: Color) -> (Color, Color, Color) {
+        let mut world = HittableList::new();
+        let mut lights = HittableList::new();
+
+        world.add(Arc::new(XZRect {
+            x0: -10 as Real,
+            x1: 10 as Real,
+            z0: -10 as Real,
+            z1: 10 as Real,
+            k: 0 as Real,
+            mtl: Arc::new(Lambertian::new((0.8 as Real, 0.8 as Real, 0.8 as Real))),
+        }));
+        world.add(Arc::new(XZRect {
+            x0: -1 as Real,
+            x1: 1 as Real,
+            z0: -1 as Real,
+            z1: 1 as Real,
+            k: 1 as Real,
+            mtl: Arc::new(Lambertian::new((0.8 as Real, 0.8 as Real, 0.8 as Real))),
+        }));
+
+        let light = || XZRect {
+            x0: 4 as Real,
+            x1: 5 as Real,
+            z0: -0.5 as Real,
+            z1: 0.5 as Real,
+            k: 0.5 as Real,
+            mtl: Arc::new(DiffuseLight::from((4 as Real, 4 as Real, 4 as Real))),
+        };
+        let light = Arc::new(FlipFace {
+            obj: Arc::new(light()),
+        });
+        world.add(light.clone());
+        lights.add(light);
+        world.add(Arc::new(YZRect {
+            y0: 5 as Real,
+            y1: 10 as Real,
+            z0: -1 as Real,
+            z1: 1 as Real,
+            k: -20 as Real,
+            mtl: Arc::new(Metal::new((C_ONE, C_ONE, C_ONE), C_ZERO)),
+        }));
+
+        let lights = Arc::new(lights);
+        let opts = TraceOptions {
+            background: Color::new(0.1, 0.2, 0.3),
+            ambient,
+            ..trace_options(false, 0)
+        };
+
+        const SAMPLES: i32 = 2000;
+        //
+        // camera looks at the floor under the plate, from the side
+        let floor_ray = Ray::new(
+            Point::new(-3 as Real, 0.5 as Real, C_ZERO),
+            Vec3::new(3 as Real, -0.5 as Real, C_ZERO),
+            C_ZERO,
+        );
+        let floor = (0..SAMPLES).fold(Color::broadcast(C_ZERO), |color, _| {
+            color
+                + Camera::ray_color(
+                    &floor_ray,
+                    &opts,
+                    &world,
+                    &lights,
+                    opts.max_depth,
+                    None,
+                    None,
+                )
+        }) * (1 as Real / SAMPLES as Real);
+
+        let sky_ray = Ray::new(
+            Point::new(-3 as Real, 0.5 as Real, C_ZERO),
+            Vec3::new(C_ZERO, 1 as Real, C_ZERO),
+            C_ZERO,
+        );
+        let sky = Camera::ray_color(&sky_ray, &opts, &world, &lights, opts.max_depth, None, None);
+
+        let mirror_ray = Ray::new(
+            Point::new(-3 as Real, 0.5 as Real, C_ZERO),
+            Vec3::new(-17 as Real, 7 as Real, C_ZERO),
+            C_ZERO,
+        );
+        let mirrored_sky = Camera::ray_color(
+            &mirror_ray,
+            &opts,
+            &world,
+            &lights,
+            opts.max_depth,
+            None,
+            None,
+        );
+
+        (floor, sky, mirrored_sky)
+    }
+
+    #[test]
+    fn test_ambient_brightens_shadows_but_not_background() {
+        let (dark_floor, dark_sky, dark_mirror) = shadowed_floor_color(Color::broadcast(C_ZERO));
+        let (lit_floor, lit_sky, lit_mirror) = shadowed_floor_color(Color::broadcast(0.5 as Real));
+
+        assert!(lit_floor.r > dark_floor.r + 0.1 as Real);
+        assert!(lit_floor.g > dark_floor.g + 0.1 as Real);
+        assert!(lit_floor.b > dark_floor.b + 0.1 as Real);
+
+        let background = Color::new(0.1, 0.2, 0.3);
+        //
+        // the sky seen in a mirror is not brightened either
+        [dark_sky, lit_sky, dark_mirror, lit_mirror]
+            .iter()
+            .for_each(|sky| {
+                assert_eq!(sky.r, background.r);
+                assert_eq!(sky.g, background.g);
+                assert_eq!(sky.b, background.b);
+            });
+    }
+
+    #[test]
+    fn test_oblique_surface_has_a_larger_footprint() {
+        use crate::{
+            lambertian::Lambertian, objects::plane::Plane, sampling::SimpleSamplingStrategy,
+        };
+
+        let cam = Camera::new(
+            Point::new(C_ZERO, C_ZERO, C_ZERO),
+            Point::new(C_ZERO, C_ZERO, -C_ONE),
+            Vec3::new(C_ZERO, C_ONE, C_ZERO),
+            40 as Real,
+            C_ONE,
+            C_ZERO,
+            C_ONE,
+            C_ZERO,
+            C_ZERO,
+        );
+        let mut s = SamplerBase::<SimpleSamplingStrategy>::new(1, Some(1));
+        let pixel_step = (C_ONE / 511 as Real, -C_ONE / 511 as Real);
+        let r = cam.get_ray_perspective_differential(C_HALF_ONE, C_HALF_ONE, pixel_step, &mut s);
+
+        //
+        // planes through the same point of the view axis, facing the camera and tilted
+        // away from it by 75 degrees
+        let mtl = Arc::new(Lambertian::new((0.5, 0.5, 0.5)));
+        let target = Point::new(C_ZERO, C_ZERO, -10 as Real);
+        let (sin_a, cos_a) = (75 as Real).to_radians().sin_cos();
+        let facing = Plane::new(target, Vec3::new(C_ZERO, C_ZERO, C_ONE), mtl.clone());
+        let oblique = Plane::new(target, Vec3::new(C_ZERO, sin_a, cos_a), mtl);
+
+        let footprint_lod = |plane: &Plane| {
+            let hit = plane.hit(&r, 0.001 as Real, C_INFINITY).unwrap();
+            let (dpdx, dpdy) = hit.footprint(&r).unwrap();
+            (
+                math::vec3::length(dpdx),
+                math::vec3::length(dpdy),
+                hit.texture_lod(&r, 0.001 as Real),
+            )
+        };
+
+        let (facing_dx, facing_dy, facing_lod) = footprint_lod(&facing);
+        let (oblique_dx, oblique_dy, oblique_lod) = footprint_lod(&oblique);
+
+        //
+        // a pixel covers 2 * 10 * tan(20) / 511 facing the camera, the tilt stretches it
+        // by 1 / cos(75) along y only
+        let pixel_width = 20 as Real * (20 as Real).to_radians().tan() / 511 as Real;
+        assert!((facing_dx - pixel_width).abs() < 1.0e-4);
+        assert!((facing_dy - pixel_width).abs() < 1.0e-4);
+        assert!((oblique_dx - pixel_width).abs() < 1.0e-3);
+        assert!((oblique_dy / facing_dy - C_ONE / cos_a).abs() < 0.05);
+        assert!(oblique_lod > facing_lod + 1 as Real);
+
+        //
+        // no differentials, full resolution
+        let plain = cam.get_ray_perspective(C_HALF_ONE, C_HALF_ONE, &mut s);
+        let hit = oblique.hit(&plain, 0.001 as Real, C_INFINITY).unwrap();
+        assert!(hit.footprint(&plain).is_none());
+        assert_eq!(hit.texture_lod(&plain, 0.001 as Real), C_ZERO);
+
+        //
+        // after a mirror bounce the footprint keeps growing with the length of the path
+        // (10 to the mirror, 20 back to the wall behind the camera)
+        let mirror = r.reflect_ray(r.at(10 as Real), Vec3::new(C_ZERO, C_ZERO, C_ONE));
+        let back_wall = Plane::new(
+            Point::new(C_ZERO, C_ZERO, 10 as Real),
+            Vec3::new(C_ZERO, C_ZERO, -C_ONE),
+            Arc::new(Lambertian::new((0.5, 0.5, 0.5))),
+        );
+        let hit = back_wall.hit(&mirror, 0.001 as Real, C_INFINITY).unwrap();
+        let (dpdx, _) = hit.footprint(&mirror).unwrap();
+        assert!((math::vec3::length(dpdx) - 3 as Real * pixel_width).abs() < 1.0e-3);
+    }
+
+    #[test]
+    fn test_cylindrical_projection_maps_x_to_azimuth() {
+        use crate::sampling::SimpleSamplingStrategy;
+        use math::vec3::{dot, normalize};
+
+        let params = RaytracerParams {
+            projection: Projection::Cylindrical,
+            lambda_max: 90f32.to_radians(),
+            ..crate::tests::test_params(None)
+        };
+        let cam = Camera::new(
+            Point::new(C_ZERO, C_ZERO, C_ZERO),
+            Point::new(C_ZERO, C_ZERO, -C_ONE),
+            Vec3::new(C_ZERO, C_ONE, C_ZERO),
+            params.vertical_fov,
+            params.aspect_ratio,
+            C_ZERO,
+            C_ONE,
+            C_ZERO,
+            C_ZERO,
+        );
+        let mut s = SamplerBase::<SimpleSamplingStrategy>::new(1, Some(1));
+        let mut dir = |x: Real, y: Real| {
+            normalize(
+                cam.get_ray_cylindrical(&params, x, y, &mut s)
+                    .unwrap()
+                    .direction,
+            )
+        };
+
+        let close = |a: Vec3, b: Vec3| dot(a, b) > 1 as Real - 1.0e-5;
+        //
+        // center looks along -w, the horizontal extremes along +/- lambda_max
+        assert!(close(dir(C_HALF_ONE, C_HALF_ONE), -cam.w));
+        assert!(close(dir(C_ONE, C_HALF_ONE), cam.u));
+        assert!(close(dir(C_ZERO, C_HALF_ONE), -cam.u));
+
+        let (sin_a, cos_a) = (C_HALF_ONE * (90 as Real).to_radians()).sin_cos();
+        assert!(close(dir(0.75, C_HALF_ONE), sin_a * cam.u - cos_a * cam.w));
+
+        //
+        // the top edge is at the vertical field of view in the center
+        let top = dir(C_HALF_ONE, C_ONE);
+        assert!(
+            (dot(top, cam.v).asin().to_degrees() - params.vertical_fov * C_HALF_ONE).abs() < 1.0e-3
+        );
+
+        //
+        // more than +/- 180 degrees would wrap around
+        let wide = RaytracerParams {
+            lambda_max: 270f32.to_radians(),
+            ..params
+        };
+        assert!(cam
+            .get_ray_cylindrical(&wide, C_ZERO, C_HALF_ONE, &mut s)
+            .is_none());
+    }
+
+    #[test]
+    fn test_every_projection_goes_through_get_ray() {
+        use crate::sampling::SimpleSamplingStrategy;
+
+        let cam = Camera::new(
+            Point::new(C_ZERO, C_ZERO, C_ZERO),
+            Point::new(C_ZERO, C_ZERO, -C_ONE),
+            Vec3::new(C_ZERO, C_ONE, C_ZERO),
+            40 as Real,
+            C_ONE,
+            C_ZERO,
+            C_ONE,
+            C_ZERO,
+            C_ZERO,
+        );
+        let mut s = SamplerBase::<SimpleSamplingStrategy>::new(1, Some(1));
+        let (u, v) = (0.3 as Real, 0.6 as Real);
+
+        //
+        // no aperture and no shutter interval, the same rays as the projections' own
+        [
+            Projection::Perspective,
+            Projection::Orthographic,
+            Projection::FishEye,
+            Projection::SphericalPanoramic,
+            Projection::Cylindrical,
+        ]
+        .iter()
+        .for_each(|&projection| {
+            let params = RaytracerParams {
+                projection,
+                ..crate::tests::test_params(None)
+            };
+            let expected = match projection {
+                Projection::Perspective => cam.get_ray_perspective(u, v, &mut s),
+                Projection::Orthographic => cam.get_ray_ortho(u, v, &mut s),
+                Projection::FishEye => cam.get_ray_fisheye(&params, u, v, &mut s).unwrap(),
+                Projection::SphericalPanoramic => {
+                    cam.get_ray_spherical_panoramic(&params, u, v, &mut s)
+                }
+                Projection::Cylindrical => cam.get_ray_cylindrical(&params, u, v, &mut s).unwrap(),
+            };
+            let r = cam.get_ray(&params, u, v, &mut s).unwrap();
+            assert_eq!(r.origin, expected.origin, "{:?}", projection);
+            assert_eq!(r.direction, expected.direction, "{:?}", projection);
+        });
+
+        //
+        // white background, the corner of the fisheye image is out of the disk and gets
+        // nothing, the center sees the background
+        let params = RaytracerParams {
+            projection: Projection::FishEye,
+            background: [C_ONE, C_ONE, C_ONE],
+            ..crate::tests::test_params(None)
+        };
+        assert!(cam.get_ray(&params, C_ZERO, C_ONE, &mut s).is_none());
+
+        let world = Arc::new(HittableList::new());
+        let lights = Arc::new(HittableList::new());
+        let (cx, cy) = (params.image_width / 2, params.image_height / 2);
+        let integrator = params.integrator.build(&params, None);
+        let corner = cam.raytrace_pixel(0, 0, &params, &world, &lights, &*integrator, &mut s);
+        let center = cam.raytrace_pixel(cx, cy, &params, &world, &lights, &*integrator, &mut s);
+        assert_eq!(corner, Color::broadcast(C_ZERO));
+        assert_eq!(center.r, params.samples_per_pixel as Real);
+    }
+
+    #[test]
+    fn test_non_finite_channels_only_drop_out_of_their_samples() {
+        use crate::sampling::SimpleSamplingStrategy;
+
+        //
+        // every other sample has a NaN green and an infinite blue channel
+        struct Unstable {
+            calls: std::cell::Cell<usize>,
+        }
+
+        impl Integrator for Unstable {
+            fn radiance(&self, _: &Ray, _: &HittableList, _: &Arc<HittableList>) -> Color {
+                self.calls.set(self.calls.get() + 1);
+                match self.calls.get() % 2 {
+                    0 => Color::new(C_ONE, Real::NAN, Real::INFINITY),
+                    _ => Color::broadcast(C_ONE),
+                }
+            }
+        }
+
+        let params = crate::tests::test_params(None);
+        let cam = Camera::new(
+            Point::new(C_ZERO, C_ZERO, C_ZERO),
+            Point::new(C_ZERO, C_ZERO, -C_ONE),
+            Vec3::new(C_ZERO, C_ONE, C_ZERO),
+            60 as Real,
+            params.aspect_ratio,
+            C_ZERO,
+            C_ONE,
+            C_ZERO,
+            C_ZERO,
+        );
+        let mut s = SamplerBase::<SimpleSamplingStrategy>::new(params.samples_per_pixel, None);
+        let integrator = Unstable {
+            calls: std::cell::Cell::new(0),
+        };
+
+        let world = Arc::new(HittableList::new());
+        let lights = Arc::new(HittableList::new());
+        let color = cam.raytrace_pixel(1, 1, &params, &world, &lights, &integrator, &mut s);
+        let spp = params.samples_per_pixel as Real;
+        assert_eq!(
+            (color.r, color.g, color.b),
+            (spp, spp / 2 as Real, spp / 2 as Real)
+        );
+    }
+
+    #[test]
+    fn test_lens_distortion_moves_corners_not_the_center() {
+        use crate::sampling::SimpleSamplingStrategy;
+        use math::vec3::{dot, normalize};
+
+        let pinhole = Camera::new(
+            Point::new(C_ZERO, C_ZERO, C_ZERO),
+            Point::new(C_ZERO, C_ZERO, -C_ONE),
+            Vec3::new(C_ZERO, C_ONE, C_ZERO),
+            60 as Real,
+            1.5 as Real,
+            C_ZERO,
+            C_ONE,
+            C_ZERO,
+            C_ZERO,
+        );
+        let mut s = SamplerBase::<SimpleSamplingStrategy>::new(1, Some(1));
+        let mut dir = |cam: &Camera, x: Real, y: Real| {
+            normalize(cam.get_ray_perspective(x, y, &mut s).direction)
+        };
+        //
+        // cosine of the angle to the view direction
+        let forward = -pinhole.w;
+
+        let undistorted = pinhole.clone().with_lens_distortion(C_ZERO, C_ZERO);
+        let barrel = pinhole
+            .clone()
+            .with_lens_distortion(0.2 as Real, 0.05 as Real);
+        let pincushion = pinhole.clone().with_lens_distortion(-0.1 as Real, C_ZERO);
+
+        assert_eq!(dir(&undistorted, 0.9, 0.2), dir(&pinhole, 0.9, 0.2));
+        assert!(dot(dir(&barrel, C_HALF_ONE, C_HALF_ONE), forward) > 1 as Real - 1.0e-6);
+
+        let corner = dot(dir(&pinhole, C_ONE, C_ONE), forward);
+        assert!(dot(dir(&barrel, C_ONE, C_ONE), forward) < corner - 0.05 as Real);
+        assert!(dot(dir(&pincushion, C_ONE, C_ONE), forward) > corner + 0.05 as Real);
+
+        //
+        // radial, the corner ray stays on the diagonal
+        let d = dir(&barrel, C_ONE, C_ONE);
+        let diagonal = dir(&pinhole, C_ONE, C_ONE);
+        let (dx, dy) = (dot(d, pinhole.u), dot(d, pinhole.v));
+        let (ex, ey) = (dot(diagonal, pinhole.u), dot(diagonal, pinhole.v));
+        assert!((dx / dy - ex / ey).abs() < 1.0e-4);
+    }
+
+    #[test]
+    fn test_autofocus_on_object_in_view_center() {
+        use crate::rectangles::XYRect;
+
+        //
+        // the test camera is at z = 5, looking at the origin
+        let params = RaytracerParams {
+            autofocus: true,
+            focus_dist: 1 as Real,
+            ..crate::tests::test_params(None)
+        };
+        let distance = 8 as Real;
+
+        let mut world = HittableList::new();
+        world.add(Arc::new(XYRect {
+            x0: -1 as Real,
+            x1: 1 as Real,
+            y0: -1 as Real,
+            y1: 1 as Real,
+            k: params.look_from[2] - distance,
+            mtl: Arc::new(Lambertian::new((0.5 as Real, 0.5 as Real, 0.5 as Real))),
+        }));
+
+        let cam = crate::RaytracerState::make_camera(&params, None, &world, C_ZERO, C_ONE);
+        assert!((cam.focus_dist() - distance).abs() < 1.0e-4);
+
+        //
+        // nothing in the center, keeps the configured distance
+        let cam =
+            crate::RaytracerState::make_camera(&params, None, &HittableList::new(), C_ZERO, C_ONE);
+        assert_eq!(cam.focus_dist(), params.focus_dist);
+
+        let manual = RaytracerParams {
+            autofocus: false,
+            ..params
+        };
+        let cam = crate::RaytracerState::make_camera(&manual, None, &world, C_ZERO, C_ONE);
+        assert_eq!(cam.focus_dist(), params.focus_dist);
+    }
+}
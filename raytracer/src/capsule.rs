@@ -0,0 +1,252 @@
+use std::sync::Arc;
+
+use math::vec3::{dot, length};
+
+use crate::{
+    aabb3::Aabb,
+    hittable::{HitRecord, Hittable},
+    material::Material,
+    onb::Onb,
+    types::{Point, Ray, Real, Vec3, C_PI, C_TWO_PI, C_ZERO},
+};
+
+/// Sphere of `radius` swept along the segment p0 - p1: a cylinder with two hemispherical caps.
+pub struct Capsule {
+    pub p0: Point,
+    pub p1: Point,
+    pub radius: Real,
+    pub mtl: Arc<dyn Material>,
+}
+
+impl Capsule {
+    pub fn new(p0: Point, p1: Point, radius: Real, mtl: Arc<dyn Material>) -> Capsule {
+        Capsule {
+            p0,
+            p1,
+            radius,
+            mtl,
+        }
+    }
+}
+
+//
+// roots of a * t^2 + 2 * half_b * t + c, in increasing order
+fn solve_quadratic(a: Real, half_b: Real, c: Real) -> Option<[Real; 2]> {
+    if a == C_ZERO {
+        return None;
+    }
+
+    let discriminant = half_b * half_b - a * c;
+    if discriminant < C_ZERO {
+        return None;
+    }
+
+    let sqrtd = discriminant.sqrt();
+    let t0 = (-half_b - sqrtd) / a;
+    let t1 = (-half_b + sqrtd) / a;
+    Some([t0.min(t1), t0.max(t1)])
+}
+
+impl Hittable for Capsule {
+    fn hit(&self, r: &Ray, t_min: Real, t_max: Real) -> Option<HitRecord> {
+        let axis = self.p1 - self.p0;
+        let axis_len_sq = dot(axis, axis);
+        let oa = r.origin - self.p0;
+
+        //
+        // p0 == p1 has no body, the capsule is the sphere around p0
+        let degenerate = axis_len_sq == C_ZERO;
+
+        //
+        // projection on the axis, relative to p0 (scaled by the axis length)
+        let axial = |t: Real| dot(oa + r.direction * t, axis);
+
+        //
+        // infinite cylinder around the axis
+        let dir_axis = dot(r.direction, axis);
+        let oa_axis = dot(oa, axis);
+        let body = solve_quadratic(
+            axis_len_sq * dot(r.direction, r.direction) - dir_axis * dir_axis,
+            axis_len_sq * dot(r.direction, oa) - oa_axis * dir_axis,
+            axis_len_sq * dot(oa, oa) - oa_axis * oa_axis - self.radius * self.radius * axis_len_sq,
+        )
+        .into_iter()
+        .flatten()
+        .filter(|&t| (C_ZERO..=axis_len_sq).contains(&axial(t)))
+        .map(|t| (t, None));
+
+        //
+        // caps, only the half of each sphere beyond its end of the segment
+        let cap = |center: Point, beyond: &dyn Fn(Real) -> bool| {
+            let oc = r.origin - center;
+            solve_quadratic(
+                dot(r.direction, r.direction),
+                dot(oc, r.direction),
+                dot(oc, oc) - self.radius * self.radius,
+            )
+            .into_iter()
+            .flatten()
+            .filter(|&t| beyond(axial(t)))
+            .map(move |t| (t, Some(center)))
+            .collect::<Vec<_>>()
+        };
+        let cap0 = cap(self.p0, &|y| degenerate || y < C_ZERO);
+        let cap1 = cap(self.p1, &|y| y > axis_len_sq);
+
+        let (t, cap_center) = body
+            .chain(cap0)
+            .chain(cap1)
+            .filter(|&(t, _)| t > t_min && t < t_max)
+            .min_by(|a, b| a.0.total_cmp(&b.0))?;
+
+        let p = r.at(t);
+        let outward_normal = match cap_center {
+            Some(center) => (p - center) / self.radius,
+            None => {
+                let y = (axial(t) / axis_len_sq).max(C_ZERO).min(1 as Real);
+                (p - (self.p0 + axis * y)) / self.radius
+            }
+        };
+
+        //
+        // u around the axis, v along the whole length, caps included (the sphere of a
+        // degenerate capsule is mapped around the y axis)
+        let uvw = Onb::from(if degenerate {
+            Vec3::new(C_ZERO, 1 as Real, C_ZERO)
+        } else {
+            axis
+        });
+        let phi = dot(outward_normal, uvw.v()).atan2(dot(outward_normal, uvw.u()));
+        let axis_len = axis_len_sq.sqrt();
+        let v = (dot(p - self.p0, uvw.w()) + self.radius) / (axis_len + 2 as Real * self.radius);
+
+        Some(HitRecord::new(
+            p,
+            outward_normal,
+            r,
+            t,
+            self.mtl.clone(),
+            (phi + C_PI) / C_TWO_PI,
+            v,
+        ))
+    }
+
+    fn bounding_box(&self, _time0: Real, _time1: Real) -> Option<Aabb> {
+        let extent = Vec3::broadcast(self.radius.abs());
+
+        Some(Aabb::new(
+            math::vec3::min(self.p0, self.p1) - extent,
+            math::vec3::max_sv(self.p0, self.p1) + extent,
+        ))
+    }
+
+    fn bounding_sphere(&self) -> Option<(Point, Real)> {
+        Some((
+            (self.p0 + self.p1) * 0.5 as Real,
+            length(self.p1 - self.p0) * 0.5 as Real + self.radius.abs(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lambertian::Lambertian;
+
+    #[test]
+    fn test_caps_and_body() {
+        let capsule = Capsule::new(
+            Point::new(0 as Real, -1 as Real, 0 as Real),
+            Point::new(0 as Real, 1 as Real, 0 as Real),
+            0.5 as Real,
+            Arc::new(Lambertian::new((0.5, 0.5, 0.5))),
+        );
+
+        let hit = |origin: Point, direction: Vec3| {
+            capsule
+                .hit(
+                    &Ray::new(origin, direction, C_ZERO),
+                    0.001 as Real,
+                    Real::MAX,
+                )
+                .unwrap()
+        };
+        let down = Vec3::new(C_ZERO, -1 as Real, C_ZERO);
+
+        //
+        // rounded end, on the sphere around p1
+        let cap = hit(Point::new(0.3 as Real, 5 as Real, C_ZERO), down);
+        assert!((cap.t - 3.6 as Real).abs() < 1.0e-4);
+        assert!(length(cap.normal - Vec3::new(0.6, 0.8, 0.0)) < 1.0e-4);
+        assert!(cap.front_face);
+
+        //
+        // middle, on the cylinder
+        let body = hit(
+            Point::new(5 as Real, 0.25 as Real, C_ZERO),
+            Vec3::new(-1 as Real, C_ZERO, C_ZERO),
+        );
+        assert!((body.t - 4.5 as Real).abs() < 1.0e-4);
+        assert!(length(body.normal - Vec3::new(1.0, 0.0, 0.0)) < 1.0e-4);
+
+        //
+        // from inside the body, out through the bottom cap
+        let inside = hit(Point::broadcast(C_ZERO), down);
+        assert!((inside.t - 1.5 as Real).abs() < 1.0e-4);
+        assert!(!inside.front_face);
+
+        //
+        // just past the end of the cap
+        assert!(capsule
+            .hit(
+                &Ray::new(Point::new(0.51 as Real, 5 as Real, C_ZERO), down, C_ZERO),
+                0.001 as Real,
+                Real::MAX
+            )
+            .is_none());
+
+        let bbox = capsule.bounding_box(C_ZERO, 1 as Real).unwrap();
+        assert_eq!(bbox.min, Point::new(-0.5, -1.5, -0.5));
+        assert_eq!(bbox.max, Point::new(0.5, 1.5, 0.5));
+    }
+
+    #[test]
+    fn test_degenerate_capsule_is_a_sphere() {
+        let center = Point::new(1 as Real, 2 as Real, 3 as Real);
+        let capsule = Capsule::new(
+            center,
+            center,
+            0.5 as Real,
+            Arc::new(Lambertian::new((0.5, 0.5, 0.5))),
+        );
+
+        let from_side = capsule
+            .hit(
+                &Ray::new(
+                    center + Vec3::new(5 as Real, C_ZERO, C_ZERO),
+                    Vec3::new(-1 as Real, C_ZERO, C_ZERO),
+                    C_ZERO,
+                ),
+                0.001 as Real,
+                Real::MAX,
+            )
+            .expect("The degenerate capsule was missed");
+        assert!((from_side.t - 4.5 as Real).abs() < 1.0e-4);
+        assert!(length(from_side.normal - Vec3::new(1.0, 0.0, 0.0)) < 1.0e-4);
+        assert!(from_side.front_face);
+        assert!(from_side.u.is_finite() && (C_ZERO..=1 as Real).contains(&from_side.v));
+
+        //
+        // from the center, out through the top
+        let inside = capsule
+            .hit(
+                &Ray::new(center, Vec3::new(C_ZERO, 1 as Real, C_ZERO), C_ZERO),
+                0.001 as Real,
+                Real::MAX,
+            )
+            .unwrap();
+        assert!((inside.t - 0.5 as Real).abs() < 1.0e-4);
+        assert!(!inside.front_face);
+        assert!((inside.v - 1 as Real).abs() < 1.0e-4);
+    }
+}
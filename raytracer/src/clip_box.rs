@@ -0,0 +1,207 @@
+use std::sync::Arc;
+
+use crate::aabb3::Aabb;
+use crate::hittable::{HitRecord, HitStats, Hittable};
+use crate::types::{Ray, Real};
+
+/// Slices an object with a box, only the parts of the object's surface inside
+/// `clip` can be hit. Useful for cutaway views.
+pub struct ClipBox {
+    pub obj: Arc<dyn Hittable>,
+    pub clip: Aabb,
+}
+
+impl ClipBox {
+    /// Offset used to step past a rejected hit when looking for the next one along the ray.
+    const HIT_STEP: Real = 0.0001 as Real;
+    /// Most hits walked along a ray, bounds the walk should a step ever fail to move past a hit.
+    const MAX_HITS: usize = 64;
+
+    pub fn new(obj: Arc<dyn Hittable>, clip: Aabb) -> ClipBox {
+        ClipBox { obj, clip }
+    }
+
+    fn inside_clip(&self, hit: &HitRecord) -> bool {
+        self.clip.contains(hit.p)
+    }
+
+    //
+    // walk the hits along the ray, the nearest one might be clipped away
+    // while a farther one is inside the box. `hit_from` hits the object from t on.
+    fn first_inside(
+        &self,
+        t_min: Real,
+        t_max: Real,
+        mut hit_from: impl FnMut(Real) -> Option<HitRecord>,
+    ) -> Option<HitRecord> {
+        let mut t_start = t_min;
+        for _ in 0..Self::MAX_HITS {
+            if t_start >= t_max {
+                break;
+            }
+
+            let hit = hit_from(t_start)?;
+            if self.inside_clip(&hit) {
+                return Some(hit);
+            }
+
+            //
+            // the step grows with t, far away a fixed one is lost in the rounding of hit.t
+            t_start = hit.t + Self::HIT_STEP.max(hit.t.abs() * Real::EPSILON * 4 as Real);
+        }
+
+        None
+    }
+}
+
+impl Hittable for ClipBox {
+    fn hit(&self, r: &Ray, t_min: Real, t_max: Real) -> Option<HitRecord> {
+        self.first_inside(t_min, t_max, |t| self.obj.hit(r, t, t_max))
+    }
+
+    fn hit_counted(
+        &self,
+        r: &Ray,
+        t_min: Real,
+        t_max: Real,
+        stats: &mut HitStats,
+    ) -> Option<HitRecord> {
+        self.first_inside(t_min, t_max, |t| self.obj.hit_counted(r, t, t_max, stats))
+    }
+
+    fn bounding_box(&self, time0: Real, time1: Real) -> Option<Aabb> {
+        let bbox = self.obj.bounding_box(time0, time1)?;
+        if !bbox.intersects(&self.clip) {
+            //
+            // object is completely clipped away, an empty box at the corner of the clip
+            // (None would mean unbounded and keep the object out of the BVH)
+            return Some(Aabb::new(self.clip.min, self.clip.min));
+        }
+
+        Some(Aabb::new(
+            math::vec3::max_sv(bbox.min, self.clip.min),
+            math::vec3::min(bbox.max, self.clip.max),
+        ))
+    }
+
+    fn node_boxes(&self, depth: u32, boxes: &mut Vec<Aabb>) {
+        self.obj.node_boxes(depth, boxes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lambertian::Lambertian;
+    use crate::objects::sphere::Sphere;
+    use crate::types::{Point, Vec3};
+
+    fn lower_half_sphere() -> ClipBox {
+        ClipBox::new(
+            Arc::new(Sphere::new(
+                Point::new(0 as Real, 0 as Real, 0 as Real),
+                1 as Real,
+                Arc::new(Lambertian::new((0.5 as Real, 0.5 as Real, 0.5 as Real))),
+            )),
+            Aabb::new(
+                Vec3::new(-2 as Real, -2 as Real, -2 as Real),
+                Vec3::new(2 as Real, 0 as Real, 2 as Real),
+            ),
+        )
+    }
+
+    #[test]
+    fn test_clip_box_keeps_only_inside_half() {
+        let clipped = lower_half_sphere();
+
+        let lower = Ray::new(
+            Point::new(-5 as Real, -0.5 as Real, 0 as Real),
+            Vec3::new(1 as Real, 0 as Real, 0 as Real),
+            0 as Real,
+        );
+        let hit = clipped.hit(&lower, 0.001, Real::MAX).unwrap();
+        assert!(hit.p.y <= 0 as Real);
+
+        let upper = Ray::new(
+            Point::new(-5 as Real, 0.5 as Real, 0 as Real),
+            Vec3::new(1 as Real, 0 as Real, 0 as Real),
+            0 as Real,
+        );
+        assert!(clipped.hit(&upper, 0.001, Real::MAX).is_none());
+
+        let bbox = clipped.bounding_box(0 as Real, 1 as Real).unwrap();
+        assert_eq!(bbox.max.y, 0 as Real);
+        assert_eq!(bbox.min.y, -1 as Real);
+    }
+
+    #[test]
+    fn test_clip_box_skips_clipped_nearest_hit() {
+        let clipped = lower_half_sphere();
+
+        //
+        // enters through the clipped away top cap, hits the inside of the bottom half
+        let down = Ray::new(
+            Point::new(0 as Real, 5 as Real, 0 as Real),
+            Vec3::new(0 as Real, -1 as Real, 0 as Real),
+            0 as Real,
+        );
+        let hit = clipped.hit(&down, 0.001, Real::MAX).unwrap();
+        assert!((hit.t - 6 as Real).abs() < 1.0e-4);
+        assert!((hit.p.y + 1 as Real).abs() < 1.0e-4);
+        assert!(!hit.front_face);
+
+        //
+        // far hit is beyond t_max, nothing is returned
+        assert!(clipped.hit(&down, 0.001, 5.5).is_none());
+    }
+
+    #[test]
+    fn test_far_clipped_hits_are_stepped_past() {
+        //
+        // the sphere is hit around t = 5000, where the fixed step is below the precision
+        // of t in single precision
+        let clipped = ClipBox::new(
+            Arc::new(Sphere::new(
+                Point::new(5000 as Real, 0 as Real, 0 as Real),
+                1 as Real,
+                Arc::new(Lambertian::new((0.5 as Real, 0.5 as Real, 0.5 as Real))),
+            )),
+            Aabb::new(
+                Vec3::new(4990 as Real, 2 as Real, -2 as Real),
+                Vec3::new(5010 as Real, 4 as Real, 2 as Real),
+            ),
+        );
+
+        let through = Ray::new(
+            Point::new(0 as Real, 0 as Real, 0 as Real),
+            Vec3::new(1 as Real, 0 as Real, 0 as Real),
+            0 as Real,
+        );
+        assert!(clipped.hit(&through, 0.001, Real::MAX).is_none());
+    }
+
+    #[test]
+    fn test_fully_clipped_object_is_bounded_and_never_hit() {
+        let clipped = ClipBox::new(
+            Arc::new(Sphere::new(
+                Point::new(5 as Real, 0 as Real, 0 as Real),
+                1 as Real,
+                Arc::new(Lambertian::new((0.5 as Real, 0.5 as Real, 0.5 as Real))),
+            )),
+            Aabb::new(
+                Vec3::new(-2 as Real, -2 as Real, -2 as Real),
+                Vec3::new(2 as Real, 2 as Real, 2 as Real),
+            ),
+        );
+
+        let bbox = clipped.bounding_box(0 as Real, 1 as Real).unwrap();
+        assert_eq!(bbox.min, bbox.max);
+
+        let through = Ray::new(
+            Point::new(-5 as Real, 0 as Real, 0 as Real),
+            Vec3::new(1 as Real, 0 as Real, 0 as Real),
+            0 as Real,
+        );
+        assert!(clipped.hit(&through, 0.001, Real::MAX).is_none());
+    }
+}
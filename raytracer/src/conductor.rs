@@ -0,0 +1,170 @@
+use crate::hittable::HitRecord;
+use crate::material::{Material, ScatterRecord};
+use crate::types::{Color, Ray, Real};
+
+/// Metal with a complex index of refraction (eta + i * k), per color channel.
+/// Unlike `Metal` the reflected color depends on the angle of incidence and goes to
+/// white at grazing angles.
+#[derive(Copy, Clone, Debug)]
+pub struct Conductor {
+    pub eta: Color,
+    pub k: Color,
+    pub roughness: Real,
+}
+
+impl Conductor {
+    pub fn new<T: Into<Color>>(eta: T, k: T, roughness: Real) -> Conductor {
+        Conductor {
+            eta: eta.into(),
+            k: k.into(),
+            roughness,
+        }
+    }
+
+    pub fn gold(roughness: Real) -> Conductor {
+        Self::new((0.143, 0.374, 1.442), (3.983, 2.385, 1.603), roughness)
+    }
+
+    pub fn copper(roughness: Real) -> Conductor {
+        Self::new((0.200, 0.924, 1.102), (3.912, 2.452, 2.142), roughness)
+    }
+
+    pub fn aluminium(roughness: Real) -> Conductor {
+        Self::new((1.657, 0.880, 0.521), (9.224, 6.270, 4.837), roughness)
+    }
+
+    pub fn silver(roughness: Real) -> Conductor {
+        Self::new((0.155, 0.117, 0.138), (4.828, 3.122, 2.147), roughness)
+    }
+}
+
+//
+// Physically based rendering, section 8.2.1, dielectric - conductor interface
+fn fresnel_conductor_channel(cos_theta: Real, eta: Real, k: Real) -> Real {
+    let cos2 = cos_theta * cos_theta;
+    let sin2 = 1 as Real - cos2;
+    let eta2 = eta * eta;
+    let k2 = k * k;
+
+    let t0 = eta2 - k2 - sin2;
+    let a2_plus_b2 = (t0 * t0 + 4 as Real * eta2 * k2).sqrt();
+    let t1 = a2_plus_b2 + cos2;
+    let a = (0.5 as Real * (a2_plus_b2 + t0)).sqrt();
+    let t2 = 2 as Real * cos_theta * a;
+    let rs = (t1 - t2) / (t1 + t2);
+
+    let t3 = cos2 * a2_plus_b2 + sin2 * sin2;
+    let t4 = t2 * sin2;
+    let rp = rs * (t3 - t4) / (t3 + t4);
+
+    0.5 as Real * (rp + rs)
+}
+
+/// Fresnel reflectance of a conductor, for light coming from air.
+pub fn fresnel_conductor(cos_theta: Real, eta: Color, k: Color) -> Color {
+    let cos_theta = cos_theta.clamp(0 as Real, 1 as Real);
+    Color::new(
+        fresnel_conductor_channel(cos_theta, eta.r, k.r),
+        fresnel_conductor_channel(cos_theta, eta.g, k.g),
+        fresnel_conductor_channel(cos_theta, eta.b, k.b),
+    )
+}
+
+impl Material for Conductor {
+    fn scatter(&self, ray: &Ray, hit_record: &HitRecord) -> Option<ScatterRecord> {
+        use crate::types::random_in_unit_sphere;
+        use math::vec3::{are_on_the_same_plane_side, dot, normalize};
+
+        let unit_direction = normalize(ray.direction);
+        let normal = normalize(hit_record.normal);
+        let reflected = ray.reflect_ray(hit_record.p, normal);
+        let scattered = reflected.direction + self.roughness * random_in_unit_sphere();
+
+        if are_on_the_same_plane_side(scattered, hit_record.normal) {
+            Some(ScatterRecord::SpecularRec {
+                ray: Ray {
+                    direction: scattered,
+                    ..reflected
+                },
+                attenuation: fresnel_conductor(dot(-unit_direction, normal), self.eta, self.k),
+            })
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reflectance_increases_toward_grazing() {
+        [
+            Conductor::gold(0 as Real),
+            Conductor::copper(0 as Real),
+            Conductor::aluminium(0 as Real),
+            Conductor::silver(0 as Real),
+        ]
+        .iter()
+        .for_each(|metal| {
+            let normal = fresnel_conductor(1 as Real, metal.eta, metal.k);
+            let grazing = fresnel_conductor(0.01 as Real, metal.eta, metal.k);
+            let tangent = fresnel_conductor(0 as Real, metal.eta, metal.k);
+
+            assert!(grazing.r > normal.r && grazing.g > normal.g && grazing.b > normal.b);
+            assert!((tangent.r - 1 as Real).abs() < 1.0E-4);
+            assert!((tangent.g - 1 as Real).abs() < 1.0E-4);
+            assert!((tangent.b - 1 as Real).abs() < 1.0E-4);
+        });
+    }
+
+    #[test]
+    fn test_gold_reflects_red_and_green_more_than_blue() {
+        let gold = Conductor::gold(0 as Real);
+        let r = fresnel_conductor(1 as Real, gold.eta, gold.k);
+
+        assert!(r.r > r.b);
+        assert!(r.g > r.b);
+        assert!(r.r > 0.9 as Real);
+    }
+
+    #[test]
+    fn test_smooth_conductor_scatters_the_mirror_ray() {
+        use crate::hittable::Hittable;
+        use crate::rectangles::XZRect;
+        use crate::types::{Point, RayDifferentials, Vec3};
+        use std::sync::Arc;
+
+        let floor = XZRect {
+            x0: -1 as Real,
+            x1: 1 as Real,
+            z0: -1 as Real,
+            z1: 1 as Real,
+            k: 0 as Real,
+            mtl: Arc::new(Conductor::gold(0 as Real)),
+        };
+
+        let origin = Point::new(-1 as Real, 1 as Real, 0 as Real);
+        let r = Ray::new(
+            origin,
+            Vec3::new(1 as Real, -1 as Real, 0 as Real),
+            0 as Real,
+        )
+        .with_differentials(RayDifferentials {
+            rx_origin: origin,
+            rx_direction: Vec3::new(1 as Real, -1 as Real, 0.01 as Real),
+            ry_origin: origin,
+            ry_direction: Vec3::new(1.01 as Real, -1 as Real, 0 as Real),
+        });
+        let hit = floor.hit(&r, 0.001, Real::MAX).unwrap();
+
+        match hit.mtl.scatter(&r, &hit) {
+            Some(ScatterRecord::SpecularRec { ray, .. }) => {
+                assert_eq!(ray, r.reflect_ray(hit.p, hit.normal));
+                assert!(ray.differentials.is_some());
+            }
+            _ => panic!("a smooth conductor should scatter specularly"),
+        }
+    }
+}
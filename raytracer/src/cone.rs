@@ -106,7 +106,7 @@ impl Hittable for Cone {
 
         use crate::types::Vec3;
         use math::vec3;
-        let dpdu = Vec3::new(-self.phi_max * p.y, self.phi_max * p.y, C_ZERO);
+        let dpdu = Vec3::new(-self.phi_max * p.y, self.phi_max * p.x, C_ZERO);
         let dpdv = Vec3::new(-p.x / (C_ONE - v), -p.y / (C_ONE - v), self.height);
 
         Some(HitRecord::new(
@@ -127,3 +127,45 @@ impl Hittable for Cone {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lambertian::Lambertian;
+    use crate::types::{Point, Ray, Vec3};
+    use math::vec3::{dot, normalize};
+
+    #[test]
+    fn test_cone_normal_is_the_outward_surface_normal() {
+        let (radius, height) = (1 as Real, 2 as Real);
+        let cone = Cone::new(
+            radius,
+            height,
+            C_TWO_PI,
+            Arc::new(Lambertian::new((0.5, 0.5, 0.5))),
+        );
+
+        (0..64).for_each(|i| {
+            //
+            // horizontal rays from outside, aimed at the axis
+            let angle = i as Real / 64 as Real * C_TWO_PI;
+            let z = (0.1 as Real + 1.8 as Real * (i % 8) as Real / 8 as Real) * height / C_TWO;
+            let origin = Point::new(5 as Real * angle.cos(), 5 as Real * angle.sin(), z);
+            let r = Ray::new(origin, Point::new(C_ZERO, C_ZERO, z) - origin, C_ZERO);
+
+            let hit = cone.hit(&r, 0.001 as Real, Real::MAX).unwrap();
+            assert!(hit.front_face);
+
+            //
+            // gradient of x^2 + y^2 - (radius / height)^2 * (z - height)^2
+            let k = (radius / height) * (radius / height);
+            let expected = normalize(Vec3::new(hit.p.x, hit.p.y, k * (height - hit.p.z)));
+            assert!(
+                dot(hit.normal, expected) > 0.9999 as Real,
+                "normal {:?} expected {:?}",
+                hit.normal,
+                expected
+            );
+        });
+    }
+}
@@ -0,0 +1,60 @@
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Polls a RON config file and decodes it again whenever its modification time changes.
+pub struct ConfigWatcher {
+    path: PathBuf,
+    last_loaded: Option<SystemTime>,
+    last_failed: Option<SystemTime>,
+}
+
+impl ConfigWatcher {
+    /// Starts watching `path`, the file's current contents count as already loaded.
+    pub fn new<P: AsRef<Path>>(path: P) -> ConfigWatcher {
+        let path = path.as_ref().to_path_buf();
+        let last_loaded = Self::modified(&path);
+
+        ConfigWatcher {
+            path,
+            last_loaded,
+            last_failed: None,
+        }
+    }
+
+    fn modified(path: &Path) -> Option<SystemTime> {
+        std::fs::metadata(path).and_then(|m| m.modified()).ok()
+    }
+
+    /// Returns the decoded config if the file changed since the last successful load.
+    /// A file that fails to decode (e.g. caught in the middle of a write) is ignored
+    /// and decoded again on the next poll.
+    pub fn poll<T: serde::de::DeserializeOwned>(&mut self) -> Option<T> {
+        let modified = Self::modified(&self.path)?;
+        if Some(modified) == self.last_loaded {
+            return None;
+        }
+
+        let decoded = std::fs::read_to_string(&self.path)
+            .map_err(|e| e.to_string())
+            .and_then(|s| ron::de::from_str::<T>(&s).map_err(|e| e.to_string()));
+
+        match decoded {
+            Ok(cfg) => {
+                self.last_loaded = Some(modified);
+                self.last_failed = None;
+                Some(cfg)
+            }
+            Err(e) => {
+                if self.last_failed != Some(modified) {
+                    println!(
+                        "Failed to reload config {}, keeping the current one: {}",
+                        self.path.display(),
+                        e
+                    );
+                    self.last_failed = Some(modified);
+                }
+                None
+            }
+        }
+    }
+}
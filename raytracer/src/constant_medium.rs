@@ -1,19 +1,24 @@
 use std::sync::Arc;
 
-use math::vec3::length;
+use math::vec3::{length, normalize};
 
 use crate::{
     hittable::{HitRecord, Hittable},
     isotropic::Isotropic,
     material::Material,
     texture::Texture,
-    types::{random_real, Color, Real},
+    types::{random_real, Color, Ray, Real},
+    volume_texture::VolumeTexture,
 };
 
 pub struct ConstantMedium {
     boundary: Arc<dyn Hittable>,
     phase_function: Arc<dyn Material>,
     neg_inv_density: Real,
+    /// Spatially varying density, replaces `neg_inv_density` when present.
+    density_volume: Option<Arc<dyn VolumeTexture>>,
+    /// Upper bound of the density of `density_volume`.
+    density_majorant: Real,
 }
 
 impl ConstantMedium {
@@ -22,6 +27,27 @@ impl ConstantMedium {
             boundary,
             phase_function: Arc::new(Isotropic { albedo: mat }),
             neg_inv_density: -1 as Real / density,
+            density_volume: None,
+            density_majorant: density,
+        }
+    }
+
+    /// Medium whose density is given by `density` at each point, negative values are
+    /// taken as empty space. Scattering events are sampled with Woodcock (delta) tracking:
+    /// tentative collisions are drawn for the homogeneous medium of the density's upper
+    /// bound, a collision at p is real with probability density(p) / bound, otherwise it
+    /// is a null collision and the ray goes on.
+    pub fn with_density_volume(
+        boundary: Arc<dyn Hittable>,
+        mat: Arc<dyn Texture>,
+        density: Arc<dyn VolumeTexture>,
+    ) -> Self {
+        Self {
+            boundary,
+            phase_function: Arc::new(Isotropic { albedo: mat }),
+            neg_inv_density: -1 as Real,
+            density_majorant: density.max_value(),
+            density_volume: Some(density),
         }
     }
 
@@ -34,6 +60,39 @@ impl ConstantMedium {
             boundary,
             phase_function: Arc::new(Isotropic::from(color)),
             neg_inv_density: -1 as Real / density,
+            density_volume: None,
+            density_majorant: density,
+        }
+    }
+}
+
+impl ConstantMedium {
+    //
+    // distance from the ray at t_enter to the first real collision, None if the ray
+    // leaves the medium before it
+    fn track_delta(
+        &self,
+        volume: &dyn VolumeTexture,
+        r: &Ray,
+        t_enter: Real,
+        distance_inside_boundary: Real,
+    ) -> Option<Real> {
+        if self.density_majorant <= 0 as Real {
+            return None;
+        }
+
+        let ray_length = length(r.direction);
+        let mut distance = 0 as Real;
+        loop {
+            distance -= (1 as Real - random_real()).ln() / self.density_majorant;
+            if distance > distance_inside_boundary {
+                return None;
+            }
+
+            let density = volume.value(r.at(t_enter + distance / ray_length));
+            if random_real() * self.density_majorant < density {
+                return Some(distance);
+            }
         }
     }
 }
@@ -75,21 +134,143 @@ impl Hittable for ConstantMedium {
 
         let ray_length = length(r.direction);
         let distance_inside_boundary = (rec2.t - rec1.t) * ray_length;
-        let hit_distance = self.neg_inv_density * (random_real().ln()) as Real;
-
-        if hit_distance > distance_inside_boundary {
-            return None;
-        }
+        let hit_distance = match self.density_volume {
+            Some(ref volume) => {
+                self.track_delta(volume.as_ref(), r, rec1.t, distance_inside_boundary)?
+            }
+            None => {
+                let hit_distance = self.neg_inv_density * (random_real().ln()) as Real;
+                if hit_distance > distance_inside_boundary {
+                    return None;
+                }
+                hit_distance
+            }
+        };
 
         let t = rec1.t + hit_distance / ray_length;
-        Some(HitRecord {
-            p: r.at(t),
-            normal: (1f32, 0f32, 0f32).into(),
+        //
+        // there is no surface, the normal faces the ray
+        Some(HitRecord::new(
+            r.at(t),
+            -normalize(r.direction),
+            r,
             t,
-            mtl: self.phase_function.clone(),
-            front_face: true,
-            u: rec1.u,
-            v: rec1.v,
-        })
+            self.phase_function.clone(),
+            rec1.u,
+            rec1.v,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aabb3::Aabb;
+    use crate::block::Block;
+    use crate::lambertian::Lambertian;
+    use crate::solid_color_texture::SolidColorTexture;
+    use crate::types::{Point, Ray, Vec3};
+    use crate::volume_texture::GridVolume;
+
+    #[test]
+    fn test_density_volume_scatters_more_in_dense_region() {
+        //
+        // unit cube of fog, dense at x = 1 and sparse at x = -1
+        let boundary = Arc::new(Block::new(
+            Point::broadcast(-1 as Real),
+            Point::broadcast(1 as Real),
+            Arc::new(Lambertian::new((0.5, 0.5, 0.5))),
+        ));
+        let density = Arc::new(GridVolume::new(
+            Aabb::new(Point::broadcast(-1 as Real), Point::broadcast(1 as Real)),
+            [2, 1, 1],
+            vec![0.01, 2.0],
+        ));
+        let medium = ConstantMedium::with_density_volume(
+            boundary,
+            Arc::new(SolidColorTexture::new((1 as Real, 1 as Real, 1 as Real))),
+            density,
+        );
+
+        let scattered = |x: Real| {
+            (0..2000)
+                .filter(|_| {
+                    let r = Ray::new(
+                        Point::new(x, 0 as Real, -5 as Real),
+                        Vec3::new(0 as Real, 0 as Real, 1 as Real),
+                        0 as Real,
+                    );
+                    medium.hit(&r, 0.001 as Real, Real::MAX).is_some()
+                })
+                .count()
+        };
+
+        let sparse = scattered(-0.9 as Real);
+        let dense = scattered(0.9 as Real);
+        assert!(dense > 3 * sparse, "dense {}, sparse {}", dense, sparse);
+    }
+
+    #[test]
+    fn test_density_gradient_matches_the_analytic_transmittance() {
+        //
+        // fog slab between z = 0 and z = 2, the density grows linearly with z,
+        // sigma(z) = 0.1 + 0.9 z, the optical depth from the entry is 0.1 z + 0.45 z^2
+        let boundary = Arc::new(Block::new(
+            Point::new(-1 as Real, -1 as Real, 0 as Real),
+            Point::new(1 as Real, 1 as Real, 2 as Real),
+            Arc::new(Lambertian::new((0.5, 0.5, 0.5))),
+        ));
+        let density = Arc::new(GridVolume::new(
+            Aabb::new(
+                Point::new(-1 as Real, -1 as Real, 0 as Real),
+                Point::new(1 as Real, 1 as Real, 2 as Real),
+            ),
+            [1, 1, 2],
+            vec![0.1, 1.9],
+        ));
+        let medium = ConstantMedium::with_density_volume(
+            boundary,
+            Arc::new(SolidColorTexture::new((1 as Real, 1 as Real, 1 as Real))),
+            density,
+        );
+
+        const RAYS: usize = 20_000;
+        let r = Ray::new(
+            Point::new(0 as Real, 0 as Real, -5 as Real),
+            Vec3::new(0 as Real, 0 as Real, 1 as Real),
+            0 as Real,
+        );
+        let scatter_depths = (0..RAYS)
+            .filter_map(|_| medium.hit(&r, 0.001 as Real, Real::MAX).map(|rec| rec.p.z))
+            .collect::<Vec<_>>();
+
+        let optical_depth = |z: Real| 0.1 as Real * z + 0.45 as Real * z * z;
+        let scattered_before =
+            |z: Real| scatter_depths.iter().filter(|&&d| d < z).count() as Real / RAYS as Real;
+
+        [0.5 as Real, 1 as Real, 1.5 as Real, 2 as Real]
+            .iter()
+            .for_each(|&z| {
+                let expected = 1 as Real - (-optical_depth(z)).exp();
+                let sampled = scattered_before(z);
+                assert!(
+                    (sampled - expected).abs() < 0.015 as Real,
+                    "z {} sampled {} expected {}",
+                    z,
+                    sampled,
+                    expected
+                );
+            });
+
+        //
+        // of the rays that reach each half, many more scatter in the dense one
+        let sparse = scattered_before(1 as Real);
+        let dense = (scattered_before(2 as Real) - sparse) / (1 as Real - sparse);
+        assert!(
+            dense > 1.5 as Real * sparse,
+            "dense {}, sparse {}",
+            dense,
+            sparse
+        );
     }
 }
@@ -5,64 +5,91 @@ use crate::types::{Color, Ray, Real};
 #[derive(Copy, Clone, Debug)]
 pub struct Dielectric {
     pub refraction_index: Real,
+    /// Optional per channel (r, g, b) refraction indices for dispersive glass.
+    pub dispersion: Option<[Real; 3]>,
 }
 
 impl Dielectric {
     pub fn new(refraction_index: Real) -> Dielectric {
-        Dielectric { refraction_index }
+        Dielectric {
+            refraction_index,
+            dispersion: None,
+        }
     }
-}
 
-impl Material for Dielectric {
-    fn scatter(&self, ray: &Ray, hit_record: &HitRecord) -> Option<ScatterRecord> {
+    /// Dispersive dielectric, each color channel refracts with its own index.
+    /// Plain RGB paths use the green channel's index.
+    pub fn dispersive(refraction_indices: [Real; 3]) -> Dielectric {
+        Dielectric {
+            refraction_index: refraction_indices[1],
+            dispersion: Some(refraction_indices),
+        }
+    }
+
+    fn scatter_with_index(
+        &self,
+        ray: &Ray,
+        hit_record: &HitRecord,
+        refraction_index: Real,
+    ) -> Option<ScatterRecord> {
         let etai_over_etat = if hit_record.front_face {
-            1 as Real / self.refraction_index
+            1 as Real / refraction_index
         } else {
-            self.refraction_index
+            refraction_index
         };
 
-        use math::vec3::{dot, normalize, reflect_unit_vector, refract};
+        use math::vec3::{dot, normalize};
         let uv = normalize(ray.direction);
         let cos_theta = dot(-uv, hit_record.normal).min(1 as Real);
-        let sin_theta = (1 as Real - cos_theta * cos_theta).sqrt();
 
-        if etai_over_etat * sin_theta > 1 as Real {
+        match ray.refract_ray(hit_record.p, hit_record.normal, etai_over_etat) {
             //
-            // reflect
-            Some(ScatterRecord::SpecularRec {
-                ray: Ray::new(
-                    hit_record.p,
-                    reflect_unit_vector(uv, normalize(hit_record.normal)),
-                    ray.time,
-                ),
+            // total internal reflection
+            None => Some(ScatterRecord::SpecularRec {
+                ray: ray.reflect_ray(hit_record.p, normalize(hit_record.normal)),
                 attenuation: Color::broadcast(1 as Real),
-            })
-        } else {
-            //
-            // schlick approximation
-            use crate::types::{random_real, schlick};
-            let reflect_probability = schlick(cos_theta, etai_over_etat);
-            if random_real() < reflect_probability {
-                Some(ScatterRecord::SpecularRec {
-                    ray: Ray::new(
-                        hit_record.p,
-                        reflect_unit_vector(uv, hit_record.normal),
-                        ray.time,
-                    ),
-                    attenuation: Color::broadcast(1 as Real),
-                })
-            } else {
+            }),
+            Some(refracted) => {
                 //
-                // refract
-                Some(ScatterRecord::SpecularRec {
-                    attenuation: Color::broadcast(1 as Real),
-                    ray: Ray::new(
-                        hit_record.p,
-                        refract(uv, hit_record.normal, etai_over_etat),
-                        ray.time,
-                    ),
-                })
+                // schlick approximation
+                use crate::types::{random_real, schlick};
+                let reflect_probability = schlick(cos_theta, etai_over_etat);
+                if random_real() < reflect_probability {
+                    Some(ScatterRecord::SpecularRec {
+                        ray: ray.reflect_ray(hit_record.p, hit_record.normal),
+                        attenuation: Color::broadcast(1 as Real),
+                    })
+                } else {
+                    //
+                    // refract
+                    Some(ScatterRecord::SpecularRec {
+                        attenuation: Color::broadcast(1 as Real),
+                        ray: refracted,
+                    })
+                }
             }
         }
     }
 }
+
+impl Material for Dielectric {
+    fn scatter(&self, ray: &Ray, hit_record: &HitRecord) -> Option<ScatterRecord> {
+        self.scatter_with_index(ray, hit_record, self.refraction_index)
+    }
+
+    fn scatter_channel(
+        &self,
+        ray: &Ray,
+        hit_record: &HitRecord,
+        channel: usize,
+    ) -> Option<ScatterRecord> {
+        let refraction_index = self
+            .dispersion
+            .map_or(self.refraction_index, |indices| indices[channel]);
+        self.scatter_with_index(ray, hit_record, refraction_index)
+    }
+
+    fn is_dispersive(&self) -> bool {
+        self.dispersion.is_some()
+    }
+}
@@ -40,6 +40,10 @@ impl Material for DiffuseLight {
         None
     }
 
+    fn emits_one_sided(&self) -> bool {
+        true
+    }
+
     fn emitted(
         &self,
         ray: &Ray,
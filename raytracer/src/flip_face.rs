@@ -1,6 +1,7 @@
 use std::sync::Arc;
 
-use crate::hittable::{HitRecord, Hittable};
+use crate::hittable::{HitRecord, HitStats, Hittable, PrimitiveId};
+use crate::types::{Point, Real, Vec3};
 
 pub struct FlipFace {
     pub obj: Arc<dyn Hittable>,
@@ -21,9 +22,52 @@ impl Hittable for FlipFace {
         t_min: crate::types::Real,
         t_max: crate::types::Real,
     ) -> Option<crate::hittable::HitRecord> {
-        self.obj.hit(r, t_min, t_max).map(|hit_rec| HitRecord {
-            front_face: !hit_rec.front_face,
-            ..hit_rec
-        })
+        self.hit_skipping(r, t_min, t_max, None)
+    }
+
+    fn hit_skipping(
+        &self,
+        r: &crate::types::Ray,
+        t_min: crate::types::Real,
+        t_max: crate::types::Real,
+        skip: Option<PrimitiveId>,
+    ) -> Option<HitRecord> {
+        self.obj
+            .hit_skipping(r, t_min, t_max, skip)
+            .map(|hit_rec| HitRecord {
+                front_face: !hit_rec.front_face,
+                ..hit_rec
+            })
+    }
+
+    fn hit_counted(
+        &self,
+        r: &crate::types::Ray,
+        t_min: Real,
+        t_max: Real,
+        stats: &mut HitStats,
+    ) -> Option<HitRecord> {
+        self.obj
+            .hit_counted(r, t_min, t_max, stats)
+            .map(|hit_rec| HitRecord {
+                front_face: !hit_rec.front_face,
+                ..hit_rec
+            })
+    }
+
+    fn pdf_value(&self, o: Point, v: Vec3) -> Real {
+        self.obj.pdf_value(o, v)
+    }
+
+    fn random(&self, o: Vec3) -> Vec3 {
+        self.obj.random(o)
+    }
+
+    fn faces(&self, origin: Point) -> Option<bool> {
+        self.obj.faces(origin).map(|faces| !faces)
+    }
+
+    fn node_boxes(&self, depth: u32, boxes: &mut Vec<crate::aabb3::Aabb>) {
+        self.obj.node_boxes(depth, boxes)
     }
 }
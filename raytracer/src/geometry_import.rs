@@ -1,6 +1,6 @@
 use std::{collections::HashMap, mem::size_of, ops::Range};
 
-use crate::{aabb3::Aabb, types::Mat4, types::Vec2, types::Vec3, types::Vec4};
+use crate::{aabb3::Aabb, types::Mat4, types::Real, types::Vec3, types::Vec4};
 
 use gltf::{buffer, image, scene::Transform};
 
@@ -29,7 +29,22 @@ pub struct GeometryNode {
     pub name: String,
     pub transform: Mat4,
     pub aabb: Aabb,
+    /// The node's triangles, indices into the geometry's vertices.
     pub indices: Vec<u32>,
+    /// Where `indices` are in the index buffer shared by all the nodes
+    /// (`ImportedGeometry::indices`). Empty for nodes that are not imported.
+    pub index_range: Range<usize>,
+}
+
+impl GeometryNode {
+    /// Whether the node's indices are whole triangles of vertices below `vertex_count`.
+    pub fn has_valid_triangles(&self, vertex_count: usize) -> bool {
+        self.indices.len() % 3 == 0
+            && self
+                .indices
+                .iter()
+                .all(|&idx| (idx as usize) < vertex_count)
+    }
 }
 
 impl std::default::Default for GeometryNode {
@@ -40,33 +55,34 @@ impl std::default::Default for GeometryNode {
             transform: math::mat4::consts::identity(),
             aabb: Aabb::default(),
             indices: Vec::new(),
+            index_range: 0..0,
         }
     }
 }
 
-#[derive(Copy, Clone, Debug)]
-pub struct GeometryVertex {
-    pub pos: Vec3,
-    pub normal: Vec3,
-    pub uv: Vec2,
-    pub color: Vec4,
-    pub tangent: Vec4,
-    pub pbr_buf_id: u32,
-}
-
-impl std::default::Default for GeometryVertex {
-    fn default() -> Self {
-        GeometryVertex {
-            pos: Vec3::new(0f32, 0f32, 0f32),
-            normal: Vec3::new(0f32, 0f32, 0f32),
-            uv: Vec2::new(0f32, 0f32),
-            color: Vec4::new(0f32, 0f32, 0f32, 1f32),
-            tangent: Vec4::new(0f32, 0f32, 0f32, 0f32),
-            pbr_buf_id: 0u32, // ..Default::default()
+/// Transform of a glTF node relative to its parent.
+pub fn node_matrix(node: &gltf::Node) -> Mat4 {
+    match node.transform() {
+        Transform::Matrix { matrix } => matrix.into(),
+        Transform::Decomposed {
+            translation,
+            rotation,
+            scale,
+        } => {
+            use math::quat;
+
+            let s = Mat4::non_uniform_scale(scale.into());
+            let r = quat::to_rotation_matrix(rotation.into());
+            let t = Mat4::translate(translation.into());
+
+            t * r * s
         }
     }
 }
 
+/// Vertex layout shared with the GL renderer, see `math::vertex_types::VertexPNTCT`.
+pub type GeometryVertex = math::vertex_types::VertexPNTCT<Real>;
+
 #[derive(Debug)]
 struct MaterialDef {
     name: String,
@@ -169,6 +185,11 @@ impl ImportedGeometry {
         &self.indices
     }
 
+    /// The node's indices, as a slice of the shared index buffer.
+    pub fn node_indices(&self, node: &GeometryNode) -> &[u32] {
+        &self.indices[node.index_range.clone()]
+    }
+
     fn process_materials(&mut self, gltf_doc: &gltf::Document) {
         let materials = gltf_doc
             .materials()
@@ -312,22 +333,7 @@ impl ImportedGeometry {
     }
 
     fn process_node(&mut self, node: &gltf::Node, gltf_doc: &gltf::Document, parent: Option<u32>) {
-        let node_matrix: Mat4 = match node.transform() {
-            Transform::Matrix { matrix } => matrix.into(),
-            Transform::Decomposed {
-                translation,
-                rotation,
-                scale,
-            } => {
-                use math::quat;
-
-                let s = Mat4::non_uniform_scale(scale.into());
-                let r = quat::to_rotation_matrix(rotation.into());
-                let t = Mat4::translate(translation.into());
-
-                t * r * s
-            }
-        };
+        let node_matrix = node_matrix(node);
 
         let node_id = self.nodes.len() as u32;
 
@@ -337,6 +343,7 @@ impl ImportedGeometry {
             transform: node_matrix,
             aabb: Aabb::default(),
             indices: Vec::new(),
+            index_range: 0..0,
         });
 
         node.children()
@@ -437,8 +444,9 @@ impl ImportedGeometry {
                 );
             }
 
-            let ext = &self.indices[idx_start..];
-            self.nodes[node_id as usize].indices.extend(ext);
+            let node = &mut self.nodes[node_id as usize];
+            node.index_range = idx_start..self.indices.len();
+            node.indices.extend(&self.indices[idx_start..]);
         }
     }
 
@@ -515,3 +523,67 @@ impl ImportedGeometry {
             });
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vertex_bytes_matches_vertex_layout() {
+        assert_eq!(size_of::<GeometryVertex>(), 17 * size_of::<Real>());
+
+        let geometry = ImportedGeometry {
+            nodes: Vec::new(),
+            vertices: vec![GeometryVertex::default(); 3],
+            indices: vec![0, 1, 2],
+            buffers: Vec::new(),
+            images: Vec::new(),
+            gltf_mat_2_pbr_mat_mapping: HashMap::new(),
+            pbr_materials: Vec::new(),
+            pixels_base_color: Vec::new(),
+            pixels_metallic_roughness: Vec::new(),
+            pixels_normal: Vec::new(),
+            aabb: Aabb::default(),
+        };
+        assert_eq!(geometry.vertex_bytes(), 3 * size_of::<GeometryVertex>());
+
+        //
+        // vertex buffer is uploaded as raw bytes, the last field ends the vertex
+        let last_field_end = std::mem::offset_of!(GeometryVertex, pbr_buf_id) + size_of::<u32>();
+        assert_eq!(size_of::<GeometryVertex>(), last_field_end);
+    }
+
+    #[test]
+    fn test_imported_nodes_are_whole_triangles_of_the_shared_buffers() {
+        let geometry = ImportedGeometry::import_from_file(&concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../data/models/teapot/teapot.glb"
+        ))
+        .unwrap();
+
+        let meshes = geometry
+            .nodes()
+            .iter()
+            .filter(|node| !node.indices.is_empty())
+            .collect::<Vec<_>>();
+        assert!(!meshes.is_empty());
+
+        meshes.iter().for_each(|node| {
+            assert!(node.has_valid_triangles(geometry.vertices().len()));
+            assert_eq!(geometry.node_indices(node), node.indices.as_slice());
+        });
+
+        //
+        // the node ranges don't overlap and cover the shared buffer
+        let mut ranges = meshes
+            .iter()
+            .map(|node| node.index_range.clone())
+            .collect::<Vec<_>>();
+        ranges.sort_by_key(|range| range.start);
+        assert!(ranges.windows(2).all(|w| w[0].end <= w[1].start));
+        assert_eq!(
+            ranges.iter().map(|range| range.len()).sum::<usize>(),
+            geometry.indices().len()
+        );
+    }
+}
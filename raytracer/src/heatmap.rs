@@ -0,0 +1,203 @@
+use math::colormap::ColorMap;
+use math::colors::RGBAColor;
+use serde::{Deserialize, Serialize};
+
+use crate::hittable::HitStats;
+use crate::types::{Color, Real};
+
+/// What the BVH heat map counts for every pixel.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HitStatsMetric {
+    NodeVisits,
+    PrimitiveTests,
+}
+
+/// Renders the work done by the primary rays (BVH node visits or primitive tests)
+/// instead of the image, to help with tuning the BVH. Counts are clamped to `max_count`.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct HitStatsView {
+    pub metric: HitStatsMetric,
+    pub palette: HeatmapPalette,
+    pub max_count: u32,
+}
+
+impl HitStatsView {
+    pub fn count(&self, stats: &HitStats) -> u32 {
+        match self.metric {
+            HitStatsMetric::NodeVisits => stats.node_visits,
+            HitStatsMetric::PrimitiveTests => stats.primitive_tests,
+        }
+    }
+}
+
+/// The named palettes of `math::colormap`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HeatmapPalette {
+    Pf1,
+    Pf2,
+    Pf3,
+    Pf4,
+    Pf5,
+    Pf6,
+    Pf7,
+    Pf8,
+    Turbo,
+    ViridisLike,
+}
+
+impl HeatmapPalette {
+    /// Name of the palette in `math::colormap::PALETTE_NAMES`.
+    pub fn name(self) -> &'static str {
+        match self {
+            HeatmapPalette::Pf1 => "pf1",
+            HeatmapPalette::Pf2 => "pf2",
+            HeatmapPalette::Pf3 => "pf3",
+            HeatmapPalette::Pf4 => "pf4",
+            HeatmapPalette::Pf5 => "pf5",
+            HeatmapPalette::Pf6 => "pf6",
+            HeatmapPalette::Pf7 => "pf7",
+            HeatmapPalette::Pf8 => "pf8",
+            HeatmapPalette::Turbo => "turbo",
+            HeatmapPalette::ViridisLike => "viridis-like",
+        }
+    }
+
+    pub fn colors(self) -> Vec<RGBAColor> {
+        math::colormap::palette_by_name(self.name())
+            .expect("Every heat map palette is a palette of math::colormap")
+    }
+}
+
+/// Palette as it appears in the config: (position, color) control stops, positions in
+/// [0, 1], joined by linear ramps (see `ColorMap::from_stops`).
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PaletteConfig {
+    pub stops: Vec<(Real, [u8; 3])>,
+}
+
+impl PaletteConfig {
+    const COLORS: u32 = 256;
+
+    pub fn colors(&self) -> Vec<RGBAColor> {
+        let stops = self
+            .stops
+            .iter()
+            .map(|&(pos, [r, g, b])| (pos as f32, RGBAColor::new(r, g, b)))
+            .collect::<Vec<_>>();
+
+        ColorMap::from_stops(&stops, Self::COLORS)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        match self.stops.iter().find(|(pos, _)| !pos.is_finite()) {
+            Some((pos, _)) => Err(format!(
+                "heatmap_palette has a stop at {}, the positions must be finite numbers",
+                pos
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+/// A `HitStatsView` together with its palette colors.
+pub struct HitStatsHeatmap {
+    pub view: HitStatsView,
+    palette: Vec<RGBAColor>,
+}
+
+impl HitStatsHeatmap {
+    pub fn new(view: HitStatsView) -> HitStatsHeatmap {
+        HitStatsHeatmap {
+            view,
+            palette: view.palette.colors(),
+        }
+    }
+
+    /// Uses `palette` instead of the view's named palette, unless it is empty.
+    pub fn with_palette(self, palette: Vec<RGBAColor>) -> HitStatsHeatmap {
+        if palette.is_empty() {
+            return self;
+        }
+
+        HitStatsHeatmap { palette, ..self }
+    }
+
+    pub fn colorize(&self, stats: &HitStats) -> Color {
+        let max_count = self.view.max_count.max(1);
+        let count = self.view.count(stats).min(max_count);
+        let idx = (count as usize * (self.palette.len() - 1)) / max_count as usize;
+
+        self.palette[idx].into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_palette_colors_the_heatmap() {
+        let palette: PaletteConfig =
+            ron::de::from_str("(stops: [(0.0, (0, 0, 0)), (1.0, (255, 128, 0))])").unwrap();
+        let heatmap = HitStatsHeatmap::new(HitStatsView {
+            metric: HitStatsMetric::PrimitiveTests,
+            palette: HeatmapPalette::Pf1,
+            max_count: 10,
+        })
+        .with_palette(palette.colors());
+
+        let colorize = |primitive_tests: u32| {
+            heatmap.colorize(&HitStats {
+                primitive_tests,
+                ..Default::default()
+            })
+        };
+
+        let (cold, hot) = (colorize(0), colorize(20));
+        assert_eq!((cold.r, cold.g, cold.b), (0 as Real, 0 as Real, 0 as Real));
+        assert_eq!(hot.r, 1 as Real);
+        assert!((hot.g - 128 as Real / 255 as Real).abs() < 1.0e-5);
+        assert_eq!(hot.b, 0 as Real);
+
+        let warm = colorize(5);
+        assert!(warm.r > 0.45 as Real && warm.r < 0.55 as Real);
+    }
+
+    #[test]
+    fn test_every_named_palette_is_in_the_colormap() {
+        let palettes = [
+            HeatmapPalette::Pf1,
+            HeatmapPalette::Pf2,
+            HeatmapPalette::Pf3,
+            HeatmapPalette::Pf4,
+            HeatmapPalette::Pf5,
+            HeatmapPalette::Pf6,
+            HeatmapPalette::Pf7,
+            HeatmapPalette::Pf8,
+            HeatmapPalette::Turbo,
+            HeatmapPalette::ViridisLike,
+        ];
+        assert_eq!(palettes.len(), math::colormap::PALETTE_NAMES.len());
+        palettes.iter().for_each(|palette| {
+            assert!(math::colormap::PALETTE_NAMES.contains(&palette.name()));
+            assert_eq!(palette.colors().len(), 256);
+        });
+
+        let view: HitStatsView =
+            ron::de::from_str("(metric: NodeVisits, palette: ViridisLike, max_count: 8)").unwrap();
+        assert_eq!(view.palette, HeatmapPalette::ViridisLike);
+    }
+
+    #[test]
+    fn test_palette_stops_must_be_finite() {
+        let palette = |pos: Real| PaletteConfig {
+            stops: vec![(0 as Real, [0, 0, 0]), (pos, [255, 255, 255])],
+        };
+
+        assert!(palette(1 as Real).validate().is_ok());
+        [Real::NAN, Real::INFINITY].iter().for_each(|&pos| {
+            let e = palette(pos).validate().err().unwrap();
+            assert!(e.contains("heatmap_palette"), "{}", e);
+        });
+    }
+}
@@ -0,0 +1,168 @@
+use std::sync::Arc;
+
+use crate::{
+    aabb3::Aabb,
+    geometry_import::{GeometryNode, GeometryVertex},
+    hittable::{HitRecord, Hittable, PrimitiveId},
+    image_texture::ImageTexture,
+    material::Material,
+    texture::Texture,
+    triangle_mesh::TriangleMesh,
+    types::{Point, Ray, Real, Vec2, Vec3, C_HALF_ONE, C_ZERO},
+};
+
+/// Terrain generated from the luminance of an image. The grid is centered on the
+/// origin in the XZ plane, spanning scale.x * scale.z, with heights in [0, scale.y].
+pub struct Heightfield {
+    pub scale: Vec3,
+    mesh: TriangleMesh,
+}
+
+impl Heightfield {
+    pub fn from_file<P: AsRef<std::path::Path>>(
+        p: P,
+        scale: Vec3,
+        mtl: Arc<dyn Material>,
+    ) -> Heightfield {
+        Self::new(&ImageTexture::new(p).with_srgb(false), scale, mtl)
+    }
+
+    pub fn new(image: &ImageTexture, scale: Vec3, mtl: Arc<dyn Material>) -> Heightfield {
+        let (width, height) = (image.width() as usize, image.height() as usize);
+        assert!(
+            width >= 2 && height >= 2,
+            "Heightfield image must be at least 2x2 pixels"
+        );
+
+        use math::vec3::{cross, normalize};
+
+        //
+        // one vertex per pixel
+        let mut vertices = (0..height)
+            .flat_map(|j| (0..width).map(move |i| (i, j)))
+            .map(|(i, j)| {
+                let u = i as Real / (width - 1) as Real;
+                let v = j as Real / (height - 1) as Real;
+                let c = image.value(u, 1 as Real - v, Point::broadcast(C_ZERO));
+                let luminance = math::vec3::luminance(Vec3::new(c.r, c.g, c.b));
+
+                GeometryVertex {
+                    pos: Vec3::new(
+                        (u - C_HALF_ONE) * scale.x,
+                        luminance * scale.y,
+                        (v - C_HALF_ONE) * scale.z,
+                    ),
+                    uv: Vec2::new(u, 1 as Real - v),
+                    ..Default::default()
+                }
+            })
+            .collect::<Vec<_>>();
+
+        //
+        // two triangles per cell, one node per row of cells
+        let nodes = (0..height - 1)
+            .map(|j| GeometryNode {
+                name: format!("heightfield row {}", j),
+                indices: (0..width - 1)
+                    .flat_map(|i| {
+                        let i00 = (j * width + i) as u32;
+                        let i10 = i00 + 1;
+                        let i01 = i00 + width as u32;
+                        let i11 = i01 + 1;
+                        [i00, i01, i10, i10, i01, i11]
+                    })
+                    .collect(),
+                ..Default::default()
+            })
+            .collect::<Vec<_>>();
+
+        //
+        // smooth normals, averaged from the faces sharing the vertex
+        let mut normals = vec![Vec3::broadcast(C_ZERO); vertices.len()];
+        nodes.iter().for_each(|node| {
+            node.indices.chunks(3).for_each(|tri| {
+                let (p0, p1, p2) = (
+                    vertices[tri[0] as usize].pos,
+                    vertices[tri[1] as usize].pos,
+                    vertices[tri[2] as usize].pos,
+                );
+                let face_normal = cross(p1 - p0, p2 - p0);
+                tri.iter()
+                    .for_each(|&idx| normals[idx as usize] += face_normal);
+            });
+        });
+
+        vertices
+            .iter_mut()
+            .zip(normals.iter())
+            .for_each(|(vtx, &n)| vtx.normal = normalize(n));
+
+        Heightfield {
+            scale,
+            mesh: TriangleMesh::from_vertices(
+                &vertices,
+                &nodes,
+                math::mat4::consts::identity(),
+                mtl,
+            ),
+        }
+    }
+}
+
+impl Hittable for Heightfield {
+    fn hit(&self, r: &Ray, t_min: Real, t_max: Real) -> Option<HitRecord> {
+        self.mesh.hit(r, t_min, t_max)
+    }
+
+    fn hit_skipping(
+        &self,
+        r: &Ray,
+        t_min: Real,
+        t_max: Real,
+        skip: Option<PrimitiveId>,
+    ) -> Option<HitRecord> {
+        self.mesh.hit_skipping(r, t_min, t_max, skip)
+    }
+
+    fn bounding_box(&self, time0: Real, time1: Real) -> Option<Aabb> {
+        self.mesh.bounding_box(time0, time1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lambertian::Lambertian;
+
+    #[test]
+    fn test_flat_heightmap_is_planar() {
+        let gray = [128u8, 128u8, 128u8, 255u8];
+        let pixels = gray.repeat(4 * 4);
+        let image = ImageTexture::from_pixels(4, 4, &pixels);
+        let scale = Vec3::new(10 as Real, 2 as Real, 10 as Real);
+        let terrain = Heightfield::new(
+            &image,
+            scale,
+            Arc::new(Lambertian::new((0.5 as Real, 0.5 as Real, 0.5 as Real))),
+        );
+
+        let expected_height = (128 as Real / 255 as Real) * scale.y;
+
+        let bbox = terrain.bounding_box(C_ZERO, 1 as Real).unwrap();
+        assert!((bbox.min.y - expected_height).abs() < 1.0E-4);
+        assert!((bbox.max.y - expected_height).abs() < 1.0E-4);
+
+        [(1.3, 0.7), (-3.1, 2.2), (4.2, -4.6), (-0.4, -2.9)]
+            .iter()
+            .for_each(|&(x, z)| {
+                let r = Ray::new(
+                    Point::new(x, 10 as Real, z),
+                    Vec3::new(C_ZERO, -1 as Real, C_ZERO),
+                    C_ZERO,
+                );
+                let hit = terrain.hit(&r, 0.001, Real::MAX).unwrap();
+                assert!((hit.p.y - expected_height).abs() < 1.0E-4);
+                assert!((hit.normal.y - 1 as Real).abs() < 1.0E-4);
+            });
+    }
+}
@@ -1,6 +1,97 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+
 use crate::aabb3::Aabb;
+use crate::generic_handle::GenericHandle;
 use crate::material::Material;
-use crate::types::{Point, Ray, Real, Vec3};
+use crate::types::{Color, Point, Ray, Real, Vec3};
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Primitive;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Instance;
+
+/// Identifies a transformed copy of an object (a wrapper around a shared child).
+pub type InstanceId = GenericHandle<Instance>;
+
+/// What a `PrimitiveId` identifies.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+enum PrimitiveKey {
+    /// An object of its own, from `next_primitive_id`.
+    Object(GenericHandle<Primitive>),
+    /// A part of an object (a triangle of a mesh), see `PrimitiveId::part`.
+    Part(GenericHandle<Primitive>, u64),
+    /// Every axis aligned rect in the plane `axis` = k (the bits of k), a ray leaving the
+    /// plane can't hit any of them.
+    AxisPlane(u8, u64),
+}
+
+/// Identifies a single primitive, so a ray spawned on its surface can skip it. Copies of
+/// a primitive placed by different instances share the primitive but not the id, the
+/// instances a hit comes out of are folded into it.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct PrimitiveId {
+    primitive: PrimitiveKey,
+    /// The instances between the primitive and the caller, 0 for none.
+    instances: u32,
+}
+
+impl PrimitiveId {
+    /// Id of the part `part` of the object with this id, for objects made of many
+    /// primitives (the triangles of a mesh).
+    pub fn part(self, part: u64) -> PrimitiveId {
+        match self.primitive {
+            PrimitiveKey::Object(object) => PrimitiveId {
+                primitive: PrimitiveKey::Part(object, part),
+                ..self
+            },
+            _ => self,
+        }
+    }
+
+    /// Id shared by the axis aligned rects in the plane where the coordinate `axis`
+    /// (0 = x, 1 = y, 2 = z) is `k`.
+    pub fn axis_plane(axis: usize, k: Real) -> PrimitiveId {
+        PrimitiveId {
+            primitive: PrimitiveKey::AxisPlane(axis as u8, k.to_bits().into()),
+            instances: 0,
+        }
+    }
+
+    /// The id as seen from outside of `instance`, for hits leaving it.
+    pub fn instanced(self, instance: InstanceId) -> PrimitiveId {
+        PrimitiveId {
+            instances: self.instances.rotate_left(7) ^ instance.handle(),
+            ..self
+        }
+    }
+
+    /// The id as seen from inside of `instance`, the inverse of `instanced`. Passed to the
+    /// children of the instance, they only match it if the primitive was hit through it.
+    pub fn uninstanced(self, instance: InstanceId) -> PrimitiveId {
+        PrimitiveId {
+            instances: (self.instances ^ instance.handle()).rotate_right(7),
+            ..self
+        }
+    }
+}
+
+/// Returns a new id, unique for the lifetime of the program.
+pub fn next_primitive_id() -> PrimitiveId {
+    static NEXT_ID: AtomicU32 = AtomicU32::new(0);
+    PrimitiveId {
+        primitive: PrimitiveKey::Object(GenericHandle::from(
+            NEXT_ID.fetch_add(1, Ordering::Relaxed),
+        )),
+        instances: 0,
+    }
+}
+
+/// Returns a new instance id, unique for the lifetime of the program and never 0.
+pub fn next_instance_id() -> InstanceId {
+    static NEXT_ID: AtomicU32 = AtomicU32::new(1);
+    InstanceId::from(NEXT_ID.fetch_add(1, Ordering::Relaxed))
+}
 
 #[derive(Clone)]
 pub struct HitRecord {
@@ -11,6 +102,16 @@ pub struct HitRecord {
     pub front_face: bool,
     pub u: Real,
     pub v: Real,
+    /// Surface tangent (world space), for primitives that provide one.
+    pub tangent: Option<Vec3>,
+    /// The primitive that was hit, if it has an id.
+    pub primitive: Option<PrimitiveId>,
+    /// Color interpolated from the vertices of the primitive, for meshes shaded with
+    /// their vertex colors. Diffuse materials tint their albedo with it.
+    pub vertex_color: Option<Color>,
+    /// Steps of the random walk the ray arrived on (see `ScatterRecord::WalkRec`), 0 for
+    /// rays that are not on one. Set by the integrator, not by the hittables.
+    pub walk_steps: u32,
 }
 
 impl HitRecord {
@@ -23,27 +124,176 @@ impl HitRecord {
         u: Real,
         v: Real,
     ) -> HitRecord {
-        let front_face = math::vec3::dot(ray.direction, outward_normal) < 0f32;
+        Self::builder(p, outward_normal, ray, t, mtl)
+            .uv(u, v)
+            .build()
+    }
 
-        HitRecord {
+    /// Starts a hit record with the required fields, the optional ones are set on the
+    /// builder. `front_face` is determined from the ray and the outward normal, the
+    /// stored normal always points against the ray.
+    pub fn builder(
+        p: Point,
+        outward_normal: Vec3,
+        ray: &Ray,
+        t: Real,
+        mtl: std::sync::Arc<dyn Material>,
+    ) -> HitRecordBuilder {
+        let mut rec = HitRecord {
             p,
-            normal: if front_face {
-                outward_normal
-            } else {
-                -outward_normal
-            },
+            normal: outward_normal,
             t,
             mtl,
-            front_face,
-            u,
-            v,
+            front_face: true,
+            u: 0 as Real,
+            v: 0 as Real,
+            tangent: None,
+            primitive: None,
+            vertex_color: None,
+            walk_steps: 0,
+        };
+        rec.set_face_normal(ray, outward_normal);
+
+        HitRecordBuilder { rec }
+    }
+
+    /// Records whether `ray` hit the front face, the side `outward_normal` points to, and
+    /// stores the normal on the side of the ray.
+    pub fn set_face_normal(&mut self, ray: &Ray, outward_normal: Vec3) {
+        self.front_face = math::vec3::dot(ray.direction, outward_normal) < 0 as Real;
+        self.normal = if self.front_face {
+            outward_normal
+        } else {
+            -outward_normal
+        };
+    }
+
+    pub fn with_primitive(self, primitive: PrimitiveId) -> HitRecord {
+        HitRecord {
+            primitive: Some(primitive),
+            ..self
+        }
+    }
+
+    /// The hit as seen from outside of `instance`, see `PrimitiveId::instanced`.
+    pub fn instanced(self, instance: InstanceId) -> HitRecord {
+        HitRecord {
+            primitive: self.primitive.map(|id| id.instanced(instance)),
+            ..self
+        }
+    }
+
+    /// Footprint of the pixel the ray comes from on the surface, (dp/dx, dp/dy). None for
+    /// rays without differentials.
+    pub fn footprint(&self, ray: &Ray) -> Option<(Vec3, Vec3)> {
+        ray.footprint(self.p, self.normal)
+    }
+
+    /// Mip level for a texture with texels `texel_size` wide on the surface: log2 of the
+    /// footprint's width in texels. 0 (the full resolution) for footprints smaller than a
+    /// texel and for rays without differentials.
+    pub fn texture_lod(&self, ray: &Ray, texel_size: Real) -> Real {
+        self.footprint(ray).map_or(0 as Real, |(dpdx, dpdy)| {
+            let width = math::vec3::length(dpdx).max(math::vec3::length(dpdy));
+            (width / texel_size).log2().max(0 as Real)
+        })
+    }
+
+    /// The primitive a ray leaving the hit point in `direction` can skip. Only primitives
+    /// that are convex or flat carry an id (spheres, triangles and rects), so a ray leaving
+    /// from the outside of the surface cannot hit the same primitive again.
+    pub fn skip_for(&self, direction: Vec3) -> Option<PrimitiveId> {
+        if self.front_face && math::vec3::dot(direction, self.normal) > 0 as Real {
+            self.primitive
+        } else {
+            None
         }
     }
 }
 
+pub struct HitRecordBuilder {
+    rec: HitRecord,
+}
+
+impl HitRecordBuilder {
+    pub fn uv(mut self, u: Real, v: Real) -> Self {
+        self.rec.u = u;
+        self.rec.v = v;
+        self
+    }
+
+    pub fn tangent(mut self, tangent: Vec3) -> Self {
+        self.rec.tangent = Some(tangent);
+        self
+    }
+
+    pub fn primitive(mut self, primitive: PrimitiveId) -> Self {
+        self.rec.primitive = Some(primitive);
+        self
+    }
+
+    pub fn vertex_color(mut self, color: Color) -> Self {
+        self.rec.vertex_color = Some(color);
+        self
+    }
+
+    pub fn build(self) -> HitRecord {
+        self.rec
+    }
+}
+
+/// Acceleration structure work done while intersecting a single ray.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct HitStats {
+    pub node_visits: u32,
+    pub primitive_tests: u32,
+}
+
 pub trait Hittable: Send + Sync {
     fn hit(&self, r: &Ray, t_min: Real, t_max: Real) -> Option<HitRecord>;
+
+    /// Same as `hit`, also counts the BVH nodes visited and primitives tested.
+    /// Objects that are not containers count as a single primitive test.
+    fn hit_counted(
+        &self,
+        r: &Ray,
+        t_min: Real,
+        t_max: Real,
+        stats: &mut HitStats,
+    ) -> Option<HitRecord> {
+        stats.primitive_tests += 1;
+        self.hit(r, t_min, t_max)
+    }
+
+    /// Same as `hit`, but ignores the primitive `skip` (the surface a secondary ray
+    /// starts on). Containers and wrappers pass `skip` down to their children.
+    fn hit_skipping(
+        &self,
+        r: &Ray,
+        t_min: Real,
+        t_max: Real,
+        _skip: Option<PrimitiveId>,
+    ) -> Option<HitRecord> {
+        self.hit(r, t_min, t_max)
+    }
+
     fn bounding_box(&self, time0: Real, time1: Real) -> Option<Aabb>;
+
+    /// Short description of the object, for scene outliners and diagnostics.
+    fn name(&self) -> Option<&str> {
+        None
+    }
+
+    /// Appends the boxes of the nodes of the object's BVHs (the ones nested in them
+    /// included), from the object down to `depth` levels below it. For the debug overlays.
+    fn node_boxes(&self, _depth: u32, _boxes: &mut Vec<Aabb>) {}
+
+    /// Sphere (center, radius) enclosing the object. The default one encloses the
+    /// bounding box, objects that are round (or rotated) can provide a tighter one.
+    fn bounding_sphere(&self) -> Option<(Point, Real)> {
+        self.bounding_box(0 as Real, 1 as Real)
+            .map(|bbox| (bbox.center(), math::vec3::length(bbox.max - bbox.center())))
+    }
     fn pdf_value(&self, _o: Point, _v: Vec3) -> Real {
         0 as Real
     }
@@ -51,4 +301,420 @@ pub trait Hittable: Send + Sync {
     fn random(&self, _v: Vec3) -> Vec3 {
         math::vec3::consts::unit_x()
     }
+
+    /// For lights that only emit from their front faces (see `Material::emits_one_sided`),
+    /// whether `origin` is in front of them. None if the object can be sampled from anywhere.
+    /// Light sampling skips the lights that turn their back to the point being lit.
+    fn faces(&self, _origin: Point) -> Option<bool> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lambertian::Lambertian;
+
+    #[test]
+    fn test_front_face_and_normal_orientation() {
+        let mtl = std::sync::Arc::new(Lambertian::new((0.5 as Real, 0.5 as Real, 0.5 as Real)));
+        let p = Point::new(0 as Real, 0 as Real, 0 as Real);
+        let up = Vec3::new(0 as Real, 1 as Real, 0 as Real);
+        let down_ray = Ray::new(Point::new(0 as Real, 1 as Real, 0 as Real), -up, 0 as Real);
+        let up_ray = Ray::new(Point::new(0 as Real, -1 as Real, 0 as Real), up, 0 as Real);
+
+        //
+        // coming from the side the outward normal points to
+        let front = HitRecord::new(p, up, &down_ray, 1 as Real, mtl.clone(), 0.25, 0.75);
+        assert!(front.front_face);
+        assert_eq!(front.normal, up);
+        assert_eq!((front.u, front.v), (0.25, 0.75));
+        assert!(front.tangent.is_none());
+
+        //
+        // from behind, the normal gets flipped to face the ray
+        let back = HitRecord::builder(p, up, &up_ray, 1 as Real, mtl)
+            .uv(0.25, 0.75)
+            .tangent(Vec3::new(1 as Real, 0 as Real, 0 as Real))
+            .build();
+        assert!(!back.front_face);
+        assert_eq!(back.normal, -up);
+        assert!(math::vec3::dot(back.normal, up_ray.direction) < 0 as Real);
+        assert_eq!((back.u, back.v), (front.u, front.v));
+        assert_eq!(
+            back.tangent,
+            Some(Vec3::new(1 as Real, 0 as Real, 0 as Real))
+        );
+    }
+
+    #[test]
+    fn test_every_primitive_stores_the_normal_against_the_ray() {
+        use crate::{
+            block::Block,
+            capsule::Capsule,
+            cone::Cone,
+            constant_medium::ConstantMedium,
+            cylinder::Cylinder,
+            flip_face::FlipFace,
+            geometry_import::{GeometryNode, GeometryVertex},
+            hyperboloid::Hyperboloid,
+            objects::{
+                displaced_sphere::DisplacedSphere,
+                plane::Plane,
+                sphere::{MovingSphere, Sphere},
+            },
+            paraboloid::Paraboloid,
+            rectangles::{XYRect, XZRect, YZRect},
+            sdf::SdfObject,
+            transform::{RotateY, Transform, Translate},
+            triangle_mesh::TriangleMesh,
+            types::{random_real_range, random_unit_vector, Mat4},
+        };
+        use math::vec3::{dot, normalize};
+        use std::sync::Arc;
+
+        let mtl: Arc<dyn Material> = Arc::new(Lambertian::new((0.5, 0.5, 0.5)));
+        let origin = Point::broadcast(0 as Real);
+        let sphere = || Arc::new(Sphere::new(origin, 1 as Real, mtl.clone()));
+        let triangle = {
+            let vertices = [
+                Vec3::new(-1 as Real, -1 as Real, 0 as Real),
+                Vec3::new(1 as Real, -1 as Real, 0 as Real),
+                Vec3::new(0 as Real, 1 as Real, 0 as Real),
+            ]
+            .map(|pos| GeometryVertex {
+                pos,
+                normal: Vec3::new(0 as Real, 0 as Real, 1 as Real),
+                ..Default::default()
+            });
+            let nodes = [GeometryNode {
+                indices: vec![0, 1, 2],
+                ..Default::default()
+            }];
+            TriangleMesh::from_vertices(
+                &vertices,
+                &nodes,
+                math::mat4::consts::identity(),
+                mtl.clone(),
+            )
+        };
+
+        let primitives: Vec<(&str, Arc<dyn Hittable>)> = vec![
+            ("sphere", sphere()),
+            (
+                "moving sphere",
+                Arc::new(MovingSphere::new(
+                    origin,
+                    Point::new(0.5 as Real, 0 as Real, 0 as Real),
+                    0 as Real,
+                    1 as Real,
+                    1 as Real,
+                    mtl.clone(),
+                )),
+            ),
+            (
+                "xy rect",
+                Arc::new(XYRect {
+                    x0: -1 as Real,
+                    x1: 1 as Real,
+                    y0: -1 as Real,
+                    y1: 1 as Real,
+                    k: 0 as Real,
+                    mtl: mtl.clone(),
+                }),
+            ),
+            (
+                "xz rect",
+                Arc::new(XZRect {
+                    x0: -1 as Real,
+                    x1: 1 as Real,
+                    z0: -1 as Real,
+                    z1: 1 as Real,
+                    k: 0 as Real,
+                    mtl: mtl.clone(),
+                }),
+            ),
+            (
+                "yz rect",
+                Arc::new(YZRect {
+                    y0: -1 as Real,
+                    y1: 1 as Real,
+                    z0: -1 as Real,
+                    z1: 1 as Real,
+                    k: 0 as Real,
+                    mtl: mtl.clone(),
+                }),
+            ),
+            (
+                "block",
+                Arc::new(Block::new(
+                    Point::broadcast(-1 as Real),
+                    Point::broadcast(1 as Real),
+                    mtl.clone(),
+                )),
+            ),
+            (
+                "cylinder",
+                Arc::new(Cylinder::new(
+                    1 as Real,
+                    -1 as Real,
+                    1 as Real,
+                    360 as Real,
+                    mtl.clone(),
+                )),
+            ),
+            (
+                "cone",
+                Arc::new(Cone::new(1 as Real, 1 as Real, 360 as Real, mtl.clone())),
+            ),
+            (
+                "capsule",
+                Arc::new(Capsule::new(
+                    Point::new(0 as Real, -0.5 as Real, 0 as Real),
+                    Point::new(0 as Real, 0.5 as Real, 0 as Real),
+                    0.5 as Real,
+                    mtl.clone(),
+                )),
+            ),
+            (
+                "paraboloid",
+                Arc::new(Paraboloid::new(
+                    1 as Real,
+                    0 as Real,
+                    1 as Real,
+                    360 as Real,
+                    mtl.clone(),
+                )),
+            ),
+            (
+                "hyperboloid",
+                Arc::new(Hyperboloid::new(
+                    Vec3::new(1 as Real, 0 as Real, -1 as Real),
+                    Vec3::new(0 as Real, 1 as Real, 1 as Real),
+                    360 as Real,
+                    mtl.clone(),
+                )),
+            ),
+            (
+                "plane",
+                Arc::new(Plane::new(
+                    origin,
+                    Vec3::new(0 as Real, 1 as Real, 0 as Real),
+                    mtl.clone(),
+                )),
+            ),
+            (
+                "displaced sphere",
+                Arc::new(DisplacedSphere::new(
+                    Sphere::new(origin, 1 as Real, mtl.clone()),
+                    0.1 as Real,
+                    2 as Real,
+                )),
+            ),
+            ("sdf", Arc::new(SdfObject::sphere(1 as Real, mtl.clone()))),
+            ("triangle mesh", Arc::new(triangle)),
+            (
+                "constant medium",
+                Arc::new(ConstantMedium::from_colored_object(
+                    sphere(),
+                    (0.5 as Real, 0.5 as Real, 0.5 as Real),
+                    2 as Real,
+                )),
+            ),
+            ("flip face", Arc::new(FlipFace { obj: sphere() })),
+            (
+                "translate",
+                Arc::new(Translate::new(
+                    sphere(),
+                    Vec3::new(0.25 as Real, 0 as Real, 0 as Real),
+                )),
+            ),
+            ("rotate y", Arc::new(RotateY::new(sphere(), 30 as Real))),
+            (
+                "transform",
+                Arc::new(Transform::new(
+                    Mat4::non_uniform_scale(Vec3::new(1 as Real, 2 as Real, 0.5 as Real)),
+                    sphere(),
+                )),
+            ),
+        ];
+
+        primitives.iter().for_each(|(name, obj)| {
+            //
+            // from outside, at points around the object, and from inside
+            let rays = (0..500).map(|i| {
+                let target = Point::new(
+                    random_real_range(-0.9, 0.9),
+                    random_real_range(-0.9, 0.9),
+                    random_real_range(-0.9, 0.9),
+                );
+                if i % 2 == 0 {
+                    let from = random_unit_vector() * 6 as Real;
+                    Ray::new(from, target - from, 0.5 as Real)
+                } else {
+                    Ray::new(target * 0.1 as Real, random_unit_vector(), 0.5 as Real)
+                }
+            });
+
+            let hits = rays
+                .filter_map(|r| obj.hit(&r, 0.001 as Real, Real::MAX).map(|rec| (r, rec)))
+                .map(|(r, rec)| {
+                    let cos = dot(normalize(r.direction), rec.normal);
+                    assert!(cos <= 1.0e-4, "{}: dot(ray, normal) = {}", name, cos);
+                })
+                .count();
+            assert!(hits > 0, "{} was never hit", name);
+        });
+
+        //
+        // moved objects keep the side they were hit on
+        let translated = Translate::new(sphere(), Vec3::new(0.25 as Real, 0 as Real, 0 as Real));
+        let from_inside = Ray::new(
+            Point::new(0.25 as Real, 0 as Real, 0 as Real),
+            Vec3::new(0 as Real, 1 as Real, 0 as Real),
+            0 as Real,
+        );
+        assert!(
+            !translated
+                .hit(&from_inside, 0.001 as Real, Real::MAX)
+                .unwrap()
+                .front_face
+        );
+    }
+
+    #[test]
+    fn test_grazing_rays_skip_the_flat_primitive_they_leave() {
+        use crate::{
+            geometry_import::{GeometryNode, GeometryVertex},
+            hittable_list::HittableList,
+            objects::triangle::Triangle,
+            rectangles::{XZRect, YZRect},
+            triangle_mesh::TriangleMesh,
+        };
+        use std::sync::Arc;
+
+        let mtl: Arc<dyn Material> = Arc::new(Lambertian::new((0.5, 0.5, 0.5)));
+        let up = Vec3::new(0 as Real, 1 as Real, 0 as Real);
+        let floor = |x0: Real, x1: Real| XZRect {
+            x0,
+            x1,
+            z0: -1 as Real,
+            z1: 1 as Real,
+            k: 0 as Real,
+            mtl: mtl.clone(),
+        };
+        //
+        // two triangles facing up, the ray stays over the first one
+        let corners = [
+            Vec3::new(-1 as Real, 0 as Real, -1 as Real),
+            Vec3::new(-1 as Real, 0 as Real, 1 as Real),
+            Vec3::new(1 as Real, 0 as Real, -1 as Real),
+            Vec3::new(1 as Real, 0 as Real, 1 as Real),
+        ];
+        let mesh = TriangleMesh::from_vertices(
+            &corners.map(|pos| GeometryVertex {
+                pos,
+                normal: up,
+                ..Default::default()
+            }),
+            &[GeometryNode {
+                indices: vec![0, 1, 2, 2, 1, 3],
+                ..Default::default()
+            }],
+            math::mat4::consts::identity(),
+            mtl.clone(),
+        );
+
+        let flat: [(&str, Arc<dyn Hittable>); 3] = [
+            ("rect", Arc::new(floor(-1 as Real, 1 as Real))),
+            ("mesh", Arc::new(mesh)),
+            (
+                "triangle",
+                Arc::new(Triangle::new(
+                    corners[0],
+                    corners[1],
+                    corners[2],
+                    mtl.clone(),
+                )),
+            ),
+        ];
+
+        flat.iter().for_each(|(name, obj)| {
+            let primary = Ray::new(
+                Point::new(-0.5 as Real, 1 as Real, -0.5 as Real),
+                -up,
+                0 as Real,
+            );
+            let rec = obj.hit(&primary, 0.001 as Real, Real::MAX).unwrap();
+            assert!(rec.primitive.is_some(), "{}", name);
+
+            //
+            // the hit point ended up slightly below the surface, the grazing bounce
+            // crosses it again past the epsilon
+            let bounce = Ray::new(
+                rec.p - up * 1.0e-5 as Real,
+                Vec3::new(-1 as Real, 0.0005 as Real, 0 as Real),
+                0 as Real,
+            );
+            assert!(
+                obj.hit(&bounce, 0.001 as Real, Real::MAX).is_some(),
+                "{}",
+                name
+            );
+
+            let skip = rec.skip_for(bounce.direction);
+            assert!(
+                obj.hit_skipping(&bounce, 0.001 as Real, Real::MAX, skip)
+                    .is_none(),
+                "{}",
+                name
+            );
+        });
+
+        //
+        // the rects of the plane are skipped together, the ones of other planes are not
+        let rec = floor(-1 as Real, 1 as Real)
+            .hit(
+                &Ray::new(
+                    Point::new(0.5 as Real, 1 as Real, 0 as Real),
+                    -up,
+                    0 as Real,
+                ),
+                0.001 as Real,
+                Real::MAX,
+            )
+            .unwrap();
+        let bounce = Ray::new(
+            rec.p - up * 1.0e-5 as Real,
+            Vec3::new(1 as Real, 0.0005 as Real, 0 as Real),
+            0 as Real,
+        );
+        let mut world = HittableList::new();
+        world.add(Arc::new(floor(1 as Real, 2 as Real)));
+        assert!(world
+            .hit_skipping(
+                &bounce,
+                0.001 as Real,
+                Real::MAX,
+                rec.skip_for(bounce.direction)
+            )
+            .is_none());
+
+        world.add(Arc::new(YZRect {
+            y0: -1 as Real,
+            y1: 1 as Real,
+            z0: -1 as Real,
+            z1: 1 as Real,
+            k: 5 as Real,
+            mtl,
+        }));
+        assert!(world
+            .hit_skipping(
+                &bounce,
+                0.001 as Real,
+                Real::MAX,
+                rec.skip_for(bounce.direction)
+            )
+            .is_some());
+    }
 }
@@ -1,26 +1,117 @@
 #![allow(dead_code)]
 
-use crate::hittable::{HitRecord, Hittable};
+use std::sync::Mutex;
+
+use crate::aabb3::Aabb;
+use crate::hittable::{HitRecord, HitStats, Hittable, PrimitiveId};
 use crate::types::{random_int, Ray, Real};
 
-#[derive(Clone)]
+/// Merged bounding box of the list, for the time interval it was computed for.
+#[derive(Copy, Clone, Debug)]
+struct CachedBoundingBox {
+    time0: Real,
+    time1: Real,
+    bbox: Option<Aabb>,
+}
+
 pub struct HittableList {
     objects: Vec<std::sync::Arc<dyn Hittable>>,
+    bbox_cache: Mutex<Option<CachedBoundingBox>>,
 }
 
 impl HittableList {
     pub fn new() -> HittableList {
         HittableList {
             objects: Vec::new(),
+            bbox_cache: Mutex::new(None),
         }
     }
 
     pub fn clear(&mut self) {
-        self.objects.clear()
+        self.objects.clear();
+        self.invalidate_bbox_cache();
     }
 
     pub fn add(&mut self, object: std::sync::Arc<dyn Hittable>) {
         self.objects.push(object);
+        self.invalidate_bbox_cache();
+    }
+
+    pub fn objects(&self) -> &[std::sync::Arc<dyn Hittable>] {
+        &self.objects
+    }
+
+    pub fn objects_len(&self) -> usize {
+        self.objects.len()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, std::sync::Arc<dyn Hittable>> {
+        self.objects.iter()
+    }
+
+    pub fn remove(&mut self, index: usize) -> std::sync::Arc<dyn Hittable> {
+        let object = self.objects.remove(index);
+        self.invalidate_bbox_cache();
+        object
+    }
+
+    fn invalidate_bbox_cache(&mut self) {
+        *self.bbox_cache.get_mut().unwrap() = None;
+    }
+
+    //
+    // An object without a bounding box makes the whole list unbounded.
+    fn compute_bounding_box(&self, time0: Real, time1: Real) -> Option<Aabb> {
+        self.objects
+            .iter()
+            .map(|object| object.bounding_box(time0, time1))
+            .reduce(|accum_box, this_box| {
+                accum_box
+                    .zip(this_box)
+                    .map(|(a, b)| crate::aabb3::merge_aabbs(&a, &b))
+            })
+            .flatten()
+    }
+
+    //
+    // every object is tested up to the closest hit so far, like the BVH does
+    fn closest_hit<F>(&self, t_max: Real, mut hit_object: F) -> Option<HitRecord>
+    where
+        F: FnMut(&dyn Hittable, Real) -> Option<HitRecord>,
+    {
+        let mut closest: Option<HitRecord> = None;
+
+        self.objects.iter().for_each(|obj| {
+            let t_closest = closest.as_ref().map_or(t_max, |hit| hit.t);
+            if let Some(hit) = hit_object(obj.as_ref(), t_closest) {
+                //
+                // coincident surfaces (faces of touching blocks) hit at the same t, the one
+                // facing the ray wins whatever order they were added in
+                let closer = closest.as_ref().map_or(true, |c| {
+                    hit.t < c.t || (hit.t == c.t && hit.front_face && !c.front_face)
+                });
+                if closer {
+                    closest = Some(hit);
+                }
+            }
+        });
+
+        closest
+    }
+}
+
+impl Default for HittableList {
+    fn default() -> HittableList {
+        HittableList::new()
+    }
+}
+
+impl std::clone::Clone for HittableList {
+    fn clone(&self) -> Self {
+        HittableList {
+            objects: self.objects.clone(),
+            bbox_cache: Mutex::new(*self.bbox_cache.lock().unwrap()),
+        }
     }
 }
 
@@ -31,46 +122,357 @@ impl std::iter::FromIterator<std::sync::Arc<dyn Hittable>> for HittableList {
     {
         HittableList {
             objects: Vec::from_iter(i),
+            bbox_cache: Mutex::new(None),
         }
     }
 }
 
+impl<'a> std::iter::IntoIterator for &'a HittableList {
+    type Item = &'a std::sync::Arc<dyn Hittable>;
+    type IntoIter = std::slice::Iter<'a, std::sync::Arc<dyn Hittable>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
 impl Hittable for HittableList {
     fn hit(&self, r: &Ray, t_min: Real, t_max: Real) -> Option<HitRecord> {
-        self.objects
-            .iter()
-            .filter_map(|obj| obj.hit(r, t_min, t_max))
-            .min_by(|hit_rec_a, hit_rec_b| {
-                if hit_rec_a.t < hit_rec_b.t {
-                    std::cmp::Ordering::Less
-                } else {
-                    std::cmp::Ordering::Greater
-                }
-            })
+        self.hit_counted(r, t_min, t_max, &mut HitStats::default())
+    }
+
+    fn hit_counted(
+        &self,
+        r: &Ray,
+        t_min: Real,
+        t_max: Real,
+        stats: &mut HitStats,
+    ) -> Option<HitRecord> {
+        self.closest_hit(t_max, |obj, t_max| obj.hit_counted(r, t_min, t_max, stats))
     }
 
-    fn bounding_box(&self, time0: Real, time1: Real) -> Option<crate::aabb3::Aabb> {
+    fn hit_skipping(
+        &self,
+        r: &Ray,
+        t_min: Real,
+        t_max: Real,
+        skip: Option<PrimitiveId>,
+    ) -> Option<HitRecord> {
+        self.closest_hit(t_max, |obj, t_max| obj.hit_skipping(r, t_min, t_max, skip))
+    }
+
+    fn name(&self) -> Option<&str> {
+        Some("list")
+    }
+
+    fn node_boxes(&self, depth: u32, boxes: &mut Vec<Aabb>) {
         self.objects
             .iter()
-            .filter_map(|object| object.bounding_box(time0, time1))
-            .reduce(|accum_box, this_box| crate::aabb3::merge_aabbs(&accum_box, &this_box))
+            .for_each(|obj| obj.node_boxes(depth, boxes));
+    }
+
+    fn bounding_box(&self, time0: Real, time1: Real) -> Option<Aabb> {
+        let mut cache = self.bbox_cache.lock().unwrap();
+
+        match *cache {
+            Some(cached) if cached.time0 == time0 && cached.time1 == time1 => cached.bbox,
+            _ => {
+                let bbox = self.compute_bounding_box(time0, time1);
+                *cache = Some(CachedBoundingBox { time0, time1, bbox });
+                bbox
+            }
+        }
     }
 
     fn pdf_value(&self, o: crate::types::Point, v: crate::types::Vec3) -> Real {
-        if self.objects.is_empty() {
-            return 0 as Real;
+        match self.objects.as_slice() {
+            [] => return 0 as Real,
+            [obj] if obj.faces(o) != Some(false) => return obj.pdf_value(o, v),
+            _ => {}
         }
 
-        let weight = 1 as Real / self.objects.len() as Real;
+        //
+        // lights turning their back to o are never picked, the others share the samples
+        let (count, sum) = self
+            .objects
+            .iter()
+            .filter(|obj| obj.faces(o) != Some(false))
+            .fold((0, 0 as Real), |(count, sum), obj| {
+                (count + 1, sum + obj.pdf_value(o, v))
+            });
 
-        self.objects
+        if count == 0 {
+            0 as Real
+        } else {
+            sum / count as Real
+        }
+    }
+
+    fn random(&self, o: crate::types::Vec3) -> crate::types::Vec3 {
+        let facing = |obj: &&std::sync::Arc<dyn Hittable>| obj.faces(o) != Some(false);
+
+        match self.objects.as_slice() {
+            [obj] if facing(&obj) => return obj.random(o),
+            _ => {}
+        }
+
+        match self.objects.iter().filter(facing).count() {
+            //
+            // same as an object that can't be sampled
+            0 => math::vec3::consts::unit_x(),
+            count => self
+                .objects
+                .iter()
+                .filter(facing)
+                .nth(random_int(0, count as i32 - 1) as usize)
+                .unwrap()
+                .random(o),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lambertian::Lambertian;
+    use crate::objects::sphere::Sphere;
+    use crate::types::Vec3;
+    use std::sync::Arc;
+
+    fn sphere(center: (Real, Real, Real), radius: Real) -> Arc<dyn Hittable> {
+        Arc::new(Sphere::new(
+            Vec3::new(center.0, center.1, center.2),
+            radius,
+            Arc::new(Lambertian::new((0.5 as Real, 0.5 as Real, 0.5 as Real))),
+        ))
+    }
+
+    fn same_box(a: Option<Aabb>, b: Option<Aabb>) -> bool {
+        match (a, b) {
+            (Some(a), Some(b)) => a.min == b.min && a.max == b.max,
+            (None, None) => true,
+            _ => false,
+        }
+    }
+
+    #[test]
+    fn test_cached_bbox_matches_computed_and_updates() {
+        let mut world = HittableList::new();
+        assert!(world.bounding_box(0 as Real, 1 as Real).is_none());
+
+        world.add(sphere((0 as Real, 0 as Real, 0 as Real), 1 as Real));
+        let cached = world.bounding_box(0 as Real, 1 as Real);
+        assert!(same_box(
+            cached,
+            world.compute_bounding_box(0 as Real, 1 as Real)
+        ));
+        //
+        // second call is served from the cache
+        assert!(same_box(cached, world.bounding_box(0 as Real, 1 as Real)));
+
+        world.add(sphere((5 as Real, 0 as Real, 0 as Real), 1 as Real));
+        let updated = world.bounding_box(0 as Real, 1 as Real).unwrap();
+        assert!(same_box(
+            Some(updated),
+            world.compute_bounding_box(0 as Real, 1 as Real)
+        ));
+        assert_eq!(updated.max.x, 6 as Real);
+
+        world.remove(1);
+        assert_eq!(
+            world.bounding_box(0 as Real, 1 as Real).unwrap().max.x,
+            1 as Real
+        );
+
+        world.clear();
+        assert!(world.bounding_box(0 as Real, 1 as Real).is_none());
+    }
+
+    #[test]
+    fn test_unbounded_object_makes_list_unbounded() {
+        let mut world = HittableList::new();
+        world.add(sphere((0 as Real, 0 as Real, 0 as Real), 1 as Real));
+        world.add(Arc::new(HittableList::new()));
+        assert!(world.bounding_box(0 as Real, 1 as Real).is_none());
+    }
+
+    #[test]
+    fn test_count_and_iteration() {
+        let objects = vec![
+            sphere((0 as Real, 0 as Real, 0 as Real), 1 as Real),
+            Arc::new(HittableList::new()),
+            sphere((5 as Real, 0 as Real, 0 as Real), 2 as Real),
+        ];
+
+        let mut world = HittableList::new();
+        objects.iter().for_each(|obj| world.add(obj.clone()));
+        assert_eq!(world.objects_len(), 3);
+
+        assert!(world
             .iter()
-            .fold(0 as Real, |sum, obj| sum + weight * obj.pdf_value(o, v))
+            .zip(objects.iter())
+            .all(|(a, b)| Arc::ptr_eq(a, b)));
+        assert_eq!(
+            (&world)
+                .into_iter()
+                .map(|obj| obj.name())
+                .collect::<Vec<_>>(),
+            vec![Some("sphere"), Some("list"), Some("sphere")]
+        );
+        assert_eq!(
+            world
+                .iter()
+                .filter_map(|obj| obj.bounding_box(0 as Real, 1 as Real))
+                .count(),
+            2
+        );
+
+        world.remove(0);
+        assert_eq!(world.objects_len(), 2);
+        assert_eq!(world.iter().count(), 2);
+    }
+
+    #[test]
+    fn test_empty_and_single_object_lists() {
+        let origin = Vec3::new(0 as Real, 0 as Real, 5 as Real);
+        let towards_sphere = Vec3::new(0 as Real, 0 as Real, -1 as Real);
+        let r = Ray::new(origin, towards_sphere, 0 as Real);
+
+        let empty = HittableList::new();
+        assert!(empty.hit(&r, 0.001 as Real, Real::MAX).is_none());
+        assert_eq!(empty.pdf_value(origin, towards_sphere), 0 as Real);
+        assert_eq!(empty.random(origin), math::vec3::consts::unit_x());
+
+        let object = sphere((0 as Real, 0 as Real, 0 as Real), 1 as Real);
+        let mut single = HittableList::new();
+        single.add(object.clone());
+
+        let hit = single.hit(&r, 0.001 as Real, Real::MAX).unwrap();
+        assert_eq!(hit.t, object.hit(&r, 0.001 as Real, Real::MAX).unwrap().t);
+        assert_eq!(
+            single.pdf_value(origin, towards_sphere),
+            object.pdf_value(origin, towards_sphere)
+        );
+        assert!(single.pdf_value(origin, towards_sphere) > 0 as Real);
+
+        //
+        // samples point at the only object
+        (0..100).for_each(|_| {
+            let dir = single.random(origin);
+            assert!(object
+                .hit(&Ray::new(origin, dir, 0 as Real), 0.001 as Real, Real::MAX)
+                .is_some());
+        });
     }
 
-    fn random(&self, v: crate::types::Vec3) -> crate::types::Vec3 {
-        let num_objects = (self.objects.len() - 1) as i32;
+    #[test]
+    fn test_one_sided_light_is_only_sampled_from_its_front() {
+        use crate::diffuse_light::DiffuseLight;
+        use crate::flip_face::FlipFace;
+        use crate::rectangles::XZRect;
+
+        let rect = |mtl: Arc<dyn crate::material::Material>| {
+            Arc::new(XZRect {
+                x0: -1 as Real,
+                x1: 1 as Real,
+                z0: -1 as Real,
+                z1: 1 as Real,
+                k: 2 as Real,
+                mtl,
+            })
+        };
+
+        //
+        // ceiling light, emitting down
+        let lamp: Arc<dyn Hittable> = Arc::new(FlipFace {
+            obj: rect(Arc::new(DiffuseLight::from((
+                4 as Real, 4 as Real, 4 as Real,
+            )))),
+        });
+        let other = sphere((10 as Real, 0 as Real, 0 as Real), 1 as Real);
+        let mut lights = HittableList::new();
+        lights.add(lamp.clone());
+        lights.add(other.clone());
+
+        let below = Vec3::new(0 as Real, 0 as Real, 0 as Real);
+        let above = Vec3::new(0 as Real, 4 as Real, 0 as Real);
+        let up = Vec3::new(0 as Real, 1 as Real, 0 as Real);
+
+        assert_eq!(lamp.faces(below), Some(true));
+        assert_eq!(lamp.faces(above), Some(false));
+        assert!(lights.pdf_value(below, up) > 0 as Real);
+        assert_eq!(lights.pdf_value(above, -up), 0 as Real);
+
+        //
+        // from above only the sphere is sampled, from below both are
+        let hits_lamp = |origin: Vec3, dir: Vec3| {
+            lamp.hit(&Ray::new(origin, dir, 0 as Real), 0.001 as Real, Real::MAX)
+                .is_some()
+        };
+        let from_below = (0..200)
+            .filter(|_| hits_lamp(below, lights.random(below)))
+            .count();
+        assert!(from_below > 50 && from_below < 200);
+        (0..200).for_each(|_| {
+            let dir = lights.random(above);
+            assert!(!hits_lamp(above, dir));
+            assert!(other
+                .hit(&Ray::new(above, dir, 0 as Real), 0.001 as Real, Real::MAX)
+                .is_some());
+        });
+
+        //
+        // surfaces that don't emit are sampled from both sides
+        let plate: Arc<dyn Hittable> = rect(Arc::new(Lambertian::new((0.5, 0.5, 0.5))));
+        assert_eq!(plate.faces(above), None);
+        assert!(plate.pdf_value(above, -up) > 0 as Real);
+        assert!(plate.pdf_value(below, up) > 0 as Real);
+    }
+
+    #[test]
+    fn test_coincident_rectangles_hit_the_front_facing_one() {
+        use crate::{flip_face::FlipFace, material::Material, rectangles::XZRect};
+
+        let rect = |mtl: &Arc<dyn Material>| XZRect {
+            x0: -1 as Real,
+            x1: 1 as Real,
+            z0: -1 as Real,
+            z1: 1 as Real,
+            k: 0 as Real,
+            mtl: mtl.clone(),
+        };
+        let front: Arc<dyn Material> = Arc::new(Lambertian::new((1 as Real, 0 as Real, 0 as Real)));
+        let back: Arc<dyn Material> = Arc::new(Lambertian::new((0 as Real, 0 as Real, 1 as Real)));
+
+        //
+        // both in the y = 0 plane, the second one turns its back to the ray from above
+        let facing: Arc<dyn Hittable> = Arc::new(rect(&front));
+        let turned: Arc<dyn Hittable> = Arc::new(FlipFace {
+            obj: Arc::new(rect(&back)),
+        });
+        let r = Ray::new(
+            Vec3::new(0.2 as Real, 2 as Real, 0.1 as Real),
+            Vec3::new(0 as Real, -1 as Real, 0 as Real),
+            0 as Real,
+        );
+
+        [
+            vec![facing.clone(), turned.clone()],
+            vec![turned.clone(), facing.clone()],
+            vec![turned.clone(), facing, turned],
+        ]
+        .into_iter()
+        .for_each(|objects| {
+            let list = objects.into_iter().collect::<HittableList>();
+            let hit = list.hit(&r, 0.001 as Real, Real::MAX).unwrap();
+            assert_eq!(hit.t, 2 as Real);
+            assert!(hit.front_face);
+            assert!(Arc::ptr_eq(&hit.mtl, &front));
 
-        self.objects[random_int(0, num_objects) as usize].random(v)
+            let skipping = list
+                .hit_skipping(&r, 0.001 as Real, Real::MAX, None)
+                .unwrap();
+            assert!(Arc::ptr_eq(&skipping.mtl, &front));
+        });
     }
 }
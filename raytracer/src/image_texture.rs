@@ -1,10 +1,45 @@
-use crate::{texture::Texture, types::Color};
+use crate::{
+    texture::Texture,
+    types::{Color, Real},
+};
+
+/// How texture coordinates outside [0, 1] are mapped back into the image.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum WrapMode {
+    /// Coordinates are clamped, the border texels get stretched.
+    #[default]
+    Clamp,
+    /// The image tiles.
+    Repeat,
+    /// The image tiles, every other tile is mirrored so the seams match.
+    Mirror,
+}
+
+impl WrapMode {
+    pub fn apply(self, x: Real) -> Real {
+        match self {
+            WrapMode::Clamp => x.clamp(0 as Real, 1 as Real),
+            WrapMode::Repeat => x - x.floor(),
+            WrapMode::Mirror => {
+                let x = x.rem_euclid(2 as Real);
+                if x > 1 as Real {
+                    2 as Real - x
+                } else {
+                    x
+                }
+            }
+        }
+    }
+}
 
 pub struct ImageTexture {
     width: u32,
     height: u32,
     bytes_per_scanline: u32,
     pixels: Vec<u8>,
+    wrap_mode: WrapMode,
+    /// Texels are sRGB encoded and get decoded to linear when sampled.
+    is_srgb: bool,
 }
 
 impl ImageTexture {
@@ -14,37 +49,69 @@ impl ImageTexture {
             height: 0,
             bytes_per_scanline: 0,
             pixels: Vec::new(),
+            wrap_mode: WrapMode::Clamp,
+            is_srgb: false,
         }
     }
 
+    /// Loads an 8 bit image file, the texels are assumed to be sRGB encoded (use
+    /// `with_srgb(false)` for data like heights or masks). Panics if the file can't be
+    /// loaded, see `load`.
     pub fn new<P: AsRef<std::path::Path>>(p: P) -> Self {
+        Self::load(p).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Like `new`, but a file that can't be opened or decoded is an error.
+    pub fn load<P: AsRef<std::path::Path>>(p: P) -> Result<Self, String> {
         use image::io::Reader as ImageReader;
 
         let img = ImageReader::open(p.as_ref())
-            .expect(&format!(
-                "Failed to open image file {}",
-                p.as_ref().display()
-            ))
+            .map_err(|e| format!("Failed to open image file {}: {}", p.as_ref().display(), e))?
             .decode()
-            .expect(&format!("Failed to decode image {}", p.as_ref().display()))
+            .map_err(|e| format!("Failed to decode image {}: {}", p.as_ref().display(), e))?
             .into_rgba8();
 
-        Self {
+        Ok(Self {
             width: img.width(),
             height: img.height(),
             bytes_per_scanline: img.width() * 4,
             pixels: img.to_vec(),
-        }
+            wrap_mode: WrapMode::Clamp,
+            is_srgb: true,
+        })
     }
 
+    /// RGBA8 texels, returned as they are (linear) unless `with_srgb(true)` is set.
     pub fn from_pixels(width: u32, height: u32, pixels: &[u8]) -> Self {
         Self {
             width,
             height,
             bytes_per_scanline: (width * 4),
             pixels: pixels.to_vec(),
+            wrap_mode: WrapMode::Clamp,
+            is_srgb: false,
         }
     }
+
+    pub fn with_wrap_mode(self, wrap_mode: WrapMode) -> Self {
+        Self { wrap_mode, ..self }
+    }
+
+    pub fn with_srgb(self, is_srgb: bool) -> Self {
+        Self { is_srgb, ..self }
+    }
+
+    pub fn is_srgb(&self) -> bool {
+        self.is_srgb
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
 }
 
 impl Texture for ImageTexture {
@@ -59,9 +126,9 @@ impl Texture for ImageTexture {
         }
 
         //
-        // Clamp input texture coordinates to [0,1] x [1,0]
-        let u = u.clamp(0.0, 1.0);
-        let v = 1.0 - v.clamp(0.0, 1.0); // Flip V to image coordinates
+        // Wrap input texture coordinates to [0,1] x [1,0]
+        let u = self.wrap_mode.apply(u);
+        let v = 1.0 - self.wrap_mode.apply(v); // Flip V to image coordinates
 
         let mut i = (u * self.width as f32) as i32;
         let mut j = (v * self.height as f32) as i32;
@@ -77,11 +144,75 @@ impl Texture for ImageTexture {
 
         let color_scale = 1.0f32 / 255.0f32;
         let start_idx = j as usize * self.bytes_per_scanline as usize + i as usize * 4;
+        let texel = |c: usize| {
+            let x = color_scale * self.pixels[start_idx + c] as f32;
+            if self.is_srgb {
+                srgb_to_linear(x)
+            } else {
+                x
+            }
+        };
+
+        Color::new(texel(0), texel(1), texel(2))
+    }
+}
+
+/// sRGB transfer function, encoded value in [0, 1] to linear.
+pub fn srgb_to_linear(x: Real) -> Real {
+    if x <= 0.04045 as Real {
+        x / 12.92 as Real
+    } else {
+        ((x + 0.055 as Real) / 1.055 as Real).powf(2.4 as Real)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Point;
+
+    //
+    // 4x1 image, the red channel is the column index
+    fn columns(wrap_mode: WrapMode) -> ImageTexture {
+        let pixels = (0..4u8).flat_map(|i| [i, 0, 0, 255]).collect::<Vec<_>>();
+        ImageTexture::from_pixels(4, 1, &pixels).with_wrap_mode(wrap_mode)
+    }
+
+    fn column_at(tex: &ImageTexture, u: Real) -> u32 {
+        let c = tex.value(u, 0.5, Point::broadcast(0 as Real));
+        (c.r * 255 as Real).round() as u32
+    }
+
+    #[test]
+    fn test_wrap_modes() {
+        assert_eq!(WrapMode::Repeat.apply(1.5), 0.5);
+        assert_eq!(WrapMode::Mirror.apply(1.5), 0.5);
+        assert_eq!(WrapMode::Mirror.apply(-0.25), 0.25);
+        assert_eq!(WrapMode::Clamp.apply(1.5), 1 as Real);
+
+        assert_eq!(column_at(&columns(WrapMode::Repeat), 1.5), 2);
+        assert_eq!(column_at(&columns(WrapMode::Clamp), 1.5), 3);
+        assert_eq!(column_at(&columns(WrapMode::Clamp), -0.5), 0);
+        //
+        // the second tile runs backwards
+        assert_eq!(column_at(&columns(WrapMode::Repeat), 1.125), 0);
+        assert_eq!(column_at(&columns(WrapMode::Mirror), 1.125), 3);
+        assert_eq!(column_at(&columns(WrapMode::Mirror), 2.125), 0);
+    }
+
+    #[test]
+    fn test_srgb_decode() {
+        let gray = ImageTexture::from_pixels(1, 1, &[128, 128, 128, 255]);
+        let p = Point::broadcast(0 as Real);
+
+        assert!((gray.value(0.5, 0.5, p).r - 0.502 as Real).abs() < 1.0e-3);
+
+        let gray = gray.with_srgb(true);
+        let c = gray.value(0.5, 0.5, p);
+        assert!((c.r - 0.2158 as Real).abs() < 1.0e-3);
+        assert_eq!(c.r, c.b);
 
-        Color::new(
-            color_scale * self.pixels[start_idx + 0] as f32,
-            color_scale * self.pixels[start_idx + 1] as f32,
-            color_scale * self.pixels[start_idx + 2] as f32,
-        )
+        assert_eq!(srgb_to_linear(0 as Real), 0 as Real);
+        assert!((srgb_to_linear(1 as Real) - 1 as Real).abs() < 1.0e-6);
     }
 }
@@ -0,0 +1,350 @@
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    camera::{Camera, TraceOptions},
+    hittable::{HitRecord, Hittable, PrimitiveId},
+    hittable_list::HittableList,
+    material::ScatterRecord,
+    onb::Onb,
+    render_stats::RenderStats,
+    types::{random_cosine_direction, Color, Ray, Real, C_INFINITY, C_ONE, C_ZERO},
+    RaytracerParams,
+};
+
+/// Computes the light arriving at the camera along a ray.
+pub trait Integrator {
+    /// Radiance along `r`, `lights` are the objects of `world` that are sampled explicitly.
+    fn radiance(&self, r: &Ray, world: &HittableList, lights: &Arc<HittableList>) -> Color;
+}
+
+/// The integrator the pixels are rendered with.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum IntegratorKind {
+    #[default]
+    PathTracer,
+    /// Light reaching the first diffuse hit straight from the lights, no indirect light.
+    DirectLightingOnly,
+    /// Grayscale image of how open the surfaces are, only the geometry within
+    /// `max_distance` of a point occludes it.
+    AmbientOcclusion { max_distance: Real },
+}
+
+impl IntegratorKind {
+    pub fn build<'a>(
+        self,
+        params: &RaytracerParams,
+        stats: Option<&'a RenderStats>,
+    ) -> Box<dyn Integrator + 'a> {
+        match self {
+            IntegratorKind::PathTracer => Box::new(PathTracer::new(params, stats)),
+            IntegratorKind::DirectLightingOnly => Box::new(DirectLightingOnly::new(params, stats)),
+            IntegratorKind::AmbientOcclusion { max_distance } => {
+                Box::new(AmbientOcclusion { max_distance })
+            }
+        }
+    }
+}
+
+/// Full global illumination, paths bounce until they leave the scene or reach the
+/// maximum depth.
+pub struct PathTracer<'a> {
+    opts: TraceOptions<'a>,
+}
+
+impl<'a> PathTracer<'a> {
+    pub fn new(params: &RaytracerParams, stats: Option<&'a RenderStats>) -> Self {
+        Self {
+            opts: TraceOptions::new(params, stats),
+        }
+    }
+}
+
+impl<'a> Integrator for PathTracer<'a> {
+    fn radiance(&self, r: &Ray, world: &HittableList, lights: &Arc<HittableList>) -> Color {
+        Camera::ray_color(
+            r,
+            &self.opts,
+            world,
+            lights,
+            self.opts.max_depth,
+            None,
+            None,
+        )
+    }
+}
+
+/// One bounce, the first diffuse hit takes a single sample of the lights (mixed with the
+/// material, like the path tracer) and only picks up what the sampled ray hits directly.
+/// Specular bounces are followed up to the maximum depth, they have no other way to see
+/// the lights. Physically based rendering, section 14.3.
+pub struct DirectLightingOnly<'a> {
+    opts: TraceOptions<'a>,
+}
+
+impl<'a> DirectLightingOnly<'a> {
+    pub fn new(params: &RaytracerParams, stats: Option<&'a RenderStats>) -> Self {
+        Self {
+            opts: TraceOptions::new(params, stats),
+        }
+    }
+
+    //
+    // light that leaves the first surface along r, background if there is none
+    fn emitted_along(&self, r: &Ray, world: &HittableList, skip: Option<PrimitiveId>) -> Color {
+        self.opts.record_ray();
+
+        match world.hit_skipping(r, 0.001 as Real, C_INFINITY, skip) {
+            Some(rec) => rec.mtl.emitted(r, &rec, rec.u, rec.v, rec.p),
+            None => TraceOptions {
+                after_diffuse: true,
+                ..self.opts
+            }
+            .miss_color(),
+        }
+    }
+
+    fn trace(
+        &self,
+        r: &Ray,
+        world: &HittableList,
+        lights: &Arc<HittableList>,
+        depth: i32,
+        skip: Option<PrimitiveId>,
+        walk_steps: u32,
+    ) -> Color {
+        if depth <= 0 {
+            self.opts.record_path_end(depth);
+            return Color::broadcast(C_ZERO);
+        }
+
+        self.opts.record_ray();
+
+        let rec = match world.hit_skipping(r, 0.001 as Real, C_INFINITY, skip) {
+            Some(rec) => HitRecord { walk_steps, ..rec },
+            None => {
+                self.opts.record_path_end(depth);
+                return self.opts.miss_color();
+            }
+        };
+
+        let emitted = rec.mtl.emitted(r, &rec, rec.u, rec.v, rec.p);
+        match rec.mtl.scatter(r, &rec) {
+            Some(ScatterRecord::SpecularRec { ray, attenuation }) => {
+                let skip = rec.skip_for(ray.direction);
+                emitted + attenuation * self.trace(&ray, world, lights, depth - 1, skip, 0)
+            }
+            Some(ScatterRecord::WalkRec {
+                ray,
+                attenuation,
+                walk_steps,
+            }) => {
+                let skip = rec.skip_for(ray.direction);
+                emitted + attenuation * self.trace(&ray, world, lights, depth - 1, skip, walk_steps)
+            }
+            Some(ScatterRecord::PdfRec { pdf, attenuation }) => {
+                self.opts.record_path_end(depth - 1);
+
+                let mixed_pdf = Camera::light_mixture_pdf(lights, rec.p, pdf);
+                let scattered_ray = Ray::new(rec.p, mixed_pdf.generate(), r.time);
+                let pdf_val = Camera::clamp_pdf(mixed_pdf.value(scattered_ray.direction));
+
+                emitted
+                    + rec.mtl.scattering(r, &rec, &scattered_ray, attenuation)
+                        * self.emitted_along(
+                            &scattered_ray,
+                            world,
+                            rec.skip_for(scattered_ray.direction),
+                        )
+                        / pdf_val
+            }
+            None => {
+                self.opts.record_path_end(depth);
+                emitted
+            }
+        }
+    }
+}
+
+impl<'a> Integrator for DirectLightingOnly<'a> {
+    fn radiance(&self, r: &Ray, world: &HittableList, lights: &Arc<HittableList>) -> Color {
+        self.trace(r, world, lights, self.opts.max_depth, None, 0)
+    }
+}
+
+/// One cosine distributed ray over the hemisphere of the first hit, white if nothing is
+/// hit within `max_distance`, black otherwise. The camera seeing nothing is white.
+pub struct AmbientOcclusion {
+    pub max_distance: Real,
+}
+
+impl Integrator for AmbientOcclusion {
+    fn radiance(&self, r: &Ray, world: &HittableList, _lights: &Arc<HittableList>) -> Color {
+        let rec = match world.hit(r, 0.001 as Real, C_INFINITY) {
+            Some(rec) => rec,
+            None => return Color::broadcast(C_ONE),
+        };
+
+        //
+        // the normal faces the ray, so the hemisphere is on the side the camera sees
+        let direction = Onb::from(rec.normal).local_from_vec(random_cosine_direction());
+        let occlusion_ray = Ray::new(rec.p, direction, r.time);
+        let max_t = self.max_distance / math::vec3::length(direction);
+
+        match world.hit_skipping(
+            &occlusion_ray,
+            0.001 as Real,
+            max_t,
+            rec.skip_for(direction),
+        ) {
+            Some(_) => Color::broadcast(C_ZERO),
+            None => Color::broadcast(C_ONE),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        diffuse_light::DiffuseLight,
+        flip_face::FlipFace,
+        lambertian::Lambertian,
+        objects::sphere::Sphere,
+        rectangles::XZRect,
+        types::{Point, Vec3, C_PI},
+    };
+
+    fn trace_options() -> TraceOptions<'static> {
+        TraceOptions {
+            background: Color::broadcast(C_ZERO),
+            ambient: Color::broadcast(C_ZERO),
+            after_diffuse: false,
+            walk_steps: 0,
+            max_depth: 8,
+            per_channel: false,
+            first_hit_light_samples: 0,
+            stats: None,
+        }
+    }
+
+    #[test]
+    fn test_ambient_occlusion_is_grayscale_and_darker_near_contacts() {
+        //
+        // unit sphere resting on a floor
+        let mut world = HittableList::new();
+        let gray = Arc::new(Lambertian::new((0.5 as Real, 0.5 as Real, 0.5 as Real)));
+        world.add(Arc::new(XZRect {
+            x0: -10 as Real,
+            x1: 10 as Real,
+            z0: -10 as Real,
+            z1: 10 as Real,
+            k: 0 as Real,
+            mtl: gray.clone(),
+        }));
+        world.add(Arc::new(Sphere::new(
+            Point::new(C_ZERO, C_ONE, C_ZERO),
+            C_ONE,
+            gray,
+        )));
+
+        let lights = Arc::new(HittableList::new());
+        let ao = AmbientOcclusion { max_distance: 2.0 };
+        let origin = Point::new(C_ZERO, 3 as Real, 5 as Real);
+
+        //
+        // average over rays at the floor next to the contact point and far from the sphere
+        let average = |target: Point| {
+            const SAMPLES: i32 = 2000;
+            (0..SAMPLES)
+                .map(|_| {
+                    let c =
+                        ao.radiance(&Ray::new(origin, target - origin, C_ZERO), &world, &lights);
+                    assert!(c.r == c.g && c.g == c.b);
+                    assert!(c.r >= C_ZERO && c.r <= C_ONE);
+                    c.r
+                })
+                .sum::<Real>()
+                / SAMPLES as Real
+        };
+
+        let near_contact = average(Point::new(C_ZERO, C_ZERO, 0.6 as Real));
+        let open_floor = average(Point::new(8 as Real, C_ZERO, -8 as Real));
+        assert!(near_contact < 0.6 as Real, "{}", near_contact);
+        assert_eq!(open_floor, C_ONE);
+        assert_eq!(average(origin + Vec3::new(C_ZERO, C_ONE, C_ZERO)), C_ONE);
+    }
+
+    #[test]
+    fn test_direct_lighting_matches_lambertian_shading_under_an_area_light() {
+        //
+        // small light 2 units above the top of a unit sphere, facing down
+        const ALBEDO: Real = 0.5;
+        const EMIT: Real = 100.0;
+        let (half_size, height) = (0.1 as Real, 3 as Real);
+
+        let mut world = HittableList::new();
+        let mut lights = HittableList::new();
+        world.add(Arc::new(Sphere::new(
+            Point::new(C_ZERO, C_ZERO, C_ZERO),
+            C_ONE,
+            Arc::new(Lambertian::new((ALBEDO, ALBEDO, ALBEDO))),
+        )));
+        let light = Arc::new(FlipFace {
+            obj: Arc::new(XZRect {
+                x0: -half_size,
+                x1: half_size,
+                z0: -half_size,
+                z1: half_size,
+                k: height,
+                mtl: Arc::new(DiffuseLight::from((EMIT, EMIT, EMIT))),
+            }),
+        });
+        world.add(light.clone());
+        lights.add(light);
+        let lights = Arc::new(lights);
+
+        //
+        // the ray hits the top of the sphere, (0, 1, 0), from the side of the light
+        let r = Ray::new(
+            Point::new(2 as Real, height, C_ZERO),
+            Vec3::new(-C_ONE, -C_ONE, C_ZERO),
+            C_ZERO,
+        );
+        let integrator = DirectLightingOnly {
+            opts: trace_options(),
+        };
+
+        const SAMPLES: i32 = 20_000;
+        let estimate = (0..SAMPLES)
+            .map(|_| integrator.radiance(&r, &world, &lights).r)
+            .sum::<Real>()
+            / SAMPLES as Real;
+
+        //
+        // irradiance at the top: integral over the light of L cos_p cos_l / d^2,
+        // reflected radiance is albedo / pi * irradiance
+        const STEPS: i32 = 100;
+        let cell = 2 as Real * half_size / STEPS as Real;
+        let irradiance = (0..STEPS * STEPS)
+            .map(|i| {
+                let x = -half_size + ((i % STEPS) as Real + 0.5 as Real) * cell;
+                let z = -half_size + ((i / STEPS) as Real + 0.5 as Real) * cell;
+                let h = height - C_ONE;
+                let d2 = x * x + z * z + h * h;
+                //
+                // both cosines are h / d
+                EMIT * h * h / (d2 * d2) * cell * cell
+            })
+            .sum::<Real>();
+        let expected = ALBEDO / C_PI * irradiance;
+
+        assert!(
+            (estimate - expected).abs() < 0.03 as Real * expected,
+            "{} {}",
+            estimate,
+            expected
+        );
+    }
+}
@@ -32,6 +32,10 @@ impl Lambertian {
 impl Material for Lambertian {
     fn scatter(&self, _ray: &Ray, hit_record: &HitRecord) -> Option<ScatterRecord> {
         let albedo = self.albedo.value(hit_record.u, hit_record.v, hit_record.p);
+        let albedo = match hit_record.vertex_color {
+            Some(tint) => albedo * tint,
+            None => albedo,
+        };
 
         Some(ScatterRecord::PdfRec {
             pdf: Arc::new(CosinePdf {
@@ -0,0 +1,3736 @@
+#![allow(dead_code)]
+
+use std::sync::Arc;
+
+use camera::Projection;
+use checker_texture::CheckerTexture;
+use diffuse_light::DiffuseLight;
+
+use image_texture::ImageTexture;
+use noise_texture::NoiseTexture;
+use rectangles::XYRect;
+use serde::{Deserialize, Serialize};
+
+pub mod aabb3;
+pub mod aperture;
+pub mod block;
+pub mod bounds_wireframe;
+pub mod bvh;
+pub mod camera;
+pub mod capsule;
+pub mod checker_texture;
+pub mod clip_box;
+pub mod conductor;
+pub mod cone;
+pub mod config_watch;
+pub mod constant_medium;
+pub mod cylinder;
+pub mod dielectric;
+pub mod diffuse_light;
+pub mod flip_face;
+pub mod generic_handle;
+pub mod geometry_import;
+pub mod heatmap;
+pub mod heightfield;
+pub mod hittable;
+pub mod hittable_list;
+pub mod hyperboloid;
+pub mod image_texture;
+pub mod integrator;
+pub mod isotropic;
+pub mod lambertian;
+pub mod material;
+pub mod metal;
+pub mod microfacet;
+pub mod mutable_material;
+pub mod noise_texture;
+pub mod nothing;
+pub mod objects;
+pub mod onb;
+pub mod oren_nayar;
+pub mod paraboloid;
+pub mod pdf;
+pub mod perlin;
+pub mod plastic;
+pub mod point_light;
+pub mod portal;
+pub mod prefab;
+pub mod quad_mesh;
+pub mod rectangles;
+pub mod render_stats;
+pub mod rough_dielectric;
+pub mod rough_metal;
+pub mod sampling;
+pub mod scene_loader;
+pub mod sdf;
+pub mod solid_color_texture;
+pub mod streaming_mesh;
+pub mod subdivision;
+pub mod subsurface;
+pub mod texture;
+pub mod thin_dielectric;
+pub mod tone_curve;
+pub mod transform;
+pub mod triangle_mesh;
+pub mod types;
+pub mod uniform_grid;
+pub mod volume_texture;
+
+use dielectric::Dielectric;
+use hittable::Hittable;
+use hittable_list::HittableList;
+use lambertian::Lambertian;
+use metal::Metal;
+use objects::sphere::Sphere;
+
+use rand::{seq::SliceRandom, Rng};
+use types::*;
+
+use crate::{
+    aperture::ApertureMask,
+    block::Block,
+    bounds_wireframe::BoundsOverlay,
+    cone::Cone,
+    config_watch::ConfigWatcher,
+    constant_medium::ConstantMedium,
+    cylinder::Cylinder,
+    flip_face::FlipFace,
+    heatmap::{HitStatsHeatmap, HitStatsView, PaletteConfig},
+    integrator::{Integrator, IntegratorKind},
+    objects::sphere::MovingSphere,
+    paraboloid::Paraboloid,
+    quad_mesh::{QuadMesh, WallData, WallType},
+    rectangles::XZRect,
+    render_stats::{RenderStats, RenderStatsSummary},
+    sampling::SamplerKind,
+    tone_curve::{ToneCurve, ToneCurveConfig},
+    transform::{RotateY, Transform, Translate},
+    triangle_mesh::TriangleMesh,
+    uniform_grid::Accelerator,
+};
+
+#[derive(Copy, Clone)]
+struct RaytracedPixel {
+    x: u32,
+    y: u32,
+    /// Downscale factor of the pass, the pixel covers `scale` x `scale` image pixels.
+    scale: u32,
+    /// Sum of the linear colors of the samples, the displayable color when `samples` is 0
+    /// (hit stats heatmap).
+    color: Color,
+    samples: u32,
+}
+
+/// Range the displayable colors are clamped to when they are quantized to 8 bits.
+pub const DEFAULT_COLOR_CLAMP: [Real; 2] = [0 as Real, 1 as Real];
+
+fn default_color_clamp() -> [Real; 2] {
+    DEFAULT_COLOR_CLAMP
+}
+
+fn default_psi_max() -> f32 {
+    180f32
+}
+
+fn default_lambda_max() -> f32 {
+    120f32
+}
+
+/// Quantizes displayable colors to 8 bit RGB, after clamping them to `clamp`.
+pub fn quantize_colors(colors: &[Color], clamp: [Real; 2]) -> Vec<u8> {
+    let quantize = |v: Real| (v.max(clamp[0]).min(clamp[1]) * C_255_999) as u8;
+
+    colors
+        .iter()
+        .flat_map(|c| [quantize(c.r), quantize(c.g), quantize(c.b)])
+        .collect()
+}
+
+/// Writes linear colors, `width` pixels per row, to a 32 bit float (.exr) image.
+pub fn save_linear_colors<P: AsRef<std::path::Path>>(
+    path: P,
+    width: u32,
+    height: u32,
+    colors: &[Color],
+) {
+    image::Rgb32FImage::from_vec(
+        width,
+        height,
+        colors
+            .iter()
+            .flat_map(|c| [c.r as f32, c.g as f32, c.b as f32])
+            .collect(),
+    )
+    .expect("Failed to create image")
+    .save(path.as_ref())
+    .unwrap_or_else(|e| panic!("Failed to save {}: {}", path.as_ref().display(), e));
+}
+
+/// Converts the linear (averaged) color of a pixel to a displayable one: gamma corrected,
+/// then remapped by the tone curve.
+pub fn display_color(linear: Color, tone_curve: Option<&ToneCurve>) -> Color {
+    //
+    // not clamped, values above 1 are kept for HDR output
+    let gamma_correct_fn = |x: Real| x.max(0 as Real).sqrt();
+
+    let color = Color {
+        r: gamma_correct_fn(linear.r),
+        g: gamma_correct_fn(linear.g),
+        b: gamma_correct_fn(linear.b),
+        ..linear
+    };
+
+    tone_curve.map_or(color, |curve| curve.apply(color))
+}
+
+/// Linear color of a pixel from the sum of its samples, black if there are none yet.
+/// Dividing by the samples actually taken (instead of `samples_per_pixel`) keeps the
+/// exposure of partially rendered pixels right.
+pub fn average_samples(sum: Color, samples: u32) -> Color {
+    if samples == 0 {
+        return Color {
+            r: 0 as Real,
+            g: 0 as Real,
+            b: 0 as Real,
+            ..sum
+        };
+    }
+
+    let scale = (samples as Real).recip();
+    Color {
+        r: sum.r * scale,
+        g: sum.g * scale,
+        b: sum.b * scale,
+        ..sum
+    }
+}
+
+#[derive(Copy, Clone, Debug, serde::Serialize, serde::Deserialize, Eq, PartialEq)]
+pub enum Scene {
+    RandomWorld,
+    TwoSpheres,
+    TexturedSpheres,
+    PerlinSpheres,
+    SimpleLight,
+    CornellBox,
+    Chapter2Final,
+    MeshTest,
+    GeometricPrimitives,
+    Blocks,
+}
+
+fn scene_random_world() -> (HittableList, HittableList) {
+    let ground_material = Arc::new(Lambertian::from_texture(Arc::new(
+        CheckerTexture::from_colors((0.2f32, 0.3f32, 0.1f32), (0.9f32, 0.9f32, 0.9f32), 4f32),
+    )));
+    let mut world = HittableList::new();
+
+    world.add(Arc::new(Sphere::new(
+        Point::new(0 as Real, -1000 as Real, 0 as Real),
+        1000 as Real,
+        ground_material,
+    )));
+
+    (-11..11).for_each(|a| {
+        (-11..11).for_each(|b| {
+            let center = Point::new(
+                a as Real + 0.9 as Real + random_real(),
+                0.2 as Real,
+                b as Real + 0.9 as Real * random_real(),
+            );
+
+            use math::vec3::{length, sqrt};
+
+            if length(center - sqrt(Point::new(4 as Real, 0.2 as Real, 0 as Real))) > 0.9 as Real {
+                let choose_mat = random_real();
+
+                if choose_mat < 0.8 as Real {
+                    //
+                    // diffuse
+                    let albedo = random_color() * random_color();
+                    let sphere_material = Arc::new(Lambertian::new(albedo));
+                    let center2 = center + Vec3::new(0f32, random_real_range(0f32, 0.5f32), 0f32);
+                    world.add(Arc::new(MovingSphere::new(
+                        center,
+                        center2,
+                        0f32,
+                        1f32,
+                        0.2 as Real,
+                        sphere_material,
+                    )));
+                } else if choose_mat < 0.95 as Real {
+                    //
+                    // metal
+                    let albedo = random_color_in_range(0.5 as Real, 1 as Real);
+                    let fuzziness = random_real_range(0 as Real, 0.5 as Real);
+                    let sphere_material = Arc::new(Metal::new(albedo, fuzziness));
+                    world.add(Arc::new(Sphere::new(center, 0.2 as Real, sphere_material)));
+                } else {
+                    //
+                    // glass
+                    let sphere_material = Arc::new(Dielectric::new(1.5 as Real));
+                    world.add(Arc::new(Sphere::new(center, 0.2 as Real, sphere_material)));
+                }
+            }
+        });
+    });
+
+    world.add(Arc::new(Sphere::new(
+        Point::new(0 as Real, 1 as Real, 0 as Real),
+        1 as Real,
+        Arc::new(Dielectric::new(1.5 as Real)),
+    )));
+
+    world.add(Arc::new(Sphere::new(
+        Point::new(-4 as Real, 1 as Real, 0 as Real),
+        1 as Real,
+        Arc::new(Lambertian::new(Color::new(
+            0.4 as Real,
+            0.2 as Real,
+            0.1 as Real,
+        ))),
+    )));
+
+    world.add(Arc::new(Sphere::new(
+        Point::new(4 as Real, 1 as Real, 0 as Real),
+        1 as Real,
+        Arc::new(Metal::new(
+            Color::new(0.7 as Real, 0.6 as Real, 0.5 as Real),
+            0 as Real,
+        )),
+    )));
+
+    let light_mtl: Arc<DiffuseLight> = Arc::new((0f32, 0f32, 0f32).into());
+    let mut lights = HittableList::new();
+    lights.add(Arc::new(XZRect {
+        x0: 213f32,
+        x1: 343f32,
+        z0: 227f32,
+        z1: 332f32,
+        k: 554f32,
+        mtl: light_mtl.clone(),
+    }));
+
+    (world, lights)
+}
+
+fn scene_two_spheres() -> (HittableList, HittableList) {
+    let checker_mtl = Arc::new(Lambertian::from_texture(Arc::new(
+        CheckerTexture::from_colors((0.2f32, 0.3f32, 0.1f32), (0.9f32, 0.9f32, 0.9f32), 4f32),
+    )));
+
+    let mut world = HittableList::new();
+
+    world.add(Arc::new(Sphere::new(
+        Point::new(0f32, -10f32, 0f32),
+        10f32,
+        checker_mtl.clone(),
+    )));
+    world.add(Arc::new(Sphere::new(
+        Point::new(0f32, 10f32, 0f32),
+        10f32,
+        checker_mtl.clone(),
+    )));
+
+    let light_mtl: Arc<DiffuseLight> = Arc::new((0f32, 0f32, 0f32).into());
+    let mut lights = HittableList::new();
+    lights.add(Arc::new(XZRect {
+        x0: 213f32,
+        x1: 343f32,
+        z0: 227f32,
+        z1: 332f32,
+        k: 554f32,
+        mtl: light_mtl.clone(),
+    }));
+
+    (world, lights)
+}
+
+fn scene_two_perlin_spheres() -> (HittableList, HittableList) {
+    let noise_mtl = Arc::new(Lambertian::from_texture(Arc::new(NoiseTexture::new(3f32))));
+
+    let mut world = HittableList::new();
+
+    let grid_tex = Arc::new(Lambertian::from_texture(Arc::new(ImageTexture::new(
+        "data/textures/uv_grids/ash_uvgrid01.jpg",
+    ))));
+
+    world.add(Arc::new(Sphere::new(
+        Point::new(0f32, -1000f32, 0f32),
+        1000f32,
+        grid_tex,
+    )));
+
+    let grid_tex = Arc::new(Lambertian::from_texture(Arc::new(ImageTexture::new(
+        "data/textures/uv_grids/ash_uvgrid03.jpg",
+    ))));
+    world.add(Arc::new(Sphere::new(
+        Point::new(4f32, 4f32, 0f32),
+        3f32,
+        grid_tex,
+    )));
+
+    world.add(Arc::new(Sphere::new(
+        Point::new(-6f32, 6f32, 2f32),
+        6f32,
+        noise_mtl.clone(),
+    )));
+
+    world.add(Arc::new(FlipFace {
+        obj: Arc::new(XZRect {
+            x0: -1000f32,
+            x1: 1000f32,
+            z0: -1000f32,
+            z1: 1000f32,
+            k: 1000f32,
+            mtl: Arc::<DiffuseLight>::new((1f32, 1f32, 1f32).into()),
+        }),
+    }));
+
+    let light_mtl: Arc<DiffuseLight> = Arc::new((0f32, 0f32, 0f32).into());
+    let mut lights = HittableList::new();
+    lights.add(Arc::new(XZRect {
+        x0: 213f32,
+        x1: 343f32,
+        z0: 227f32,
+        z1: 332f32,
+        k: 554f32,
+        mtl: light_mtl.clone(),
+    }));
+
+    (world, lights)
+}
+
+fn scene_textured_spheres() -> (HittableList, HittableList) {
+    let image_texture = Arc::new(Lambertian::from_texture(Arc::new(ImageTexture::new(
+        "data/textures/misc/earthmap.jpg",
+    ))));
+
+    let mut world = HittableList::new();
+
+    world.add(Arc::new(Sphere::new(
+        Point::new(0f32, 0f32, 0f32),
+        2f32,
+        image_texture.clone(),
+    )));
+
+    let light_mtl: Arc<DiffuseLight> = Arc::new((0f32, 0f32, 0f32).into());
+    let mut lights = HittableList::new();
+    lights.add(Arc::new(XZRect {
+        x0: 213f32,
+        x1: 343f32,
+        z0: 227f32,
+        z1: 332f32,
+        k: 554f32,
+        mtl: light_mtl.clone(),
+    }));
+
+    (world, lights)
+}
+
+fn scene_simple_light() -> (HittableList, HittableList) {
+    let noise_mtl = Arc::new(Lambertian::from_texture(Arc::new(NoiseTexture::new(3f32))));
+
+    let mut world = HittableList::new();
+
+    world.add(Arc::new(Sphere::new(
+        Point::new(0f32, -1000f32, 0f32),
+        1000f32,
+        noise_mtl.clone(),
+    )));
+    world.add(Arc::new(Sphere::new(
+        Point::new(0f32, 2f32, 0f32),
+        2f32,
+        noise_mtl.clone(),
+    )));
+
+    let diffuse_light: Arc<DiffuseLight> = Arc::new((4f32, 4f32, 4f32).into());
+    world.add(Arc::new(XYRect {
+        x0: 3f32,
+        x1: 5f32,
+        y0: 1f32,
+        y1: 3f32,
+        k: -2f32,
+        mtl: diffuse_light,
+    }));
+
+    let red_light: Arc<DiffuseLight> = Arc::new((4f32, 2f32, 0f32).into());
+    world.add(Arc::new(Sphere::new(
+        Point::new(0f32, 8f32, 0f32),
+        2f32,
+        red_light,
+    )));
+
+    let light_mtl: Arc<DiffuseLight> = Arc::new((0f32, 0f32, 0f32).into());
+    let mut lights = HittableList::new();
+    lights.add(Arc::new(XZRect {
+        x0: 213f32,
+        x1: 343f32,
+        z0: 227f32,
+        z1: 332f32,
+        k: 554f32,
+        mtl: light_mtl.clone(),
+    }));
+
+    (world, lights)
+}
+
+fn scene_cornell_box() -> (HittableList, HittableList) {
+    let colors = [
+        (0.65f32, 0.05f32, 0.05f32),
+        (0.73f32, 0.73f32, 0.73f32),
+        (0.12f32, 0.45f32, 0.15f32),
+    ]
+    .iter()
+    .map(|color| Arc::new(Lambertian::new(*color)))
+    .collect::<Vec<_>>();
+
+    let walls = [
+        WallData {
+            wt: WallType::YZ,
+            a: 0f32,
+            b: 555f32,
+            c: 0f32,
+            d: 555f32,
+            k: 555f32,
+            color_id: 2,
+        },
+        //
+        // yz_rect>(0, 555, 0, 555, 0, red)
+        WallData {
+            wt: WallType::YZ,
+            a: 0f32,
+            b: 555f32,
+            c: 0f32,
+            d: 555f32,
+            k: 0f32,
+            color_id: 0,
+        },
+        //
+        // xz_rect>(0, 555, 0, 555, 0, white)
+        WallData {
+            wt: WallType::XZ,
+            a: 0f32,
+            b: 555f32,
+            c: 0f32,
+            d: 555f32,
+            k: 0f32,
+            color_id: 1,
+        },
+        //
+        // xz_rect>(0, 555, 0, 555, 555, white)
+        WallData {
+            wt: WallType::XZ,
+            a: 0f32,
+            b: 555f32,
+            c: 0f32,
+            d: 555f32,
+            k: 555f32,
+            color_id: 1,
+        },
+        //
+        // xy_rect>(0, 555, 0, 555, 555, white)
+        WallData {
+            wt: WallType::XY,
+            a: 0f32,
+            b: 555f32,
+            c: 0f32,
+            d: 555f32,
+            k: 555f32,
+            color_id: 1,
+        },
+    ];
+
+    let mut world = HittableList::new();
+    world.add(Arc::new(QuadMesh::from_walls(&walls, &colors)));
+
+    let light_mtl: Arc<DiffuseLight> = Arc::new((15f32, 15f32, 15f32).into());
+    let light = Arc::new(FlipFace {
+        obj: Arc::new(XZRect {
+            x0: 213f32,
+            x1: 343f32,
+            z0: 227f32,
+            z1: 332f32,
+            k: 554f32,
+            mtl: light_mtl,
+        }),
+    });
+    world.add(light);
+
+    let box1 = Arc::new(Block::new(
+        (0f32, 0f32, 0f32),
+        (165f32, 330f32, 165f32),
+        colors[1].clone(),
+    ));
+    let box1 = Arc::new(RotateY::new(box1, 15f32));
+    let box1 = Arc::new(Translate::new(box1, (265f32, 0f32, 295f32).into()));
+    world.add(box1);
+
+    let glass = Arc::new(Dielectric::new(1.5f32));
+    let glass_sphere = Arc::new(Sphere::new((190f32, 90f32, 190f32).into(), 90f32, glass));
+    world.add(glass_sphere);
+
+    let light_mtl: Arc<DiffuseLight> = Arc::new((0f32, 0f32, 0f32).into());
+    let mut lights = HittableList::new();
+    lights.add(Arc::new(FlipFace {
+        obj: Arc::new(XZRect {
+            x0: 213f32,
+            x1: 343f32,
+            z0: 227f32,
+            z1: 332f32,
+            k: 554f32,
+            mtl: light_mtl.clone(),
+        }),
+    }));
+
+    lights.add(Arc::new(Sphere::new(
+        (190f32, 90f32, 190f32).into(),
+        90f32,
+        light_mtl.clone(),
+    )));
+
+    (world, lights)
+}
+
+fn scene_cornell_box_smoke() -> HittableList {
+    let colors = [
+        (0.65f32, 0.05f32, 0.05f32),
+        (0.73f32, 0.73f32, 0.73f32),
+        (0.12f32, 0.45f32, 0.15f32),
+    ]
+    .iter()
+    .map(|color| Arc::new(Lambertian::new(*color)))
+    .collect::<Vec<_>>();
+
+    let light: Arc<DiffuseLight> = Arc::new((7f32, 7f32, 7f32).into());
+
+    let walls = [
+        WallData {
+            wt: WallType::YZ,
+            a: 0f32,
+            b: 555f32,
+            c: 0f32,
+            d: 555f32,
+            k: 555f32,
+            color_id: 2,
+        },
+        //
+        // yz_rect>(0, 555, 0, 555, 0, red)
+        WallData {
+            wt: WallType::YZ,
+            a: 0f32,
+            b: 555f32,
+            c: 0f32,
+            d: 555f32,
+            k: 0f32,
+            color_id: 0,
+        },
+        //
+        // xz_rect>(0, 555, 0, 555, 0, white)
+        WallData {
+            wt: WallType::XZ,
+            a: 0f32,
+            b: 555f32,
+            c: 0f32,
+            d: 555f32,
+            k: 0f32,
+            color_id: 1,
+        },
+        //
+        // xz_rect>(0, 555, 0, 555, 555, white)
+        WallData {
+            wt: WallType::XZ,
+            a: 0f32,
+            b: 555f32,
+            c: 0f32,
+            d: 555f32,
+            k: 555f32,
+            color_id: 1,
+        },
+        //
+        // xy_rect>(0, 555, 0, 555, 555, white)
+        WallData {
+            wt: WallType::XY,
+            a: 0f32,
+            b: 555f32,
+            c: 0f32,
+            d: 555f32,
+            k: 555f32,
+            color_id: 1,
+        },
+    ];
+
+    let mut world = HittableList::new();
+    world.add(Arc::new(QuadMesh::from_walls(&walls, &colors)));
+
+    world.add(Arc::new(XZRect {
+        x0: 113f32,
+        x1: 443f32,
+        z0: 127f32,
+        z1: 432f32,
+        k: 554f32,
+        mtl: light,
+    }));
+
+    let box1 = Arc::new(Block::new(
+        (0f32, 0f32, 0f32),
+        (165f32, 330f32, 165f32),
+        colors[1].clone(),
+    ));
+    let box1 = Arc::new(RotateY::new(box1, 15f32));
+    let box1 = Arc::new(Translate::new(box1, (265f32, 0f32, 295f32).into()));
+
+    world.add(Arc::new(ConstantMedium::from_colored_object(
+        box1,
+        (0f32, 0f32, 0f32),
+        0.01f32,
+    )));
+
+    let box2 = Arc::new(Block::new(
+        (0f32, 0f32, 0f32),
+        (165f32, 165f32, 165f32),
+        colors[1].clone(),
+    ));
+    let box2 = Arc::new(RotateY::new(box2, -18f32));
+    let box2 = Arc::new(Translate::new(box2, (130f32, 0f32, 65f32).into()));
+
+    world.add(Arc::new(ConstantMedium::from_colored_object(
+        box2,
+        (1f32, 1f32, 1f32),
+        0.01f32,
+    )));
+
+    world
+}
+
+fn scene_final_chapter2(accelerator: Accelerator) -> (HittableList, HittableList) {
+    let mut rng = rand::thread_rng();
+    let mut world = HittableList::new();
+
+    let white = Arc::new(Lambertian::new((0.73f32, 0.73f32, 0.73f32)));
+
+    let ground = Arc::new(Lambertian::new((0.48_f32, 0.83_f32, 0.53_f32)));
+
+    const NUM_BOXES: i32 = 20;
+
+    let mut boxlist = Vec::<Arc<dyn Hittable>>::with_capacity((NUM_BOXES * NUM_BOXES) as usize);
+
+    (0..NUM_BOXES).for_each(|i| {
+        (0..NUM_BOXES).for_each(|j| {
+            let w = 100_f32;
+            let x0 = -1000_f32 + i as f32 * w;
+            let z0 = -1000_f32 + j as f32 * w;
+            let y0 = 0_f32;
+            let x1 = x0 + w;
+            let y1 = rng.gen_range(1f32, 101f32);
+            let z1 = z0 + w;
+
+            boxlist.push(Arc::new(Block::new(
+                Vec3::new(x0, y0, z0),
+                Vec3::new(x1, y1, z1),
+                ground.clone(),
+            )));
+        });
+    });
+
+    world.add(accelerator.build(boxlist.as_mut_slice(), 0_f32, 1_f32));
+
+    let light = Arc::new(DiffuseLight::from((17f32, 17f32, 17f32)));
+
+    world.add(Arc::new(FlipFace {
+        obj: Arc::new(XZRect {
+            x0: 123_f32,
+            x1: 423_f32,
+            z0: 147_f32,
+            z1: 412_f32,
+            k: 554_f32,
+            mtl: light.clone(),
+        }),
+    }));
+
+    let center = Vec3::new(400_f32, 400_f32, 200_f32);
+
+    world.add(Arc::new(MovingSphere::new(
+        center,
+        center + Vec3::new(30_f32, 0_f32, 0_f32),
+        0_f32,
+        1_f32,
+        50_f32,
+        Arc::new(Lambertian::new((0.7_f32, 0.3_f32, 0.1_f32))),
+    )));
+
+    world.add(Arc::new(Sphere::new(
+        Vec3::new(260_f32, 150_f32, 45_f32),
+        50_f32,
+        Arc::new(Dielectric::new(1.5_f32)),
+    )));
+
+    world.add(Arc::new(Sphere::new(
+        Vec3::new(0_f32, 150_f32, 145_f32),
+        50_f32,
+        Arc::new(Metal::new((0.8_f32, 0.8_f32, 0.9_f32), 10_f32)),
+    )));
+
+    let boundary = Arc::new(Sphere::new(
+        Vec3::new(360_f32, 150_f32, 145_f32),
+        70_f32,
+        Arc::new(Dielectric::new(1.5_f32)),
+    ));
+    world.add(boundary.clone());
+
+    world.add(Arc::new(ConstantMedium::from_colored_object(
+        boundary.clone(),
+        (0.2_f32, 0.4_f32, 0.9_f32),
+        0.2_f32,
+    )));
+
+    let boundary = Arc::new(Sphere::new(
+        Vec3::broadcast(0_f32),
+        5000_f32,
+        Arc::new(Dielectric::new(1.5_f32)),
+    ));
+    world.add(Arc::new(ConstantMedium::from_colored_object(
+        boundary.clone(),
+        Color::broadcast(1f32),
+        0.0001_f32,
+    )));
+
+    let emat = Arc::new(Lambertian::from_texture(Arc::new(ImageTexture::new(
+        "data/textures/misc/earthmap.jpg",
+    ))));
+    world.add(Arc::new(Sphere::new(
+        (400f32, 200f32, 400f32).into(),
+        100f32,
+        emat,
+    )));
+
+    let pertex = Arc::new(NoiseTexture::new(0.1_f32));
+    world.add(Arc::new(Sphere::new(
+        Vec3::new(220_f32, 280_f32, 300_f32),
+        80_f32,
+        Arc::new(Lambertian::from_texture(pertex)),
+    )));
+
+    const NUM_SPHERES: i32 = 1000;
+
+    let mut boxlist2 = (0..NUM_SPHERES)
+        .map(|_| -> Arc<dyn Hittable> {
+            let center = Vec3::new(
+                165_f32 * random_real(),
+                165_f32 * random_real(),
+                165_f32 * random_real(),
+            );
+
+            Arc::new(Sphere::new(center, 10f32, white.clone()))
+        })
+        .collect::<Vec<Arc<dyn Hittable>>>();
+
+    let node = accelerator.build(boxlist2.as_mut_slice(), 0f32, 1f32);
+    let node = Arc::new(RotateY::new(node, 15f32));
+    let node = Arc::new(Translate::new(node, (-100f32, 270f32, 395f32).into()));
+
+    world.add(node);
+
+    let light_mtl: Arc<DiffuseLight> = Arc::new((0f32, 0f32, 0f32).into());
+    let mut lights = HittableList::new();
+    lights.add(Arc::new(XZRect {
+        x0: 213f32,
+        x1: 343f32,
+        z0: 227f32,
+        z1: 332f32,
+        k: 554f32,
+        mtl: light_mtl.clone(),
+    }));
+
+    (world, lights)
+}
+
+fn scene_geometric_primitives() -> (HittableList, HittableList) {
+    let mut world = HittableList::new();
+
+    //
+    // add floor
+    let floor_mtl = Arc::new(Lambertian::from_texture(Arc::new(ImageTexture::new(
+        "data/textures/uv_grids/ash_uvgrid01.jpg",
+    ))));
+
+    let floor = Arc::new(XZRect {
+        x0: -1000f32,
+        x1: 1000f32,
+        z0: -1000f32,
+        z1: 1000f32,
+        k: 0f32,
+        mtl: floor_mtl,
+    });
+
+    world.add(floor);
+
+    world.add(Arc::new(FlipFace {
+        obj: Arc::new(XZRect {
+            x0: -1000f32,
+            x1: 1000f32,
+            z0: -1000f32,
+            z1: 1000f32,
+            k: 1000f32,
+            mtl: Arc::new(DiffuseLight::from((1f32, 1f32, 1f32))),
+        }),
+    }));
+
+    let block_mtl = Arc::new(Lambertian::from_texture(Arc::new(ImageTexture::new(
+        "data/textures/uv_grids/ash_uvgrid03.jpg",
+    ))));
+
+    let block = Arc::new(Block::unit_cube(block_mtl));
+
+    use math::quat;
+    use math::vec3;
+
+    let r = quat::to_rotation_matrix(quat::Quat::axis_angle(180 as Real, vec3::consts::unit_y()));
+    let t = Mat4::translate((65f32, 65f32, 0f32).into());
+    let s = Mat4::uniform_scale(35f32);
+    let transformed_block = Arc::new(Transform::new(t * r * s, block.clone()));
+    world.add(transformed_block);
+
+    let r = random_rotation_matrix();
+    let s = Mat4::uniform_scale(35f32);
+    let t = Mat4::translate((-65f32, 65f32, 0f32).into());
+    let final_tf = t * r * s;
+    let transformed_block = Arc::new(Transform::new(final_tf, block.clone()));
+    world.add(transformed_block);
+
+    let cyl_mtl = Arc::new(Lambertian::from_texture(Arc::new(ImageTexture::new(
+        "data/textures/uv_grids/ash_uvgrid01.jpg",
+    ))));
+
+    let cyl = Arc::new(Cylinder::unit(None, cyl_mtl));
+
+    let r = quat::to_rotation_matrix(quat::Quat::axis_angle(45 as Real, vec3::consts::unit_y()));
+    let t = Mat4::translate((40f32, 30f32, 30f32).into());
+    let s = Mat4::non_uniform_scale((15f32, 15f32, 65f32).into());
+
+    let transformed_cyl = Arc::new(Transform::new(t * r * s, cyl));
+    world.add(transformed_cyl);
+
+    //
+    // partial cylinder
+    let cyl_mtl = Arc::new(Lambertian::from_texture(Arc::new(ImageTexture::new(
+        "data/textures/uv_grids/ash_uvgrid08.jpg",
+    ))));
+
+    let cyl = Arc::new(Cylinder::unit(Some(C_PI), cyl_mtl));
+    let r = quat::to_rotation_matrix(quat::Quat::axis_angle(-45 as Real, vec3::consts::unit_y()));
+    let t = Mat4::translate((-40f32, 30f32, 30f32).into());
+    let s = Mat4::non_uniform_scale((15f32, 15f32, 65f32).into());
+    world.add(Arc::new(Transform::new(t * r * s, cyl)));
+
+    let cone_mtl = Arc::new(Lambertian::from_texture(Arc::new(ImageTexture::new(
+        "data/textures/uv_grids/ash_uvgrid03.jpg",
+    ))));
+    let r = quat::to_rotation_matrix(quat::Quat::axis_angle(90 as Real, vec3::consts::unit_x()));
+    let t = Mat4::translate((-25f32, 25f32, 50f32).into());
+    let s = Mat4::uniform_scale(25f32);
+    let cone = Arc::new(Cone::unit(None, cone_mtl.clone()));
+
+    world.add(Arc::new(Transform::new(t * r * s, cone.clone())));
+
+    let r = quat::to_rotation_matrix(quat::Quat::axis_angle(-90 as Real, vec3::consts::unit_x()))
+        * quat::to_rotation_matrix(quat::Quat::axis_angle(-90 as Real, vec3::consts::unit_z()));
+    let t = Mat4::translate((25f32, 0f32, 50f32).into());
+    let cone = Arc::new(Cone::unit(Some(330f32.to_radians()), cone_mtl.clone()));
+    world.add(Arc::new(Transform::new(t * r * s, cone)));
+
+    let hyp_mtl = Arc::new(Lambertian::from_texture(Arc::new(ImageTexture::new(
+        "data/textures/uv_grids/ash_uvgrid10.jpg",
+    ))));
+
+    let r = quat::to_rotation_matrix(quat::Quat::axis_angle(90 as Real, vec3::consts::unit_x()));
+    let t = Mat4::translate((-0f32, 25f32, 100f32).into());
+    let s = Mat4::uniform_scale(25f32);
+
+    let hyp = Arc::new(Paraboloid::new(
+        0.5f32,
+        -0.5f32,
+        1f32,
+        C_TWO_PI,
+        hyp_mtl.clone(),
+    ));
+
+    world.add(Arc::new(Transform::new(t * r * s, hyp.clone())));
+
+    let r = quat::to_rotation_matrix(quat::Quat::axis_angle(-120 as Real, vec3::consts::unit_y()))
+        * quat::to_rotation_matrix(quat::Quat::axis_angle(90 as Real, vec3::consts::unit_x()));
+    let t = Mat4::translate((-30f32, 25f32, 100f32).into());
+    let s = Mat4::uniform_scale(25f32);
+    let hyp = Arc::new(Paraboloid::new(
+        0.5f32,
+        -0.5f32,
+        1f32,
+        300f32.to_radians(),
+        hyp_mtl.clone(),
+    ));
+
+    world.add(Arc::new(Transform::new(t * r * s, hyp.clone())));
+
+    let mut lights = HittableList::new();
+    lights.add(Arc::new(FlipFace {
+        obj: Arc::new(XZRect {
+            x0: -1000f32,
+            x1: 1000f32,
+            z0: -1000f32,
+            z1: 1000f32,
+            k: 1000f32,
+            mtl: Arc::<DiffuseLight>::new((0f32, 0f32, 0f32).into()),
+        }),
+    }));
+
+    (world, lights)
+}
+
+fn scene_mesh() -> (HittableList, HittableList) {
+    let mut world = HittableList::new();
+
+    //
+    // add floor
+    let floor_mtl = Arc::new(Lambertian::from_texture(Arc::new(ImageTexture::new(
+        "data/textures/uv_grids/ash_uvgrid01.jpg",
+    ))));
+
+    let floor = Arc::new(XZRect {
+        x0: -1000f32,
+        x1: 1000f32,
+        z0: -1000f32,
+        z1: 1000f32,
+        k: 0f32,
+        mtl: floor_mtl,
+    });
+
+    world.add(floor);
+
+    world.add(Arc::new(FlipFace {
+        obj: Arc::new(XZRect {
+            x0: -1000f32,
+            x1: 1000f32,
+            z0: -1000f32,
+            z1: 1000f32,
+            k: 1000f32,
+            mtl: Arc::new(DiffuseLight::from((1f32, 1f32, 1f32))),
+        }),
+    }));
+
+    let teapot_mtl = Arc::new(Lambertian::from_texture(Arc::new(ImageTexture::new(
+        "data/textures/uv_grids/ash_uvgrid09.jpg",
+    ))));
+
+    use math::{mat4, quat, vec3};
+
+    let r = quat::to_rotation_matrix(quat::Quat::axis_angle(180f32, vec3::consts::unit_x()));
+    let t = mat4::Mat4::translate((0f32, 0f32, 0f32).into());
+    let xf = t * r;
+
+    let model_file =
+        // "data/models/cone.glb";
+    "data/models/teapot/teapot.glb";
+    // "data/models/teapot.lid.glb";
+
+    let teapot = Arc::new(TriangleMesh::from_file(&model_file, xf, teapot_mtl, 0));
+    world.add(teapot);
+
+    //
+    // area light above the teapot, the far sky is too dim to light it on its own
+    let lamp = Arc::new(XZRect {
+        x0: -15f32,
+        x1: 15f32,
+        z0: -15f32,
+        z1: 15f32,
+        k: 40f32,
+        mtl: Arc::new(DiffuseLight::from((6f32, 6f32, 6f32))),
+    });
+    let lamp = Arc::new(FlipFace { obj: lamp });
+    world.add(lamp.clone());
+
+    let mut lights = HittableList::new();
+    lights.add(lamp);
+
+    (world, lights)
+}
+
+fn scene_blocks() -> (HittableList, HittableList) {
+    let mut world = HittableList::new();
+
+    //
+    // add floor
+    let floor_mtl = Arc::new(Lambertian::from_texture(Arc::new(ImageTexture::new(
+        "data/textures/uv_grids/ash_uvgrid01.jpg",
+    ))));
+
+    let floor = Arc::new(XZRect {
+        x0: -1000f32,
+        x1: 1000f32,
+        z0: -1000f32,
+        z1: 1000f32,
+        k: 0f32,
+        mtl: floor_mtl,
+    });
+
+    world.add(floor);
+
+    world.add(Arc::new(FlipFace {
+        obj: Arc::new(XZRect {
+            x0: -1000f32,
+            x1: 1000f32,
+            z0: -1000f32,
+            z1: 1000f32,
+            k: 1000f32,
+            mtl: Arc::new(DiffuseLight::from((1f32, 1f32, 1f32))),
+        }),
+    }));
+
+    use math::{mat4, quat, vec3};
+
+    let r = quat::to_rotation_matrix(quat::Quat::axis_angle(20f32, vec3::consts::unit_y()));
+    let t = mat4::Mat4::translate((-7f32, 5f32, 10f32).into());
+    let s = mat4::Mat4::non_uniform_scale((5f32, 10f32, 5f32).into());
+    let xf = t * r * s;
+
+    use math::color_palette;
+
+    let yellow_checkers = Arc::new(Lambertian::from_texture(Arc::new(
+        CheckerTexture::from_colors(
+            color_palette::material_design::YELLOW,
+            color_palette::material_design::BLACK,
+            4f32,
+        ),
+    )));
+
+    let block0 = Arc::new(Transform::new(
+        xf,
+        Arc::new(Block::unit_cube(yellow_checkers)),
+    ));
+    world.add(block0);
+
+    let green_checkers = Arc::new(Lambertian::from_texture(Arc::new(
+        CheckerTexture::from_colors(
+            color_palette::material_design::GREEN,
+            color_palette::material_design::BLACK,
+            4f32,
+        ),
+    )));
+
+    let t = mat4::Mat4::translate((0f32, 5f32, 0f32).into());
+    let s = mat4::Mat4::non_uniform_scale((5f32, 10f32, 5f32).into());
+    let xf = t * s;
+
+    let block1 = Arc::new(Transform::new(
+        xf,
+        Arc::new(Block::unit_cube(green_checkers)),
+    ));
+    world.add(block1);
+
+    let orange_checkers = Arc::new(Lambertian::from_texture(Arc::new(
+        CheckerTexture::from_colors(
+            color_palette::material_design::RED,
+            color_palette::material_design::BLACK,
+            4f32,
+        ),
+    )));
+
+    let r = quat::to_rotation_matrix(quat::Quat::axis_angle(-20f32, vec3::consts::unit_y()));
+    let t = mat4::Mat4::translate((7f32, 5f32, 10f32).into());
+    let s = mat4::Mat4::non_uniform_scale((5f32, 10f32, 5f32).into());
+    let xf = t * r * s;
+
+    let block2 = Arc::new(Transform::new(
+        xf,
+        Arc::new(Block::unit_cube(orange_checkers)),
+    ));
+    world.add(block2);
+
+    let mut lights = HittableList::new();
+    lights.add(Arc::new(FlipFace {
+        obj: Arc::new(XZRect {
+            x0: -1000f32,
+            x1: 1000f32,
+            z0: -1000f32,
+            z1: 1000f32,
+            k: 1000f32,
+            mtl: Arc::<DiffuseLight>::new((0f32, 0f32, 0f32).into()),
+        }),
+    }));
+
+    (world, lights)
+}
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct RaytracerUserConfig {
+    pub workers: i32,
+    pub worker_block_pixels: i32,
+    pub aspect_ratio: Real,
+    pub image_width: i32,
+    pub samples_per_pixel: i32,
+    pub max_ray_depth: i32,
+    pub vertical_fov: Real,
+    pub look_from: [Real; 3],
+    pub look_at: [Real; 3],
+    pub world_up: [Real; 3],
+    pub aperture: Real,
+    pub focus_dist: Real,
+    pub shuffle_workblocks: bool,
+    #[serde(default)]
+    pub background: [Real; 3],
+    #[serde(default)]
+    pub projection: Projection,
+    #[serde(default = "default_psi_max")]
+    pub psi_max: f32,
+    #[serde(default = "default_lambda_max")]
+    pub lambda_max: f32,
+    #[serde(default)]
+    pub per_channel_tracing: bool,
+    #[serde(default)]
+    pub first_hit_light_samples: i32,
+    #[serde(default)]
+    pub animation: Option<AnimationParams>,
+    #[serde(default)]
+    pub ambient: [Real; 3],
+    #[serde(default)]
+    pub hit_stats_view: Option<HitStatsView>,
+    #[serde(default)]
+    pub seed: Option<u64>,
+    #[serde(default)]
+    pub autofocus: bool,
+    #[serde(default)]
+    pub accelerator: Accelerator,
+    #[serde(default)]
+    pub lens_distortion: [Real; 2],
+    #[serde(default = "default_color_clamp")]
+    pub color_clamp: [Real; 2],
+    #[serde(default)]
+    pub stereo: Option<StereoParams>,
+    #[serde(default)]
+    pub aperture_blades: u32,
+    #[serde(default)]
+    pub show_bounds: Option<BoundsOverlay>,
+    #[serde(default)]
+    pub integrator: IntegratorKind,
+    #[serde(default)]
+    pub sampler: SamplerKind,
+}
+
+impl RaytracerUserConfig {
+    /// Checks the values that decode but can't be rendered.
+    pub fn validate(&self) -> Result<(), String> {
+        if (1..3).contains(&self.aperture_blades) {
+            return Err(format!(
+                "aperture_blades is {}, a diaphragm needs at least 3 blades (0 for the round opening)",
+                self.aperture_blades
+            ));
+        }
+
+        use num::integer::Roots;
+        let grid = (self.samples_per_pixel.max(0) as u32).sqrt();
+        if self.sampler != SamplerKind::NRooks && grid * grid != self.samples_per_pixel as u32 {
+            return Err(format!(
+                "samples_per_pixel is {}, the {:?} sampler needs a square number of samples",
+                self.samples_per_pixel, self.sampler
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+//
+// (de)serializes the fields of a patch as the plain values, so a scene override is written
+// like the full config, just with fewer fields
+mod patch_field {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<T, S>(value: &Option<T>, s: S) -> Result<S::Ok, S::Error>
+    where
+        T: Serialize,
+        S: Serializer,
+    {
+        match value {
+            Some(value) => value.serialize(s),
+            None => s.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, T, D>(d: D) -> Result<Option<T>, D::Error>
+    where
+        T: Deserialize<'de>,
+        D: Deserializer<'de>,
+    {
+        T::deserialize(d).map(Some)
+    }
+}
+
+macro_rules! define_user_config_patch {
+    ($($field:ident: $field_type:ty),* $(,)?) => {
+        /// Per scene override of the default `RaytracerUserConfig`, only the fields that are
+        /// present replace the defaults.
+        #[derive(Copy, Clone, Debug, Default, Serialize, Deserialize)]
+        pub struct RaytracerUserConfigPatch {
+            $(
+                #[serde(default, skip_serializing_if = "Option::is_none", with = "patch_field")]
+                pub $field: Option<$field_type>,
+            )*
+        }
+
+        impl RaytracerUserConfigPatch {
+            pub fn apply(&self, defaults: RaytracerUserConfig) -> RaytracerUserConfig {
+                RaytracerUserConfig {
+                    $($field: self.$field.unwrap_or(defaults.$field),)*
+                }
+            }
+        }
+    };
+}
+
+define_user_config_patch!(
+    workers: i32,
+    worker_block_pixels: i32,
+    aspect_ratio: Real,
+    image_width: i32,
+    samples_per_pixel: i32,
+    max_ray_depth: i32,
+    vertical_fov: Real,
+    look_from: [Real; 3],
+    look_at: [Real; 3],
+    world_up: [Real; 3],
+    aperture: Real,
+    focus_dist: Real,
+    shuffle_workblocks: bool,
+    background: [Real; 3],
+    projection: Projection,
+    psi_max: f32,
+    lambda_max: f32,
+    per_channel_tracing: bool,
+    first_hit_light_samples: i32,
+    animation: Option<AnimationParams>,
+    ambient: [Real; 3],
+    hit_stats_view: Option<HitStatsView>,
+    seed: Option<u64>,
+    autofocus: bool,
+    accelerator: Accelerator,
+    lens_distortion: [Real; 2],
+    color_clamp: [Real; 2],
+    stereo: Option<StereoParams>,
+    aperture_blades: u32,
+    show_bounds: Option<BoundsOverlay>,
+    integrator: IntegratorKind,
+    sampler: SamplerKind,
+);
+
+/// Renders `frames` images, frame `i` covers the time interval [i / fps, (i + 1) / fps].
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct AnimationParams {
+    pub frames: u32,
+    pub fps: u32,
+}
+
+/// How the images of the two eyes are put together.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StereoOutput {
+    /// Left eye image on the left, right eye image on the right, the output is twice as wide.
+    #[default]
+    SideBySide,
+    /// Red channel from the left eye, green and blue from the right eye (red/cyan glasses).
+    Anaglyph,
+}
+
+/// Renders the scene from two eyes, `eye_separation` apart along the camera's right (u)
+/// axis, centered on `look_from`. The eyes look in parallel directions.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct StereoParams {
+    pub eye_separation: Real,
+    #[serde(default)]
+    pub output: StereoOutput,
+}
+
+impl StereoOutput {
+    /// Puts the eyes' images (`width` pixels per row) together, returns the combined image
+    /// and its width.
+    pub fn combine(self, left: &[Color], right: &[Color], width: usize) -> (Vec<Color>, usize) {
+        match self {
+            StereoOutput::SideBySide => (
+                left.chunks(width)
+                    .zip(right.chunks(width))
+                    .flat_map(|(l, r)| l.iter().chain(r.iter()).cloned())
+                    .collect(),
+                width * 2,
+            ),
+            StereoOutput::Anaglyph => (
+                left.iter()
+                    .zip(right.iter())
+                    .map(|(l, r)| Color { r: l.r, ..*r })
+                    .collect(),
+                width,
+            ),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct RaytracerParams {
+    pub workers: i32,
+    pub worker_block_pixels: i32,
+    pub image_width: i32,
+    pub image_height: i32,
+    pub aspect_ratio: Real,
+    pub samples_per_pixel: i32,
+    pub max_ray_depth: i32,
+    pub vertical_fov: Real,
+    pub look_from: [Real; 3],
+    pub look_at: [Real; 3],
+    pub world_up: [Real; 3],
+    pub aperture: Real,
+    pub focus_dist: Real,
+    pub shuffle_workblocks: bool,
+    pub background: [Real; 3],
+    pub projection: Projection,
+    pub psi_max: f32,
+    pub lambda_max: f32,
+    /// Trace r, g, b as separate paths after hitting a dispersive material. Only
+    /// dispersion splits a path, colored glass and other tinted materials scale the
+    /// channels of a shared path, which gives the same colors in expectation.
+    pub per_channel_tracing: bool,
+    /// Explicit samples per light at the first diffuse hit of a path, 0 picks a random
+    /// light. The later diffuse hits always pick a random light: splitting at every one
+    /// would multiply the paths by the samples of all the lights at every bounce.
+    pub first_hit_light_samples: i32,
+    pub animation: Option<AnimationParams>,
+    /// Constant environment light picked up by scattered rays that miss the scene.
+    pub ambient: [Real; 3],
+    /// Render a heat map of the BVH work done by primary rays instead of the image.
+    pub hit_stats_view: Option<HitStatsView>,
+    /// Fixed seed for reproducible runs, the shuffled work block order, the sampler's sets
+    /// and the random stream of every pixel are derived from it.
+    pub seed: Option<u64>,
+    /// Focus on the object in the center of the view, `focus_dist` is used if there is none.
+    pub autofocus: bool,
+    /// Acceleration structure for the large object groups of the scenes.
+    pub accelerator: Accelerator,
+    /// Radial distortion coefficients (k1, k2) of the perspective camera, zero for a pinhole.
+    pub lens_distortion: [Real; 2],
+    /// (min, max) the colors are clamped to when quantized to 8 bits, the float image
+    /// is not clamped.
+    pub color_clamp: [Real; 2],
+    /// Two eye rendering, for the blocking renders (`render`), the window shows the view
+    /// from `look_from`.
+    pub stereo: Option<StereoParams>,
+    /// Diaphragm blades, the lens opening is a regular polygon with that many sides and
+    /// out of focus highlights take its shape. 0 for the round opening.
+    pub aperture_blades: u32,
+    /// Emissive wireframe of bounding boxes drawn over the scene, for debugging the
+    /// acceleration structures.
+    pub show_bounds: Option<BoundsOverlay>,
+    /// Path tracing, or one of the cheaper integrators for previews and debugging.
+    pub integrator: IntegratorKind,
+    /// How the samples of a pixel are spread over it, every worker owns a sampler built
+    /// with this strategy.
+    pub sampler: SamplerKind,
+}
+
+impl std::convert::From<RaytracerUserConfig> for RaytracerParams {
+    fn from(c: RaytracerUserConfig) -> Self {
+        let image_height = (c.image_width as Real / c.aspect_ratio) as i32;
+
+        Self {
+            workers: c.workers,
+            worker_block_pixels: c.worker_block_pixels,
+            image_width: c.image_width,
+            image_height,
+            aspect_ratio: c.aspect_ratio,
+            samples_per_pixel: c.samples_per_pixel,
+            max_ray_depth: c.max_ray_depth,
+            vertical_fov: c.vertical_fov,
+            look_from: c.look_from,
+            look_at: c.look_at,
+            world_up: c.world_up,
+            aperture: c.aperture,
+            focus_dist: c.focus_dist,
+            shuffle_workblocks: c.shuffle_workblocks,
+            background: c.background,
+            projection: c.projection,
+            psi_max: c.psi_max.to_radians(),
+            lambda_max: c.lambda_max.to_radians(),
+            per_channel_tracing: c.per_channel_tracing,
+            first_hit_light_samples: c.first_hit_light_samples,
+            animation: c.animation,
+            ambient: c.ambient,
+            hit_stats_view: c.hit_stats_view,
+            seed: c.seed,
+            autofocus: c.autofocus,
+            accelerator: c.accelerator,
+            lens_distortion: c.lens_distortion,
+            color_clamp: c.color_clamp,
+            stereo: c.stereo,
+            aperture_blades: c.aperture_blades,
+            show_bounds: c.show_bounds,
+            integrator: c.integrator,
+            sampler: c.sampler,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct WorkBlock {
+    xdim: (i32, i32),
+    ydim: (i32, i32),
+    /// Downscale factor of the pass the block belongs to, the dimensions are in pass pixels.
+    scale: u32,
+}
+
+//
+// evaluates $body with the type $s aliased to the sample strategy of $kind, once for every
+// strategy, so the generic render code is instantiated for all of them
+macro_rules! with_sample_strategy {
+    ($kind:expr, $s:ident => $body:expr) => {
+        match $kind {
+            SamplerKind::NRooks => {
+                type $s = sampling::NRooksSamplingStrategy;
+                $body
+            }
+            SamplerKind::Jittered => {
+                type $s = sampling::JitteredSamplingStrategy;
+                $body
+            }
+            SamplerKind::MultiJittered => {
+                type $s = sampling::MultiJitteredSamplingStrategy;
+                $body
+            }
+            SamplerKind::Simple => {
+                type $s = sampling::SimpleSamplingStrategy;
+                $body
+            }
+        }
+    };
+}
+
+/// What the worker pool needs to trace the work blocks of a render.
+struct RenderJob {
+    cam: camera::Camera,
+    params: RaytracerParams,
+    world: Arc<HittableList>,
+    lights: Arc<HittableList>,
+    heatmap: Option<HitStatsHeatmap>,
+    stats: Option<Arc<RenderStats>>,
+    workblocks_done: Arc<std::sync::atomic::AtomicI32>,
+    cancel_token: Arc<std::sync::atomic::AtomicBool>,
+}
+
+impl RenderJob {
+    /// Traces the work blocks on a pool of `params.workers` threads, the pixels are sent to
+    /// `tx` as they are done. Returns the thread driving the pool, it ends when all the
+    /// blocks are done or the render is cancelled.
+    fn spawn(
+        self,
+        workblocks: Vec<WorkBlock>,
+        tx: std::sync::mpsc::Sender<RaytracedPixel>,
+    ) -> std::thread::JoinHandle<()> {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(self.params.workers.max(1) as usize)
+            .build()
+            .unwrap_or_else(|e| panic!("Failed to create the worker pool: {}", e));
+
+        std::thread::spawn(move || pool.install(|| self.run(workblocks, tx)))
+    }
+
+    //
+    // the blocks are picked up from the back of `workblocks`, the passes one after the
+    // other (coarsest first), the blocks of a pass in parallel
+    fn run(&self, workblocks: Vec<WorkBlock>, tx: std::sync::mpsc::Sender<RaytracedPixel>) {
+        with_sample_strategy!(self.params.sampler, S => {
+            self.run_with(workblocks, tx, RaytracerState::make_sampler::<S>(&self.params))
+        })
+    }
+
+    //
+    // every worker traces its blocks with its own clone of `s`
+    fn run_with<S>(
+        &self,
+        workblocks: Vec<WorkBlock>,
+        tx: std::sync::mpsc::Sender<RaytracedPixel>,
+        s: sampling::SamplerBase<S>,
+    ) where
+        S: sampling::SampleStrategy + Clone + Send + Sync,
+    {
+        use rayon::prelude::*;
+        use std::sync::atomic::Ordering;
+
+        let mut passes: Vec<Vec<WorkBlock>> = Vec::new();
+        workblocks
+            .into_iter()
+            .rev()
+            .for_each(|block| match passes.last_mut() {
+                Some(pass) if pass[0].scale == block.scale => pass.push(block),
+                _ => passes.push(vec![block]),
+            });
+
+        let finished = passes.into_iter().try_for_each(|pass| {
+            //
+            // the preview passes are not part of the statistics
+            let init = || {
+                (
+                    tx.clone(),
+                    s.clone(),
+                    self.params
+                        .integrator
+                        .build(&self.params, self.stats.as_deref()),
+                    self.params.integrator.build(&self.params, None),
+                )
+            };
+
+            //
+            // bridged, a worker takes the next block when it's done, splitting the vector
+            // would hand out chunks of it and lose the (shuffled) order of the pass
+            pass.into_iter().par_bridge().try_for_each_init(
+                init,
+                |(tx, s, integrator, preview), block| {
+                    if self.cancel_token.load(Ordering::SeqCst) {
+                        return Err(());
+                    }
+
+                    let block_start = std::time::Instant::now();
+                    RaytracerState::trace_block(
+                        &self.cam,
+                        &block,
+                        &self.params,
+                        &self.world,
+                        &self.lights,
+                        self.heatmap.as_ref(),
+                        if block.scale == 1 {
+                            &**integrator
+                        } else {
+                            &**preview
+                        },
+                        s,
+                        |pixel| tx.send(pixel).expect("Failed to send pixel to main"),
+                    );
+
+                    if let Some(stats) = self.stats.as_ref().filter(|_| block.scale == 1) {
+                        stats.record_block(block_start.elapsed());
+                    }
+                    self.workblocks_done.fetch_add(1, Ordering::SeqCst);
+                    Ok(())
+                },
+            )
+        });
+
+        if finished.is_err() {
+            println!("Render cancelled");
+        }
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct RaytracerConfig {
+    /// Format version of the file, see `CONFIG_VERSION`. Files written before the version
+    /// was recorded are version 0.
+    #[serde(default)]
+    pub version: u32,
+    pub active_scene: Scene,
+    pub default_params: RaytracerUserConfig,
+    /// Scenes with their overrides of `default_params`.
+    pub defined_scenes: Vec<(Scene, Option<RaytracerUserConfigPatch>)>,
+    /// Watch the config file and restart the render when it changes.
+    #[serde(default)]
+    pub hot_reload: bool,
+    /// Applied to the final image, after gamma correction.
+    #[serde(default)]
+    pub tone_curve: Option<ToneCurveConfig>,
+    /// Downscale factors of the preview passes rendered before the full resolution image,
+    /// coarsest first (e.g. [8, 4]). The full resolution pass is always rendered last.
+    #[serde(default)]
+    pub resolution_schedule: Vec<u32>,
+    /// Palette of the hit stats heat map, replaces the named palette of the view.
+    #[serde(default)]
+    pub heatmap_palette: Option<PaletteConfig>,
+    /// Grayscale image of the lens opening (see `ApertureMask`), out of focus highlights
+    /// take its shape. Replaces the polygon of `aperture_blades`.
+    #[serde(default)]
+    pub aperture_mask: Option<String>,
+    /// Scene description file (see `scene_loader`) rendered instead of the built in scene
+    /// of `active_scene`, with the params of `active_scene` and the camera of the file.
+    #[serde(default)]
+    pub scene_file: Option<String>,
+    /// Screenshots also write the linear image as 32 bit floats, to `.exr` files.
+    #[serde(default)]
+    pub output_hdr: bool,
+}
+
+pub const CONFIG_FILE: &str = "data/config/raytracer.config.ron";
+
+/// Version of the config format written by this build.
+/// Version 0 files lack the fields added after the first ones, those get their defaults.
+pub const CONFIG_VERSION: u32 = 1;
+
+impl RaytracerConfig {
+    /// Decodes a config file's contents, older versions are migrated to `CONFIG_VERSION`.
+    pub fn from_ron(text: &str) -> Result<RaytracerConfig, String> {
+        ron::de::from_str::<RaytracerConfig>(text)
+            .map_err(|e| e.to_string())
+            .and_then(|cfg| cfg.migrated().validated())
+    }
+
+    /// Rejects the values that decode but can't be rendered, in the defaults and in the
+    /// params of every scene.
+    pub fn validated(self) -> Result<RaytracerConfig, String> {
+        std::iter::once(self.default_params)
+            .chain(
+                self.defined_scenes
+                    .iter()
+                    .filter_map(|(_, patch)| patch.map(|patch| patch.apply(self.default_params))),
+            )
+            .try_for_each(|params| params.validate())?;
+
+        if let Some(palette) = self.heatmap_palette.as_ref() {
+            palette.validate()?;
+        }
+        if let Some(tone_curve) = self.tone_curve.as_ref() {
+            tone_curve.validate()?;
+        }
+
+        Ok(self)
+    }
+
+    /// Brings a config of an older version up to `CONFIG_VERSION`.
+    pub fn migrated(self) -> RaytracerConfig {
+        if self.version > CONFIG_VERSION {
+            println!(
+                "Config version {} is newer than the supported version {}, unknown fields are ignored",
+                self.version, CONFIG_VERSION
+            );
+            return self;
+        }
+
+        //
+        // 0 -> 1: only fields were added, the decoder already filled them with their defaults
+        RaytracerConfig {
+            version: CONFIG_VERSION,
+            ..self
+        }
+    }
+}
+
+/// Floats per `Color`.
+pub const COLOR_CHANNELS: usize = 4;
+
+//
+// the reinterpretation below relies on Color being COLOR_CHANNELS packed f32s
+const _: () = {
+    assert!(std::mem::size_of::<Color>() == COLOR_CHANNELS * std::mem::size_of::<f32>());
+    assert!(std::mem::align_of::<Color>() == std::mem::align_of::<f32>());
+};
+
+/// Samples accumulated for the pixels of the image.
+struct PixelSamples {
+    /// Sum of the linear colors of the samples.
+    sums: Vec<Color>,
+    counts: Vec<u32>,
+    /// Downscale factor of the pass the samples come from.
+    scales: Vec<u32>,
+}
+
+impl PixelSamples {
+    fn new(pixels: usize) -> PixelSamples {
+        PixelSamples {
+            sums: vec![Color::broadcast(0 as Real); pixels],
+            counts: vec![0; pixels],
+            scales: vec![u32::MAX; pixels],
+        }
+    }
+}
+
+//
+// Adds the samples of a pixel of a pass to the image, the pixels of the preview passes are
+// upscaled to the area they cover. Samples of a finer pass replace the ones of the coarser
+// passes and are never replaced by them, the pixels of the passes can arrive in any order.
+fn store_pixel(
+    image: &mut [Color],
+    samples: &mut PixelSamples,
+    params: &RaytracerParams,
+    tone_curve: Option<&ToneCurve>,
+    pixel: &RaytracedPixel,
+) {
+    let (width, height) = (params.image_width as u32, params.image_height as u32);
+    let x0 = (pixel.x * pixel.scale).min(width);
+    let y0 = (pixel.y * pixel.scale).min(height);
+
+    (y0..(y0 + pixel.scale).min(height)).for_each(|y| {
+        (x0..(x0 + pixel.scale).min(width)).for_each(|x| {
+            let idx = (y * width + x) as usize;
+            if pixel.scale > samples.scales[idx] {
+                return;
+            }
+
+            if pixel.scale < samples.scales[idx] {
+                samples.sums[idx] = Color::broadcast(0 as Real);
+                samples.counts[idx] = 0;
+                samples.scales[idx] = pixel.scale;
+            }
+
+            if pixel.samples == 0 {
+                image[idx] = pixel.color;
+                return;
+            }
+
+            samples.sums[idx] = samples.sums[idx] + pixel.color;
+            samples.counts[idx] += pixel.samples;
+            image[idx] = display_color(
+                average_samples(samples.sums[idx], samples.counts[idx]),
+                tone_curve,
+            );
+        });
+    });
+}
+
+//
+// The colors the displayed pixels are made from, before the gamma correction and the tone
+// curve. Pixels without samples (the hit stats heatmap) are taken as displayed.
+fn linear_colors(image: &[Color], samples: &PixelSamples) -> Vec<Color> {
+    image
+        .iter()
+        .zip(samples.sums.iter().zip(samples.counts.iter()))
+        .map(|(&displayed, (&sum, &count))| {
+            if count == 0 {
+                displayed
+            } else {
+                average_samples(sum, count)
+            }
+        })
+        .collect()
+}
+
+fn colors_as_f32_slice(colors: &[Color]) -> &[f32] {
+    unsafe {
+        std::slice::from_raw_parts(colors.as_ptr() as *const f32, colors.len() * COLOR_CHANNELS)
+    }
+}
+
+pub struct RaytracerState {
+    pub params: RaytracerParams,
+    /// Drives the worker pool, done when the image is done or the render is cancelled.
+    worker: Option<std::thread::JoinHandle<()>>,
+    pub workblocks_done: std::sync::Arc<std::sync::atomic::AtomicI32>,
+    pub total_workblocks: u32,
+    pub image_pixels: Vec<Color>,
+    /// Samples the displayed pixels are made from.
+    pixel_samples: PixelSamples,
+    tone_curve: Option<ToneCurve>,
+    cancel_token: Arc<std::sync::atomic::AtomicBool>,
+    pub timestamp: std::time::Instant,
+    pub raytracing_time: std::time::Duration,
+    rx: std::sync::mpsc::Receiver<RaytracedPixel>,
+    /// Statistics of the render and the CSV file they are written to when the image is
+    /// done, only collected when asked for.
+    stats: Option<(Arc<RenderStats>, std::path::PathBuf)>,
+}
+
+impl std::ops::Drop for RaytracerState {
+    fn drop(&mut self) {
+        if let Some(worker) = self.worker.take() {
+            worker.join().expect("Failed to join worker!");
+        }
+    }
+}
+
+impl RaytracerState {
+    /// The config's aperture mask image, if it has one.
+    pub fn load_aperture_mask(
+        tracer_cfg: &RaytracerConfig,
+    ) -> Result<Option<Arc<ApertureMask>>, String> {
+        tracer_cfg
+            .aperture_mask
+            .as_ref()
+            .map(|path| ApertureMask::load(path).map(Arc::new))
+            .transpose()
+    }
+
+    pub fn load_config() -> RaytracerConfig {
+        let text = std::fs::read_to_string(CONFIG_FILE).expect("Failed to open config file");
+
+        RaytracerConfig::from_ron(&text)
+            .unwrap_or_else(|e| panic!("Failed to decode config file: {}", e))
+    }
+
+    pub fn active_scene(tracer_cfg: &RaytracerConfig) -> (Scene, RaytracerParams) {
+        tracer_cfg
+            .defined_scenes
+            .iter()
+            .find(|(scene_type, _)| *scene_type == tracer_cfg.active_scene)
+            .map(|(scene_type, scene_params)| {
+                (
+                    *scene_type,
+                    scene_params
+                        .map_or(tracer_cfg.default_params, |patch| {
+                            patch.apply(tracer_cfg.default_params)
+                        })
+                        .into(),
+                )
+            })
+            .expect("Specified scene not found ...")
+    }
+
+    pub fn build_scene(
+        scene_type: Scene,
+        accelerator: Accelerator,
+    ) -> (HittableList, HittableList) {
+        match scene_type {
+            Scene::RandomWorld => scene_random_world(),
+            Scene::CornellBox => scene_cornell_box(),
+            Scene::Chapter2Final => scene_final_chapter2(accelerator),
+            Scene::SimpleLight => scene_simple_light(),
+            Scene::PerlinSpheres => scene_two_perlin_spheres(),
+            Scene::TwoSpheres => scene_two_spheres(),
+            Scene::GeometricPrimitives => scene_geometric_primitives(),
+            Scene::MeshTest => scene_mesh(),
+            Scene::Blocks => scene_blocks(),
+            _ => todo!("Unimplemented"),
+        }
+    }
+
+    /// The scene with the debug overlays asked for by the params added to the world.
+    pub fn build_scene_for(
+        scene_type: Scene,
+        params: &RaytracerParams,
+    ) -> (HittableList, HittableList) {
+        let (world, lights) = Self::build_scene(scene_type, params.accelerator);
+        Self::with_overlays(world, lights, params)
+    }
+
+    /// The params and the scene of the config: the scene file if there is one, the built in
+    /// scene of `active_scene` otherwise.
+    pub fn build_active_scene(
+        tracer_cfg: &RaytracerConfig,
+    ) -> Result<(RaytracerParams, HittableList, HittableList), String> {
+        let (scene_type, params) = Self::active_scene(tracer_cfg);
+
+        match tracer_cfg.scene_file.as_ref() {
+            Some(scene_file) => {
+                let description = scene_loader::SceneDescription::from_file(scene_file)?;
+                let params = description.params(params);
+                let (world, lights) = description
+                    .build()
+                    .map_err(|e| format!("Invalid scene file {}: {}", scene_file, e))?;
+                let (world, lights) = Self::with_overlays(world, lights, &params);
+                Ok((params, world, lights))
+            }
+            None => {
+                let (world, lights) = Self::build_scene_for(scene_type, &params);
+                Ok((params, world, lights))
+            }
+        }
+    }
+
+    //
+    // the debug overlays asked for by the params added to the world
+    fn with_overlays(
+        mut world: HittableList,
+        lights: HittableList,
+        params: &RaytracerParams,
+    ) -> (HittableList, HittableList) {
+        let wireframe = params.show_bounds.and_then(|overlay| {
+            bounds_wireframe::bounds_wireframe(&world, overlay, 0 as Real, 1 as Real)
+        });
+        if let Some(wireframe) = wireframe {
+            world.add(wireframe);
+        }
+
+        (world, lights)
+    }
+
+    /// The camera of the params, `aperture_mask` (the config's mask image) replaces the
+    /// polygon of `aperture_blades`.
+    pub fn make_camera(
+        params: &RaytracerParams,
+        aperture_mask: Option<&Arc<ApertureMask>>,
+        world: &dyn Hittable,
+        time0: Real,
+        time1: Real,
+    ) -> camera::Camera {
+        let focus_dist = if params.autofocus {
+            camera::Camera::autofocus_distance(
+                params.look_from.into(),
+                params.look_at.into(),
+                world,
+            )
+            .unwrap_or(params.focus_dist)
+        } else {
+            params.focus_dist
+        };
+
+        camera::Camera::new(
+            params.look_from.into(),
+            params.look_at.into(),
+            params.world_up.into(),
+            params.vertical_fov,
+            params.aspect_ratio,
+            params.aperture,
+            focus_dist,
+            time0,
+            time1,
+        )
+        .with_lens_distortion(params.lens_distortion[0], params.lens_distortion[1])
+        .with_aperture_mask(match (aperture_mask, params.aperture_blades) {
+            (Some(mask), _) => Some(Arc::clone(mask)),
+            (None, 0) => None,
+            (None, blades) => Some(Arc::new(ApertureMask::polygon(blades, 256))),
+        })
+    }
+
+    /// Cameras of the left and the right eye, `eye_separation` apart along the right (u)
+    /// axis of the camera built by `make_camera`. Both eyes focus at the distance it uses.
+    pub fn make_eye_cameras(
+        params: &RaytracerParams,
+        aperture_mask: Option<&Arc<ApertureMask>>,
+        world: &dyn Hittable,
+        eye_separation: Real,
+        time0: Real,
+        time1: Real,
+    ) -> [camera::Camera; 2] {
+        let center = Self::make_camera(params, aperture_mask, world, time0, time1);
+        let half_offset = center.u() * (eye_separation * 0.5 as Real);
+
+        let eye = |offset: Vec3| {
+            let shift = |p: [Real; 3]| {
+                let p = Vec3::from(p) + offset;
+                [p.x, p.y, p.z]
+            };
+
+            Self::make_camera(
+                &RaytracerParams {
+                    look_from: shift(params.look_from),
+                    look_at: shift(params.look_at),
+                    focus_dist: center.focus_dist(),
+                    autofocus: false,
+                    ..*params
+                },
+                aperture_mask,
+                world,
+                time0,
+                time1,
+            )
+        };
+
+        [eye(-half_offset), eye(half_offset)]
+    }
+
+    /// Raytraces a pixel and converts it to a displayable (gamma corrected) color.
+    fn trace_pixel<S: sampling::SampleStrategy>(
+        cam: &camera::Camera,
+        x: i32,
+        y: i32,
+        params: &RaytracerParams,
+        world: &Arc<HittableList>,
+        lights: &Arc<HittableList>,
+        heatmap: Option<&HitStatsHeatmap>,
+        tone_curve: Option<&ToneCurve>,
+        integrator: &dyn Integrator,
+        s: &mut sampling::SamplerBase<S>,
+    ) -> Color {
+        if let Some(heatmap) = heatmap {
+            return heatmap.colorize(&cam.primary_hit_stats(x, y, params, world, s));
+        }
+
+        display_color(
+            Self::trace_linear_pixel(cam, x, y, params, world, lights, integrator, s),
+            tone_curve,
+        )
+    }
+
+    /// Raytraces a pixel, the color is the average of the samples.
+    fn trace_linear_pixel<S: sampling::SampleStrategy>(
+        cam: &camera::Camera,
+        x: i32,
+        y: i32,
+        params: &RaytracerParams,
+        world: &Arc<HittableList>,
+        lights: &Arc<HittableList>,
+        integrator: &dyn Integrator,
+        s: &mut sampling::SamplerBase<S>,
+    ) -> Color {
+        average_samples(
+            Self::trace_pixel_samples(cam, x, y, params, world, lights, integrator, s),
+            params.samples_per_pixel as u32,
+        )
+    }
+
+    /// Raytraces a pixel, the color is the sum of the samples.
+    fn trace_pixel_samples<S: sampling::SampleStrategy>(
+        cam: &camera::Camera,
+        x: i32,
+        y: i32,
+        params: &RaytracerParams,
+        world: &Arc<HittableList>,
+        lights: &Arc<HittableList>,
+        integrator: &dyn Integrator,
+        s: &mut sampling::SamplerBase<S>,
+    ) -> Color {
+        cam.raytrace_pixel(x, y, params, world, lights, integrator, s)
+    }
+
+    /// Blocking render of a whole image, used when there is no window to display progress.
+    pub fn render_frame(
+        cam: &camera::Camera,
+        params: &RaytracerParams,
+        world: &Arc<HittableList>,
+        lights: &Arc<HittableList>,
+        tone_curve: Option<&ToneCurve>,
+        heatmap: Option<&HitStatsHeatmap>,
+    ) -> image::RgbImage {
+        let pixels = quantize_colors(
+            &Self::render_pixels(cam, params, world, lights, tone_curve, heatmap, None),
+            params.color_clamp,
+        );
+
+        image::RgbImage::from_vec(
+            params.image_width as u32,
+            params.image_height as u32,
+            pixels,
+        )
+        .expect("Failed to create image")
+    }
+
+    /// Blocking render of the displayable pixel colors, row by row.
+    fn render_pixels(
+        cam: &camera::Camera,
+        params: &RaytracerParams,
+        world: &Arc<HittableList>,
+        lights: &Arc<HittableList>,
+        tone_curve: Option<&ToneCurve>,
+        heatmap: Option<&HitStatsHeatmap>,
+        stats: Option<&RenderStats>,
+    ) -> Vec<Color> {
+        with_sample_strategy!(params.sampler, S => {
+            Self::render_pixels_with::<S, _>(params, stats, |x, y, integrator, s| {
+                Self::trace_pixel(
+                    cam,
+                    x,
+                    y,
+                    params,
+                    world,
+                    lights,
+                    heatmap,
+                    tone_curve,
+                    integrator,
+                    s,
+                )
+            })
+        })
+    }
+
+    /// Blocking render of the linear pixel colors (the heatmap colors when the hit stats
+    /// view is on), row by row.
+    fn render_linear_pixels(
+        cam: &camera::Camera,
+        params: &RaytracerParams,
+        world: &Arc<HittableList>,
+        lights: &Arc<HittableList>,
+        heatmap: Option<&HitStatsHeatmap>,
+        stats: Option<&RenderStats>,
+    ) -> Vec<Color> {
+        with_sample_strategy!(params.sampler, S => {
+            Self::render_pixels_with::<S, _>(params, stats, |x, y, integrator, s| match heatmap {
+                Some(heatmap) => heatmap.colorize(&cam.primary_hit_stats(x, y, params, world, s)),
+                None => Self::trace_linear_pixel(cam, x, y, params, world, lights, integrator, s),
+            })
+        })
+    }
+
+    /// The heat map of the hit stats view, if it is on, with the config's palette. Built
+    /// once per render, the render functions take the result.
+    pub fn make_heatmap(
+        params: &RaytracerParams,
+        heatmap_palette: Option<&PaletteConfig>,
+    ) -> Option<HitStatsHeatmap> {
+        params.hit_stats_view.map(|view| {
+            let heatmap = HitStatsHeatmap::new(view);
+            match heatmap_palette {
+                Some(palette) => heatmap.with_palette(palette.colors()),
+                None => heatmap,
+            }
+        })
+    }
+
+    /// The sampler the workers start from, built from the seed of `params` if there is one.
+    /// `S` is the strategy picked by `params.sampler`, see `with_sample_strategy`.
+    fn make_sampler<S: sampling::SampleStrategy>(
+        params: &RaytracerParams,
+    ) -> sampling::SamplerBase<S> {
+        let _seeded = types::seed_random(params.seed);
+        sampling::SamplerBase::<S>::new(params.samples_per_pixel, None)
+    }
+
+    //
+    // every worker builds its integrator once, the rows start from a clone of the sampler
+    fn render_pixels_with<S, F>(
+        params: &RaytracerParams,
+        stats: Option<&RenderStats>,
+        trace: F,
+    ) -> Vec<Color>
+    where
+        S: sampling::SampleStrategy + Clone + Send + Sync,
+        F: Fn(i32, i32, &dyn Integrator, &mut sampling::SamplerBase<S>) -> Color + Sync,
+    {
+        use rayon::prelude::*;
+
+        let s = Self::make_sampler::<S>(params);
+        (0..params.image_height)
+            .into_par_iter()
+            .map_init(
+                || params.integrator.build(params, stats),
+                |integrator, y| {
+                    let mut s = s.clone();
+                    (0..params.image_width)
+                        .map(|x| trace(x, y, &**integrator, &mut s))
+                        .collect::<Vec<_>>()
+                },
+            )
+            .flatten_iter()
+            .collect()
+    }
+
+    /// Renders the animation frames and writes them as frame_NNNN.png in `output_dir`.
+    pub fn render_animation<P: AsRef<std::path::Path>>(
+        params: &RaytracerParams,
+        aperture_mask: Option<&Arc<ApertureMask>>,
+        world: HittableList,
+        lights: HittableList,
+        tone_curve: Option<&ToneCurve>,
+        heatmap: Option<&HitStatsHeatmap>,
+        output_dir: P,
+    ) -> Vec<std::path::PathBuf> {
+        let animation = params
+            .animation
+            .expect("Animation parameters are not set for this scene");
+        std::fs::create_dir_all(output_dir.as_ref()).unwrap_or_else(|e| {
+            panic!(
+                "Failed to create output directory {}: {}",
+                output_dir.as_ref().display(),
+                e
+            )
+        });
+
+        let world = Arc::new(world);
+        let lights = Arc::new(lights);
+        let frame_duration = (animation.fps as Real).recip();
+
+        (0..animation.frames)
+            .map(|frame| {
+                let time0 = frame as Real * frame_duration;
+                let cam = Self::make_camera(
+                    params,
+                    aperture_mask,
+                    world.as_ref(),
+                    time0,
+                    time0 + frame_duration,
+                );
+                let frame_file = output_dir.as_ref().join(format!("frame_{:04}.png", frame));
+
+                Self::render_frame(&cam, params, &world, &lights, tone_curve, heatmap)
+                    .save(&frame_file)
+                    .unwrap_or_else(|e| {
+                        panic!("Failed to save frame {}: {}", frame_file.display(), e)
+                    });
+                println!("Frame {}/{} done", frame + 1, animation.frames);
+
+                frame_file
+            })
+            .collect()
+    }
+
+    /// Reloads the config if it changed on disk and starts a new render with it. A config
+    /// or a scene that can't be rendered is reported and the current render goes on.
+    pub fn reload(
+        watcher: &mut ConfigWatcher,
+        stats_csv: Option<std::path::PathBuf>,
+    ) -> Option<RaytracerState> {
+        watcher.poll::<RaytracerConfig>().and_then(|tracer_cfg| {
+            tracer_cfg
+                .migrated()
+                .validated()
+                .and_then(|tracer_cfg| Self::new(&tracer_cfg, stats_csv))
+                .map_err(|e| println!("Invalid config, keeping the current one: {}", e))
+                .ok()
+        })
+    }
+
+    /// Splits the image into work blocks, in the order the workers pick them up.
+    fn make_workblocks(params: &RaytracerParams) -> Vec<WorkBlock> {
+        let blocks_x = (params.image_width / params.worker_block_pixels) + 1;
+        let blocks_y = (params.image_height / params.worker_block_pixels) + 1;
+
+        let mut workblocks = vec![];
+        (0..blocks_y).for_each(|yblk| {
+            (0..blocks_x).for_each(|xblk| {
+                workblocks.push(WorkBlock {
+                    scale: 1,
+                    xdim: (
+                        (xblk * params.worker_block_pixels).min(params.image_width),
+                        ((xblk + 1) * params.worker_block_pixels).min(params.image_width),
+                    ),
+                    ydim: (
+                        (yblk * params.worker_block_pixels).min(params.image_height),
+                        ((yblk + 1) * params.worker_block_pixels).min(params.image_height),
+                    ),
+                });
+            });
+        });
+
+        if params.shuffle_workblocks {
+            match params.seed {
+                Some(seed) => {
+                    use rand::SeedableRng;
+                    workblocks.shuffle(&mut rand::rngs::StdRng::seed_from_u64(seed));
+                }
+                None => workblocks.shuffle(&mut rand::thread_rng()),
+            }
+        }
+
+        workblocks
+    }
+
+    /// Parameters of a pass rendered at 1/`scale` of the image resolution.
+    fn pass_params(params: &RaytracerParams, scale: u32) -> RaytracerParams {
+        let scale = scale as i32;
+        //
+        // at least 2 pixels, the camera divides by (size - 1)
+        RaytracerParams {
+            image_width: ((params.image_width + scale - 1) / scale).max(2),
+            image_height: ((params.image_height + scale - 1) / scale).max(2),
+            ..*params
+        }
+    }
+
+    /// Work blocks of all the passes in `resolution_schedule` and of the full resolution
+    /// pass, in the order the workers pick them up (coarsest pass first).
+    fn make_pass_workblocks(
+        params: &RaytracerParams,
+        resolution_schedule: &[u32],
+    ) -> Vec<WorkBlock> {
+        //
+        // workers pop blocks from the back
+        std::iter::once(1)
+            .chain(
+                resolution_schedule
+                    .iter()
+                    .rev()
+                    .cloned()
+                    .filter(|&scale| scale > 1),
+            )
+            .flat_map(|scale| {
+                Self::make_workblocks(&Self::pass_params(params, scale))
+                    .into_iter()
+                    .map(move |block| WorkBlock { scale, ..block })
+            })
+            .collect()
+    }
+
+    /// Traces the pixels of a work block, `send` gets them as they are done. The preview
+    /// passes are traced with an `integrator` that doesn't record statistics.
+    fn trace_block<S: sampling::SampleStrategy, F: FnMut(RaytracedPixel)>(
+        cam: &camera::Camera,
+        block: &WorkBlock,
+        params: &RaytracerParams,
+        world: &Arc<HittableList>,
+        lights: &Arc<HittableList>,
+        heatmap: Option<&HitStatsHeatmap>,
+        integrator: &dyn Integrator,
+        s: &mut sampling::SamplerBase<S>,
+        mut send: F,
+    ) {
+        let pass_params = Self::pass_params(params, block.scale);
+
+        (block.ydim.0..block.ydim.1).rev().for_each(|y| {
+            (block.xdim.0..block.xdim.1).for_each(|x| {
+                let (color, samples) = match heatmap {
+                    Some(heatmap) => (
+                        heatmap.colorize(&cam.primary_hit_stats(x, y, &pass_params, world, s)),
+                        0,
+                    ),
+                    None => (
+                        Self::trace_pixel_samples(
+                            cam,
+                            x,
+                            y,
+                            &pass_params,
+                            world,
+                            lights,
+                            integrator,
+                            s,
+                        ),
+                        pass_params.samples_per_pixel as u32,
+                    ),
+                };
+
+                send(RaytracedPixel {
+                    x: x as u32,
+                    y: y as u32,
+                    scale: block.scale,
+                    color,
+                    samples,
+                });
+            });
+        });
+    }
+
+    /// Starts rendering the config's active scene, the render statistics are written to
+    /// `stats_csv` when the image is done.
+    pub fn new(
+        tracer_cfg: &RaytracerConfig,
+        stats_csv: Option<std::path::PathBuf>,
+    ) -> Result<RaytracerState, String> {
+        let (mut params, world, lights) = Self::build_active_scene(tracer_cfg)?;
+
+        let workblocks = Self::make_pass_workblocks(&params, &tracer_cfg.resolution_schedule);
+
+        let total_workblocks = workblocks.len() as u32;
+
+        let aperture_mask = Self::load_aperture_mask(tracer_cfg)?;
+        let cam = Self::make_camera(&params, aperture_mask.as_ref(), &world, 0f32, 1f32);
+        params.focus_dist = cam.focus_dist();
+
+        let workblocks_done = Arc::new(std::sync::atomic::AtomicI32::new(0));
+        let cancel_token = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let stats = stats_csv.map(|path| (Arc::new(RenderStats::new(params.max_ray_depth)), path));
+
+        let (tx, rx) = std::sync::mpsc::channel::<RaytracedPixel>();
+        let worker = RenderJob {
+            cam,
+            params,
+            world: Arc::new(world),
+            lights: Arc::new(lights),
+            heatmap: Self::make_heatmap(&params, tracer_cfg.heatmap_palette.as_ref()),
+            stats: stats.as_ref().map(|(stats, _)| Arc::clone(stats)),
+            workblocks_done: Arc::clone(&workblocks_done),
+            cancel_token: Arc::clone(&cancel_token),
+        }
+        .spawn(workblocks, tx);
+
+        Ok(RaytracerState {
+            total_workblocks,
+            params,
+            worker: Some(worker),
+            workblocks_done,
+            image_pixels: vec![
+                Color::broadcast(0 as Real);
+                (params.image_width * params.image_height) as usize
+            ],
+            pixel_samples: PixelSamples::new((params.image_width * params.image_height) as usize),
+            tone_curve: tracer_cfg.tone_curve.as_ref().map(ToneCurve::new),
+            cancel_token,
+            timestamp: std::time::Instant::now(),
+            raytracing_time: std::time::Duration::from_millis(0),
+            rx,
+            stats,
+        })
+    }
+
+    fn export_stats(&self) {
+        if let Some((stats, path)) = self.stats.as_ref() {
+            match stats.summary().write_csv(path) {
+                Ok(()) => println!("Render statistics written to {}", path.display()),
+                Err(e) => println!(
+                    "Failed to write render statistics to {}: {}",
+                    path.display(),
+                    e
+                ),
+            }
+        }
+    }
+
+    /// The image as floats, `COLOR_CHANNELS` (r, g, b, a) per pixel, for uploading/saving.
+    pub fn as_f32_slice(&self) -> &[f32] {
+        colors_as_f32_slice(&self.image_pixels)
+    }
+
+    /// The image before the gamma correction and the tone curve, for HDR output.
+    pub fn linear_pixels(&self) -> Vec<Color> {
+        linear_colors(&self.image_pixels, &self.pixel_samples)
+    }
+
+    pub fn raytracing_finished(&mut self) -> bool {
+        let is_finished = self
+            .workblocks_done
+            .load(std::sync::atomic::Ordering::SeqCst)
+            > self.total_workblocks as i32;
+
+        if self
+            .workblocks_done
+            .load(std::sync::atomic::Ordering::SeqCst)
+            == self.total_workblocks as i32
+        {
+            self.workblocks_done
+                .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            self.export_stats();
+        }
+
+        is_finished
+    }
+
+    pub fn cancel_work(&mut self) {
+        self.cancel_token
+            .store(true, std::sync::atomic::Ordering::SeqCst);
+    }
+
+    pub fn recv_pixels(&mut self) {
+        while let Ok(pixel) = self.rx.try_recv() {
+            store_pixel(
+                &mut self.image_pixels,
+                &mut self.pixel_samples,
+                &self.params,
+                self.tone_curve.as_ref(),
+                &pixel,
+            );
+        }
+    }
+}
+
+/// Image produced by `render`.
+pub struct RenderResult {
+    /// Displayable (gamma corrected, unclamped) colors, `width` pixels per row, rows in
+    /// increasing y order.
+    pub pixels: Vec<Color>,
+    /// The colors `pixels` are made from, averaged samples before the gamma correction and
+    /// the tone curve (the same as `pixels` for the hit stats heatmap).
+    pub linear_pixels: Vec<Color>,
+    pub width: u32,
+    pub height: u32,
+    /// Range the displayable colors are clamped to when quantized to 8 bits.
+    pub color_clamp: [Real; 2],
+    /// Statistics of the render, if they were collected.
+    pub stats: Option<RenderStatsSummary>,
+}
+
+impl RenderResult {
+    /// Writes the linear colors to `<path>.exr` and the displayable ones, quantized to
+    /// 8 bits, to `<path>.png`. Returns the paths of the two files.
+    pub fn save_linear_and_display<P: AsRef<std::path::Path>>(
+        &self,
+        path: P,
+    ) -> (std::path::PathBuf, std::path::PathBuf) {
+        let exr_file = path.as_ref().with_extension("exr");
+        let png_file = path.as_ref().with_extension("png");
+
+        save_linear_colors(&exr_file, self.width, self.height, &self.linear_pixels);
+
+        image::RgbImage::from_vec(
+            self.width,
+            self.height,
+            quantize_colors(&self.pixels, self.color_clamp),
+        )
+        .expect("Failed to create image")
+        .save(&png_file)
+        .unwrap_or_else(|e| panic!("Failed to save {}: {}", png_file.display(), e));
+
+        (exr_file, png_file)
+    }
+}
+
+/// Renders the config's active scene on the calling thread (and the rayon pool), without
+/// opening a window. The render statistics are only collected with `collect_stats`.
+pub fn render(config: RaytracerConfig, collect_stats: bool) -> Result<RenderResult, String> {
+    let (params, world, lights) = RaytracerState::build_active_scene(&config)?;
+    let aperture_mask = RaytracerState::load_aperture_mask(&config)?;
+    let cam = RaytracerState::make_camera(
+        &params,
+        aperture_mask.as_ref(),
+        &world,
+        0 as Real,
+        1 as Real,
+    );
+    let tone_curve = config.tone_curve.as_ref().map(ToneCurve::new);
+    let heatmap = RaytracerState::make_heatmap(&params, config.heatmap_palette.as_ref());
+    let stats = collect_stats.then(|| RenderStats::new(params.max_ray_depth));
+
+    let world = Arc::new(world);
+    let lights = Arc::new(lights);
+
+    let started = std::time::Instant::now();
+    let (linear_pixels, width) = match params.stereo {
+        None => (
+            RaytracerState::render_linear_pixels(
+                &cam,
+                &params,
+                &world,
+                &lights,
+                heatmap.as_ref(),
+                stats.as_ref(),
+            ),
+            params.image_width as usize,
+        ),
+        Some(stereo) => {
+            let [left, right] = RaytracerState::make_eye_cameras(
+                &params,
+                aperture_mask.as_ref(),
+                world.as_ref(),
+                stereo.eye_separation,
+                0 as Real,
+                1 as Real,
+            )
+            .map(|eye| {
+                RaytracerState::render_linear_pixels(
+                    &eye,
+                    &params,
+                    &world,
+                    &lights,
+                    heatmap.as_ref(),
+                    stats.as_ref(),
+                )
+            });
+
+            stereo
+                .output
+                .combine(&left, &right, params.image_width as usize)
+        }
+    };
+    if let Some(stats) = stats.as_ref() {
+        stats.record_block(started.elapsed());
+    }
+
+    let pixels = if params.hit_stats_view.is_some() {
+        linear_pixels.clone()
+    } else {
+        linear_pixels
+            .iter()
+            .map(|&c| display_color(c, tone_curve.as_ref()))
+            .collect()
+    };
+
+    Ok(RenderResult {
+        pixels,
+        linear_pixels,
+        width: width as u32,
+        height: params.image_height as u32,
+        color_clamp: params.color_clamp,
+        stats: stats.map(|stats| stats.summary()),
+    })
+}
+
+//
+// the scene and the config are shared with the worker threads
+const _: () = {
+    fn assert_send_sync<T: Send + Sync + ?Sized>() {}
+
+    fn assert_shared_types() {
+        assert_send_sync::<dyn Hittable>();
+        assert_send_sync::<HittableList>();
+        assert_send_sync::<RaytracerConfig>();
+        assert_send_sync::<RenderStats>();
+    }
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    pub(crate) fn test_user_config(animation: Option<AnimationParams>) -> RaytracerUserConfig {
+        RaytracerUserConfig {
+            workers: 1,
+            worker_block_pixels: 16,
+            aspect_ratio: 1 as Real,
+            image_width: 24,
+            samples_per_pixel: 4,
+            max_ray_depth: 4,
+            vertical_fov: 60 as Real,
+            look_from: [0 as Real, 0 as Real, 5 as Real],
+            look_at: [0 as Real, 0 as Real, 0 as Real],
+            world_up: [0 as Real, 1 as Real, 0 as Real],
+            aperture: 0 as Real,
+            focus_dist: 5 as Real,
+            shuffle_workblocks: false,
+            background: [0 as Real, 0 as Real, 0 as Real],
+            projection: Projection::Perspective,
+            psi_max: 90f32,
+            lambda_max: 180f32,
+            per_channel_tracing: false,
+            first_hit_light_samples: 0,
+            animation,
+            ambient: [0 as Real, 0 as Real, 0 as Real],
+            hit_stats_view: None,
+            seed: None,
+            autofocus: false,
+            accelerator: Accelerator::Bvh,
+            lens_distortion: [0 as Real, 0 as Real],
+            color_clamp: DEFAULT_COLOR_CLAMP,
+            stereo: None,
+            aperture_blades: 0,
+            show_bounds: None,
+            integrator: IntegratorKind::PathTracer,
+            sampler: SamplerKind::MultiJittered,
+        }
+    }
+
+    pub(crate) fn test_params(animation: Option<AnimationParams>) -> RaytracerParams {
+        test_user_config(animation).into()
+    }
+
+    //
+    // horizontal position of the bright pixels in the image
+    fn bright_pixels_center(img: &image::RgbImage) -> Real {
+        let (sum, count) = img
+            .enumerate_pixels()
+            .filter(|(_, _, px)| px[0] > 128)
+            .fold((0 as Real, 0), |(sum, count), (x, _, _)| {
+                (sum + x as Real, count + 1)
+            });
+        assert!(count > 0, "moving sphere not visible");
+        sum / count as Real
+    }
+
+    #[test]
+    fn test_partial_pixels_are_exposed_by_their_sample_count() {
+        let params = RaytracerParams {
+            image_width: 2,
+            image_height: 1,
+            samples_per_pixel: 16,
+            ..test_params(None)
+        };
+        let mut image = vec![Color::broadcast(-1 as Real); 2];
+        let mut samples = PixelSamples::new(2);
+
+        //
+        // half of the samples of the first pixel are in, each one is 0.25
+        let half = RaytracedPixel {
+            x: 0,
+            y: 0,
+            scale: 1,
+            color: Color::broadcast(8 as Real * 0.25 as Real),
+            samples: 8,
+        };
+        store_pixel(&mut image, &mut samples, &params, None, &half);
+
+        let expected = display_color(Color::broadcast(0.25 as Real), None);
+        assert!((image[0].r - expected.r).abs() < 1.0e-6);
+        assert!((image[0].r - 0.5 as Real).abs() < 1.0e-6);
+        //
+        // dividing by the target count would make it darker
+        let by_target = display_color(
+            average_samples(half.color, params.samples_per_pixel as u32),
+            None,
+        );
+        assert!(by_target.r < 0.4 as Real);
+
+        //
+        // the rest of the samples don't change the exposure
+        store_pixel(&mut image, &mut samples, &params, None, &half);
+        assert_eq!(samples.counts[0], 16);
+        assert!((image[0].r - expected.r).abs() < 1.0e-6);
+
+        //
+        // no samples yet
+        assert_eq!(image[1].r, -1 as Real);
+        assert_eq!(average_samples(Color::broadcast(1 as Real), 0).r, 0 as Real);
+    }
+
+    #[test]
+    fn test_hdr_output_keeps_the_linear_values() {
+        let params = RaytracerParams {
+            image_width: 2,
+            image_height: 1,
+            samples_per_pixel: 4,
+            ..test_params(None)
+        };
+        let mut image = vec![Color::broadcast(0 as Real); 2];
+        let mut samples = PixelSamples::new(2);
+
+        //
+        // a bright pixel, above what the 8 bit images can hold, and a heatmap pixel
+        store_pixel(
+            &mut image,
+            &mut samples,
+            &params,
+            None,
+            &RaytracedPixel {
+                x: 0,
+                y: 0,
+                scale: 1,
+                color: Color::new(16 as Real, 4 as Real, 1 as Real),
+                samples: 4,
+            },
+        );
+        let heat = Color::new(1 as Real, 0 as Real, 0.5 as Real);
+        store_pixel(
+            &mut image,
+            &mut samples,
+            &params,
+            None,
+            &RaytracedPixel {
+                x: 1,
+                y: 0,
+                scale: 1,
+                color: heat,
+                samples: 0,
+            },
+        );
+
+        let linear = linear_colors(&image, &samples);
+        assert_eq!((linear[0].r, linear[0].g, linear[0].b), (4.0, 1.0, 0.25));
+        assert_eq!(image[0].r, 2 as Real);
+        assert_eq!(
+            (linear[1].r, linear[1].g, linear[1].b),
+            (heat.r, heat.g, heat.b)
+        );
+
+        let exr_file =
+            std::env::temp_dir().join(format!("raytracer_hdr_{}.exr", std::process::id()));
+        save_linear_colors(&exr_file, 2, 1, &linear);
+        let saved = image::open(&exr_file).unwrap().into_rgb32f();
+        std::fs::remove_file(&exr_file).unwrap();
+        assert_eq!(saved.get_pixel(0, 0).0, [4.0, 1.0, 0.25]);
+        assert_eq!(saved.get_pixel(1, 0).0, [1.0, 0.0, 0.5]);
+    }
+
+    #[test]
+    fn test_stereo_eyes_are_separated_along_u_and_see_parallax() {
+        use math::vec3::length;
+
+        let params = test_params(None);
+        let mut world = HittableList::new();
+        world.add(Arc::new(Sphere::new(
+            Point::new(0 as Real, 0 as Real, 2 as Real),
+            0.3 as Real,
+            Arc::new(DiffuseLight::from((4 as Real, 4 as Real, 4 as Real))),
+        )));
+        let world = Arc::new(world);
+        let lights = Arc::new(HittableList::new());
+
+        let center =
+            RaytracerState::make_camera(&params, None, world.as_ref(), 0 as Real, 1 as Real);
+        let [left, right] = RaytracerState::make_eye_cameras(
+            &params,
+            None,
+            world.as_ref(),
+            1 as Real,
+            0 as Real,
+            1 as Real,
+        );
+
+        assert!(length(right.origin() - left.origin() - center.u()) < 1.0e-5);
+        assert!(length((left.origin() + right.origin()) * 0.5 as Real - center.origin()) < 1.0e-5);
+        assert!(length(left.u() - center.u()) < 1.0e-5);
+        assert!(length(right.u() - center.u()) < 1.0e-5);
+
+        //
+        // the sphere is close, the left eye sees it further to the right than the right eye
+        let width = params.image_width as usize;
+        let render_eye = |eye: &camera::Camera| {
+            RaytracerState::render_linear_pixels(eye, &params, &world, &lights, None, None)
+        };
+        let sphere_center = |pixels: &[Color]| {
+            let (sum, count) = pixels
+                .iter()
+                .enumerate()
+                .filter(|(_, c)| c.r > 1 as Real)
+                .fold((0 as Real, 0), |(sum, count), (i, _)| {
+                    (sum + (i % width) as Real, count + 1)
+                });
+            assert!(count > 0, "sphere not visible");
+            sum / count as Real
+        };
+
+        let left_pixels = render_eye(&left);
+        let right_pixels = render_eye(&right);
+        assert!(sphere_center(&left_pixels) - sphere_center(&right_pixels) > 4 as Real);
+
+        let (side_by_side, combined_width) =
+            StereoOutput::SideBySide.combine(&left_pixels, &right_pixels, width);
+        assert_eq!(combined_width, width * 2);
+        assert_eq!(side_by_side.len(), left_pixels.len() * 2);
+        side_by_side
+            .chunks(width * 2)
+            .zip(left_pixels.chunks(width).zip(right_pixels.chunks(width)))
+            .for_each(|(row, (l, r))| {
+                assert!(row[..width].iter().zip(l).all(|(a, b)| a.r == b.r));
+                assert!(row[width..].iter().zip(r).all(|(a, b)| a.r == b.r));
+            });
+
+        let (anaglyph, anaglyph_width) =
+            StereoOutput::Anaglyph.combine(&left_pixels, &right_pixels, width);
+        assert_eq!(anaglyph_width, width);
+        anaglyph
+            .iter()
+            .zip(left_pixels.iter().zip(right_pixels.iter()))
+            .for_each(|(a, (l, r))| assert_eq!((a.r, a.g, a.b), (l.r, r.g, r.b)));
+    }
+
+    #[test]
+    fn test_animation_renders_moving_sphere_frames() {
+        let params = test_params(Some(AnimationParams { frames: 3, fps: 2 }));
+
+        let mut world = HittableList::new();
+        world.add(Arc::new(MovingSphere::new(
+            Point::new(-2 as Real, 0 as Real, 0 as Real),
+            Point::new(0 as Real, 0 as Real, 0 as Real),
+            0 as Real,
+            1 as Real,
+            0.5 as Real,
+            Arc::new(DiffuseLight::from((4 as Real, 4 as Real, 4 as Real))),
+        )));
+
+        let output_dir =
+            std::env::temp_dir().join(format!("raytracer_animation_{}", std::process::id()));
+        let frames = RaytracerState::render_animation(
+            &params,
+            None,
+            world,
+            HittableList::new(),
+            None,
+            None,
+            &output_dir,
+        );
+
+        assert_eq!(frames.len(), 3);
+        let centers = frames
+            .iter()
+            .enumerate()
+            .map(|(i, frame_file)| {
+                assert_eq!(
+                    frame_file.file_name().unwrap().to_str().unwrap(),
+                    format!("frame_{:04}.png", i)
+                );
+                bright_pixels_center(&image::open(frame_file).unwrap().to_rgb8())
+            })
+            .collect::<Vec<_>>();
+
+        std::fs::remove_dir_all(&output_dir).unwrap();
+
+        //
+        // the sphere moves to the right, one sphere radius (a few pixels) per frame
+        assert!(centers[1] > centers[0] + 1 as Real);
+        assert!(centers[2] > centers[1] + 1 as Real);
+    }
+
+    #[test]
+    fn test_hit_stats_heatmap_is_hot_over_dense_geometry() {
+        let mut params = test_params(None);
+        params.hit_stats_view = Some(HitStatsView {
+            metric: heatmap::HitStatsMetric::NodeVisits,
+            palette: heatmap::HeatmapPalette::ViridisLike,
+            max_count: 64,
+        });
+
+        //
+        // lots of small spheres on the left, a single big one on the right
+        let mtl: Arc<dyn material::Material> =
+            Arc::new(Lambertian::new((0.5 as Real, 0.5 as Real, 0.5 as Real)));
+        let mut objects = (0..64)
+            .map(|i| {
+                Arc::new(Sphere::new(
+                    Point::new(
+                        -2 as Real + (i % 8) as Real * 0.2 as Real,
+                        -0.75 as Real + (i / 8) as Real * 0.2 as Real,
+                        0 as Real,
+                    ),
+                    0.1 as Real,
+                    mtl.clone(),
+                )) as Arc<dyn Hittable>
+            })
+            .collect::<Vec<_>>();
+        objects.push(Arc::new(Sphere::new(
+            Point::new(1.25 as Real, 0 as Real, 0 as Real),
+            0.5 as Real,
+            mtl,
+        )));
+
+        let mut world = HittableList::new();
+        world.add(crate::bvh::BvhNode::new(&mut objects, 0 as Real, 1 as Real));
+        let world = Arc::new(world);
+        let lights = Arc::new(HittableList::new());
+
+        let cam = RaytracerState::make_camera(&params, None, world.as_ref(), 0 as Real, 1 as Real);
+        let mut s = crate::sampling::MultiJitteredSampler::new(params.samples_per_pixel, None);
+        let half_width = params.image_width / 2;
+        let (left, right) = (0..params.image_height)
+            .flat_map(|y| (0..params.image_width).map(move |x| (x, y)))
+            .fold((0, 0), |(left, right), (x, y)| {
+                let visits = cam
+                    .primary_hit_stats(x, y, &params, &world, &mut s)
+                    .node_visits;
+                if x < half_width {
+                    (left + visits, right)
+                } else {
+                    (left, right + visits)
+                }
+            });
+        assert!(left > right, "left {} right {}", left, right);
+
+        //
+        // brighter palette colors for higher counts
+        let heatmap = RaytracerState::make_heatmap(&params, None);
+        let img =
+            RaytracerState::render_frame(&cam, &params, &world, &lights, None, heatmap.as_ref());
+        let luminance = |x0: u32, x1: u32| {
+            img.enumerate_pixels()
+                .filter(|(x, _, _)| *x >= x0 && *x < x1)
+                .map(|(_, _, px)| {
+                    math::vec3::luminance(Vec3::new(px[0] as Real, px[1] as Real, px[2] as Real))
+                })
+                .sum::<Real>()
+        };
+        let half_width = half_width as u32;
+        assert!(luminance(0, half_width) > luminance(half_width, img.width()));
+    }
+
+    #[test]
+    fn test_colors_as_f32_slice() {
+        let colors = vec![
+            Color::new_with_alpha(0.1, 0.2, 0.3, 1.0),
+            Color::new_with_alpha(0.4, 0.5, 0.6, 0.5),
+            Color::new_with_alpha(0.7, 0.8, 0.9, 0.0),
+        ];
+        let floats = colors_as_f32_slice(&colors);
+
+        assert_eq!(floats.len(), colors.len() * COLOR_CHANNELS);
+        assert_eq!(&floats[..5], &[0.1, 0.2, 0.3, 1.0, 0.4]);
+        floats
+            .chunks(COLOR_CHANNELS)
+            .zip(colors.iter())
+            .for_each(|(px, c)| assert_eq!(px, &[c.r, c.g, c.b, c.a]));
+        assert!(colors_as_f32_slice(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_white_is_kept_and_quantized_to_255() {
+        let world = Arc::new(HittableList::new());
+        let lights = Arc::new(HittableList::new());
+
+        let render = |background: Real| {
+            let mut cfg = test_user_config(None);
+            cfg.background = [background; 3];
+            let params: RaytracerParams = cfg.into();
+            let cam =
+                RaytracerState::make_camera(&params, None, world.as_ref(), 0 as Real, 1 as Real);
+
+            (
+                RaytracerState::render_pixels(&cam, &params, &world, &lights, None, None, None),
+                RaytracerState::render_frame(&cam, &params, &world, &lights, None, None),
+            )
+        };
+
+        let (pixels, img) = render(1 as Real);
+        assert!(pixels
+            .iter()
+            .all(|c| c.r == 1 as Real && c.g == 1 as Real && c.b == 1 as Real));
+        assert!(img.pixels().all(|px| px.0 == [255, 255, 255]));
+
+        //
+        // brighter than white: only the 8 bit output is clamped
+        let (pixels, img) = render(4 as Real);
+        assert!(pixels.iter().all(|c| c.r == 2 as Real));
+        assert!(img.pixels().all(|px| px.0 == [255, 255, 255]));
+
+        let half = Color::new(0.5, 0.5, 0.5);
+        assert_eq!(
+            quantize_colors(&[half], DEFAULT_COLOR_CLAMP),
+            vec![127, 127, 127]
+        );
+        assert_eq!(
+            quantize_colors(&[half], [0 as Real, 0.25 as Real]),
+            vec![63, 63, 63]
+        );
+    }
+
+    #[test]
+    fn test_scene_overrides_only_the_fields_it_sets() {
+        let defaults = test_user_config(None);
+        let config_text = format!(
+            "(active_scene: TwoSpheres, default_params: {}, defined_scenes: [\
+             (TwoSpheres, Some((samples_per_pixel: 1024))), \
+             (CornellBox, Some((seed: Some(7), look_at: (1.0, 2.0, 3.0)))), \
+             (RandomWorld, None)])",
+            ron::ser::to_string(&defaults).unwrap()
+        );
+        let mut tracer_cfg: RaytracerConfig = ron::de::from_str(&config_text).unwrap();
+
+        let scene_config = |tracer_cfg: &RaytracerConfig, scene: Scene| {
+            let (_, patch) = tracer_cfg
+                .defined_scenes
+                .iter()
+                .find(|(s, _)| *s == scene)
+                .unwrap();
+            patch.map_or(tracer_cfg.default_params, |patch| {
+                patch.apply(tracer_cfg.default_params)
+            })
+        };
+        let same = |a: RaytracerUserConfig, b: RaytracerUserConfig| {
+            assert_eq!(format!("{:?}", a), format!("{:?}", b))
+        };
+
+        same(
+            scene_config(&tracer_cfg, Scene::TwoSpheres),
+            RaytracerUserConfig {
+                samples_per_pixel: 1024,
+                ..defaults
+            },
+        );
+        same(
+            scene_config(&tracer_cfg, Scene::CornellBox),
+            RaytracerUserConfig {
+                seed: Some(7),
+                look_at: [1 as Real, 2 as Real, 3 as Real],
+                ..defaults
+            },
+        );
+        same(scene_config(&tracer_cfg, Scene::RandomWorld), defaults);
+
+        let (scene, params) = RaytracerState::active_scene(&tracer_cfg);
+        assert_eq!(scene, Scene::TwoSpheres);
+        assert_eq!(params.samples_per_pixel, 1024);
+        assert_eq!(params.workers, defaults.workers);
+
+        //
+        // only the overridden fields are written back
+        tracer_cfg.defined_scenes.truncate(1);
+        let written = ron::ser::to_string(&tracer_cfg.defined_scenes).unwrap();
+        assert_eq!(written, "[(TwoSpheres,Some((samples_per_pixel:1024)))]");
+    }
+
+    #[test]
+    fn test_unversioned_config_gets_the_defaults_of_the_newer_fields() {
+        let old_config = r#"(
+            active_scene: TwoSpheres,
+            default_params: (
+                workers: 2,
+                worker_block_pixels: 8,
+                aspect_ratio: 1.5,
+                image_width: 300,
+                samples_per_pixel: 16,
+                max_ray_depth: 8,
+                vertical_fov: 40.0,
+                look_from: (13.0, 2.0, 3.0),
+                look_at: (0.0, 0.0, 0.0),
+                world_up: (0.0, 1.0, 0.0),
+                aperture: 0.1,
+                focus_dist: 10.0,
+                shuffle_workblocks: false,
+            ),
+            defined_scenes: [],
+        )"#;
+
+        let tracer_cfg = RaytracerConfig::from_ron(old_config).unwrap();
+        assert_eq!(tracer_cfg.version, CONFIG_VERSION);
+        assert!(!tracer_cfg.hot_reload);
+
+        let params = RaytracerParams::from(tracer_cfg.default_params);
+        assert_eq!(params.image_width, 300);
+        assert_eq!(params.samples_per_pixel, 16);
+        assert_eq!(params.background, [0 as Real; 3]);
+        assert!(matches!(params.projection, Projection::Perspective));
+        assert_eq!(params.psi_max, default_psi_max().to_radians());
+        assert_eq!(params.lambda_max, default_lambda_max().to_radians());
+        assert_eq!(params.color_clamp, DEFAULT_COLOR_CLAMP);
+
+        //
+        // newer files are read as far as they are understood
+        let newer = old_config.replacen("(", "(version: 99,", 1);
+        assert_eq!(RaytracerConfig::from_ron(&newer).unwrap().version, 99);
+    }
+
+    #[test]
+    fn test_config_rejects_diaphragms_with_fewer_than_3_blades() {
+        let config = |blades: u32| {
+            format!(
+                r#"(
+                    active_scene: TwoSpheres,
+                    default_params: (
+                        workers: 2,
+                        worker_block_pixels: 8,
+                        aspect_ratio: 1.5,
+                        image_width: 300,
+                        samples_per_pixel: 16,
+                        max_ray_depth: 8,
+                        vertical_fov: 40.0,
+                        look_from: (13.0, 2.0, 3.0),
+                        look_at: (0.0, 0.0, 0.0),
+                        world_up: (0.0, 1.0, 0.0),
+                        aperture: 0.1,
+                        focus_dist: 10.0,
+                        shuffle_workblocks: false,
+                        aperture_blades: {},
+                    ),
+                    defined_scenes: [],
+                )"#,
+                blades
+            )
+        };
+
+        [0, 3, 6].iter().for_each(|&blades| {
+            let tracer_cfg = RaytracerConfig::from_ron(&config(blades)).unwrap();
+            assert_eq!(tracer_cfg.default_params.aperture_blades, blades);
+        });
+        [1, 2].iter().for_each(|&blades| {
+            let e = RaytracerConfig::from_ron(&config(blades)).err().unwrap();
+            assert!(e.contains("aperture_blades"), "{}", e);
+        });
+    }
+
+    #[test]
+    fn test_config_rejects_non_square_samples_for_grid_samplers() {
+        let config = |samples_per_pixel: i32, sampler: &str| {
+            format!(
+                r#"(
+                    active_scene: TwoSpheres,
+                    default_params: (
+                        workers: 2,
+                        worker_block_pixels: 8,
+                        aspect_ratio: 1.5,
+                        image_width: 300,
+                        samples_per_pixel: 16,
+                        max_ray_depth: 8,
+                        vertical_fov: 40.0,
+                        look_from: (13.0, 2.0, 3.0),
+                        look_at: (0.0, 0.0, 0.0),
+                        world_up: (0.0, 1.0, 0.0),
+                        aperture: 0.1,
+                        focus_dist: 10.0,
+                        shuffle_workblocks: false,
+                        sampler: {},
+                    ),
+                    defined_scenes: [
+                        (TwoSpheres, Some((samples_per_pixel: {}))),
+                    ],
+                )"#,
+                sampler, samples_per_pixel
+            )
+        };
+
+        [(9, "Jittered"), (16, "Simple"), (32, "NRooks")]
+            .iter()
+            .for_each(|&(spp, sampler)| {
+                assert!(RaytracerConfig::from_ron(&config(spp, sampler)).is_ok());
+            });
+
+        //
+        // the scene overrides are checked too, not only the defaults
+        ["Jittered", "MultiJittered", "Simple"]
+            .iter()
+            .for_each(|&sampler| {
+                let e = RaytracerConfig::from_ron(&config(32, sampler))
+                    .err()
+                    .unwrap();
+                assert!(e.contains("samples_per_pixel"), "{}", e);
+            });
+    }
+
+    #[test]
+    fn test_config_reload_restarts_with_new_params() {
+        let config_file =
+            std::env::temp_dir().join(format!("raytracer_config_{}.ron", std::process::id()));
+        let write_config = |samples_per_pixel: i32| {
+            let tracer_cfg = RaytracerConfig {
+                version: CONFIG_VERSION,
+                active_scene: Scene::TwoSpheres,
+                default_params: RaytracerUserConfig {
+                    samples_per_pixel,
+                    ..test_user_config(None)
+                },
+                defined_scenes: vec![(Scene::TwoSpheres, None)],
+                hot_reload: true,
+                tone_curve: None,
+                resolution_schedule: Vec::new(),
+                heatmap_palette: None,
+                aperture_mask: None,
+                scene_file: None,
+                output_hdr: false,
+            };
+            std::fs::write(&config_file, ron::ser::to_string(&tracer_cfg).unwrap()).unwrap();
+        };
+        //
+        // explicit timestamps, writes in quick succession can end up with the same mtime
+        let set_modified = |secs: u64| {
+            std::fs::File::options()
+                .write(true)
+                .open(&config_file)
+                .unwrap()
+                .set_modified(std::time::UNIX_EPOCH + std::time::Duration::from_secs(secs))
+                .unwrap();
+        };
+
+        write_config(4);
+        set_modified(1000);
+        let mut watcher = ConfigWatcher::new(&config_file);
+        assert!(RaytracerState::reload(&mut watcher, None).is_none());
+
+        write_config(16);
+        set_modified(2000);
+        let mut raytracer =
+            RaytracerState::reload(&mut watcher, None).expect("Changed config was not reloaded");
+        assert_eq!(raytracer.params.samples_per_pixel, 16);
+        raytracer.cancel_work();
+        drop(raytracer);
+        assert!(RaytracerState::reload(&mut watcher, None).is_none());
+
+        //
+        // partially written file is ignored, and read again once the write is complete
+        std::fs::write(&config_file, "(active_scene: TwoSph").unwrap();
+        set_modified(3000);
+        assert!(RaytracerState::reload(&mut watcher, None).is_none());
+
+        write_config(9);
+        set_modified(3000);
+        let mut raytracer =
+            RaytracerState::reload(&mut watcher, None).expect("Completed config was not reloaded");
+        assert_eq!(raytracer.params.samples_per_pixel, 9);
+        raytracer.cancel_work();
+        drop(raytracer);
+
+        std::fs::remove_file(&config_file).unwrap();
+    }
+
+    #[test]
+    fn test_preview_passes_are_refined_to_the_full_render() {
+        //
+        // the heatmap view is deterministic, the render can be compared pixel by pixel
+        let mut params = RaytracerParams {
+            image_width: 30,
+            image_height: 20,
+            aspect_ratio: 1.5 as Real,
+            worker_block_pixels: 4,
+            ..test_params(None)
+        };
+        params.hit_stats_view = Some(HitStatsView {
+            metric: heatmap::HitStatsMetric::NodeVisits,
+            palette: heatmap::HeatmapPalette::Pf1,
+            max_count: 16,
+        });
+
+        let coarse = RaytracerState::pass_params(&params, 8);
+        assert_eq!((coarse.image_width, coarse.image_height), (4, 3));
+
+        let blocks = RaytracerState::make_pass_workblocks(&params, &[8, 4]);
+        let pixels_in_pass = |scale: u32| {
+            blocks
+                .iter()
+                .filter(|b| b.scale == scale)
+                .map(|b| (b.xdim.1 - b.xdim.0) * (b.ydim.1 - b.ydim.0))
+                .sum::<i32>()
+        };
+        assert_eq!(pixels_in_pass(8), 4 * 3);
+        assert_eq!(pixels_in_pass(4), 8 * 5);
+        assert_eq!(pixels_in_pass(1), 30 * 20);
+        //
+        // popped from the back, coarsest first
+        let pop_order = blocks.iter().rev().map(|b| b.scale).collect::<Vec<_>>();
+        assert!(pop_order.windows(2).all(|w| w[0] >= w[1]));
+
+        let mtl: Arc<dyn material::Material> =
+            Arc::new(Lambertian::new((0.5 as Real, 0.5 as Real, 0.5 as Real)));
+        let mut objects = (0..40)
+            .map(|i| {
+                Arc::new(Sphere::new(
+                    Point::new(
+                        (i % 8) as Real * 0.5 as Real - 2 as Real,
+                        (i / 8) as Real * 0.5 as Real - 1 as Real,
+                        0 as Real,
+                    ),
+                    0.2 as Real,
+                    mtl.clone(),
+                )) as Arc<dyn Hittable>
+            })
+            .collect::<Vec<_>>();
+        let mut world = HittableList::new();
+        world.add(crate::bvh::BvhNode::new(&mut objects, 0 as Real, 1 as Real));
+        let world = Arc::new(world);
+        let lights = Arc::new(HittableList::new());
+        let cam = RaytracerState::make_camera(&params, None, world.as_ref(), 0 as Real, 1 as Real);
+        let heatmap = params.hit_stats_view.map(HitStatsHeatmap::new);
+        let mut s = crate::sampling::MultiJitteredSampler::new(params.samples_per_pixel, None);
+
+        let size = (params.image_width * params.image_height) as usize;
+        let mut image = vec![Color::broadcast(-1 as Real); size];
+        let mut samples = PixelSamples::new(size);
+        let mut late_coarse_pixel = None;
+
+        blocks.iter().rev().for_each(|block| {
+            //
+            // the coarse pass alone fills the whole image
+            if block.scale == 4 {
+                assert!(samples.scales.iter().all(|&scale| scale != u32::MAX));
+            }
+
+            RaytracerState::trace_block(
+                &cam,
+                block,
+                &params,
+                &world,
+                &lights,
+                heatmap.as_ref(),
+                &*params.integrator.build(&params, None),
+                &mut s,
+                |pixel| {
+                    if pixel.scale == 8 {
+                        late_coarse_pixel = Some(pixel);
+                    }
+                    store_pixel(&mut image, &mut samples, &params, None, &pixel)
+                },
+            );
+        });
+
+        //
+        // preview pixels arriving after the full resolution ones don't overwrite them
+        let late_coarse_pixel = late_coarse_pixel.unwrap();
+        store_pixel(&mut image, &mut samples, &params, None, &late_coarse_pixel);
+        assert!(samples.scales.iter().all(|&scale| scale == 1));
+
+        let direct = RaytracerState::render_pixels(
+            &cam,
+            &params,
+            &world,
+            &lights,
+            None,
+            heatmap.as_ref(),
+            None,
+        );
+        assert!(image
+            .iter()
+            .zip(direct.iter())
+            .all(|(a, b)| a.r == b.r && a.g == b.g && a.b == b.b));
+        assert!(direct
+            .iter()
+            .any(|c| (c.r, c.g, c.b) != (direct[0].r, direct[0].g, direct[0].b)));
+    }
+
+    #[test]
+    fn test_worker_pool_renders_the_same_image_as_a_single_thread() {
+        //
+        // shaded spheres under a white sky, seeded so every pixel is traced from its own
+        // stream whichever worker gets it
+        let params = RaytracerParams {
+            image_width: 40,
+            image_height: 30,
+            aspect_ratio: 4 as Real / 3 as Real,
+            worker_block_pixels: 4,
+            workers: 4,
+            shuffle_workblocks: true,
+            seed: Some(7),
+            background: [1 as Real, 1 as Real, 1 as Real],
+            ..test_params(None)
+        };
+
+        let mtl: Arc<dyn material::Material> =
+            Arc::new(Lambertian::new((0.5 as Real, 0.5 as Real, 0.5 as Real)));
+        let mut objects = (0..60)
+            .map(|i| {
+                Arc::new(Sphere::new(
+                    Point::new(
+                        (i % 10) as Real * 0.5 as Real - 2.5 as Real,
+                        (i / 10) as Real * 0.5 as Real - 1.5 as Real,
+                        0 as Real,
+                    ),
+                    0.2 as Real,
+                    mtl.clone(),
+                )) as Arc<dyn Hittable>
+            })
+            .collect::<Vec<_>>();
+        let mut world = HittableList::new();
+        world.add(crate::bvh::BvhNode::new(&mut objects, 0 as Real, 1 as Real));
+        let world = Arc::new(world);
+        let lights = Arc::new(HittableList::new());
+        let cam = RaytracerState::make_camera(&params, None, world.as_ref(), 0 as Real, 1 as Real);
+
+        let size = (params.image_width * params.image_height) as usize;
+        let blocks = RaytracerState::make_pass_workblocks(&params, &[4]);
+
+        //
+        // the reference, the blocks one after the other on this thread
+        let mut s =
+            RaytracerState::make_sampler::<sampling::MultiJitteredSamplingStrategy>(&params);
+        let mut expected = vec![Color::broadcast(-1 as Real); size];
+        let mut expected_samples = PixelSamples::new(size);
+        blocks.iter().rev().for_each(|block| {
+            RaytracerState::trace_block(
+                &cam,
+                block,
+                &params,
+                &world,
+                &lights,
+                None,
+                &*params.integrator.build(&params, None),
+                &mut s,
+                |pixel| store_pixel(&mut expected, &mut expected_samples, &params, None, &pixel),
+            );
+        });
+
+        let workblocks_done = Arc::new(std::sync::atomic::AtomicI32::new(0));
+        let (tx, rx) = std::sync::mpsc::channel::<RaytracedPixel>();
+        let worker = RenderJob {
+            cam: cam.clone(),
+            params,
+            world: Arc::clone(&world),
+            lights: Arc::clone(&lights),
+            heatmap: None,
+            stats: Some(Arc::new(RenderStats::new(params.max_ray_depth))),
+            workblocks_done: Arc::clone(&workblocks_done),
+            cancel_token: Arc::new(std::sync::atomic::AtomicBool::new(false)),
+        }
+        .spawn(blocks.clone(), tx);
+
+        //
+        // the channel closes when the pool is done
+        let mut image = vec![Color::broadcast(-1 as Real); size];
+        let mut samples = PixelSamples::new(size);
+        rx.iter()
+            .for_each(|pixel| store_pixel(&mut image, &mut samples, &params, None, &pixel));
+        worker.join().unwrap();
+
+        assert_eq!(
+            workblocks_done.load(std::sync::atomic::Ordering::SeqCst),
+            blocks.len() as i32
+        );
+        assert!(samples.scales.iter().all(|&scale| scale == 1));
+        assert!(image
+            .iter()
+            .zip(expected.iter())
+            .all(|(a, b)| a.r == b.r && a.g == b.g && a.b == b.b));
+        assert!(image
+            .iter()
+            .any(|c| (c.r, c.g, c.b) != (image[0].r, image[0].g, image[0].b)));
+    }
+
+    #[test]
+    fn test_seeded_workblock_shuffle_is_reproducible() {
+        let params = RaytracerParams {
+            shuffle_workblocks: true,
+            worker_block_pixels: 4,
+            seed: Some(42),
+            ..test_params(None)
+        };
+
+        let first = RaytracerState::make_workblocks(&params);
+        let second = RaytracerState::make_workblocks(&params);
+        assert_eq!(first, second);
+
+        let in_order = RaytracerState::make_workblocks(&RaytracerParams {
+            shuffle_workblocks: false,
+            ..params
+        });
+        assert_eq!(first.len(), in_order.len());
+        assert_ne!(first, in_order);
+
+        let other_seed = RaytracerState::make_workblocks(&RaytracerParams {
+            seed: Some(7),
+            ..params
+        });
+        assert_ne!(first, other_seed);
+    }
+
+    #[test]
+    fn test_workers_pick_up_the_blocks_in_order() {
+        //
+        // not a multiple of the block size, there are no empty blocks at the edges
+        let params = RaytracerParams {
+            image_width: 42,
+            image_height: 30,
+            aspect_ratio: 7 as Real / 5 as Real,
+            worker_block_pixels: 4,
+            workers: 2,
+            shuffle_workblocks: true,
+            seed: Some(3),
+            background: [1 as Real, 1 as Real, 1 as Real],
+            ..test_params(None)
+        };
+
+        let world = Arc::new(HittableList::new());
+        let lights = Arc::new(HittableList::new());
+        let cam = RaytracerState::make_camera(&params, None, world.as_ref(), 0 as Real, 1 as Real);
+        let blocks = RaytracerState::make_pass_workblocks(&params, &[1]);
+
+        let (tx, rx) = std::sync::mpsc::channel::<RaytracedPixel>();
+        let worker = RenderJob {
+            cam,
+            params,
+            world,
+            lights,
+            heatmap: None,
+            stats: None,
+            workblocks_done: Arc::new(std::sync::atomic::AtomicI32::new(0)),
+            cancel_token: Arc::new(std::sync::atomic::AtomicBool::new(false)),
+        }
+        .spawn(blocks.clone(), tx);
+
+        //
+        // blocks numbered in pickup order, from the back of the vector
+        let block_of = |pixel: &RaytracedPixel| {
+            blocks
+                .iter()
+                .rev()
+                .position(|b| {
+                    (b.xdim.0..b.xdim.1).contains(&(pixel.x as i32))
+                        && (b.ydim.0..b.ydim.1).contains(&(pixel.y as i32))
+                })
+                .unwrap()
+        };
+        let mut started = Vec::new();
+        rx.iter().map(|pixel| block_of(&pixel)).for_each(|block| {
+            if !started.contains(&block) {
+                started.push(block);
+            }
+        });
+        worker.join().unwrap();
+
+        //
+        // a block is taken once the ones before it are, with two workers at most one
+        // of those (on the other worker) hasn't sent a pixel yet
+        assert_eq!(started.len(), blocks.len());
+        assert!(started
+            .iter()
+            .enumerate()
+            .all(|(position, &block)| block <= position + 1));
+    }
+
+    #[test]
+    fn test_seeded_renders_repeat_and_neighbour_pixels_are_decorrelated() {
+        use crate::{flip_face::FlipFace, lambertian::Lambertian, rectangles::XZRect};
+
+        //
+        // orthographic view straight down on a floor under a square light (the rays start
+        // at the image plane, halfway between them), every pixel has the same expected
+        // color and the noise is all there is to look at
+        let params = RaytracerParams {
+            projection: Projection::Orthographic,
+            seed: Some(11),
+            workers: 4,
+            ..test_params(None)
+        };
+        let mut world = HittableList::new();
+        world.add(Arc::new(XZRect {
+            x0: -100 as Real,
+            x1: 100 as Real,
+            z0: -100 as Real,
+            z1: 100 as Real,
+            k: 0 as Real,
+            mtl: Arc::new(Lambertian::new((0.5 as Real, 0.5 as Real, 0.5 as Real))),
+        }));
+        world.add(Arc::new(FlipFace {
+            obj: Arc::new(XZRect {
+                x0: -2 as Real,
+                x1: 2 as Real,
+                z0: -2 as Real,
+                z1: 2 as Real,
+                k: 1 as Real,
+                mtl: Arc::new(DiffuseLight::from((1 as Real, 1 as Real, 1 as Real))),
+            }),
+        }));
+        let world = Arc::new(world);
+        let lights = Arc::new(HittableList::new());
+        let frame_camera = |time: Real| {
+            camera::Camera::new(
+                Point::new(0 as Real, 0.9 as Real, 0 as Real),
+                Point::new(0 as Real, 0 as Real, 0 as Real),
+                Vec3::new(0 as Real, 0 as Real, -1 as Real),
+                params.vertical_fov,
+                params.aspect_ratio,
+                0 as Real,
+                0.4 as Real,
+                time,
+                time,
+            )
+        };
+        let render = |params: &RaytracerParams, time: Real| {
+            RaytracerState::render_linear_pixels(
+                &frame_camera(time),
+                params,
+                &world,
+                &lights,
+                None,
+                None,
+            )
+            .iter()
+            .map(|c| c.r)
+            .collect::<Vec<_>>()
+        };
+
+        let first = render(&params, 0 as Real);
+        assert_eq!(first, render(&params, 0 as Real));
+        assert_ne!(
+            first,
+            render(
+                &RaytracerParams {
+                    seed: Some(12),
+                    ..params
+                },
+                0 as Real
+            )
+        );
+
+        //
+        // the next frame of an animation gets its own noise
+        assert_ne!(first, render(&params, 0.5 as Real));
+
+        //
+        // correlation of the residuals of horizontal neighbours
+        let mean = first.iter().sum::<Real>() / first.len() as Real;
+        let residual = first.iter().map(|c| c - mean).collect::<Vec<_>>();
+        let variance = residual.iter().map(|r| r * r).sum::<Real>() / residual.len() as Real;
+        assert!(variance > 0 as Real);
+
+        let width = params.image_width as usize;
+        let pairs = (0..residual.len())
+            .filter(|i| (i + 1) % width != 0)
+            .map(|i| residual[i] * residual[i + 1])
+            .collect::<Vec<_>>();
+        let correlation = pairs.iter().sum::<Real>() / (pairs.len() as Real * variance);
+        assert!(correlation.abs() < 0.25 as Real, "{}", correlation);
+    }
+
+    #[test]
+    fn test_the_config_picks_the_workers_sampling_strategy() {
+        const SPP: usize = 16;
+
+        //
+        // the samples of a pixel, from the sampler a worker starts with, as the indices of
+        // the 1/n wide strips of the unit square their x and y coordinates fall in
+        let pixel_samples = |sampler: SamplerKind, n: Real| {
+            let params = RaytracerParams {
+                samples_per_pixel: SPP as i32,
+                sampler,
+                ..test_params(None)
+            };
+            let samples = with_sample_strategy!(params.sampler, S => {
+                let mut s = RaytracerState::make_sampler::<S>(&params);
+                (0..SPP).map(|_| s.sample_unit_square()).collect::<Vec<_>>()
+            });
+            samples
+                .iter()
+                .for_each(|p| assert!(p.x >= 0 as Real && p.x < 1 as Real && p.y < 1 as Real));
+
+            let mut strips = [|p: &Vec2| p.x, |p: &Vec2| p.y].map(|coord| {
+                samples
+                    .iter()
+                    .map(|p| (coord(p) * n) as usize)
+                    .collect::<Vec<_>>()
+            });
+            strips.iter_mut().for_each(|s| s.sort_unstable());
+            (samples, strips)
+        };
+
+        let one_per_strip = (0..SPP).collect::<Vec<_>>();
+        let four_per_strip = (0..SPP).map(|i| i / 4).collect::<Vec<_>>();
+
+        [SamplerKind::NRooks, SamplerKind::MultiJittered]
+            .iter()
+            .for_each(|&kind| {
+                let (_, strips) = pixel_samples(kind, SPP as Real);
+                assert_eq!(strips, [one_per_strip.clone(), one_per_strip.clone()]);
+            });
+
+        let (jittered, strips) = pixel_samples(SamplerKind::Jittered, 4 as Real);
+        assert_eq!(strips, [four_per_strip.clone(), four_per_strip.clone()]);
+
+        //
+        // the simple sampler is the only one with the samples at the centers of the cells
+        let (simple, strips) = pixel_samples(SamplerKind::Simple, 4 as Real);
+        assert_eq!(strips, [four_per_strip.clone(), four_per_strip]);
+        let centered = |samples: &[Vec2]| {
+            samples.iter().all(|p| {
+                [p.x, p.y]
+                    .iter()
+                    .all(|c| ((c * 4 as Real).fract() - 0.5 as Real).abs() < 1.0e-5)
+            })
+        };
+        assert!(centered(&simple));
+        assert!(!centered(&jittered));
+    }
+}
@@ -13,15 +13,58 @@ pub enum ScatterRecord {
         pdf: Arc<dyn Pdf>,
         attenuation: Color,
     },
+    /// Step of a random walk inside an object (subsurface scattering), followed like a
+    /// specular bounce. `walk_steps` counts the steps taken so far, the material gets it
+    /// back on the next hit as `HitRecord::walk_steps`.
+    WalkRec {
+        ray: Ray,
+        attenuation: Color,
+        walk_steps: u32,
+    },
 }
 
 pub trait Material: Send + Sync {
     fn scatter(&self, ray: &Ray, hit_record: &HitRecord) -> Option<ScatterRecord>;
 
+    /// Scatter for a path that only carries a single color channel (0 = r, 1 = g, 2 = b).
+    fn scatter_channel(
+        &self,
+        ray: &Ray,
+        hit_record: &HitRecord,
+        _channel: usize,
+    ) -> Option<ScatterRecord> {
+        self.scatter(ray, hit_record)
+    }
+
+    /// True if the material's response depends on the color channel, such paths
+    /// get split into one path per channel when per channel tracing is enabled.
+    /// Only the direction matters here, a material that just attenuates the channels
+    /// differently is not dispersive.
+    fn is_dispersive(&self) -> bool {
+        false
+    }
+
+    /// True for emitters that only emit from the front faces of the objects.
+    fn emits_one_sided(&self) -> bool {
+        false
+    }
+
     fn scattering_pdf(&self, _ray: &Ray, _hit_record: &HitRecord, _scattered: &Ray) -> Real {
         0 as Real
     }
 
+    /// Bsdf times the cosine of the scattered direction, for the `attenuation` of a
+    /// `PdfRec`. Materials whose lobes have different colors evaluate it themselves.
+    fn scattering(
+        &self,
+        ray: &Ray,
+        hit_record: &HitRecord,
+        scattered: &Ray,
+        attenuation: Color,
+    ) -> Color {
+        attenuation * self.scattering_pdf(ray, hit_record, scattered)
+    }
+
     fn emitted(
         &self,
         _ray: &Ray,
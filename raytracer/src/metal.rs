@@ -20,14 +20,19 @@ impl Metal {
 impl Material for Metal {
     fn scatter(&self, ray: &Ray, hit_record: &HitRecord) -> Option<ScatterRecord> {
         use crate::types::random_in_unit_sphere;
-        use math::vec3::{are_on_the_same_plane_side, normalize, reflect_unit_vector};
+        use math::vec3::{are_on_the_same_plane_side, normalize};
 
-        let reflected = reflect_unit_vector(ray.direction, normalize(hit_record.normal));
-        let scattered = reflected + self.fuzziness * random_in_unit_sphere();
+        let reflected = ray.reflect_ray(hit_record.p, normalize(hit_record.normal));
+        let scattered = reflected.direction + self.fuzziness * random_in_unit_sphere();
 
         if are_on_the_same_plane_side(scattered, hit_record.normal) {
+            //
+            // the differentials follow the mirror direction
             Some(ScatterRecord::SpecularRec {
-                ray: Ray::new(hit_record.p, scattered, ray.time),
+                ray: Ray {
+                    direction: scattered,
+                    ..reflected
+                },
                 attenuation: self.albedo,
             })
         } else {
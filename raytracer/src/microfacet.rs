@@ -0,0 +1,298 @@
+use math::vec3::dot;
+
+use crate::{
+    onb::Onb,
+    types::{random_real, Real, Vec3, C_ONE, C_PI, C_TWO_PI, C_ZERO},
+};
+
+/// Distribution of the microfacet normals of a rough surface.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MicrofacetDistribution {
+    /// Trowbridge-Reitz, long tails, highlights with a glow around them.
+    Ggx,
+    Beckmann,
+}
+
+/// Microfacet model, `alpha` is the roughness of the distribution (the slope deviation
+/// for Beckmann). Physically based rendering, section 8.4.
+#[derive(Copy, Clone, Debug)]
+pub struct Microfacet {
+    pub distribution: MicrofacetDistribution,
+    pub alpha: Real,
+}
+
+impl Microfacet {
+    pub fn ggx(alpha: Real) -> Microfacet {
+        Microfacet {
+            distribution: MicrofacetDistribution::Ggx,
+            alpha,
+        }
+    }
+
+    pub fn beckmann(alpha: Real) -> Microfacet {
+        Microfacet {
+            distribution: MicrofacetDistribution::Beckmann,
+            alpha,
+        }
+    }
+
+    /// Density of the microfacet normals making an angle with cosine `cos_theta` with the
+    /// macro surface normal.
+    pub fn d(&self, cos_theta: Real) -> Real {
+        if cos_theta <= C_ZERO {
+            return C_ZERO;
+        }
+
+        let cos2 = cos_theta * cos_theta;
+        let tan2 = (C_ONE - cos2) / cos2;
+        let alpha2 = self.alpha * self.alpha;
+
+        match self.distribution {
+            MicrofacetDistribution::Ggx => {
+                alpha2 / (C_PI * cos2 * cos2 * (alpha2 + tan2) * (alpha2 + tan2))
+            }
+            MicrofacetDistribution::Beckmann => {
+                (-tan2 / alpha2).exp() / (C_PI * alpha2 * cos2 * cos2)
+            }
+        }
+    }
+
+    //
+    // Smith's auxiliary function, for a direction with cosine `cos_theta` to the normal
+    fn lambda(&self, cos_theta: Real) -> Real {
+        let cos2 = cos_theta * cos_theta;
+        if cos2 >= C_ONE {
+            return C_ZERO;
+        }
+        let tan2 = (C_ONE - cos2) / cos2;
+
+        match self.distribution {
+            MicrofacetDistribution::Ggx => {
+                ((C_ONE + self.alpha * self.alpha * tan2).sqrt() - C_ONE) * 0.5 as Real
+            }
+            MicrofacetDistribution::Beckmann => {
+                //
+                // rational approximation of the erf based expression
+                let a = C_ONE / (self.alpha * tan2.sqrt());
+                if a >= 1.6 as Real {
+                    C_ZERO
+                } else {
+                    (C_ONE - 1.259 as Real * a + 0.396 as Real * a * a)
+                        / (3.535 as Real * a + 2.181 as Real * a * a)
+                }
+            }
+        }
+    }
+
+    /// Masking of a direction with cosine `cos_theta` to the normal.
+    pub fn g1(&self, cos_theta: Real) -> Real {
+        C_ONE / (C_ONE + self.lambda(cos_theta.abs()))
+    }
+
+    /// Masking-shadowing of the directions in and out (cosines to the normal), height
+    /// correlated.
+    pub fn g(&self, cos_o: Real, cos_i: Real) -> Real {
+        C_ONE / (C_ONE + self.lambda(cos_o.abs()) + self.lambda(cos_i.abs()))
+    }
+
+    /// Microfacet normal around `n`, with density `pdf_half_vector`, D(h) * cos(theta_h).
+    pub fn sample_half_vector(&self, n: Vec3) -> Vec3 {
+        let (u1, u2) = (random_real(), random_real());
+        let alpha2 = self.alpha * self.alpha;
+
+        let tan2 = match self.distribution {
+            MicrofacetDistribution::Ggx => alpha2 * u1 / (C_ONE - u1),
+            MicrofacetDistribution::Beckmann => -alpha2 * (C_ONE - u1).ln(),
+        };
+        let cos_theta = C_ONE / (C_ONE + tan2).sqrt();
+        let sin_theta = (C_ONE - cos_theta * cos_theta).max(C_ZERO).sqrt();
+        let (sin_phi, cos_phi) = (C_TWO_PI * u2).sin_cos();
+
+        Onb::from(n).local_from_pt(sin_theta * cos_phi, sin_theta * sin_phi, cos_theta)
+    }
+
+    /// Density of the half vectors returned by `sample_half_vector`, per solid angle.
+    pub fn pdf_half_vector(&self, n: Vec3, h: Vec3) -> Real {
+        let cos_theta = dot(n, h);
+        self.d(cos_theta) * cos_theta.max(C_ZERO)
+    }
+
+    /// Microfacet normal around `n` among the ones visible from the unit direction `wo`,
+    /// with density `pdf_visible_half_vector`. Unlike `sample_half_vector` none of them
+    /// faces away from `wo`. GGX only, Heitz, Sampling the GGX distribution of visible
+    /// normals.
+    pub fn sample_visible_half_vector(&self, n: Vec3, wo: Vec3) -> Vec3 {
+        use math::vec3::{cross, normalize};
+        debug_assert_eq!(self.distribution, MicrofacetDistribution::Ggx);
+
+        //
+        // stretch the view direction to the configuration of alpha = 1 (a hemisphere)
+        let frame = Onb::from(n);
+        let vh = normalize(Vec3::new(
+            self.alpha * dot(wo, frame.u()),
+            self.alpha * dot(wo, frame.v()),
+            dot(wo, frame.w()),
+        ));
+        let len2 = vh.x * vh.x + vh.y * vh.y;
+        let t1 = if len2 > C_ZERO {
+            Vec3::new(-vh.y, vh.x, C_ZERO) / len2.sqrt()
+        } else {
+            Vec3::new(C_ONE, C_ZERO, C_ZERO)
+        };
+        let t2 = cross(vh, t1);
+
+        //
+        // point on the projected hemisphere, a disk with its back half squashed
+        let r = random_real().sqrt();
+        let (sin_phi, cos_phi) = (C_TWO_PI * random_real()).sin_cos();
+        let p1 = r * cos_phi;
+        let s = 0.5 as Real * (C_ONE + vh.z);
+        let p2 = (C_ONE - s) * (C_ONE - p1 * p1).max(C_ZERO).sqrt() + s * r * sin_phi;
+        let nh = p1 * t1 + p2 * t2 + (C_ONE - p1 * p1 - p2 * p2).max(C_ZERO).sqrt() * vh;
+
+        let h = normalize(Vec3::new(
+            self.alpha * nh.x,
+            self.alpha * nh.y,
+            nh.z.max(C_ZERO),
+        ));
+        frame.local_from_vec(h)
+    }
+
+    /// Density of the half vectors returned by `sample_visible_half_vector`, per solid
+    /// angle, D(h) * G1(wo) * max(0, wo . h) / (wo . n).
+    pub fn pdf_visible_half_vector(&self, n: Vec3, wo: Vec3, h: Vec3) -> Real {
+        let cos_o = dot(wo, n);
+        if cos_o <= C_ZERO {
+            return C_ZERO;
+        }
+
+        self.d(dot(n, h)) * self.g1(cos_o) * dot(wo, h).max(C_ZERO) / cos_o
+    }
+
+    /// Glossy reflection of the viewer at the unit direction `wo` off a microfacet visible
+    /// from it, `n` is the unit normal on the viewer's side. Returns the microfacet normal
+    /// and the reflected unit direction, which may end up below the surface. GGX only.
+    pub fn sample_reflection(&self, n: Vec3, wo: Vec3) -> (Vec3, Vec3) {
+        let h = self.sample_visible_half_vector(n, wo);
+        (h, math::vec3::reflect_unit_vector(-wo, h))
+    }
+
+    /// Density, per solid angle, of `sample_reflection` returning the unit direction `wi`.
+    pub fn pdf_reflection(&self, n: Vec3, wo: Vec3, wi: Vec3) -> Real {
+        let h = wo + wi;
+        if math::vec3::is_near_zero(h) {
+            return C_ZERO;
+        }
+
+        let h = math::vec3::normalize(h);
+        self.pdf_visible_half_vector(n, wo, h) / (4 as Real * dot(wo, h))
+    }
+
+    /// Bsdf * cos / pdf of a reflection from `sample_reflection`, without the Fresnel
+    /// term: D G / (4 cos_o cos_i) * cos_i over D G1(wo) / (4 cos_o).
+    pub fn reflection_weight(&self, cos_o: Real, cos_i: Real) -> Real {
+        self.g(cos_o, cos_i) / self.g1(cos_o)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sampled_half_vectors_follow_the_distribution() {
+        const N: usize = 100_000;
+        const BINS: usize = 10;
+        let n = math::vec3::normalize(Vec3::new(0.3 as Real, 1 as Real, -0.2 as Real));
+
+        [Microfacet::ggx(0.3), Microfacet::beckmann(0.3)]
+            .iter()
+            .for_each(|mf| {
+                //
+                // histogram of cos(theta_h) against the integral of D(h) cos(theta_h) over
+                // each bin (d omega = 2 pi d cos)
+                let mut histogram = [0usize; BINS];
+                (0..N).for_each(|_| {
+                    let h = mf.sample_half_vector(n);
+                    assert!((math::vec3::length(h) - C_ONE).abs() < 1.0e-4);
+                    let cos_theta = dot(h, n).clamp(C_ZERO, C_ONE - 1.0e-6 as Real);
+                    histogram[(cos_theta * BINS as Real) as usize] += 1;
+                });
+
+                let expected = |bin: usize| {
+                    const STEPS: usize = 1000;
+                    let width = C_ONE / (BINS * STEPS) as Real;
+                    (0..STEPS)
+                        .map(|i| {
+                            let c = (bin * STEPS + i) as Real * width + 0.5 as Real * width;
+                            mf.d(c) * c * C_TWO_PI * width
+                        })
+                        .sum::<Real>()
+                };
+
+                assert!(((0..BINS).map(expected).sum::<Real>() - C_ONE).abs() < 0.01);
+                (0..BINS).for_each(|bin| {
+                    let fraction = histogram[bin] as Real / N as Real;
+                    assert!(
+                        (fraction - expected(bin)).abs() < 0.01,
+                        "{:?} bin {} sampled {} expected {}",
+                        mf.distribution,
+                        bin,
+                        fraction,
+                        expected(bin)
+                    );
+                });
+            });
+    }
+
+    #[test]
+    fn test_visible_half_vectors_follow_their_pdf() {
+        use math::vec3::normalize;
+
+        const N: usize = 100_000;
+        const BINS: usize = 10;
+        let mf = Microfacet::ggx(0.4);
+        let n = normalize(Vec3::new(0.3 as Real, 1 as Real, -0.2 as Real));
+        let frame = Onb::from(n);
+        let wo = normalize(frame.local_from_pt(0.9 as Real, C_ZERO, 0.3 as Real));
+
+        //
+        // histogram of cos(theta_h), against the integral of the pdf over every bin (the
+        // pdf depends on the azimuth for an oblique wo)
+        let mut histogram = [0usize; BINS];
+        (0..N).for_each(|_| {
+            let h = mf.sample_visible_half_vector(n, wo);
+            assert!((math::vec3::length(h) - C_ONE).abs() < 1.0e-4);
+            assert!(dot(wo, h) >= -1.0e-5);
+            let cos_theta = dot(h, n).clamp(C_ZERO, C_ONE - 1.0e-6 as Real);
+            histogram[(cos_theta * BINS as Real) as usize] += 1;
+        });
+
+        let expected = |bin: usize| {
+            const STEPS: usize = 200;
+            let width = C_ONE / (BINS * STEPS) as Real;
+            let dphi = C_TWO_PI / STEPS as Real;
+            (0..STEPS * STEPS)
+                .map(|i| {
+                    let c = (bin * STEPS + i % STEPS) as Real * width + 0.5 as Real * width;
+                    let phi = (i / STEPS) as Real * dphi;
+                    let s = (C_ONE - c * c).sqrt();
+                    let h = frame.local_from_pt(s * phi.cos(), s * phi.sin(), c);
+                    mf.pdf_visible_half_vector(n, wo, h) * width * dphi
+                })
+                .sum::<Real>()
+        };
+
+        assert!(((0..BINS).map(expected).sum::<Real>() - C_ONE).abs() < 0.01);
+        (0..BINS).for_each(|bin| {
+            let fraction = histogram[bin] as Real / N as Real;
+            assert!(
+                (fraction - expected(bin)).abs() < 0.01,
+                "bin {} sampled {} expected {}",
+                bin,
+                fraction,
+                expected(bin)
+            );
+        });
+    }
+}
@@ -0,0 +1,171 @@
+use std::sync::{Arc, RwLock};
+
+use crate::hittable::HitRecord;
+use crate::lambertian::Lambertian;
+use crate::material::{Material, ScatterRecord};
+use crate::metal::Metal;
+use crate::types::{Color, Point, Ray, Real};
+
+/// Parameters of a `MutableMaterial`. A roughness of 1 (or more) is a diffuse surface,
+/// anything below is a metal with the roughness as fuzziness.
+#[derive(Copy, Clone, Debug)]
+pub struct MaterialParams {
+    pub albedo: Color,
+    pub roughness: Real,
+    pub emission: Color,
+}
+
+impl MaterialParams {
+    fn is_diffuse(&self) -> bool {
+        self.roughness >= 1 as Real
+    }
+}
+
+/// Material whose parameters can be changed while it is shared by the scene
+/// (live tweaking in the previewer). Scattering reads the parameters under a read lock.
+pub struct MutableMaterial {
+    inner: RwLock<MaterialParams>,
+}
+
+impl MutableMaterial {
+    pub fn new(params: MaterialParams) -> MutableMaterial {
+        MutableMaterial {
+            inner: RwLock::new(params),
+        }
+    }
+
+    pub fn params(&self) -> MaterialParams {
+        *self.inner.read().expect("Material lock poisoned")
+    }
+
+    pub fn set_params(&self, params: MaterialParams) {
+        *self.inner.write().expect("Material lock poisoned") = params;
+    }
+
+    pub fn set_albedo<T: Into<Color>>(&self, albedo: T) {
+        self.inner.write().expect("Material lock poisoned").albedo = albedo.into();
+    }
+
+    pub fn set_roughness(&self, roughness: Real) {
+        self.inner
+            .write()
+            .expect("Material lock poisoned")
+            .roughness = roughness;
+    }
+
+    pub fn set_emission<T: Into<Color>>(&self, emission: T) {
+        self.inner.write().expect("Material lock poisoned").emission = emission.into();
+    }
+}
+
+impl Material for MutableMaterial {
+    fn scatter(&self, ray: &Ray, hit_record: &HitRecord) -> Option<ScatterRecord> {
+        let params = self.params();
+
+        if params.is_diffuse() {
+            Lambertian::new(params.albedo).scatter(ray, hit_record)
+        } else {
+            Metal::new(params.albedo, params.roughness.max(0 as Real)).scatter(ray, hit_record)
+        }
+    }
+
+    fn scattering_pdf(&self, ray: &Ray, hit_record: &HitRecord, scattered: &Ray) -> Real {
+        let params = self.params();
+
+        if params.is_diffuse() {
+            Lambertian::new(params.albedo).scattering_pdf(ray, hit_record, scattered)
+        } else {
+            0 as Real
+        }
+    }
+
+    fn emitted(
+        &self,
+        _ray: &Ray,
+        hit_record: &HitRecord,
+        _u: Real,
+        _v: Real,
+        _point: Point,
+    ) -> Color {
+        if hit_record.front_face {
+            self.params().emission
+        } else {
+            Color::broadcast(0 as Real)
+        }
+    }
+}
+
+impl std::convert::From<MaterialParams> for Arc<MutableMaterial> {
+    fn from(params: MaterialParams) -> Self {
+        Arc::new(MutableMaterial::new(params))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Vec3;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    //
+    // ray straight down onto the xz plane
+    fn scatter_down(mtl: &dyn Material) -> Option<ScatterRecord> {
+        let ray = Ray::new(
+            Point::new(0 as Real, 1 as Real, 0 as Real),
+            Vec3::new(0 as Real, -1 as Real, 0 as Real),
+            0 as Real,
+        );
+        let hit = HitRecord::new(
+            Point::new(0 as Real, 0 as Real, 0 as Real),
+            Vec3::new(0 as Real, 1 as Real, 0 as Real),
+            &ray,
+            1 as Real,
+            Arc::new(Lambertian::new((1 as Real, 1 as Real, 1 as Real))),
+            0 as Real,
+            0 as Real,
+        );
+
+        mtl.scatter(&ray, &hit)
+    }
+
+    fn attenuation(rec: Option<ScatterRecord>) -> Color {
+        match rec.unwrap() {
+            ScatterRecord::SpecularRec { attenuation, .. } => attenuation,
+            ScatterRecord::PdfRec { attenuation, .. } => attenuation,
+            ScatterRecord::WalkRec { attenuation, .. } => attenuation,
+        }
+    }
+
+    #[test]
+    fn test_albedo_change_from_another_thread() {
+        assert_send_sync::<MutableMaterial>();
+
+        let mtl: Arc<MutableMaterial> = MaterialParams {
+            albedo: Color::new(0.8, 0.1, 0.1),
+            roughness: 1 as Real,
+            emission: Color::broadcast(0 as Real),
+        }
+        .into();
+
+        let before = attenuation(scatter_down(mtl.as_ref()));
+        assert_eq!(before.r, 0.8 as Real);
+
+        let shared = Arc::clone(&mtl);
+        std::thread::spawn(move || shared.set_albedo((0.1 as Real, 0.1 as Real, 0.9 as Real)))
+            .join()
+            .unwrap();
+
+        let after = attenuation(scatter_down(mtl.as_ref()));
+        assert_eq!(after.r, 0.1 as Real);
+        assert_eq!(after.b, 0.9 as Real);
+
+        //
+        // dropping the roughness turns it into a specular reflector
+        mtl.set_roughness(0 as Real);
+        assert!(matches!(
+            scatter_down(mtl.as_ref()),
+            Some(ScatterRecord::SpecularRec { .. })
+        ));
+    }
+}
@@ -0,0 +1,89 @@
+use std::sync::Arc;
+
+use crate::{
+    aabb3::Aabb,
+    hittable::{HitRecord, Hittable},
+    types::{Ray, Real},
+};
+
+/// Object that is never hit, a placeholder for optional parts of a scene so they can be
+/// added without branching. The bounding box is the empty box, merging it with other
+/// boxes leaves them unchanged.
+pub struct Nothing;
+
+impl Nothing {
+    /// The object, or `Nothing` in its place.
+    pub fn or(obj: Option<Arc<dyn Hittable>>) -> Arc<dyn Hittable> {
+        obj.unwrap_or_else(|| Arc::new(Nothing))
+    }
+}
+
+impl Hittable for Nothing {
+    fn hit(&self, _r: &Ray, _t_min: Real, _t_max: Real) -> Option<HitRecord> {
+        None
+    }
+
+    fn name(&self) -> Option<&str> {
+        Some("nothing")
+    }
+
+    fn bounding_box(&self, _time0: Real, _time1: Real) -> Option<Aabb> {
+        Some(Aabb::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hittable_list::HittableList;
+    use crate::lambertian::Lambertian;
+    use crate::objects::sphere::Sphere;
+    use crate::types::{random_unit_vector, Point};
+
+    #[test]
+    fn test_nothing_is_never_hit_and_does_not_change_a_list() {
+        let mtl = Arc::new(Lambertian::new((0.5, 0.5, 0.5)));
+        let spheres = [
+            Point::new(0 as Real, 0 as Real, 0 as Real),
+            Point::new(3 as Real, 1 as Real, -2 as Real),
+        ]
+        .iter()
+        .map(|&center| Arc::new(Sphere::new(center, 1 as Real, mtl.clone())) as Arc<dyn Hittable>)
+        .collect::<Vec<_>>();
+
+        let mut without = HittableList::new();
+        let mut with = HittableList::new();
+        with.add(Nothing::or(None));
+        spheres.iter().for_each(|obj| {
+            without.add(obj.clone());
+            with.add(obj.clone());
+            with.add(Arc::new(Nothing));
+        });
+        assert_eq!(with.objects_len(), 5);
+
+        let bbox = without.bounding_box(0 as Real, 1 as Real).unwrap();
+        let bbox_with = with.bounding_box(0 as Real, 1 as Real).unwrap();
+        assert_eq!(bbox.min, bbox_with.min);
+        assert_eq!(bbox.max, bbox_with.max);
+
+        let center = Point::new(1.5 as Real, 0.5 as Real, -1 as Real);
+        (0..500).for_each(|_| {
+            let origin = center + random_unit_vector() * 10 as Real;
+            let r = Ray::new(origin, center + random_unit_vector() - origin, 0 as Real);
+
+            assert!(Nothing.hit(&r, 0.001 as Real, Real::MAX).is_none());
+            match (
+                without.hit(&r, 0.001 as Real, Real::MAX),
+                with.hit(&r, 0.001 as Real, Real::MAX),
+            ) {
+                (Some(a), Some(b)) => assert_eq!((a.t, a.p), (b.t, b.p)),
+                (a, b) => assert_eq!(a.is_some(), b.is_some()),
+            }
+        });
+
+        //
+        // `or` keeps the object it is given
+        let sphere = Nothing::or(Some(spheres[0].clone()));
+        assert!(Arc::ptr_eq(&sphere, &spheres[0]));
+    }
+}
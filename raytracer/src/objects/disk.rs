@@ -1,3 +1,4 @@
+use crate::aabb3::Aabb;
 use crate::hittable::{HitRecord, Hittable};
 use crate::material::Material;
 use crate::types::{Point, Ray, Real, Vec3};
@@ -37,6 +38,8 @@ impl Hittable for Disk {
                     r,
                     t,
                     std::sync::Arc::clone(&self.mtl),
+                    0 as Real,
+                    0 as Real,
                 ))
             } else {
                 //
@@ -47,4 +50,52 @@ impl Hittable for Disk {
             None
         }
     }
+
+    fn bounding_box(&self, _time0: Real, _time1: Real) -> Option<Aabb> {
+        //
+        // the disk spans radius * sin(angle between the axis and the normal) along an axis
+        let n = math::vec3::normalize(self.normal);
+        let extent = Vec3::new(
+            self.radius * (1 as Real - n.x * n.x).max(0 as Real).sqrt(),
+            self.radius * (1 as Real - n.y * n.y).max(0 as Real).sqrt(),
+            self.radius * (1 as Real - n.z * n.z).max(0 as Real).sqrt(),
+        );
+
+        Some(Aabb::new(self.origin - extent, self.origin + extent).padded(0.0001 as Real))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lambertian::Lambertian;
+
+    #[test]
+    fn test_disk_hits_are_inside_its_bounding_box() {
+        let disk = Disk {
+            origin: Point::new(1 as Real, 2 as Real, 3 as Real),
+            normal: Vec3::new(1 as Real, 1 as Real, 0 as Real),
+            radius: 2 as Real,
+            mtl: std::sync::Arc::new(Lambertian::new((0.5 as Real, 0.5 as Real, 0.5 as Real))),
+        };
+        let bbox = disk.bounding_box(0 as Real, 1 as Real).unwrap();
+
+        //
+        // rays along the normal, through points near the rim
+        let rim = math::vec3::normalize(Vec3::new(1 as Real, -1 as Real, 0 as Real));
+        [
+            rim * 1.99 as Real,
+            Vec3::new(0 as Real, 0 as Real, 1.99 as Real),
+        ]
+        .iter()
+        .for_each(|&offset| {
+            let r = Ray::new(
+                disk.origin + offset - disk.normal * 5 as Real,
+                disk.normal,
+                0 as Real,
+            );
+            let hit = disk.hit(&r, 0.001 as Real, Real::MAX).unwrap();
+            assert!(bbox.contains(hit.p), "{:?}", hit.p);
+        });
+    }
 }
@@ -0,0 +1,174 @@
+use math::vec3::{dot, length, normalize};
+
+use crate::aabb3::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::objects::sphere::Sphere;
+use crate::perlin::PerlinNoise;
+use crate::sdf::{gradient_normal, sphere_trace, HIT_EPSILON};
+use crate::types::{Point, Ray, Real};
+
+/// Sphere whose radius is perturbed by Perlin noise (terrain balls, asteroids). The
+/// displacement depends only on the direction from the center, it is the noise sampled at
+/// the unit direction scaled by `frequency`, times `amplitude`.
+pub struct DisplacedSphere {
+    pub base: Sphere,
+    pub noise: PerlinNoise,
+    pub amplitude: Real,
+    pub frequency: Real,
+}
+
+impl DisplacedSphere {
+    const MAX_STEPS: u32 = 128;
+
+    pub fn new(base: Sphere, amplitude: Real, frequency: Real) -> DisplacedSphere {
+        DisplacedSphere {
+            base,
+            noise: PerlinNoise::new(),
+            amplitude,
+            frequency,
+        }
+    }
+
+    fn radius(&self) -> Real {
+        self.base.radius.abs()
+    }
+
+    fn outer_radius(&self) -> Real {
+        self.radius() + self.amplitude.abs()
+    }
+
+    //
+    // distance to the displaced surface along the radial direction, negative inside.
+    // Only an estimate of the euclidean distance, see `lipschitz`.
+    fn distance(&self, p: Point) -> Real {
+        let d = p - self.base.center;
+        let dist = length(d);
+        if dist <= 0 as Real {
+            return -self.radius();
+        }
+
+        let displacement = self.amplitude * self.noise.noise(d / dist * self.frequency);
+        dist - (self.radius() + displacement)
+    }
+
+    //
+    // bound on how fast the distance estimate changes, steps are shortened by it so that
+    // the march does not jump over the surface
+    fn lipschitz(&self) -> Real {
+        let inner_radius = (self.radius() - self.amplitude.abs()).max(HIT_EPSILON);
+        1 as Real + 2 as Real * self.amplitude.abs() * self.frequency / inner_radius
+    }
+
+    //
+    // parametric range of the ray inside the sphere enclosing the displaced surface
+    fn bounds_interval(&self, r: &Ray, t_min: Real, t_max: Real) -> Option<(Real, Real)> {
+        let oc = r.origin - self.base.center;
+        let a = dot(r.direction, r.direction);
+        let half_b = dot(oc, r.direction);
+        let c = dot(oc, oc) - self.outer_radius() * self.outer_radius();
+
+        let discriminant = half_b * half_b - a * c;
+        if discriminant < 0 as Real {
+            return None;
+        }
+
+        let sqrtd = discriminant.sqrt();
+        let t0 = ((-half_b - sqrtd) / a).max(t_min);
+        let t1 = ((-half_b + sqrtd) / a).min(t_max);
+
+        if t1 < t0 {
+            None
+        } else {
+            Some((t0, t1))
+        }
+    }
+}
+
+impl Hittable for DisplacedSphere {
+    fn hit(&self, r: &Ray, t_min: Real, t_max: Real) -> Option<HitRecord> {
+        let t = sphere_trace(
+            |p| self.distance(p),
+            r,
+            self.bounds_interval(r, t_min, t_max)?,
+            1 as Real / (self.lipschitz() * length(r.direction)),
+            Self::MAX_STEPS,
+        )?;
+        let p = r.at(t);
+        let (u, v) = self.base.get_uv(normalize(p - self.base.center));
+
+        Some(HitRecord::new(
+            p,
+            gradient_normal(|p| self.distance(p), p),
+            r,
+            t,
+            self.base.mtl.clone(),
+            u,
+            v,
+        ))
+    }
+
+    fn name(&self) -> Option<&str> {
+        Some("displaced sphere")
+    }
+
+    fn bounding_box(&self, _time0: Real, _time1: Real) -> Option<Aabb> {
+        Some(Aabb::new(
+            self.base.center - Point::broadcast(self.outer_radius()),
+            self.base.center + Point::broadcast(self.outer_radius()),
+        ))
+    }
+
+    fn bounding_sphere(&self) -> Option<(Point, Real)> {
+        Some((self.base.center, self.outer_radius()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lambertian::Lambertian;
+    use crate::types::{random_unit_vector, C_ZERO};
+
+    fn unit_sphere() -> Sphere {
+        Sphere::new(
+            Point::broadcast(C_ZERO),
+            1 as Real,
+            std::sync::Arc::new(Lambertian::new((0.5, 0.5, 0.5))),
+        )
+    }
+
+    #[test]
+    fn test_zero_amplitude_is_the_plain_sphere_and_noise_displaces() {
+        let plain = DisplacedSphere::new(unit_sphere(), C_ZERO, 4 as Real);
+
+        (0..200).for_each(|_| {
+            let origin = random_unit_vector() * 4 as Real;
+            let target = random_unit_vector() * 0.5 as Real;
+            let r = Ray::new(origin, target - origin, C_ZERO);
+
+            let expected = plain.base.hit(&r, 0.001 as Real, Real::MAX).unwrap();
+            let traced = plain.hit(&r, 0.001 as Real, Real::MAX).unwrap();
+            assert!(length(expected.p - traced.p) < 1.0e-3);
+            assert!(dot(expected.normal, traced.normal) > 0.999 as Real);
+        });
+
+        //
+        // rays aimed at the center, the hit distance from the center is the displaced radius
+        let bumpy = DisplacedSphere::new(unit_sphere(), 0.3 as Real, 4 as Real);
+        let radii = (0..200)
+            .map(|_| {
+                let origin = random_unit_vector() * 4 as Real;
+                let r = Ray::new(origin, -origin, C_ZERO);
+                let hit = bumpy.hit(&r, 0.001 as Real, Real::MAX).unwrap();
+
+                assert!((bumpy.distance(hit.p)).abs() < 1.0e-3);
+                length(hit.p)
+            })
+            .collect::<Vec<_>>();
+
+        let min_radius = radii.iter().cloned().fold(Real::MAX, Real::min);
+        let max_radius = radii.iter().cloned().fold(C_ZERO, Real::max);
+        assert!(min_radius >= 0.7 as Real && max_radius <= 1.3 as Real);
+        assert!(max_radius - min_radius > 0.1 as Real);
+    }
+}
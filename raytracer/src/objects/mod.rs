@@ -1,4 +1,5 @@
-// pub mod disk;
-// pub mod plane;
+pub mod disk;
+pub mod displaced_sphere;
+pub mod plane;
 pub mod sphere;
-// pub mod triangle;
+pub mod triangle;
@@ -1,3 +1,4 @@
+use crate::aabb3::Aabb;
 use crate::hittable::{HitRecord, Hittable};
 use crate::material::Material;
 use crate::types::{Point, Ray, Real, Vec3};
@@ -48,6 +49,8 @@ impl Hittable for Plane {
                 ray,
                 temp,
                 std::sync::Arc::clone(&self.mtl),
+                0 as Real,
+                0 as Real,
             ))
         } else {
             //
@@ -55,4 +58,8 @@ impl Hittable for Plane {
             None
         }
     }
+
+    fn bounding_box(&self, _time0: Real, _time1: Real) -> Option<Aabb> {
+        None
+    }
 }
@@ -1,28 +1,71 @@
 use math::vec3::{self, length_squared};
 
 use crate::aabb3::Aabb;
-use crate::hittable::{HitRecord, Hittable};
+use crate::hittable::{next_primitive_id, HitRecord, Hittable, PrimitiveId};
 use crate::material::Material;
 use crate::onb::Onb;
 use crate::types::{random_to_sphere, Point, Ray, Real};
 
+/// How texture coordinates are assigned to points on a sphere.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum SphereUvMapping {
+    /// u from the longitude, v from the latitude angle. Texels bunch up at the poles.
+    #[default]
+    LatLong,
+    /// Lambert cylindrical equal-area projection, u from the longitude, v from the height.
+    /// Equal areas in uv space cover equal areas on the sphere.
+    EqualArea,
+}
+
 #[derive(Clone)]
 pub struct Sphere {
     pub center: Point,
     pub radius: Real,
     pub mtl: std::sync::Arc<dyn Material>,
+    pub uv_mapping: SphereUvMapping,
+    id: PrimitiveId,
 }
 
 impl Sphere {
     pub fn new(center: Point, radius: Real, mtl: std::sync::Arc<dyn Material>) -> Sphere {
+        Self::with_uv_mapping(center, radius, mtl, SphereUvMapping::LatLong)
+    }
+
+    pub fn with_uv_mapping(
+        center: Point,
+        radius: Real,
+        mtl: std::sync::Arc<dyn Material>,
+        uv_mapping: SphereUvMapping,
+    ) -> Sphere {
         Sphere {
             center,
             radius,
             mtl,
+            uv_mapping,
+            id: next_primitive_id(),
         }
     }
 
-    fn get_uv(p: Point) -> (f32, f32) {
+    pub(crate) fn get_uv(&self, p: Point) -> (f32, f32) {
+        match self.uv_mapping {
+            SphereUvMapping::LatLong => Self::get_uv_lat_long(p),
+            SphereUvMapping::EqualArea => Self::get_uv_equal_area(p),
+        }
+    }
+
+    fn get_uv_equal_area(p: Point) -> (f32, f32) {
+        //
+        // same u as the lat/long mapping, v is linear in the height, so
+        // v: 0 at Y=-1, 0.5 on the equator, 1 at Y=+1
+        let phi = (-p.z).atan2(p.x) + std::f64::consts::PI as Real;
+
+        (
+            phi / (2f64 * std::f64::consts::PI) as Real,
+            ((p.y + 1 as Real) * 0.5 as Real).clamp(0 as Real, 1 as Real),
+        )
+    }
+
+    fn get_uv_lat_long(p: Point) -> (f32, f32) {
         //
         // p: a given point on the sphere of radius one, centered at the origin.
         // u: returned value [0,1] of angle around the Y axis from X=-1.
@@ -67,27 +110,51 @@ impl Hittable for Sphere {
 
         let t = root;
         let p = r.at(t);
+        //
+        // a negative radius flips the normal to point inwards (hollow sphere / bubble),
+        // texture coordinates still come from the actual surface point
         let outward_normal = (p - self.center) / self.radius;
-        let (u, v) = Self::get_uv(outward_normal);
-
-        Some(HitRecord::new(
-            p,
-            outward_normal,
-            r,
-            t,
-            self.mtl.clone(),
-            u,
-            v,
-        ))
+        let (u, v) = self.get_uv((p - self.center) / self.radius.abs());
+
+        let hit_rec = HitRecord::new(p, outward_normal, r, t, self.mtl.clone(), u, v);
+        //
+        // a hollow sphere is not convex from the side its normals point to, so it can't be skipped
+        if self.radius > 0 as Real {
+            Some(hit_rec.with_primitive(self.id))
+        } else {
+            Some(hit_rec)
+        }
+    }
+
+    fn hit_skipping(
+        &self,
+        r: &Ray,
+        t_min: Real,
+        t_max: Real,
+        skip: Option<PrimitiveId>,
+    ) -> Option<HitRecord> {
+        if skip == Some(self.id) {
+            return None;
+        }
+
+        self.hit(r, t_min, t_max)
+    }
+
+    fn name(&self) -> Option<&str> {
+        Some("sphere")
     }
 
     fn bounding_box(&self, _time0: Real, _time1: Real) -> Option<Aabb> {
         Some(Aabb::new(
-            self.center - Point::broadcast(self.radius),
-            self.center + Point::broadcast(self.radius),
+            self.center - Point::broadcast(self.radius.abs()),
+            self.center + Point::broadcast(self.radius.abs()),
         ))
     }
 
+    fn bounding_sphere(&self) -> Option<(Point, Real)> {
+        Some((self.center, self.radius.abs()))
+    }
+
     fn pdf_value(&self, o: Point, v: crate::types::Vec3) -> Real {
         self.hit(
             &Ray::new(o, v, 0 as Real),
@@ -120,6 +187,7 @@ pub struct MovingSphere {
     pub time0: Real,
     pub time1: Real,
     pub mtl: std::sync::Arc<dyn Material>,
+    id: PrimitiveId,
 }
 
 impl MovingSphere {
@@ -138,6 +206,7 @@ impl MovingSphere {
             mtl,
             time0,
             time1,
+            id: next_primitive_id(),
         }
     }
 
@@ -174,29 +243,38 @@ impl Hittable for MovingSphere {
         let t = root;
         let p = r.at(t);
         let outward_normal = (p - self.center(r.time)) / self.radius;
-        let (u, v) = Sphere::get_uv(outward_normal);
-
-        Some(HitRecord::new(
-            p,
-            outward_normal,
-            r,
-            t,
-            self.mtl.clone(),
-            u,
-            v,
-        ))
+        let (u, v) = Sphere::get_uv_lat_long((p - self.center(r.time)) / self.radius.abs());
+
+        let hit_rec = HitRecord::new(p, outward_normal, r, t, self.mtl.clone(), u, v);
+        if self.radius > 0 as Real {
+            Some(hit_rec.with_primitive(self.id))
+        } else {
+            Some(hit_rec)
+        }
     }
 
-    fn bounding_box(&self, time0: Real, time1: Real) -> Option<crate::aabb3::Aabb> {
-        let box_t0 = Aabb::new(
-            self.center(time0) - Point::broadcast(self.radius),
-            self.center(time0) + Point::broadcast(self.radius),
-        );
+    fn hit_skipping(
+        &self,
+        r: &Ray,
+        t_min: Real,
+        t_max: Real,
+        skip: Option<PrimitiveId>,
+    ) -> Option<HitRecord> {
+        if skip == Some(self.id) {
+            return None;
+        }
 
-        let box_t1 = Aabb::new(
-            self.center(time1) - Point::broadcast(self.radius),
-            self.center(time1) + Point::broadcast(self.radius),
-        );
+        self.hit(r, t_min, t_max)
+    }
+
+    fn name(&self) -> Option<&str> {
+        Some("moving sphere")
+    }
+
+    fn bounding_box(&self, time0: Real, time1: Real) -> Option<crate::aabb3::Aabb> {
+        let radius = Point::broadcast(self.radius.abs());
+        let box_t0 = Aabb::new(self.center(time0) - radius, self.center(time0) + radius);
+        let box_t1 = Aabb::new(self.center(time1) - radius, self.center(time1) + radius);
 
         Some(crate::aabb3::merge_aabbs(&box_t0, &box_t1))
     }
@@ -225,3 +303,205 @@ impl Hittable for MovingSphere {
         uvw.local_from_vec(random_to_sphere(self.radius, distance_squared))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //
+    // Coefficient of variation of the number of (uniformly distributed) sphere points
+    // that land in each cell of a uniform uv grid. Zero means every cell covers the
+    // same surface area.
+    fn uv_cell_area_variation(mapping: SphereUvMapping) -> Real {
+        const POINTS: usize = 20000;
+        const CELLS: usize = 8;
+
+        let golden_angle = std::f64::consts::PI as Real * (3 as Real - (5 as Real).sqrt());
+        let mut counts = [[0u32; CELLS]; CELLS];
+
+        (0..POINTS).for_each(|i| {
+            //
+            // Fibonacci sphere, evenly spread points
+            let y = 1 as Real - (2 * i + 1) as Real / POINTS as Real;
+            let r = (1 as Real - y * y).sqrt();
+            let (sin_phi, cos_phi) = (golden_angle * i as Real).sin_cos();
+            let p = Point::new(r * cos_phi, y, r * sin_phi);
+
+            let (u, v) = match mapping {
+                SphereUvMapping::LatLong => Sphere::get_uv_lat_long(p),
+                SphereUvMapping::EqualArea => Sphere::get_uv_equal_area(p),
+            };
+
+            let cell = |x: Real| ((x * CELLS as Real) as usize).min(CELLS - 1);
+            counts[cell(v)][cell(u)] += 1;
+        });
+
+        let mean = POINTS as Real / (CELLS * CELLS) as Real;
+        let variance = counts
+            .iter()
+            .flatten()
+            .map(|&c| (c as Real - mean) * (c as Real - mean))
+            .sum::<Real>()
+            / (CELLS * CELLS) as Real;
+
+        variance.sqrt() / mean
+    }
+
+    #[test]
+    fn test_equal_area_mapping_is_more_uniform() {
+        let lat_long = uv_cell_area_variation(SphereUvMapping::LatLong);
+        let equal_area = uv_cell_area_variation(SphereUvMapping::EqualArea);
+
+        assert!(equal_area < 0.05);
+        assert!(equal_area * 4.0 < lat_long);
+    }
+
+    #[test]
+    fn test_mappings_agree_on_equator_and_poles() {
+        [
+            Point::new(1 as Real, 0 as Real, 0 as Real),
+            Point::new(0 as Real, 1 as Real, 0 as Real),
+            Point::new(0 as Real, -1 as Real, 0 as Real),
+            Point::new(0 as Real, 0 as Real, -1 as Real),
+        ]
+        .iter()
+        .for_each(|&p| {
+            let (u0, v0) = Sphere::get_uv_lat_long(p);
+            let (u1, v1) = Sphere::get_uv_equal_area(p);
+            assert!((u0 - u1).abs() < 1.0E-5);
+            assert!((v0 - v1).abs() < 1.0E-5);
+        });
+    }
+
+    //
+    // air bubble of radius 0.5 inside glass, rays travel along +x at height h
+    fn bubble_hit(radius: Real, h: Real) -> (Ray, HitRecord) {
+        let bubble = Sphere::new(
+            Point::new(0 as Real, 0 as Real, 0 as Real),
+            radius,
+            std::sync::Arc::new(crate::dielectric::Dielectric::new(1.5 as Real)),
+        );
+        let r = Ray::new(
+            Point::new(-2 as Real, h, 0 as Real),
+            crate::types::Vec3::new(1 as Real, 0 as Real, 0 as Real),
+            0 as Real,
+        );
+        let hit = bubble.hit(&r, 0.001 as Real, Real::MAX).unwrap();
+        (r, hit)
+    }
+
+    #[test]
+    fn test_negative_radius_flips_normal() {
+        let (r, outer) = bubble_hit(0.5 as Real, 0 as Real);
+        let (_, bubble) = bubble_hit(-0.5 as Real, 0 as Real);
+
+        assert!(outer.front_face);
+        assert!(!bubble.front_face);
+        //
+        // same surface point and uv, shading normal still faces the incoming ray
+        assert_eq!(outer.t, bubble.t);
+        assert_eq!((outer.u, outer.v), (bubble.u, bubble.v));
+        assert!(vec3::dot(bubble.normal, r.direction) < 0 as Real);
+
+        let bbox = Sphere::new(
+            Point::new(0 as Real, 0 as Real, 0 as Real),
+            -0.5 as Real,
+            outer.mtl.clone(),
+        )
+        .bounding_box(0 as Real, 1 as Real)
+        .unwrap();
+        assert_eq!(bbox.min.x, -0.5 as Real);
+        assert_eq!(bbox.max.x, 0.5 as Real);
+    }
+
+    #[test]
+    fn test_bubble_refracts_like_glass_to_air() {
+        use crate::material::ScatterRecord;
+
+        //
+        // fraction of scattered rays that continue into the sphere
+        let transmitted = |radius: Real, h: Real| {
+            let (r, hit) = bubble_hit(radius, h);
+            (0..1000)
+                .filter(|_| match hit.mtl.scatter(&r, &hit) {
+                    Some(ScatterRecord::SpecularRec { ray, .. }) => {
+                        vec3::dot(ray.direction, hit.normal) < 0 as Real
+                    }
+                    _ => false,
+                })
+                .count()
+        };
+
+        //
+        // incidence angle with sin = 0.8: air -> glass refracts most of the light,
+        // glass -> air (the bubble) is past the critical angle and reflects everything
+        assert!(transmitted(0.5 as Real, 0.4 as Real) > 500);
+        assert_eq!(transmitted(-0.5 as Real, 0.4 as Real), 0);
+
+        //
+        // below the critical angle the bubble transmits, bending away from the normal
+        let (r, hit) = bubble_hit(-0.5 as Real, 0.15 as Real);
+        let refracted = (0..1000)
+            .find_map(|_| match hit.mtl.scatter(&r, &hit) {
+                Some(ScatterRecord::SpecularRec { ray, .. })
+                    if vec3::dot(ray.direction, hit.normal) < 0 as Real =>
+                {
+                    Some(ray.direction)
+                }
+                _ => None,
+            })
+            .unwrap();
+        let sin_t = vec3::length(vec3::cross(vec3::normalize(refracted), hit.normal));
+        assert!((sin_t - 0.45 as Real).abs() < 1.0e-4);
+    }
+
+    #[test]
+    fn test_secondary_ray_skips_its_own_sphere() {
+        use crate::hittable_list::HittableList;
+        use crate::types::Vec3;
+        use std::sync::Arc;
+
+        let mtl = Arc::new(crate::lambertian::Lambertian::new((0.5, 0.5, 0.5)));
+        let ground = Arc::new(Sphere::new(
+            Point::new(0 as Real, -1000 as Real, 0 as Real),
+            1000 as Real,
+            mtl.clone(),
+        ));
+        let up = Vec3::new(0 as Real, 1 as Real, 0 as Real);
+
+        let primary = Ray::new(Point::new(0.3, 5 as Real, 0.2), -up, 0 as Real);
+        let rec = ground.hit(&primary, 0.001 as Real, Real::MAX).unwrap();
+        assert_eq!(rec.primitive, Some(ground.id));
+
+        //
+        // the hit point ended up slightly below the surface, the bounced ray
+        // re-hits the sphere at t ~ 0, even past the usual epsilon
+        let bounce = Ray::new(rec.p - up * 0.002 as Real, up, 0 as Real);
+        let self_hit = ground.hit(&bounce, 0.001 as Real, Real::MAX).unwrap();
+        assert!(self_hit.t < 0.01 as Real);
+
+        let skip = rec.skip_for(bounce.direction);
+        assert_eq!(skip, Some(ground.id));
+        assert!(ground
+            .hit_skipping(&bounce, 0.001 as Real, Real::MAX, skip)
+            .is_none());
+
+        //
+        // other objects are still hit, also through a container
+        let mut world = HittableList::new();
+        world.add(ground.clone());
+        world.add(Arc::new(Sphere::new(
+            Point::new(0.3, 3 as Real, 0.2),
+            1 as Real,
+            mtl,
+        )));
+        let next = world
+            .hit_skipping(&bounce, 0.001 as Real, Real::MAX, skip)
+            .unwrap();
+        assert!((next.t - 2.002 as Real).abs() < 1.0e-3);
+
+        //
+        // a ray going into the sphere (refraction) must still find its far side
+        assert_eq!(rec.skip_for(-up), None);
+    }
+}
@@ -1,4 +1,5 @@
-use crate::hittable::{HitRecord, Hittable};
+use crate::aabb3::Aabb;
+use crate::hittable::{next_primitive_id, HitRecord, Hittable, PrimitiveId};
 use crate::material::Material;
 use crate::types::{Point, Ray, Real, Vec3};
 
@@ -9,6 +10,7 @@ pub struct Triangle {
     pub v2: Point,
     pub normal: Vec3,
     pub mtl: std::sync::Arc<dyn Material>,
+    id: PrimitiveId,
 }
 
 impl Triangle {
@@ -19,6 +21,7 @@ impl Triangle {
             v2,
             normal: math::vec3::normalize(math::vec3::cross(v1 - v0, v2 - v0)),
             mtl,
+            id: next_primitive_id(),
         }
     }
 }
@@ -87,12 +90,38 @@ impl Hittable for Triangle {
 
         //
         // Point lies inside the triangle
-        Some(HitRecord::new(
-            p,
-            self.normal,
-            ray,
-            t,
-            std::sync::Arc::clone(&self.mtl),
-        ))
+        Some(
+            HitRecord::new(
+                p,
+                self.normal,
+                ray,
+                t,
+                std::sync::Arc::clone(&self.mtl),
+                0 as Real,
+                0 as Real,
+            )
+            .with_primitive(self.id),
+        )
+    }
+
+    fn hit_skipping(
+        &self,
+        r: &Ray,
+        t_min: Real,
+        t_max: Real,
+        skip: Option<PrimitiveId>,
+    ) -> Option<HitRecord> {
+        if skip == Some(self.id) {
+            return None;
+        }
+
+        self.hit(r, t_min, t_max)
+    }
+
+    fn bounding_box(&self, _time0: Real, _time1: Real) -> Option<Aabb> {
+        let mut bbox = Aabb::new(self.v0, self.v0);
+        bbox.add_point(self.v1);
+        bbox.add_point(self.v2);
+        Some(bbox.padded(0.0001 as Real))
     }
 }
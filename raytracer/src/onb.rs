@@ -1,4 +1,4 @@
-use math::vec3::{cross, normalize};
+use math::vec3::{normalize, orthonormal_basis};
 
 use crate::types::{Real, Vec3};
 
@@ -32,14 +32,7 @@ impl Onb {
 impl std::convert::From<Vec3> for Onb {
     fn from(n: Vec3) -> Self {
         let axis_2 = normalize(n);
-        let a = if axis_2.x.abs() > 0.9 {
-            math::vec3::consts::unit_y()
-        } else {
-            math::vec3::consts::unit_x()
-        };
-
-        let axis_1 = normalize(cross(axis_2, a));
-        let axis_0 = cross(axis_2, axis_1);
+        let (axis_0, axis_1) = orthonormal_basis(axis_2);
 
         Self {
             axis: [axis_0, axis_1, axis_2],
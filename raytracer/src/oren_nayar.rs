@@ -0,0 +1,151 @@
+use std::sync::Arc;
+
+use math::vec3::{dot, normalize};
+
+use crate::hittable::HitRecord;
+use crate::material::{Material, ScatterRecord};
+use crate::pdf::CosinePdf;
+use crate::solid_color_texture::SolidColorTexture;
+use crate::texture::Texture;
+use crate::types::{Color, Ray, Real, Vec3, C_PI};
+
+/// Rough diffuse surface (concrete, fabric, clay), Oren-Nayar model. Unlike `Lambertian`
+/// it gets brighter when lit and seen at grazing angles from the same side (retroreflection).
+/// `sigma` is the roughness, the standard deviation of the microfacet angles in radians,
+/// at 0 the material is `Lambertian`.
+#[derive(Clone)]
+pub struct OrenNayar {
+    pub albedo: Arc<dyn Texture>,
+    pub sigma: Real,
+}
+
+impl OrenNayar {
+    pub fn new<T>(albedo: T, sigma: Real) -> OrenNayar
+    where
+        T: Into<Color>,
+    {
+        OrenNayar {
+            albedo: Arc::new(SolidColorTexture::new(albedo)),
+            sigma,
+        }
+    }
+
+    pub fn from_texture(albedo: Arc<dyn Texture>, sigma: Real) -> OrenNayar {
+        OrenNayar { albedo, sigma }
+    }
+
+    //
+    // reflectance relative to a lambertian surface, for unit vectors on the side of the normal
+    fn lambertian_scale(&self, n: Vec3, to_viewer: Vec3, to_light: Vec3) -> Real {
+        let sigma2 = self.sigma * self.sigma;
+        let a = 1 as Real - 0.5 as Real * sigma2 / (sigma2 + 0.33 as Real);
+        let b = 0.45 as Real * sigma2 / (sigma2 + 0.09 as Real);
+
+        let cos_view = dot(n, to_viewer);
+        let cos_light = dot(n, to_light);
+
+        //
+        // cos(phi_view - phi_light) * sin(alpha) * tan(beta), with alpha the larger and beta
+        // the smaller of the two polar angles, is the dot product of the directions projected
+        // on the tangent plane over the larger cosine
+        let tangent_dot = dot(to_viewer - n * cos_view, to_light - n * cos_light);
+        let max_cos = cos_view.max(cos_light);
+        if max_cos <= 0 as Real {
+            return a;
+        }
+
+        a + b * tangent_dot.max(0 as Real) / max_cos
+    }
+}
+
+impl Material for OrenNayar {
+    fn scatter(&self, _ray: &Ray, hit_record: &HitRecord) -> Option<ScatterRecord> {
+        let albedo = self.albedo.value(hit_record.u, hit_record.v, hit_record.p);
+
+        //
+        // still sampled like a lambertian surface, the difference is in scattering_pdf
+        Some(ScatterRecord::PdfRec {
+            pdf: Arc::new(CosinePdf {
+                uvw: hit_record.normal.into(),
+            }),
+            attenuation: albedo,
+        })
+    }
+
+    fn scattering_pdf(&self, ray: &Ray, hit_record: &HitRecord, scattered: &Ray) -> Real {
+        let to_light = normalize(scattered.direction);
+        let cosine = dot(hit_record.normal, to_light);
+        if cosine < 0 as Real {
+            return 0 as Real;
+        }
+
+        cosine / C_PI
+            * self.lambertian_scale(hit_record.normal, -normalize(ray.direction), to_light)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lambertian::Lambertian;
+    use crate::types::{random_cosine_direction, Point, C_ZERO};
+
+    //
+    // hit on the z = 0 plane, normal +z, seen along `to_viewer` and lit from `to_light`
+    fn reflected(mtl: Arc<dyn Material>, to_viewer: Vec3, to_light: Vec3) -> Real {
+        let p = Point::broadcast(C_ZERO);
+        let r = Ray::new(p + to_viewer, -to_viewer, C_ZERO);
+        let hit = HitRecord::new(
+            p,
+            Vec3::new(C_ZERO, C_ZERO, 1 as Real),
+            &r,
+            1 as Real,
+            mtl.clone(),
+            C_ZERO,
+            C_ZERO,
+        );
+
+        mtl.scattering_pdf(&r, &hit, &Ray::new(p, to_light, C_ZERO))
+    }
+
+    fn at_angle(degrees: Real) -> Vec3 {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        Vec3::new(sin, C_ZERO, cos)
+    }
+
+    #[test]
+    fn test_grazing_retroreflection_and_sigma_zero() {
+        let lambertian: Arc<dyn Material> = Arc::new(Lambertian::new((0.5, 0.5, 0.5)));
+        let rough: Arc<dyn Material> = Arc::new(OrenNayar::new((0.5, 0.5, 0.5), 0.5 as Real));
+        let smooth: Arc<dyn Material> = Arc::new(OrenNayar::new((0.5, 0.5, 0.5), C_ZERO));
+
+        //
+        // viewer and light on the same side, close to the horizon
+        let grazing = at_angle(80 as Real);
+        assert!(
+            reflected(rough.clone(), grazing, grazing)
+                > 1.5 as Real * reflected(lambertian.clone(), grazing, grazing)
+        );
+        //
+        // head on, rough surfaces are darker
+        let normal = at_angle(C_ZERO);
+        assert!(
+            reflected(rough.clone(), normal, normal)
+                < reflected(lambertian.clone(), normal, normal)
+        );
+        //
+        // light from the opposite side gets no boost
+        let opposite = Vec3::new(-grazing.x, grazing.y, grazing.z);
+        assert!(
+            reflected(rough.clone(), grazing, opposite)
+                < reflected(lambertian.clone(), grazing, opposite)
+        );
+
+        (0..100).for_each(|_| {
+            let to_viewer = random_cosine_direction();
+            let to_light = random_cosine_direction();
+            let expected = reflected(lambertian.clone(), to_viewer, to_light);
+            assert!((reflected(smooth.clone(), to_viewer, to_light) - expected).abs() < 1.0e-6);
+        });
+    }
+}
@@ -6,7 +6,7 @@ use crate::{
     types::{random_real, Point, Real, Vec3},
 };
 
-pub trait Pdf {
+pub trait Pdf: Send + Sync {
     fn value(&self, direction: Vec3) -> Real;
     fn generate(&self) -> Vec3;
 }
@@ -46,6 +46,10 @@ pub struct HittablePdf {
 
 impl Pdf for HittablePdf {
     fn value(&self, direction: Vec3) -> Real {
+        if self.obj.faces(self.origin) == Some(false) {
+            return 0 as Real;
+        }
+
         self.obj.pdf_value(self.origin, direction)
     }
 
@@ -0,0 +1,261 @@
+use std::sync::Arc;
+
+use math::vec3::{dot, normalize};
+
+use crate::{
+    hittable::HitRecord,
+    material::{Material, ScatterRecord},
+    microfacet::Microfacet,
+    pdf::{CosinePdf, Pdf},
+    types::{random_real, schlick, Color, Ray, Real, Vec3, C_ONE, C_PI, C_ZERO},
+};
+
+/// Refraction index of the clear coat, the Fresnel reflectance at normal incidence is 4%.
+const COAT_REFRACTION_INDEX: Real = 1.5;
+
+/// Diffuse base under a glossy clear coat (plastic, varnished wood, ceramic). The light is
+/// either reflected by the coat's GGX microfacets (like a rough `Metal`, tinted by
+/// `specular_tint`) or goes through the coat and bounces off the base (like `Lambertian`),
+/// with the Fresnel reflectance of the coat. Seen head on it is mostly diffuse, towards
+/// grazing angles the reflection off the coat takes over.
+///
+/// The bounces are importance sampled with a mixture of the two lobes, the bsdf and its
+/// density cover both of them, so the light sampling is weighted against the whole
+/// material.
+#[derive(Copy, Clone, Debug)]
+pub struct PlasticMaterial {
+    pub diffuse_albedo: Color,
+    pub specular_tint: Color,
+    /// Alpha of the coat's GGX distribution.
+    pub roughness: Real,
+}
+
+impl PlasticMaterial {
+    pub fn new<T: Into<Color>>(diffuse_albedo: T, specular_tint: T, roughness: Real) -> Self {
+        PlasticMaterial {
+            diffuse_albedo: diffuse_albedo.into(),
+            specular_tint: specular_tint.into(),
+            roughness,
+        }
+    }
+
+    fn microfacet(&self) -> Microfacet {
+        Microfacet::ggx(self.roughness)
+    }
+
+    /// Probability of a glossy bounce for a viewer at cosine `cos_o` to the normal.
+    pub fn glossy_probability(&self, cos_o: Real) -> Real {
+        schlick(cos_o.clamp(C_ZERO, C_ONE), COAT_REFRACTION_INDEX)
+    }
+
+    /// Bsdf times the cosine of the scattered unit direction `wi`, for the viewer at `wo`,
+    /// both lobes together. `n` is the unit normal on the viewer's side.
+    pub fn bsdf_cos(&self, wo: Vec3, wi: Vec3, n: Vec3) -> Color {
+        let (cos_o, cos_i) = (dot(wo, n), dot(wi, n));
+        if cos_o <= C_ZERO || cos_i <= C_ZERO {
+            return Color::broadcast(C_ZERO);
+        }
+
+        //
+        // F D G / (4 cos_o cos_i) * cos_i off the coat, what the coat lets through
+        // reaches the base
+        let microfacet = self.microfacet();
+        let h = normalize(wo + wi);
+        let glossy = self.glossy_probability(dot(wo, h))
+            * microfacet.d(dot(n, h))
+            * microfacet.g(cos_o, cos_i)
+            / (4 as Real * cos_o);
+        let diffuse = (C_ONE - self.glossy_probability(cos_o)) * cos_i / C_PI;
+
+        self.specular_tint * glossy + self.diffuse_albedo * diffuse
+    }
+
+    /// Density, per solid angle, of the scattered unit direction `wi` for the viewer at
+    /// `wo`, both lobes together. `n` is the unit normal on the viewer's side. The glossy
+    /// reflections that end up below the surface are absorbed but part of the density.
+    pub fn pdf(&self, wo: Vec3, wi: Vec3, n: Vec3) -> Real {
+        let glossy_probability = self.glossy_probability(dot(wo, n));
+
+        let glossy = self.microfacet().pdf_reflection(n, wo, wi);
+        let diffuse = dot(wi, n).max(C_ZERO) / C_PI;
+
+        glossy_probability * glossy + (C_ONE - glossy_probability) * diffuse
+    }
+}
+
+/// Mixture of the glossy and the diffuse lobe of a `PlasticMaterial`, for one viewer.
+pub struct PlasticPdf {
+    material: PlasticMaterial,
+    wo: Vec3,
+    n: Vec3,
+    diffuse: CosinePdf,
+}
+
+impl Pdf for PlasticPdf {
+    fn value(&self, direction: Vec3) -> Real {
+        self.material.pdf(self.wo, normalize(direction), self.n)
+    }
+
+    fn generate(&self) -> Vec3 {
+        if random_real() < self.material.glossy_probability(dot(self.wo, self.n)) {
+            //
+            // the coat's reflection, its Fresnel term is in `bsdf_cos`
+            let (_, wi) = self
+                .material
+                .microfacet()
+                .sample_reflection(self.n, self.wo);
+            wi
+        } else {
+            self.diffuse.generate()
+        }
+    }
+}
+
+impl Material for PlasticMaterial {
+    fn scatter(&self, ray: &Ray, hit_record: &HitRecord) -> Option<ScatterRecord> {
+        Some(ScatterRecord::PdfRec {
+            pdf: Arc::new(PlasticPdf {
+                material: *self,
+                wo: -normalize(ray.direction),
+                n: normalize(hit_record.normal),
+                diffuse: CosinePdf {
+                    uvw: hit_record.normal.into(),
+                },
+            }),
+            //
+            // the lobes are colored by `scattering`
+            attenuation: Color::broadcast(C_ONE),
+        })
+    }
+
+    fn scattering_pdf(&self, ray: &Ray, hit_record: &HitRecord, scattered: &Ray) -> Real {
+        self.pdf(
+            -normalize(ray.direction),
+            normalize(scattered.direction),
+            normalize(hit_record.normal),
+        )
+    }
+
+    fn scattering(
+        &self,
+        ray: &Ray,
+        hit_record: &HitRecord,
+        scattered: &Ray,
+        _attenuation: Color,
+    ) -> Color {
+        self.bsdf_cos(
+            -normalize(ray.direction),
+            normalize(scattered.direction),
+            normalize(hit_record.normal),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{random_unit_vector, Point, C_TWO_PI};
+
+    #[test]
+    fn test_fresnel_favors_the_coat_at_grazing_angles_and_the_mixture_is_unbiased() {
+        const N: usize = 50_000;
+        //
+        // the base only reflects red, the coat only green
+        let plastic = Arc::new(PlasticMaterial::new(
+            (C_ONE, C_ZERO, C_ZERO),
+            (C_ZERO, C_ONE, C_ZERO),
+            0.2,
+        ));
+        let n = Vec3::new(C_ZERO, C_ONE, C_ZERO);
+        let viewer = |cos_o: Real| Vec3::new((C_ONE - cos_o * cos_o).sqrt(), cos_o, C_ZERO);
+
+        //
+        // light reflected towards a viewer at cosine cos_o, sampled like the integrator
+        // does it
+        let reflected = |cos_o: Real| {
+            let wo = viewer(cos_o);
+            let ray = Ray::new(wo, -wo, C_ZERO);
+            let rec = HitRecord::new(
+                Point::new(C_ZERO, C_ZERO, C_ZERO),
+                n,
+                &ray,
+                C_ONE,
+                plastic.clone(),
+                C_ZERO,
+                C_ZERO,
+            );
+            let (pdf, attenuation) = match plastic.scatter(&ray, &rec) {
+                Some(ScatterRecord::PdfRec { pdf, attenuation }) => (pdf, attenuation),
+                _ => panic!("plastic is sampled through its pdf"),
+            };
+
+            (0..N)
+                .map(|_| {
+                    let scattered = Ray::new(rec.p, pdf.generate(), C_ZERO);
+                    let pdf_val = pdf.value(scattered.direction);
+                    assert!(
+                        (pdf_val - plastic.scattering_pdf(&ray, &rec, &scattered)).abs() < 1.0e-4
+                    );
+                    if pdf_val > C_ZERO {
+                        plastic.scattering(&ray, &rec, &scattered, attenuation) / pdf_val
+                    } else {
+                        Color::broadcast(C_ZERO)
+                    }
+                })
+                .fold(Color::broadcast(C_ZERO), |sum, c| sum + c)
+                / N as Real
+        };
+
+        //
+        // the bsdf * cos over the hemisphere (d omega = d cos d phi)
+        let expected = |cos_o: Real| {
+            const STEPS: usize = 400;
+            let wo = viewer(cos_o);
+            (0..STEPS * STEPS)
+                .map(|i| {
+                    let cos_i = ((i % STEPS) as Real + 0.5 as Real) / STEPS as Real;
+                    let phi = ((i / STEPS) as Real + 0.5 as Real) / STEPS as Real * C_TWO_PI;
+                    let sin_i = (C_ONE - cos_i * cos_i).sqrt();
+                    let wi = Vec3::new(sin_i * phi.cos(), cos_i, sin_i * phi.sin());
+                    plastic.bsdf_cos(wo, wi, n)
+                })
+                .fold(Color::broadcast(C_ZERO), |sum, c| sum + c)
+                * (C_TWO_PI / (STEPS * STEPS) as Real)
+        };
+
+        let glossy_fraction = |c: Color| c.g / (c.r + c.g);
+        let head_on = reflected(C_ONE);
+        let grazing = reflected(0.05 as Real);
+        assert!(glossy_fraction(head_on) < 0.1 as Real, "{:?}", head_on);
+        assert!(glossy_fraction(grazing) > 0.45 as Real, "{:?}", grazing);
+
+        [C_ONE, 0.5 as Real, 0.05 as Real]
+            .iter()
+            .for_each(|&cos_o| {
+                let (sampled, expected) = (reflected(cos_o), expected(cos_o));
+                assert!(
+                    (sampled.r - expected.r).abs() < 0.02 as Real
+                        && (sampled.g - expected.g).abs() < 0.02 as Real,
+                    "{} {:?} {:?}",
+                    cos_o,
+                    sampled,
+                    expected
+                );
+            });
+
+        //
+        // both lobes together, over the whole sphere of directions
+        [C_ONE, 0.5 as Real, 0.05 as Real]
+            .iter()
+            .for_each(|&cos_o| {
+                let wo = viewer(cos_o);
+                let integral = (0..N * 4)
+                    .map(|_| plastic.pdf(wo, random_unit_vector(), n))
+                    .sum::<Real>()
+                    * 4 as Real
+                    * C_PI
+                    / (N * 4) as Real;
+                assert!((integral - C_ONE).abs() < 0.05, "{} {}", cos_o, integral);
+            });
+    }
+}
@@ -0,0 +1,106 @@
+use std::sync::Arc;
+
+use crate::aabb3::Aabb;
+use crate::diffuse_light::DiffuseLight;
+use crate::hittable::{HitRecord, Hittable, PrimitiveId};
+use crate::objects::sphere::Sphere;
+use crate::types::{Color, Point, Ray, Real, Vec3, C_PI};
+
+/// Small spherical light, specified by its emitted power instead of its radiance, so the
+/// irradiance it delivers falls off with the inverse square of the distance
+/// (E = power / (4 * pi * d^2)). Add it to both the world and the lights list.
+pub struct PointLight {
+    sphere: Sphere,
+}
+
+impl PointLight {
+    pub fn new<T: Into<Color>>(center: Point, radius: Real, power: T) -> PointLight {
+        //
+        // a lambertian emitter with radiance L emits pi * L per unit area,
+        // spread over the sphere's area of 4 * pi * r^2
+        let power: Color = power.into();
+        let radiance = power * (4 as Real * C_PI * C_PI * radius * radius).recip();
+
+        PointLight {
+            sphere: Sphere::new(center, radius, Arc::new(DiffuseLight::from(radiance))),
+        }
+    }
+}
+
+impl Hittable for PointLight {
+    fn hit(&self, r: &Ray, t_min: Real, t_max: Real) -> Option<HitRecord> {
+        self.sphere.hit(r, t_min, t_max)
+    }
+
+    fn hit_skipping(
+        &self,
+        r: &Ray,
+        t_min: Real,
+        t_max: Real,
+        skip: Option<PrimitiveId>,
+    ) -> Option<HitRecord> {
+        self.sphere.hit_skipping(r, t_min, t_max, skip)
+    }
+
+    fn bounding_box(&self, time0: Real, time1: Real) -> Option<Aabb> {
+        self.sphere.bounding_box(time0, time1)
+    }
+
+    fn bounding_sphere(&self) -> Option<(Point, Real)> {
+        self.sphere.bounding_sphere()
+    }
+
+    fn pdf_value(&self, o: Point, v: Vec3) -> Real {
+        self.sphere.pdf_value(o, v)
+    }
+
+    fn random(&self, o: Vec3) -> Vec3 {
+        self.sphere.random(o)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use math::vec3::{dot, normalize};
+
+    //
+    // Monte Carlo estimate of the irradiance at p (surface normal n), sampling the light
+    fn irradiance(light: &PointLight, p: Point, n: Vec3) -> Real {
+        const SAMPLES: i32 = 4096;
+
+        (0..SAMPLES)
+            .filter_map(|_| {
+                let dir = light.random(p);
+                let ray = Ray::new(p, dir, 0 as Real);
+                light.hit(&ray, 0.001 as Real, Real::MAX).map(|hit| {
+                    let radiance = hit.mtl.emitted(&ray, &hit, hit.u, hit.v, hit.p);
+                    let cosine = dot(normalize(dir), n).max(0 as Real);
+                    radiance.g * cosine / light.pdf_value(p, dir)
+                })
+            })
+            .sum::<Real>()
+            / SAMPLES as Real
+    }
+
+    #[test]
+    fn test_irradiance_falls_off_with_inverse_square() {
+        let p = Point::new(0 as Real, 0 as Real, 0 as Real);
+        let n = Vec3::new(0 as Real, 1 as Real, 0 as Real);
+        let power = 100 as Real;
+        let light = |height: Real| {
+            PointLight::new(
+                Point::new(0 as Real, height, 0 as Real),
+                0.05 as Real,
+                (power, power, power),
+            )
+        };
+
+        let near = irradiance(&light(2 as Real), p, n);
+        let far = irradiance(&light(4 as Real), p, n);
+
+        let expected_near = power / (4 as Real * C_PI * 4 as Real);
+        assert!((near - expected_near).abs() < 0.01 as Real * expected_near);
+        assert!((far / near - 0.25 as Real).abs() < 0.01 as Real);
+    }
+}
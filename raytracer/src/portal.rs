@@ -0,0 +1,217 @@
+use std::sync::Arc;
+
+use crate::{
+    aabb3::Aabb,
+    hittable::{HitRecord, HitStats, Hittable},
+    material::{Material, ScatterRecord},
+    types::{Color, Mat4, Point, Ray, Real},
+};
+
+/// Teleports rays: a ray hitting `rect` continues from the hit point moved by
+/// `transform_to_exit` (usually to the paired portal), in the same scene. `rect` only gives
+/// the shape of the portal, its material is replaced by the crossing.
+///
+/// The crossing is a scatter event of the portal's surface, so a ray that misses
+/// everything on the exit side ends in the background, and every crossing is a bounce of
+/// the path: rays looping between facing portals end when the path reaches its maximum
+/// depth.
+pub struct Portal {
+    rect: Arc<dyn Hittable>,
+    crossing: Arc<PortalCrossing>,
+}
+
+impl Portal {
+    pub fn new(rect: Arc<dyn Hittable>, transform_to_exit: Mat4) -> Portal {
+        Portal {
+            rect,
+            crossing: Arc::new(PortalCrossing { transform_to_exit }),
+        }
+    }
+}
+
+impl Portal {
+    fn crossing_hit(&self, entry: HitRecord) -> HitRecord {
+        HitRecord {
+            mtl: self.crossing.clone(),
+            primitive: None,
+            ..entry
+        }
+    }
+}
+
+impl Hittable for Portal {
+    fn hit(&self, r: &Ray, t_min: Real, t_max: Real) -> Option<HitRecord> {
+        self.rect
+            .hit(r, t_min, t_max)
+            .map(|entry| self.crossing_hit(entry))
+    }
+
+    fn hit_counted(
+        &self,
+        r: &Ray,
+        t_min: Real,
+        t_max: Real,
+        stats: &mut HitStats,
+    ) -> Option<HitRecord> {
+        self.rect
+            .hit_counted(r, t_min, t_max, stats)
+            .map(|entry| self.crossing_hit(entry))
+    }
+
+    fn bounding_box(&self, time0: Real, time1: Real) -> Option<Aabb> {
+        self.rect.bounding_box(time0, time1)
+    }
+
+    fn bounding_sphere(&self) -> Option<(Point, Real)> {
+        self.rect.bounding_sphere()
+    }
+}
+
+/// Surface of a portal, scatters the rays to the exit unchanged.
+pub struct PortalCrossing {
+    transform_to_exit: Mat4,
+}
+
+impl Material for PortalCrossing {
+    fn scatter(&self, ray: &Ray, hit_record: &HitRecord) -> Option<ScatterRecord> {
+        //
+        // continue from the hit point, on the exit side
+        Some(ScatterRecord::SpecularRec {
+            ray: math::ray::transform(
+                &self.transform_to_exit,
+                &Ray {
+                    origin: hit_record.p,
+                    ..*ray
+                },
+            ),
+            attenuation: Color::broadcast(1 as Real),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::{Camera, TraceOptions};
+    use crate::diffuse_light::DiffuseLight;
+    use crate::hittable_list::HittableList;
+    use crate::lambertian::Lambertian;
+    use crate::material::Material;
+    use crate::objects::sphere::Sphere;
+    use crate::rectangles::XYRect;
+    use crate::types::{Vec3, C_ZERO};
+    use math::vec3::length;
+
+    fn portal_rect(mtl: Arc<dyn Material>) -> Arc<dyn Hittable> {
+        Arc::new(XYRect {
+            x0: -1 as Real,
+            x1: 1 as Real,
+            y0: -1 as Real,
+            y1: 1 as Real,
+            k: C_ZERO,
+            mtl,
+        })
+    }
+
+    fn radiance(world: &HittableList, r: &Ray, background: Color) -> Color {
+        let opts = TraceOptions {
+            background,
+            ambient: Color::broadcast(C_ZERO),
+            after_diffuse: false,
+            walk_steps: 0,
+            max_depth: 16,
+            per_channel: false,
+            first_hit_light_samples: 0,
+            stats: None,
+        };
+        Camera::ray_color(
+            r,
+            &opts,
+            world,
+            &Arc::new(HittableList::new()),
+            opts.max_depth,
+            None,
+            None,
+        )
+    }
+
+    #[test]
+    fn test_portal_teleports_and_budget_terminates() {
+        let mtl: Arc<dyn Material> = Arc::new(Lambertian::new((0.5, 0.5, 0.5)));
+        let r = Ray::new(
+            Point::new(C_ZERO, C_ZERO, 5 as Real),
+            Vec3::new(C_ZERO, C_ZERO, -1 as Real),
+            C_ZERO,
+        );
+
+        //
+        // the exit portal is at x = 10, with a sphere behind it
+        let mut world = HittableList::new();
+        world.add(Arc::new(Portal::new(
+            portal_rect(mtl.clone()),
+            Mat4::translate(Vec3::new(10 as Real, C_ZERO, C_ZERO)),
+        )));
+        world.add(Arc::new(Sphere::new(
+            Point::new(10 as Real, C_ZERO, -5 as Real),
+            1 as Real,
+            mtl.clone(),
+        )));
+
+        let entry = world.hit(&r, 0.001 as Real, Real::MAX).unwrap();
+        assert!((entry.t - 5 as Real).abs() < 1.0e-4);
+        let exit_ray = match entry.mtl.scatter(&r, &entry) {
+            Some(ScatterRecord::SpecularRec { ray, attenuation }) => {
+                assert_eq!(attenuation.r, 1 as Real);
+                ray
+            }
+            _ => panic!("the portal did not scatter the ray"),
+        };
+        assert!(length(exit_ray.origin - Point::new(10 as Real, C_ZERO, C_ZERO)) < 1.0e-4);
+        let hit = world.hit(&exit_ray, 0.001 as Real, Real::MAX).unwrap();
+        assert!(length(hit.p - Point::new(10 as Real, C_ZERO, -4 as Real)) < 1.0e-4);
+        assert!(hit.front_face);
+
+        //
+        // exit right behind the entry, facing it: the ray would loop forever, the path
+        // ends at its maximum depth
+        let mut looping = HittableList::new();
+        looping.add(Arc::new(Portal::new(
+            portal_rect(mtl.clone()),
+            Mat4::translate(Vec3::new(C_ZERO, C_ZERO, 1 as Real)),
+        )));
+        let color = radiance(&looping, &r, Color::broadcast(1 as Real));
+        assert_eq!((color.r, color.g, color.b), (C_ZERO, C_ZERO, C_ZERO));
+    }
+
+    #[test]
+    fn test_rays_missing_everything_past_the_exit_see_the_background() {
+        //
+        // a light right behind the entry portal, the exit at x = 10 opens to empty space
+        let mtl: Arc<dyn Material> = Arc::new(Lambertian::new((0.5, 0.5, 0.5)));
+        let mut world = HittableList::new();
+        world.add(Arc::new(Portal::new(
+            portal_rect(mtl),
+            Mat4::translate(Vec3::new(10 as Real, C_ZERO, C_ZERO)),
+        )));
+        world.add(Arc::new(XYRect {
+            x0: -2 as Real,
+            x1: 2 as Real,
+            y0: -2 as Real,
+            y1: 2 as Real,
+            k: -1 as Real,
+            mtl: Arc::new(DiffuseLight::from((4 as Real, 0 as Real, 0 as Real))),
+        }));
+
+        let background = Color::new(C_ZERO, C_ZERO, 0.5 as Real);
+        let color = radiance(
+            &world,
+            &Ray::new(
+                Point::new(C_ZERO, C_ZERO, 5 as Real),
+                Vec3::new(C_ZERO, C_ZERO, -1 as Real),
+                C_ZERO,
+            ),
+            background,
+        );
+        assert_eq!((color.r, color.g, color.b), (C_ZERO, C_ZERO, 0.5 as Real));
+    }
+}
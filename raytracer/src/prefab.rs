@@ -0,0 +1,123 @@
+use std::sync::Arc;
+
+use crate::{hittable_list::HittableList, transform::Transform, types::Mat4};
+
+/// A scene (objects + lights) that can be placed into other scenes, any number of times.
+/// The copies share the objects of the prefab, each one is wrapped in its own `Transform`.
+#[derive(Clone)]
+pub struct ScenePrefab {
+    pub world: HittableList,
+    pub lights: HittableList,
+}
+
+impl ScenePrefab {
+    pub fn new(world: HittableList, lights: HittableList) -> ScenePrefab {
+        ScenePrefab { world, lights }
+    }
+
+    /// Adds a copy of the prefab, placed by `obj2world`, to the scene's objects and lights.
+    pub fn append_to(&self, world: &mut HittableList, lights: &mut HittableList, obj2world: Mat4) {
+        if self.world.objects_len() != 0 {
+            world.add(Arc::new(Transform::new(
+                obj2world,
+                Arc::new(self.world.clone()),
+            )));
+        }
+
+        //
+        // an empty list can't be sampled
+        if self.lights.objects_len() != 0 {
+            lights.add(Arc::new(Transform::new(
+                obj2world,
+                Arc::new(self.lights.clone()),
+            )));
+        }
+    }
+}
+
+impl std::convert::From<(HittableList, HittableList)> for ScenePrefab {
+    fn from((world, lights): (HittableList, HittableList)) -> Self {
+        ScenePrefab::new(world, lights)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diffuse_light::DiffuseLight;
+    use crate::flip_face::FlipFace;
+    use crate::hittable::Hittable;
+    use crate::lambertian::Lambertian;
+    use crate::objects::sphere::Sphere;
+    use crate::rectangles::XZRect;
+    use crate::types::{Point, Ray, Real, Vec3, C_ZERO};
+    use math::vec3::length;
+
+    #[test]
+    fn test_prefab_appended_twice() {
+        //
+        // unit sphere at the origin, lit by a small panel above it
+        let mut world = HittableList::new();
+        world.add(Arc::new(Sphere::new(
+            Point::broadcast(C_ZERO),
+            1 as Real,
+            Arc::new(Lambertian::new((0.5, 0.5, 0.5))),
+        )));
+        let mut lights = HittableList::new();
+        lights.add(Arc::new(FlipFace {
+            obj: Arc::new(XZRect {
+                x0: -0.5,
+                x1: 0.5,
+                z0: -0.5,
+                z1: 0.5,
+                k: 3 as Real,
+                mtl: Arc::new(DiffuseLight::from((4 as Real, 4 as Real, 4 as Real))),
+            }),
+        }));
+        let prefab = ScenePrefab::from((world, lights));
+
+        let mut scene = HittableList::new();
+        let mut scene_lights = HittableList::new();
+        prefab.append_to(
+            &mut scene,
+            &mut scene_lights,
+            Mat4::translate(Vec3::new(5 as Real, C_ZERO, C_ZERO)),
+        );
+        prefab.append_to(
+            &mut scene,
+            &mut scene_lights,
+            Mat4::translate(Vec3::new(-5 as Real, C_ZERO, C_ZERO)) * Mat4::uniform_scale(2 as Real),
+        );
+        assert_eq!(scene.objects_len(), 2);
+        assert_eq!(scene_lights.objects_len(), 2);
+
+        let down = Vec3::new(C_ZERO, C_ZERO, -1 as Real);
+        let hit = |x: Real| {
+            scene.hit(
+                &Ray::new(Point::new(x, C_ZERO, 10 as Real), down, C_ZERO),
+                0.001,
+                Real::MAX,
+            )
+        };
+
+        let right = hit(5 as Real).unwrap();
+        assert!(length(right.p - Point::new(5 as Real, C_ZERO, 1 as Real)) < 1.0e-4);
+        assert!(length(right.normal - Vec3::new(C_ZERO, C_ZERO, 1 as Real)) < 1.0e-4);
+
+        let left = hit(-5 as Real).unwrap();
+        assert!(length(left.p - Point::new(-5 as Real, C_ZERO, 2 as Real)) < 1.0e-4);
+
+        assert!(hit(C_ZERO).is_none());
+        assert!(hit(3 as Real).is_none());
+
+        //
+        // the copy's light is where the copy is
+        let below_right = Point::new(5 as Real, C_ZERO, C_ZERO);
+        let up = Vec3::new(C_ZERO, 1 as Real, C_ZERO);
+        assert!(scene_lights.pdf_value(below_right, up) > C_ZERO);
+        assert_eq!(
+            scene_lights.pdf_value(Point::new(20 as Real, C_ZERO, C_ZERO), up),
+            C_ZERO
+        );
+    }
+}
@@ -0,0 +1,277 @@
+use std::sync::Arc;
+
+use crate::{
+    aabb3::Aabb,
+    hittable::{HitRecord, Hittable},
+    material::Material,
+    rectangles::{XYRect, XZRect, YZRect},
+    types::{Ray, Real, Vec3},
+};
+
+/// Plane of an axis aligned wall.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum WallType {
+    XZ,
+    YZ,
+    XY,
+}
+
+/// Axis aligned wall: the [a, b] x [c, d] rectangle in the wall's plane, at `k` on the
+/// third axis (same as the rect types, `a`, `b` are x0, x1 for XY and XZ walls, y0, y1
+/// for YZ walls).
+#[derive(Copy, Clone, Debug)]
+pub struct WallData {
+    pub wt: WallType,
+    pub a: Real,
+    pub b: Real,
+    pub c: Real,
+    pub d: Real,
+    pub k: Real,
+    pub color_id: usize,
+}
+
+impl WallData {
+    /// The wall as a standalone rect.
+    pub fn to_rect(&self, mtl: Arc<dyn Material>) -> Arc<dyn Hittable> {
+        match self.wt {
+            WallType::XY => Arc::new(XYRect {
+                x0: self.a,
+                x1: self.b,
+                y0: self.c,
+                y1: self.d,
+                k: self.k,
+                mtl,
+            }),
+            WallType::XZ => Arc::new(XZRect {
+                x0: self.a,
+                x1: self.b,
+                z0: self.c,
+                z1: self.d,
+                k: self.k,
+                mtl,
+            }),
+            WallType::YZ => Arc::new(YZRect {
+                y0: self.a,
+                y1: self.b,
+                z0: self.c,
+                z1: self.d,
+                k: self.k,
+                mtl,
+            }),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Quad {
+    /// Axis of the normal.
+    axis: usize,
+    /// In plane axes, u comes from the first one, v from the second one.
+    plane_axes: [usize; 2],
+    min: [Real; 2],
+    max: [Real; 2],
+    k: Real,
+    mtl: u32,
+}
+
+/// Axis aligned quads (walls, floors of box heavy scenes) in one object, the hit test
+/// loops over the flat quad array instead of going through one `Hittable` per rect.
+/// Hits are the same as the ones of the rects the quads are built from.
+pub struct QuadMesh {
+    quads: Vec<Quad>,
+    materials: Vec<Arc<dyn Material>>,
+}
+
+impl QuadMesh {
+    /// Quads for the walls, the materials are indexed by the walls' `color_id`.
+    pub fn from_walls<M: Material + 'static>(walls: &[WallData], materials: &[Arc<M>]) -> QuadMesh {
+        let quads = walls
+            .iter()
+            .map(|wall| {
+                let (axis, plane_axes) = match wall.wt {
+                    WallType::XY => (2, [0, 1]),
+                    WallType::XZ => (1, [0, 2]),
+                    WallType::YZ => (0, [1, 2]),
+                };
+
+                assert!(
+                    wall.color_id < materials.len(),
+                    "Wall material {} out of range",
+                    wall.color_id
+                );
+
+                Quad {
+                    axis,
+                    plane_axes,
+                    min: [wall.a, wall.c],
+                    max: [wall.b, wall.d],
+                    k: wall.k,
+                    mtl: wall.color_id as u32,
+                }
+            })
+            .collect();
+
+        QuadMesh {
+            quads,
+            materials: materials
+                .iter()
+                .map(|mtl| mtl.clone() as Arc<dyn Material>)
+                .collect(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.quads.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.quads.is_empty()
+    }
+}
+
+impl Hittable for QuadMesh {
+    fn hit(&self, r: &Ray, t_min: Real, t_max: Real) -> Option<HitRecord> {
+        //
+        // (quad, t, u coordinate, v coordinate) of the closest hit so far
+        let mut closest: Option<(&Quad, Real, Real, Real)> = None;
+
+        self.quads.iter().for_each(|quad| {
+            let t = (quad.k - r.origin[quad.axis]) / r.direction[quad.axis];
+            let t_closest = closest.map_or(t_max, |(_, t, _, _)| t);
+
+            if t < t_min || t > t_closest {
+                return;
+            }
+
+            let [a0, a1] = quad.plane_axes;
+            let u = r.origin[a0] + t * r.direction[a0];
+            let v = r.origin[a1] + t * r.direction[a1];
+
+            if u < quad.min[0] || u > quad.max[0] || v < quad.min[1] || v > quad.max[1] {
+                return;
+            }
+
+            closest = Some((quad, t, u, v));
+        });
+
+        closest.map(|(quad, t, u, v)| {
+            let mut normal = Vec3::broadcast(0 as Real);
+            normal[quad.axis] = 1 as Real;
+
+            HitRecord::new(
+                r.at(t),
+                normal,
+                r,
+                t,
+                self.materials[quad.mtl as usize].clone(),
+                (u - quad.min[0]) / (quad.max[0] - quad.min[0]),
+                (v - quad.min[1]) / (quad.max[1] - quad.min[1]),
+            )
+        })
+    }
+
+    fn name(&self) -> Option<&str> {
+        Some("quad mesh")
+    }
+
+    fn bounding_box(&self, _time0: Real, _time1: Real) -> Option<Aabb> {
+        //
+        // same padding as the rects
+        self.quads
+            .iter()
+            .map(|quad| {
+                let mut min = Vec3::broadcast(quad.k - 0.0001 as Real);
+                let mut max = Vec3::broadcast(quad.k + 0.0001 as Real);
+                quad.plane_axes.iter().enumerate().for_each(|(i, &axis)| {
+                    min[axis] = quad.min[i];
+                    max[axis] = quad.max[i];
+                });
+                Aabb::new(min, max)
+            })
+            .reduce(|a, b| crate::aabb3::merge_aabbs(&a, &b))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hittable_list::HittableList;
+    use crate::lambertian::Lambertian;
+    use crate::types::{random_real_range, random_unit_vector, Point};
+    use std::iter::FromIterator;
+
+    #[test]
+    fn test_cornell_walls_hit_like_the_rects() {
+        let colors = [(0.65, 0.05, 0.05), (0.73, 0.73, 0.73), (0.12, 0.45, 0.15)]
+            .iter()
+            .map(|&color| Arc::new(Lambertian::new(color)))
+            .collect::<Vec<_>>();
+
+        let wall = |wt: WallType, k: Real, color_id: usize| WallData {
+            wt,
+            a: 0 as Real,
+            b: 555 as Real,
+            c: 0 as Real,
+            d: 555 as Real,
+            k,
+            color_id,
+        };
+        let walls = [
+            wall(WallType::YZ, 555 as Real, 2),
+            wall(WallType::YZ, 0 as Real, 0),
+            wall(WallType::XZ, 0 as Real, 1),
+            wall(WallType::XZ, 555 as Real, 1),
+            wall(WallType::XY, 555 as Real, 1),
+        ];
+
+        let mesh = QuadMesh::from_walls(&walls, &colors);
+        let rects = HittableList::from_iter(
+            walls
+                .iter()
+                .map(|wall| wall.to_rect(colors[wall.color_id].clone())),
+        );
+        assert_eq!(mesh.len(), 5);
+
+        let mesh_box = mesh.bounding_box(0 as Real, 1 as Real).unwrap();
+        let rects_box = rects.bounding_box(0 as Real, 1 as Real).unwrap();
+        assert_eq!(mesh_box.min, rects_box.min);
+        assert_eq!(mesh_box.max, rects_box.max);
+
+        //
+        // from inside the box, and from outside through the open side
+        let mut hits = 0;
+        (0..2000).for_each(|i| {
+            let origin = if i % 2 == 0 {
+                Point::new(
+                    random_real_range(1 as Real, 554 as Real),
+                    random_real_range(1 as Real, 554 as Real),
+                    random_real_range(1 as Real, 554 as Real),
+                )
+            } else {
+                Point::new(278 as Real, 278 as Real, -800 as Real)
+            };
+            let target = Point::broadcast(278 as Real) + random_unit_vector() * 400 as Real;
+            let r = Ray::new(origin, target - origin, 0 as Real);
+
+            match (
+                mesh.hit(&r, 0.001 as Real, Real::MAX),
+                rects.hit(&r, 0.001 as Real, Real::MAX),
+            ) {
+                (Some(m), Some(h)) => {
+                    hits += 1;
+                    assert_eq!(m.t, h.t);
+                    assert_eq!(m.p, h.p);
+                    assert_eq!(m.normal, h.normal);
+                    assert_eq!(m.front_face, h.front_face);
+                    assert_eq!((m.u, m.v), (h.u, h.v));
+                    assert_eq!(
+                        Arc::as_ptr(&m.mtl) as *const (),
+                        Arc::as_ptr(&h.mtl) as *const ()
+                    );
+                }
+                (m, h) => assert_eq!(m.is_some(), h.is_some()),
+            }
+        });
+        assert!(hits > 1000);
+    }
+}
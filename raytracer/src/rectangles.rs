@@ -4,7 +4,7 @@ use math::vec3::{dot, length, length_squared};
 
 use crate::{
     aabb3::Aabb,
-    hittable::{HitRecord, Hittable},
+    hittable::{HitRecord, Hittable, PrimitiveId},
     material::Material,
     types::{random_real_range, Ray, Real, Vec3},
 };
@@ -33,15 +33,36 @@ impl Hittable for XYRect {
             return None;
         }
 
-        Some(HitRecord::new(
-            r.at(t),
-            Vec3::new(0 as Real, 0 as Real, 1 as Real),
-            r,
-            t,
-            self.mtl.clone(),
-            (x - self.x0) / (self.x1 - self.x0),
-            (y - self.y0) / (self.y1 - self.y0),
-        ))
+        Some(
+            HitRecord::new(
+                r.at(t),
+                Vec3::new(0 as Real, 0 as Real, 1 as Real),
+                r,
+                t,
+                self.mtl.clone(),
+                (x - self.x0) / (self.x1 - self.x0),
+                (y - self.y0) / (self.y1 - self.y0),
+            )
+            .with_primitive(PrimitiveId::axis_plane(2, self.k)),
+        )
+    }
+
+    fn hit_skipping(
+        &self,
+        r: &Ray,
+        t_min: Real,
+        t_max: Real,
+        skip: Option<PrimitiveId>,
+    ) -> Option<HitRecord> {
+        if skip == Some(PrimitiveId::axis_plane(2, self.k)) {
+            return None;
+        }
+
+        self.hit(r, t_min, t_max)
+    }
+
+    fn name(&self) -> Option<&str> {
+        Some("xy rect")
     }
 
     fn bounding_box(&self, _t0: Real, _t11: Real) -> Option<Aabb> {
@@ -75,6 +96,10 @@ impl Hittable for XYRect {
 
         random_point - origin
     }
+
+    fn faces(&self, origin: crate::types::Point) -> Option<bool> {
+        self.mtl.emits_one_sided().then_some(origin.z > self.k)
+    }
 }
 
 //
@@ -103,15 +128,36 @@ impl Hittable for XZRect {
             return None;
         }
 
-        Some(HitRecord::new(
-            r.at(t),
-            Vec3::new(0 as Real, 1 as Real, 0 as Real),
-            r,
-            t,
-            self.mtl.clone(),
-            (x - self.x0) / (self.x1 - self.x0),
-            (z - self.z0) / (self.z1 - self.z0),
-        ))
+        Some(
+            HitRecord::new(
+                r.at(t),
+                Vec3::new(0 as Real, 1 as Real, 0 as Real),
+                r,
+                t,
+                self.mtl.clone(),
+                (x - self.x0) / (self.x1 - self.x0),
+                (z - self.z0) / (self.z1 - self.z0),
+            )
+            .with_primitive(PrimitiveId::axis_plane(1, self.k)),
+        )
+    }
+
+    fn hit_skipping(
+        &self,
+        r: &Ray,
+        t_min: Real,
+        t_max: Real,
+        skip: Option<PrimitiveId>,
+    ) -> Option<HitRecord> {
+        if skip == Some(PrimitiveId::axis_plane(1, self.k)) {
+            return None;
+        }
+
+        self.hit(r, t_min, t_max)
+    }
+
+    fn name(&self) -> Option<&str> {
+        Some("xz rect")
     }
 
     fn bounding_box(&self, _t0: Real, _t11: Real) -> Option<Aabb> {
@@ -145,6 +191,10 @@ impl Hittable for XZRect {
 
         random_point - origin
     }
+
+    fn faces(&self, origin: crate::types::Point) -> Option<bool> {
+        self.mtl.emits_one_sided().then_some(origin.y > self.k)
+    }
 }
 
 //
@@ -173,15 +223,36 @@ impl Hittable for YZRect {
             return None;
         }
 
-        Some(HitRecord::new(
-            r.at(t),
-            Vec3::new(1 as Real, 0 as Real, 0 as Real),
-            r,
-            t,
-            self.mtl.clone(),
-            (y - self.y0) / (self.y1 - self.y0),
-            (z - self.z0) / (self.z1 - self.z0),
-        ))
+        Some(
+            HitRecord::new(
+                r.at(t),
+                Vec3::new(1 as Real, 0 as Real, 0 as Real),
+                r,
+                t,
+                self.mtl.clone(),
+                (y - self.y0) / (self.y1 - self.y0),
+                (z - self.z0) / (self.z1 - self.z0),
+            )
+            .with_primitive(PrimitiveId::axis_plane(0, self.k)),
+        )
+    }
+
+    fn hit_skipping(
+        &self,
+        r: &Ray,
+        t_min: Real,
+        t_max: Real,
+        skip: Option<PrimitiveId>,
+    ) -> Option<HitRecord> {
+        if skip == Some(PrimitiveId::axis_plane(0, self.k)) {
+            return None;
+        }
+
+        self.hit(r, t_min, t_max)
+    }
+
+    fn name(&self) -> Option<&str> {
+        Some("yz rect")
     }
 
     fn bounding_box(&self, _t0: Real, _t11: Real) -> Option<Aabb> {
@@ -215,4 +286,8 @@ impl Hittable for YZRect {
 
         random_point - origin
     }
+
+    fn faces(&self, origin: crate::types::Point) -> Option<bool> {
+        self.mtl.emits_one_sided().then_some(origin.x > self.k)
+    }
 }
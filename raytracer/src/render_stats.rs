@@ -0,0 +1,176 @@
+use std::io::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Counters shared by the workers of a render.
+#[derive(Debug)]
+pub struct RenderStats {
+    started: Instant,
+    rays: AtomicU64,
+    /// Number of paths that ended after `i` bounces.
+    bounce_histogram: Vec<AtomicU64>,
+    block_times: Mutex<Vec<Duration>>,
+}
+
+impl RenderStats {
+    pub fn new(max_ray_depth: i32) -> RenderStats {
+        RenderStats {
+            started: Instant::now(),
+            rays: AtomicU64::new(0),
+            bounce_histogram: (0..=max_ray_depth.max(0))
+                .map(|_| AtomicU64::new(0))
+                .collect(),
+            block_times: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn record_ray(&self) {
+        self.rays.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_path(&self, bounces: i32) {
+        let bucket = (bounces.max(0) as usize).min(self.bounce_histogram.len() - 1);
+        self.bounce_histogram[bucket].fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_block(&self, time: Duration) {
+        self.block_times
+            .lock()
+            .expect("Stats lock poisoned")
+            .push(time);
+    }
+
+    pub fn summary(&self) -> RenderStatsSummary {
+        RenderStatsSummary {
+            total_rays: self.rays.load(Ordering::Relaxed),
+            render_time: self.started.elapsed(),
+            bounce_histogram: self
+                .bounce_histogram
+                .iter()
+                .map(|count| count.load(Ordering::Relaxed))
+                .collect(),
+            block_times: self
+                .block_times
+                .lock()
+                .expect("Stats lock poisoned")
+                .clone(),
+        }
+    }
+}
+
+/// Statistics of a finished render, written as a row of a CSV file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RenderStatsSummary {
+    pub total_rays: u64,
+    pub render_time: Duration,
+    pub bounce_histogram: Vec<u64>,
+    pub block_times: Vec<Duration>,
+}
+
+impl RenderStatsSummary {
+    pub const CSV_HEADER: &'static str = "total_rays,render_seconds,rays_per_second,\
+        average_bounces,median_bounces,work_blocks,average_block_ms,max_block_ms,bounce_histogram";
+
+    pub fn rays_per_second(&self) -> f64 {
+        self.total_rays as f64 / self.render_time.as_secs_f64().max(f64::EPSILON)
+    }
+
+    fn paths(&self) -> u64 {
+        self.bounce_histogram.iter().sum()
+    }
+
+    pub fn average_bounces(&self) -> f64 {
+        let bounces = self
+            .bounce_histogram
+            .iter()
+            .enumerate()
+            .map(|(bounces, &count)| bounces as u64 * count)
+            .sum::<u64>();
+
+        bounces as f64 / self.paths().max(1) as f64
+    }
+
+    pub fn median_bounces(&self) -> usize {
+        let half = self.paths().div_ceil(2);
+        let mut paths = 0;
+
+        self.bounce_histogram
+            .iter()
+            .position(|&count| {
+                paths += count;
+                paths >= half
+            })
+            .unwrap_or(0)
+    }
+
+    pub fn csv_row(&self) -> String {
+        let block_ms = |t: &Duration| t.as_secs_f64() * 1000f64;
+        let average_block_ms = self.block_times.iter().map(block_ms).sum::<f64>()
+            / self.block_times.len().max(1) as f64;
+        let max_block_ms = self.block_times.iter().map(block_ms).fold(0f64, f64::max);
+
+        //
+        // the histogram goes into a single column, counts separated by spaces
+        let histogram = self
+            .bounce_histogram
+            .iter()
+            .map(|count| count.to_string())
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        format!(
+            "{},{:.3},{:.1},{:.3},{},{},{:.3},{:.3},{}",
+            self.total_rays,
+            self.render_time.as_secs_f64(),
+            self.rays_per_second(),
+            self.average_bounces(),
+            self.median_bounces(),
+            self.block_times.len(),
+            average_block_ms,
+            max_block_ms,
+            histogram
+        )
+    }
+
+    pub fn write_csv<P: AsRef<std::path::Path>>(&self, path: P) -> std::io::Result<()> {
+        let mut f = std::fs::File::create(path)?;
+        writeln!(f, "{}", Self::CSV_HEADER)?;
+        writeln!(f, "{}", self.csv_row())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_header_and_row() {
+        let summary = RenderStatsSummary {
+            total_rays: 1000,
+            render_time: Duration::from_secs(2),
+            //
+            // 4 paths, with 0, 1, 1 and 2 bounces
+            bounce_histogram: vec![1, 2, 1],
+            block_times: vec![Duration::from_millis(10), Duration::from_millis(30)],
+        };
+
+        assert_eq!(
+            RenderStatsSummary::CSV_HEADER,
+            "total_rays,render_seconds,rays_per_second,average_bounces,median_bounces,\
+             work_blocks,average_block_ms,max_block_ms,bounce_histogram"
+        );
+        assert_eq!(
+            summary.csv_row(),
+            "1000,2.000,500.0,1.000,1,2,20.000,30.000,1 2 1"
+        );
+
+        let stats = RenderStats::new(2);
+        (0..3).for_each(|_| stats.record_ray());
+        stats.record_path(1);
+        stats.record_path(7);
+        let summary = stats.summary();
+        assert_eq!(summary.total_rays, 3);
+        assert_eq!(summary.bounce_histogram, vec![0, 1, 1]);
+    }
+}
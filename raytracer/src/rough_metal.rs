@@ -0,0 +1,177 @@
+use math::vec3::{dot, normalize};
+
+use crate::{
+    hittable::HitRecord,
+    material::{Material, ScatterRecord},
+    microfacet::Microfacet,
+    types::{Color, Ray, Real, Vec3, C_ONE, C_ZERO},
+};
+
+/// Metal with GGX distributed microfacets, the physically based counterpart of the fuzzed
+/// `Metal`. The reflecting microfacet is importance sampled among the ones visible from
+/// the viewer and the weight of the bounce is the masking-shadowing of the microfacets
+/// over the masking of the view, so rough metal loses the light that would bounce more
+/// than once between the microfacets instead of reflecting all of it.
+#[derive(Copy, Clone, Debug)]
+pub struct RoughMetal {
+    pub albedo: Color,
+    /// In [0, 1], the alpha of the GGX distribution. 0 is a mirror.
+    pub roughness: Real,
+}
+
+impl RoughMetal {
+    pub fn new<T: Into<Color>>(albedo: T, roughness: Real) -> RoughMetal {
+        RoughMetal {
+            albedo: albedo.into(),
+            roughness: roughness.clamp(C_ZERO, C_ONE),
+        }
+    }
+
+    fn microfacet(&self) -> Microfacet {
+        Microfacet::ggx(self.roughness)
+    }
+
+    /// Microfacet normal the viewer at the unit direction `wo` is reflected by and the
+    /// weight of the reflection (bsdf * cos / pdf, without the albedo), `n` is the unit
+    /// normal on the viewer's side. None if the reflection goes below the surface.
+    pub fn sample(&self, wo: Vec3, n: Vec3) -> Option<(Vec3, Real)> {
+        let microfacet = self.microfacet();
+        let (h, wi) = microfacet.sample_reflection(n, wo);
+        let cos_i = dot(wi, n);
+        if cos_i <= C_ZERO {
+            return None;
+        }
+
+        Some((h, microfacet.reflection_weight(dot(wo, n), cos_i)))
+    }
+}
+
+impl Material for RoughMetal {
+    fn scatter(&self, ray: &Ray, hit_record: &HitRecord) -> Option<ScatterRecord> {
+        let wo = -normalize(ray.direction);
+        let n = normalize(hit_record.normal);
+
+        self.sample(wo, n)
+            .map(|(h, weight)| ScatterRecord::SpecularRec {
+                //
+                // the differentials follow the mirror direction of the microfacet
+                ray: ray.reflect_ray(hit_record.p, h),
+                attenuation: self.albedo * weight,
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        metal::Metal,
+        types::{Point, C_TWO_PI},
+    };
+    use std::sync::Arc;
+
+    #[test]
+    fn test_ggx_lobe_widens_with_roughness_and_reflects_the_single_scattered_light() {
+        const N: usize = 20_000;
+        let n = Vec3::new(C_ZERO, C_ONE, C_ZERO);
+
+        //
+        // (mean reflected fraction of white light, mean cosine to the mirror direction)
+        // for a ray coming in at cosine cos_o, materials reflecting white
+        let lobe = |mtl: Arc<dyn Material>, cos_o: Real| {
+            let sin_o = (C_ONE - cos_o * cos_o).sqrt();
+            let ray = Ray::new(
+                Point::new(-sin_o, cos_o, C_ZERO),
+                Vec3::new(sin_o, -cos_o, C_ZERO),
+                C_ZERO,
+            );
+            let mirror = Vec3::new(sin_o, cos_o, C_ZERO);
+            let rec = HitRecord::new(
+                Point::new(C_ZERO, C_ZERO, C_ZERO),
+                n,
+                &ray,
+                C_ONE,
+                mtl.clone(),
+                C_ZERO,
+                C_ZERO,
+            );
+
+            let (energy, spread) = (0..N)
+                .filter_map(|_| match mtl.scatter(&ray, &rec) {
+                    Some(ScatterRecord::SpecularRec { ray, attenuation }) => {
+                        assert!(dot(ray.direction, n) > C_ZERO);
+                        Some((attenuation.r, dot(normalize(ray.direction), mirror)))
+                    }
+                    _ => None,
+                })
+                .fold((C_ZERO, C_ZERO), |(e, s), (a, c)| (e + a, s + c));
+            (energy / N as Real, spread / N as Real)
+        };
+        let white = (C_ONE, C_ONE, C_ONE);
+
+        //
+        // roughness 0 is a mirror, like the metal without fuzz
+        [C_ONE, 0.3 as Real].iter().for_each(|&cos_o| {
+            let (energy, spread) = lobe(Arc::new(RoughMetal::new(white, C_ZERO)), cos_o);
+            assert!((energy - C_ONE).abs() < 1.0e-4, "{}", energy);
+            assert!((spread - C_ONE).abs() < 1.0e-4, "{}", spread);
+            let (_, fuzz_spread) = lobe(Arc::new(Metal::new(white, C_ZERO)), cos_o);
+            assert!((fuzz_spread - C_ONE).abs() < 1.0e-4);
+        });
+
+        //
+        // the lobe widens with the roughness
+        let (_, smooth_spread) = lobe(Arc::new(RoughMetal::new(white, 0.1 as Real)), C_ONE);
+        let (rough_energy, rough_spread) = lobe(Arc::new(RoughMetal::new(white, C_ONE)), C_ONE);
+        assert!(smooth_spread > 0.9 as Real, "{}", smooth_spread);
+        assert!(rough_spread < 0.5 as Real, "{}", rough_spread);
+
+        //
+        // the reflected light is the integral of the bsdf * cos over the hemisphere,
+        // D G / (4 cos_o), only the light that bounces more than once between the
+        // microfacets is lost (d omega = d cos d phi)
+        const STEPS: usize = 400;
+        [
+            (0.1 as Real, C_ONE),
+            (0.5, 0.5),
+            (C_ONE, C_ONE),
+            (C_ONE, 0.2),
+        ]
+        .iter()
+        .for_each(|&(roughness, cos_o)| {
+            let metal = RoughMetal::new(white, roughness);
+            let microfacet = metal.microfacet();
+            let wo = Vec3::new((C_ONE - cos_o * cos_o).sqrt(), C_ZERO, cos_o);
+            let cell = C_TWO_PI / (STEPS * STEPS) as Real;
+            let expected = (0..STEPS * STEPS)
+                .map(|i| {
+                    let cos_i = ((i % STEPS) as Real + 0.5 as Real) / STEPS as Real;
+                    let phi = ((i / STEPS) as Real + 0.5 as Real) / STEPS as Real * C_TWO_PI;
+                    let sin_i = (C_ONE - cos_i * cos_i).sqrt();
+                    let wi = Vec3::new(sin_i * phi.cos(), sin_i * phi.sin(), cos_i);
+                    microfacet.d(normalize(wo + wi).z) * microfacet.g(cos_o, cos_i)
+                        / (4 as Real * cos_o)
+                })
+                .sum::<Real>()
+                * cell;
+
+            let (energy, _) = lobe(Arc::new(metal), cos_o);
+            assert!(energy <= C_ONE && expected <= C_ONE);
+            assert!(
+                (energy - expected).abs() < 0.02 as Real,
+                "{} {} {} {}",
+                roughness,
+                cos_o,
+                energy,
+                expected
+            );
+        });
+
+        //
+        // the fuzzed metal at full fuzz is narrower and reflects all of the light
+        let (fuzz_energy, fuzz_spread) = lobe(Arc::new(Metal::new(white, C_ONE)), C_ONE);
+        assert!(fuzz_spread > rough_spread, "{}", fuzz_spread);
+        assert!((fuzz_energy - C_ONE).abs() < 1.0e-4, "{}", fuzz_energy);
+        assert!(rough_energy < 0.5 as Real, "{}", rough_energy);
+    }
+}
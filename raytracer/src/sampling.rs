@@ -1,7 +1,6 @@
-use crate::types::{
-    random_int, random_real, Real, Vec2, Vec3, C_HALF_ONE, C_ONE, C_TWO, C_TWO_PI, C_ZERO,
-};
-use num::{integer::Roots, Zero};
+use crate::types::{random_int, random_real, random_shuffle, Real, Vec2, Vec3, C_HALF_ONE};
+use num::integer::Roots;
+use serde::{Deserialize, Serialize};
 
 pub trait SampleStrategy {
     fn generate_samples(sets: u32, samples_in_set: u32) -> Vec<Vec2>;
@@ -15,6 +14,8 @@ where
     samples: Vec<Vec2>,
     disk_samples: Vec<Vec2>,
     hemisphere_samples: Vec<Vec3>,
+    /// Exponent of the cos^n(theta) density of the hemisphere samples.
+    cosine_power: Real,
     shuffled_indices: Vec<u32>,
     sets: u32,
     samples_in_set: u32,
@@ -54,77 +55,25 @@ where
         }
 
         let mut indices = (0..num_samples).map(|i| i as u32).collect::<Vec<_>>();
-        let mut rng = rand::thread_rng();
 
         let mut shuffled_indices: Vec<u32> = Vec::new();
         (0..num_sets as u32).for_each(|_| {
-            use rand::seq::SliceRandom;
-
-            indices.shuffle(&mut rng);
+            random_shuffle(&mut indices);
             shuffled_indices.extend(indices.clone());
         });
 
-        //
-        // map unit square samples to unit disk
         let disk_samples = samples
             .iter()
-            .map(|&s| {
-                let sp = C_TWO * s - Vec2::broadcast(C_ONE);
-
-                let (r, phi) = if sp.x > -sp.y {
-                    if sp.x > sp.y {
-                        // sector 1
-                        (sp.x, sp.y / sp.x)
-                    } else {
-                        // sector2
-                        (sp.y, C_TWO - sp.x / sp.y)
-                    }
-                } else {
-                    if sp.x < sp.y {
-                        // sector 3
-                        (-sp.x, C_TWO * C_TWO + sp.y / sp.x)
-                    } else {
-                        // sector 4
-                        let r = -sp.y;
-                        let phi = if !sp.y.is_zero() {
-                            6 as Real - sp.x / sp.y
-                        } else {
-                            C_ZERO
-                        };
-
-                        (r, phi)
-                    }
-                };
-
-                let phi = phi * (0.25 as Real);
-                let (sin_phi, cos_phi) = phi.sin_cos();
-
-                Vec2 {
-                    x: r * cos_phi,
-                    y: r * sin_phi,
-                }
-            })
+            .map(|&s| math::vec2::square_to_disk(s))
             .collect::<Vec<_>>();
 
-        let hemisphere_samples = samples
-            .iter()
-            .map(|&s| {
-                let (sin_phi, cos_phi) = (C_TWO_PI * s).x.sin_cos();
-                let cos_theta = (C_ONE - s.y).powf(C_ONE / (C_ONE + std::f32::consts::E));
-                let sin_theta = (C_ONE - cos_theta * cos_theta).sqrt();
-
-                Vec3 {
-                    x: sin_theta * cos_phi,
-                    y: sin_theta * sin_phi,
-                    z: cos_theta,
-                }
-            })
-            .collect::<Vec<_>>();
+        let hemisphere_samples = Self::hemisphere_samples(&samples, 1 as Real);
 
         Self {
             samples,
             disk_samples,
             hemisphere_samples,
+            cosine_power: 1 as Real,
             shuffled_indices,
             count: 0,
             jump: 0,
@@ -134,6 +83,32 @@ where
         }
     }
 
+    /// Hemisphere samples distributed like cos^n(theta) around the pole, for Phong like
+    /// glossy lobes. 1 is the cosine distribution of diffuse surfaces, the default.
+    pub fn with_cosine_power(self, n: Real) -> Self {
+        Self {
+            hemisphere_samples: Self::hemisphere_samples(&self.samples, n),
+            cosine_power: n,
+            ..self
+        }
+    }
+
+    pub fn cosine_power(&self) -> Real {
+        self.cosine_power
+    }
+
+    fn hemisphere_samples(samples: &[Vec2], n: Real) -> Vec<Vec3> {
+        samples
+            .iter()
+            .map(|&s| math::vec2::square_to_cosine_power_hemisphere(s, n))
+            .collect()
+    }
+
+    /// Starts over from a random set, the next sample is the first one of its set.
+    pub fn restart(&mut self) {
+        self.count = 0;
+    }
+
     pub fn sample_unit_square(&mut self) -> Vec2 {
         if self.count % self.samples_in_set as usize == 0 {
             self.jump = (random_int(0, std::i32::MAX - 1) % (self.sets as i32)
@@ -177,6 +152,21 @@ where
     }
 }
 
+/// Strategy the workers' samplers are built with, picked in the config. All but `NRooks`
+/// need a square number of samples per pixel.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SamplerKind {
+    /// One sample in every row and every column of an N x N grid.
+    NRooks,
+    /// One sample in every cell of a sqrt(N) x sqrt(N) grid.
+    Jittered,
+    /// Jittered and N-rooks at the same time.
+    #[default]
+    MultiJittered,
+    /// The centers of the cells of a sqrt(N) x sqrt(N) grid, no randomness.
+    Simple,
+}
+
 #[derive(Clone)]
 pub struct NRooksSamplingStrategy {}
 
@@ -280,3 +270,47 @@ impl SampleStrategy for SimpleSamplingStrategy {
 }
 
 pub type SimpleSampler = SamplerBase<SimpleSamplingStrategy>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hemisphere_samples_follow_the_cosine_power() {
+        //
+        // the mean of cos(theta) for a cos^n(theta) density is (n + 1) / (n + 2)
+        let mean_cos = |n: Real| {
+            let mut smp =
+                SamplerBase::<MultiJitteredSamplingStrategy>::new(64, None).with_cosine_power(n);
+            assert_eq!(smp.cosine_power(), n);
+
+            const N: usize = 20_000;
+            (0..N)
+                .map(|_| {
+                    let dir = smp.sample_unit_hemisphere();
+                    assert!((math::vec3::length(dir) - 1 as Real).abs() < 1.0e-4);
+                    assert!(dir.z >= 0 as Real);
+                    dir.z
+                })
+                .sum::<Real>()
+                / N as Real
+        };
+
+        let cosine = SamplerBase::<MultiJitteredSamplingStrategy>::new(64, None);
+        assert_eq!(cosine.cosine_power(), 1 as Real);
+
+        let means = [1 as Real, 10 as Real, 100 as Real].map(|n| {
+            let mean = mean_cos(n);
+            let expected = (n + 1 as Real) / (n + 2 as Real);
+            assert!(
+                (mean - expected).abs() < 0.01,
+                "n {} {} {}",
+                n,
+                mean,
+                expected
+            );
+            mean
+        });
+        assert!(means[0] < means[1] && means[1] < means[2]);
+    }
+}
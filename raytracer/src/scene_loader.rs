@@ -0,0 +1,481 @@
+use std::{collections::HashMap, sync::Arc};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    block::Block,
+    checker_texture::CheckerTexture,
+    dielectric::Dielectric,
+    diffuse_light::DiffuseLight,
+    flip_face::FlipFace,
+    hittable::Hittable,
+    hittable_list::HittableList,
+    image_texture::ImageTexture,
+    isotropic::Isotropic,
+    lambertian::Lambertian,
+    material::Material,
+    metal::Metal,
+    noise_texture::NoiseTexture,
+    objects::sphere::Sphere,
+    plastic::PlasticMaterial,
+    rectangles::{XYRect, XZRect, YZRect},
+    rough_metal::RoughMetal,
+    solid_color_texture::SolidColorTexture,
+    texture::Texture,
+    transform::{RotateY, Translate},
+    types::{Point, Real, Vec3},
+    RaytracerParams,
+};
+
+fn default_world_up() -> [Real; 3] {
+    [0 as Real, 1 as Real, 0 as Real]
+}
+
+fn default_srgb() -> bool {
+    true
+}
+
+/// View of a scene file, replaces the one of the config's params.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct SceneCamera {
+    pub look_from: [Real; 3],
+    pub look_at: [Real; 3],
+    #[serde(default = "default_world_up")]
+    pub world_up: [Real; 3],
+    pub vertical_fov: Real,
+    #[serde(default)]
+    pub aperture: Real,
+    /// The distance from `look_from` to `look_at` if missing.
+    #[serde(default)]
+    pub focus_dist: Option<Real>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum TextureDescription {
+    Solid([Real; 3]),
+    Checker {
+        odd: [Real; 3],
+        even: [Real; 3],
+        repeat_factor: Real,
+    },
+    Noise {
+        scale: Real,
+    },
+    /// Image file, relative to the working directory like the models of the scenes.
+    Image {
+        path: String,
+        #[serde(default = "default_srgb")]
+        srgb: bool,
+    },
+}
+
+/// Color of a material, a constant or one of the scene's textures.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum TextureSlot {
+    Color([Real; 3]),
+    Texture(String),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum MaterialDescription {
+    Lambertian {
+        albedo: TextureSlot,
+    },
+    Metal {
+        albedo: [Real; 3],
+        fuzziness: Real,
+    },
+    /// GGX microfacet metal, `roughness` in [0, 1].
+    RoughMetal {
+        albedo: [Real; 3],
+        roughness: Real,
+    },
+    /// Diffuse base under a GGX microfacet clear coat.
+    Plastic {
+        diffuse_albedo: [Real; 3],
+        specular_tint: [Real; 3],
+        roughness: Real,
+    },
+    Dielectric {
+        refraction_index: Real,
+    },
+    DiffuseLight {
+        emit: TextureSlot,
+    },
+    Isotropic {
+        albedo: TextureSlot,
+    },
+}
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum ShapeDescription {
+    Sphere {
+        center: [Real; 3],
+        radius: Real,
+    },
+    XYRect {
+        x0: Real,
+        x1: Real,
+        y0: Real,
+        y1: Real,
+        k: Real,
+    },
+    XZRect {
+        x0: Real,
+        x1: Real,
+        z0: Real,
+        z1: Real,
+        k: Real,
+    },
+    YZRect {
+        y0: Real,
+        y1: Real,
+        z0: Real,
+        z1: Real,
+        k: Real,
+    },
+    /// Axis aligned box between the corners `p0` and `p1`.
+    Block {
+        p0: [Real; 3],
+        p1: [Real; 3],
+    },
+}
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum TransformDescription {
+    /// Degrees.
+    RotateY(Real),
+    Translate([Real; 3]),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ObjectDescription {
+    pub shape: ShapeDescription,
+    /// Name of one of the scene's materials.
+    pub material: String,
+    /// Applied to the shape in order.
+    #[serde(default)]
+    pub transforms: Vec<TransformDescription>,
+    /// The front face is the other side (lights on the ceiling facing down).
+    #[serde(default)]
+    pub flip_face: bool,
+    /// The object is sampled explicitly, like the lights (and the glass spheres that make
+    /// caustics) of the built in scenes.
+    #[serde(default)]
+    pub light: bool,
+}
+
+/// Scene read from a RON file instead of built by one of the `scene_*` functions. The
+/// materials and textures are named, the objects refer to the materials and the materials
+/// to the textures by their names.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SceneDescription {
+    #[serde(default)]
+    pub camera: Option<SceneCamera>,
+    #[serde(default)]
+    pub background: Option<[Real; 3]>,
+    #[serde(default)]
+    pub textures: HashMap<String, TextureDescription>,
+    pub materials: HashMap<String, MaterialDescription>,
+    pub objects: Vec<ObjectDescription>,
+}
+
+impl SceneDescription {
+    pub fn from_ron(text: &str) -> Result<SceneDescription, String> {
+        ron::de::from_str::<SceneDescription>(text).map_err(|e| e.to_string())
+    }
+
+    pub fn from_file<P: AsRef<std::path::Path>>(p: P) -> Result<SceneDescription, String> {
+        let text = std::fs::read_to_string(p.as_ref())
+            .map_err(|e| format!("Failed to open scene file {}: {}", p.as_ref().display(), e))?;
+
+        Self::from_ron(&text).map_err(|e| {
+            format!(
+                "Failed to decode scene file {}: {}",
+                p.as_ref().display(),
+                e
+            )
+        })
+    }
+
+    /// `params` with the camera and the background of the scene, if it has them.
+    pub fn params(&self, params: RaytracerParams) -> RaytracerParams {
+        let params = RaytracerParams {
+            background: self.background.unwrap_or(params.background),
+            ..params
+        };
+
+        match self.camera {
+            Some(cam) => RaytracerParams {
+                look_from: cam.look_from,
+                look_at: cam.look_at,
+                world_up: cam.world_up,
+                vertical_fov: cam.vertical_fov,
+                aperture: cam.aperture,
+                focus_dist: cam.focus_dist.unwrap_or_else(|| {
+                    math::vec3::length(Vec3::from(cam.look_at) - Vec3::from(cam.look_from))
+                }),
+                ..params
+            },
+            None => params,
+        }
+    }
+
+    /// The world and the objects in it that are sampled as lights.
+    pub fn build(&self) -> Result<(HittableList, HittableList), String> {
+        let textures = self
+            .textures
+            .iter()
+            .map(|(name, texture)| Ok((name.as_str(), Self::build_texture(texture)?)))
+            .collect::<Result<HashMap<_, _>, String>>()?;
+
+        let texture = |slot: &TextureSlot| -> Result<Arc<dyn Texture>, String> {
+            match slot {
+                TextureSlot::Color(color) => Ok(Arc::new(SolidColorTexture::new(*color))),
+                TextureSlot::Texture(name) => textures
+                    .get(name.as_str())
+                    .cloned()
+                    .ok_or_else(|| format!("Unknown texture {}", name)),
+            }
+        };
+
+        let materials = self
+            .materials
+            .iter()
+            .map(|(name, material)| {
+                let mtl: Arc<dyn Material> = match material {
+                    MaterialDescription::Lambertian { albedo } => {
+                        Arc::new(Lambertian::from_texture(texture(albedo)?))
+                    }
+                    MaterialDescription::Metal { albedo, fuzziness } => {
+                        Arc::new(Metal::new(*albedo, *fuzziness))
+                    }
+                    MaterialDescription::RoughMetal { albedo, roughness } => {
+                        Arc::new(RoughMetal::new(*albedo, *roughness))
+                    }
+                    MaterialDescription::Plastic {
+                        diffuse_albedo,
+                        specular_tint,
+                        roughness,
+                    } => Arc::new(PlasticMaterial::new(
+                        *diffuse_albedo,
+                        *specular_tint,
+                        *roughness,
+                    )),
+                    MaterialDescription::Dielectric { refraction_index } => {
+                        Arc::new(Dielectric::new(*refraction_index))
+                    }
+                    MaterialDescription::DiffuseLight { emit } => {
+                        Arc::new(DiffuseLight::with_texture(texture(emit)?))
+                    }
+                    MaterialDescription::Isotropic { albedo } => Arc::new(Isotropic {
+                        albedo: texture(albedo)?,
+                    }),
+                };
+                Ok((name.as_str(), mtl))
+            })
+            .collect::<Result<HashMap<_, _>, String>>()?;
+
+        let mut world = HittableList::new();
+        let mut lights = HittableList::new();
+        self.objects.iter().try_for_each(|object| {
+            let mtl = materials
+                .get(object.material.as_str())
+                .cloned()
+                .ok_or_else(|| format!("Unknown material {}", object.material))?;
+
+            let obj = object.transforms.iter().fold(
+                Self::build_shape(&object.shape, mtl),
+                |obj, transform| match *transform {
+                    TransformDescription::RotateY(angle) => Arc::new(RotateY::new(obj, angle)),
+                    TransformDescription::Translate(offset) => {
+                        Arc::new(Translate::new(obj, offset.into()))
+                    }
+                },
+            );
+            let obj: Arc<dyn Hittable> = if object.flip_face {
+                Arc::new(FlipFace { obj })
+            } else {
+                obj
+            };
+
+            if object.light {
+                lights.add(obj.clone());
+            }
+            world.add(obj);
+            Ok::<(), String>(())
+        })?;
+
+        Ok((world, lights))
+    }
+
+    fn build_texture(texture: &TextureDescription) -> Result<Arc<dyn Texture>, String> {
+        Ok(match texture {
+            TextureDescription::Solid(color) => Arc::new(SolidColorTexture::new(*color)),
+            TextureDescription::Checker {
+                odd,
+                even,
+                repeat_factor,
+            } => Arc::new(CheckerTexture::from_colors(*odd, *even, *repeat_factor)),
+            TextureDescription::Noise { scale } => Arc::new(NoiseTexture::new(*scale)),
+            TextureDescription::Image { path, srgb } => {
+                Arc::new(ImageTexture::load(path)?.with_srgb(*srgb))
+            }
+        })
+    }
+
+    fn build_shape(shape: &ShapeDescription, mtl: Arc<dyn Material>) -> Arc<dyn Hittable> {
+        match *shape {
+            ShapeDescription::Sphere { center, radius } => {
+                Arc::new(Sphere::new(Point::from(center), radius, mtl))
+            }
+            ShapeDescription::XYRect { x0, x1, y0, y1, k } => Arc::new(XYRect {
+                x0,
+                x1,
+                y0,
+                y1,
+                k,
+                mtl,
+            }),
+            ShapeDescription::XZRect { x0, x1, z0, z1, k } => Arc::new(XZRect {
+                x0,
+                x1,
+                z0,
+                z1,
+                k,
+                mtl,
+            }),
+            ShapeDescription::YZRect { y0, y1, z0, z1, k } => Arc::new(YZRect {
+                y0,
+                y1,
+                z0,
+                z1,
+                k,
+                mtl,
+            }),
+            ShapeDescription::Block { p0, p1 } => {
+                Arc::new(Block::new(Point::from(p0), Point::from(p1), mtl))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{random_unit_vector, Ray, C_INFINITY};
+
+    const CORNELL_BOX: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../data/scenes/cornell_box.ron"
+    );
+
+    #[test]
+    fn test_cornell_box_file_matches_the_built_in_scene() {
+        let description = SceneDescription::from_file(CORNELL_BOX).unwrap();
+        let (world, lights) = description.build().unwrap();
+        let (expected_world, expected_lights) = crate::scene_cornell_box();
+        assert_eq!(lights.objects_len(), expected_lights.objects_len());
+
+        let params = description.params(crate::tests::test_params(None));
+        assert_eq!(params.look_from, [278 as Real, 278 as Real, -800 as Real]);
+        assert_eq!(params.focus_dist, 10 as Real);
+        assert_eq!(params.background, [0 as Real; 3]);
+
+        //
+        // rays in all directions from inside the box (open at z = 0) see the same surfaces
+        // and lights
+        let origin = Point::new(278 as Real, 400 as Real, 100 as Real);
+        (0..500).for_each(|_| {
+            let r = Ray::new(origin, random_unit_vector(), 0 as Real);
+            match (
+                world.hit(&r, 0.001, C_INFINITY),
+                expected_world.hit(&r, 0.001, C_INFINITY),
+            ) {
+                (Some(hit), Some(expected)) => {
+                    assert!(
+                        (hit.t - expected.t).abs() < 1.0e-3,
+                        "{} {}",
+                        hit.t,
+                        expected.t
+                    );
+                    let emitted = hit.mtl.emitted(&r, &hit, hit.u, hit.v, hit.p);
+                    let expected_emitted = expected
+                        .mtl
+                        .emitted(&r, &expected, expected.u, expected.v, expected.p);
+                    assert_eq!(emitted.r, expected_emitted.r);
+                }
+                (None, None) => {}
+                (hit, expected) => panic!("{:?} {:?}", hit.map(|h| h.t), expected.map(|h| h.t)),
+            }
+
+            let v = random_unit_vector();
+            let pdf = lights.pdf_value(origin, v);
+            assert!((pdf - expected_lights.pdf_value(origin, v)).abs() < 1.0e-6);
+        });
+    }
+
+    #[test]
+    fn test_metals_file_builds() {
+        let description = SceneDescription::from_file(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../data/scenes/metals.ron"
+        ))
+        .unwrap();
+        let (world, lights) = description.build().unwrap();
+        assert_eq!(world.objects_len(), 10);
+        assert_eq!(lights.objects_len(), 1);
+    }
+
+    #[test]
+    fn test_plastic_file_builds() {
+        let description = SceneDescription::from_file(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../data/scenes/plastic.ron"
+        ))
+        .unwrap();
+        let (world, lights) = description.build().unwrap();
+        assert_eq!(world.objects_len(), 9);
+        assert_eq!(lights.objects_len(), 1);
+    }
+
+    #[test]
+    fn test_unknown_names_are_errors() {
+        let scene = |material: &str, albedo: &str| {
+            SceneDescription::from_ron(&format!(
+                "(materials: {{\"gray\": Lambertian(albedo: {})}}, \
+                 objects: [(shape: Sphere(center: (0.0, 0.0, 0.0), radius: 1.0), \
+                 material: \"{}\")])",
+                albedo, material
+            ))
+            .unwrap()
+            .build()
+            .map(|(world, _)| world.objects_len())
+        };
+
+        assert_eq!(scene("gray", "Color((0.5, 0.5, 0.5))"), Ok(1));
+        assert_eq!(
+            scene("grey", "Color((0.5, 0.5, 0.5))"),
+            Err("Unknown material grey".to_string())
+        );
+        assert_eq!(
+            scene("gray", "Texture(\"checker\")"),
+            Err("Unknown texture checker".to_string())
+        );
+        assert!(SceneDescription::from_ron("(objects: [])").is_err());
+    }
+
+    #[test]
+    fn test_missing_image_texture_is_an_error() {
+        let description = SceneDescription::from_ron(
+            "(textures: {\"wood\": Image(path: \"no/such/image.png\")}, \
+             materials: {\"floor\": Lambertian(albedo: Texture(\"wood\"))}, \
+             objects: [(shape: Sphere(center: (0.0, 0.0, 0.0), radius: 1.0), \
+             material: \"floor\")])",
+        )
+        .unwrap();
+
+        let err = description.build().map(|_| ()).unwrap_err();
+        assert!(err.contains("no/such/image.png"), "{}", err);
+    }
+}
@@ -0,0 +1,292 @@
+use std::sync::Arc;
+
+use math::vec3::{length, max_component, normalize};
+
+use crate::{
+    aabb3::Aabb,
+    hittable::{HitRecord, Hittable},
+    material::Material,
+    types::{Point, Ray, Real, Vec2, Vec3, C_ZERO},
+};
+
+/// Signed distance function, negative inside the object.
+pub type SdfFn = Box<dyn Fn(Vec3) -> Real + Send + Sync>;
+
+/// Distance at which a point is considered to be on the surface.
+pub(crate) const HIT_EPSILON: Real = 1.0E-4 as Real;
+
+/// Sphere tracing of the ray over [t_start, t_end], the parameter of the first point
+/// closer than `HIT_EPSILON` to the surface. Steps are the distance times `step_scale`,
+/// which must make up for the overestimation of `distance` and for the length of the
+/// ray direction. A ray starting on the surface (a bounce off it) only hits once it got
+/// farther than `2 * HIT_EPSILON` from the surface, grazing rays would otherwise hit the
+/// surface they leave.
+pub(crate) fn sphere_trace<F: Fn(Point) -> Real>(
+    distance: F,
+    r: &Ray,
+    (t_start, t_end): (Real, Real),
+    step_scale: Real,
+    max_steps: u32,
+) -> Option<Real> {
+    const LEAVE_DISTANCE: Real = 2 as Real * HIT_EPSILON;
+
+    let mut leaving = distance(r.origin).abs() < LEAVE_DISTANCE;
+    let mut t = t_start;
+    for _ in 0..max_steps {
+        if t > t_end {
+            return None;
+        }
+
+        //
+        // the distance is unsigned, so that rays starting inside the object find the surface too
+        let distance = distance(r.at(t)).abs();
+        leaving = leaving && distance < LEAVE_DISTANCE;
+
+        if !leaving && distance < HIT_EPSILON {
+            return Some(t);
+        }
+
+        t += distance.max(HIT_EPSILON) * step_scale;
+    }
+
+    None
+}
+
+/// Normal of the surface at p, the gradient of the distance by central differences.
+pub(crate) fn gradient_normal<F: Fn(Point) -> Real>(distance: F, p: Point) -> Vec3 {
+    const H: Real = 1.0E-4 as Real;
+
+    let d = |offset: Vec3| distance(p + offset) - distance(p - offset);
+    normalize(Vec3::new(
+        d(Vec3::new(H, C_ZERO, C_ZERO)),
+        d(Vec3::new(C_ZERO, H, C_ZERO)),
+        d(Vec3::new(C_ZERO, C_ZERO, H)),
+    ))
+}
+
+/// Object defined by a signed distance field, intersected by sphere tracing inside `bounds`.
+/// The distance function must not overestimate the distance to the surface.
+pub struct SdfObject {
+    sdf: SdfFn,
+    bounds: Aabb,
+    mtl: Arc<dyn Material>,
+}
+
+impl SdfObject {
+    const MAX_STEPS: u32 = 256;
+
+    pub fn new<F>(sdf: F, bounds: Aabb, mtl: Arc<dyn Material>) -> SdfObject
+    where
+        F: Fn(Vec3) -> Real + Send + Sync + 'static,
+    {
+        SdfObject {
+            sdf: Box::new(sdf),
+            bounds,
+            mtl,
+        }
+    }
+
+    pub fn sphere(radius: Real, mtl: Arc<dyn Material>) -> SdfObject {
+        Self::new(
+            sphere(radius),
+            Aabb::new(Point::broadcast(-radius), Point::broadcast(radius)),
+            mtl,
+        )
+    }
+
+    pub fn cube(half_extents: Vec3, mtl: Arc<dyn Material>) -> SdfObject {
+        Self::new(
+            cube(half_extents),
+            Aabb::new(-half_extents, half_extents),
+            mtl,
+        )
+    }
+
+    /// Torus around the y axis.
+    pub fn torus(major_radius: Real, minor_radius: Real, mtl: Arc<dyn Material>) -> SdfObject {
+        let extent = Vec3::new(
+            major_radius + minor_radius,
+            minor_radius,
+            major_radius + minor_radius,
+        );
+
+        Self::new(
+            torus(major_radius, minor_radius),
+            Aabb::new(-extent, extent),
+            mtl,
+        )
+    }
+
+    //
+    // parametric range of the ray inside the bounds
+    fn bounds_interval(&self, r: &Ray, t_min: Real, t_max: Real) -> Option<(Real, Real)> {
+        (0..3).try_fold((t_min, t_max), |(t0, t1), a| {
+            let inv_d = 1 as Real / r.direction[a];
+            let ta = (self.bounds.min[a] - r.origin[a]) * inv_d;
+            let tb = (self.bounds.max[a] - r.origin[a]) * inv_d;
+
+            let t0 = t0.max(ta.min(tb));
+            let t1 = t1.min(ta.max(tb));
+
+            if t1 < t0 {
+                None
+            } else {
+                Some((t0, t1))
+            }
+        })
+    }
+}
+
+impl Hittable for SdfObject {
+    fn hit(&self, r: &Ray, t_min: Real, t_max: Real) -> Option<HitRecord> {
+        let t = sphere_trace(
+            &self.sdf,
+            r,
+            self.bounds_interval(r, t_min, t_max)?,
+            1 as Real / length(r.direction),
+            Self::MAX_STEPS,
+        )?;
+        let p = r.at(t);
+
+        Some(HitRecord::new(
+            p,
+            gradient_normal(&self.sdf, p),
+            r,
+            t,
+            self.mtl.clone(),
+            C_ZERO,
+            C_ZERO,
+        ))
+    }
+
+    fn bounding_box(&self, _time0: Real, _time1: Real) -> Option<Aabb> {
+        Some(self.bounds)
+    }
+}
+
+pub fn sphere(radius: Real) -> impl Fn(Vec3) -> Real + Send + Sync {
+    move |p: Vec3| length(p) - radius
+}
+
+/// Box centered at the origin.
+pub fn cube(half_extents: Vec3) -> impl Fn(Vec3) -> Real + Send + Sync {
+    move |p: Vec3| {
+        let q = Vec3::new(
+            p.x.abs() - half_extents.x,
+            p.y.abs() - half_extents.y,
+            p.z.abs() - half_extents.z,
+        );
+
+        length(math::vec3::max(q, C_ZERO)) + max_component(q).min(C_ZERO)
+    }
+}
+
+/// Torus around the y axis.
+pub fn torus(major_radius: Real, minor_radius: Real) -> impl Fn(Vec3) -> Real + Send + Sync {
+    move |p: Vec3| {
+        let q = Vec2::new((p.x * p.x + p.z * p.z).sqrt() - major_radius, p.y);
+
+        (q.x * q.x + q.y * q.y).sqrt() - minor_radius
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lambertian::Lambertian;
+    use crate::objects::sphere::Sphere;
+    use crate::types::{random_real_range, random_unit_vector};
+    use math::vec3::dot;
+
+    #[test]
+    fn test_sdf_sphere_matches_analytic_sphere() {
+        let mtl: Arc<dyn Material> = Arc::new(Lambertian::new((0.5, 0.5, 0.5)));
+        let sdf_sphere = SdfObject::sphere(1 as Real, mtl.clone());
+        let sphere = Sphere::new(Point::broadcast(C_ZERO), 1 as Real, mtl);
+
+        let mut hits = 0;
+        (0..500).for_each(|_| {
+            //
+            // rays from outside, aimed at points around the sphere
+            let origin = random_unit_vector() * random_real_range(2 as Real, 5 as Real);
+            let target = random_unit_vector() * random_real_range(0 as Real, 1.5 as Real);
+            let r = Ray::new(origin, target - origin, C_ZERO);
+
+            let expected = sphere.hit(&r, 0.001 as Real, Real::MAX);
+            let traced = sdf_sphere.hit(&r, 0.001 as Real, Real::MAX);
+
+            //
+            // grazing rays converge slowly and can go either way within the tolerance
+            let closest = length(math::vec3::cross(normalize(r.direction), -r.origin));
+            if (closest - 1 as Real).abs() < 0.02 as Real {
+                return;
+            }
+
+            match (expected, traced) {
+                (Some(expected), Some(traced)) => {
+                    hits += 1;
+                    assert!(length(expected.p - traced.p) < 1.0e-3);
+                    assert!(dot(expected.normal, traced.normal) > 0.999 as Real);
+                    assert_eq!(expected.front_face, traced.front_face);
+                }
+                (expected, traced) => assert_eq!(expected.is_some(), traced.is_some()),
+            }
+        });
+
+        assert!(hits > 100);
+
+        //
+        // a ray starting inside finds the far side
+        let inside = Ray::new(
+            Point::broadcast(C_ZERO),
+            Vec3::new(1 as Real, C_ZERO, C_ZERO),
+            C_ZERO,
+        );
+        let hit = sdf_sphere.hit(&inside, 0.001 as Real, Real::MAX).unwrap();
+        assert!((hit.t - 1 as Real).abs() < 1.0e-3);
+        assert!(!hit.front_face);
+    }
+
+    #[test]
+    fn test_grazing_rays_leave_the_surface() {
+        let mtl: Arc<dyn Material> = Arc::new(Lambertian::new((0.5, 0.5, 0.5)));
+        let sdf_sphere = SdfObject::sphere(1 as Real, mtl);
+
+        //
+        // rays bouncing off the top of the sphere, at cosines down to 0.02 to the normal
+        [0.3 as Real, 0.1, 0.05, 0.02].iter().for_each(|&cos| {
+            let leaving = Ray::new(
+                Point::new(C_ZERO, 1 as Real, C_ZERO),
+                Vec3::new((1 as Real - cos * cos).sqrt(), cos, C_ZERO),
+                C_ZERO,
+            );
+            assert!(
+                sdf_sphere.hit(&leaving, 0.001 as Real, Real::MAX).is_none(),
+                "{}",
+                cos
+            );
+        });
+
+        //
+        // refracted into the sphere at a grazing angle, the far side is hit
+        let entering = Ray::new(
+            Point::new(C_ZERO, 1 as Real, C_ZERO),
+            Vec3::new(1 as Real, -0.05 as Real, C_ZERO),
+            C_ZERO,
+        );
+        let hit = sdf_sphere.hit(&entering, 0.001 as Real, Real::MAX).unwrap();
+        assert!(hit.t > 0.05 as Real);
+        assert!(!hit.front_face);
+    }
+
+    #[test]
+    fn test_example_sdfs() {
+        let p = Vec3::new(2 as Real, C_ZERO, C_ZERO);
+
+        assert!((cube(Vec3::broadcast(1 as Real))(p) - 1 as Real).abs() < 1.0e-6);
+        assert!(
+            (cube(Vec3::broadcast(1 as Real))(Point::broadcast(C_ZERO)) + 1 as Real).abs() < 1.0e-6
+        );
+        assert!((torus(2 as Real, 0.5 as Real)(p) + 0.5 as Real).abs() < 1.0e-6);
+    }
+}
@@ -0,0 +1,766 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    ops::Range,
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    aabb3::{merge_aabbs, Aabb},
+    geometry_import::GeometryVertex,
+    hittable::{HitRecord, HitStats, Hittable},
+    material::Material,
+    triangle_mesh::{intersect_triangle, Shading},
+    types::{Mat3, Mat4, Ray, Real, Vec2, Vec3, Vec4},
+};
+
+const AABB_MIN_EXTENT: Real = 1.0E-4;
+
+/// Maximum number of triangles in a leaf of the BVH, the unit that is decoded and cached.
+const LEAF_TRIANGLES: usize = 64;
+
+/// Caches the leaf budget is split over, a ray only locks the cache of the leaf it reaches.
+const CACHE_SHARDS: usize = 8;
+
+/// Deepest tree the traversal stack can hold, the median split halves the triangles at
+/// every level.
+const MAX_DEPTH: usize = 64;
+
+/// Where a `StreamingMesh` decodes its triangles from, on demand.
+pub trait TriangleSource: Send + Sync {
+    fn triangle_count(&self) -> usize;
+
+    /// Object space vertices of triangle `i`.
+    fn triangle(&self, i: usize) -> [GeometryVertex; 3];
+}
+
+/// Indexed triangle list, for meshes that are generated instead of imported.
+pub struct IndexedTriangles {
+    pub vertices: Vec<GeometryVertex>,
+    pub indices: Vec<u32>,
+}
+
+impl TriangleSource for IndexedTriangles {
+    fn triangle_count(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    fn triangle(&self, i: usize) -> [GeometryVertex; 3] {
+        let idx = &self.indices[i * 3..i * 3 + 3];
+        [0, 1, 2].map(|k| self.vertices[idx[k] as usize])
+    }
+}
+
+/// Triangles of a binary glTF file (.glb), decoded from the memory mapped file when they
+/// are asked for. Only where the accessors are in the file and the nodes' transforms are
+/// kept, the vertices come out with the transforms applied like the ones of
+/// `ImportedGeometry`.
+pub struct MappedGltfTriangles {
+    mapping: mmapio::Mmap,
+    primitives: Vec<MappedPrimitive>,
+    /// Triangles before each primitive, the total at the end.
+    first_triangles: Vec<usize>,
+}
+
+#[derive(Copy, Clone, Debug)]
+struct MappedAccessor {
+    /// Where the first element starts in the mapping.
+    offset: usize,
+    stride: usize,
+    count: usize,
+    data_type: gltf::accessor::DataType,
+    normalized: bool,
+}
+
+struct MappedPrimitive {
+    positions: MappedAccessor,
+    normals: Option<MappedAccessor>,
+    tex_coords: Option<MappedAccessor>,
+    indices: Option<MappedAccessor>,
+    transform: Mat4,
+    normals_transform: Mat4,
+}
+
+impl MappedGltfTriangles {
+    pub fn open<P: AsRef<std::path::Path>>(path: P) -> Result<MappedGltfTriangles, String> {
+        let file = std::fs::File::open(path.as_ref()).map_err(|e| e.to_string())?;
+        let mapping =
+            unsafe { mmapio::MmapOptions::new().map(&file) }.map_err(|e| e.to_string())?;
+
+        let (document, bin) = {
+            let glb = gltf::Glb::from_slice(&mapping).map_err(|e| e.to_string())?;
+            let bin = glb
+                .bin
+                .ok_or("No binary chunk, only .glb files can be mapped")?;
+            //
+            // the chunks borrow from the mapping, the binary one is found by its address
+            let start = bin.as_ptr() as usize - mapping.as_ptr() as usize;
+            let root = gltf::json::Root::from_slice(&glb.json).map_err(|e| e.to_string())?;
+            (
+                gltf::Document::from_json(root).map_err(|e| e.to_string())?,
+                start..start + bin.len(),
+            )
+        };
+
+        let mut triangles = MappedGltfTriangles {
+            mapping,
+            primitives: Vec::new(),
+            first_triangles: vec![0],
+        };
+        for scene in document.scenes() {
+            for node in scene.nodes() {
+                triangles.add_node(&node, &bin, math::mat4::consts::identity())?;
+            }
+        }
+
+        Ok(triangles)
+    }
+
+    //
+    // children first, the same order as ImportedGeometry
+    fn add_node(
+        &mut self,
+        node: &gltf::Node,
+        bin: &Range<usize>,
+        parent: Mat4,
+    ) -> Result<(), String> {
+        let transform = parent * crate::geometry_import::node_matrix(node);
+        for child in node.children() {
+            self.add_node(&child, bin, transform)?;
+        }
+
+        let mesh = match node.mesh() {
+            Some(mesh) => mesh,
+            None => return Ok(()),
+        };
+
+        let normals_transform = math::mat4::invert(&transform).transpose();
+        for primitive in mesh.primitives() {
+            if primitive.mode() != gltf::mesh::Mode::Triangles {
+                return Err(format!(
+                    "Primitive {} is not a triangle list",
+                    primitive.index()
+                ));
+            }
+
+            let positions = primitive
+                .get(&gltf::Semantic::Positions)
+                .ok_or_else(|| format!("Missing positions on primitive {}", primitive.index()))
+                .and_then(|a| Self::map_accessor(&a, bin))?;
+            let normals = primitive
+                .get(&gltf::Semantic::Normals)
+                .map(|a| Self::map_accessor(&a, bin))
+                .transpose()?;
+            let tex_coords = primitive
+                .get(&gltf::Semantic::TexCoords(0))
+                .map(|a| Self::map_accessor(&a, bin))
+                .transpose()?;
+            let indices = primitive
+                .indices()
+                .map(|a| Self::map_accessor(&a, bin))
+                .transpose()?;
+
+            let count = indices.as_ref().map_or(positions.count, |idx| idx.count) / 3;
+            self.first_triangles
+                .push(self.first_triangles.last().unwrap() + count);
+            self.primitives.push(MappedPrimitive {
+                positions,
+                normals,
+                tex_coords,
+                indices,
+                transform,
+                normals_transform,
+            });
+        }
+
+        Ok(())
+    }
+
+    fn map_accessor(
+        accessor: &gltf::Accessor,
+        bin: &Range<usize>,
+    ) -> Result<MappedAccessor, String> {
+        if accessor.sparse().is_some() {
+            return Err(format!("Accessor {} is sparse", accessor.index()));
+        }
+        let view = accessor
+            .view()
+            .ok_or_else(|| format!("Accessor {} has no buffer view", accessor.index()))?;
+        if !matches!(view.buffer().source(), gltf::buffer::Source::Bin) {
+            return Err(format!(
+                "Accessor {} is not in the binary chunk",
+                accessor.index()
+            ));
+        }
+
+        let mapped = MappedAccessor {
+            offset: bin.start + view.offset() + accessor.offset(),
+            stride: view.stride().unwrap_or(accessor.size()),
+            count: accessor.count(),
+            data_type: accessor.data_type(),
+            normalized: accessor.normalized(),
+        };
+        let end = mapped.offset + mapped.stride * mapped.count.saturating_sub(1) + accessor.size();
+        if end > bin.end {
+            return Err(format!(
+                "Accessor {} is past the binary chunk",
+                accessor.index()
+            ));
+        }
+
+        Ok(mapped)
+    }
+
+    //
+    // component k of element i, as a float (normalized integers are mapped to [0, 1])
+    fn read(&self, accessor: &MappedAccessor, i: usize, k: usize) -> Real {
+        use gltf::accessor::DataType;
+
+        let at = accessor.offset + i * accessor.stride + k * accessor.data_type.size();
+        let bytes = &self.mapping[at..at + accessor.data_type.size()];
+        let (value, max) = match accessor.data_type {
+            DataType::F32 => {
+                return f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as Real
+            }
+            DataType::U8 => (bytes[0] as Real, u8::MAX as Real),
+            DataType::U16 => (
+                u16::from_le_bytes([bytes[0], bytes[1]]) as Real,
+                u16::MAX as Real,
+            ),
+            DataType::U32 => (
+                u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as Real,
+                u32::MAX as Real,
+            ),
+            DataType::I8 => (bytes[0] as i8 as Real, i8::MAX as Real),
+            DataType::I16 => (
+                i16::from_le_bytes([bytes[0], bytes[1]]) as Real,
+                i16::MAX as Real,
+            ),
+        };
+
+        if accessor.normalized {
+            value / max
+        } else {
+            value
+        }
+    }
+
+    fn read_index(&self, accessor: &MappedAccessor, i: usize) -> usize {
+        use gltf::accessor::DataType;
+
+        let at = accessor.offset + i * accessor.stride;
+        let bytes = &self.mapping[at..];
+        match accessor.data_type {
+            DataType::U8 => bytes[0] as usize,
+            DataType::U16 => u16::from_le_bytes([bytes[0], bytes[1]]) as usize,
+            _ => u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize,
+        }
+    }
+
+    fn vertex(&self, primitive: &MappedPrimitive, i: usize) -> GeometryVertex {
+        assert!(
+            i < primitive.positions.count,
+            "Vertex index {} out of range",
+            i
+        );
+        let vec3 = |accessor: &MappedAccessor, w: Real| {
+            Vec4::new(
+                self.read(accessor, i, 0),
+                self.read(accessor, i, 1),
+                self.read(accessor, i, 2),
+                w,
+            )
+        };
+
+        GeometryVertex {
+            pos: (primitive.transform * vec3(&primitive.positions, 1 as Real)).xyz(),
+            normal: primitive
+                .normals
+                .as_ref()
+                .map_or(Vec3::broadcast(0 as Real), |n| {
+                    math::vec3::normalize((primitive.normals_transform * vec3(n, 0 as Real)).xyz())
+                }),
+            uv: primitive
+                .tex_coords
+                .as_ref()
+                .map_or(Vec2::broadcast(0 as Real), |uv| {
+                    Vec2::new(self.read(uv, i, 0), self.read(uv, i, 1))
+                }),
+            ..GeometryVertex::default()
+        }
+    }
+}
+
+impl TriangleSource for MappedGltfTriangles {
+    fn triangle_count(&self) -> usize {
+        *self.first_triangles.last().unwrap()
+    }
+
+    fn triangle(&self, i: usize) -> [GeometryVertex; 3] {
+        let p = self.first_triangles.partition_point(|&first| first <= i) - 1;
+        let primitive = &self.primitives[p];
+        let first = (i - self.first_triangles[p]) * 3;
+
+        [0, 1, 2].map(|k| {
+            let vertex = match primitive.indices.as_ref() {
+                Some(indices) => self.read_index(indices, first + k),
+                None => first + k,
+            };
+            self.vertex(primitive, vertex)
+        })
+    }
+}
+
+/// Counters of the decoded leaves cache of a `StreamingMesh`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct StreamingStats {
+    /// Leaves decoded from the source, a leaf evicted and needed again counts again.
+    pub loads: usize,
+    /// Leaves in the cache now.
+    pub resident: usize,
+    /// Most leaves that were in the cache at the same time (summed over the shards of
+    /// the cache, an upper bound).
+    pub peak_resident: usize,
+}
+
+enum TreeNode {
+    Interior { bbox: Aabb, children: [usize; 2] },
+    Leaf { bbox: Aabb, leaf: usize },
+}
+
+type DecodedLeaf = Arc<Vec<[GeometryVertex; 3]>>;
+
+struct LeafCache {
+    budget: usize,
+    leaves: HashMap<usize, DecodedLeaf>,
+    /// Leaf ids, the least recently used first.
+    recency: VecDeque<usize>,
+    stats: StreamingStats,
+}
+
+impl LeafCache {
+    fn get(&mut self, leaf: usize) -> Option<DecodedLeaf> {
+        let triangles = self.leaves.get(&leaf)?.clone();
+        //
+        // linear in the budget, which is a handful of leaves
+        if let Some(pos) = self.recency.iter().position(|&l| l == leaf) {
+            self.recency.remove(pos);
+        }
+        self.recency.push_back(leaf);
+        Some(triangles)
+    }
+
+    fn insert(&mut self, leaf: usize, triangles: DecodedLeaf) {
+        //
+        // decoded by another thread in the meantime
+        if self.leaves.contains_key(&leaf) {
+            return;
+        }
+
+        while self.leaves.len() >= self.budget {
+            match self.recency.pop_front() {
+                Some(evicted) => {
+                    self.leaves.remove(&evicted);
+                }
+                None => break,
+            }
+        }
+
+        self.leaves.insert(leaf, triangles);
+        self.recency.push_back(leaf);
+        self.stats.loads += 1;
+        self.stats.resident = self.leaves.len();
+        self.stats.peak_resident = self.stats.peak_resident.max(self.stats.resident);
+    }
+}
+
+/// Triangle mesh for models that don't fit in memory once decoded. Only a BVH over groups
+/// of triangles (the leaves) is built up front, the world space vertices of a leaf are
+/// decoded from `source` when a ray reaches it and kept in a cache of at most
+/// `leaf_budget` leaves, the least recently used leaf is evicted to make room. The cache is
+/// split in shards (by leaf) with a share of the budget each, so the render threads don't
+/// wait on a single lock. Shaded like a smooth `TriangleMesh` with a single material.
+pub struct StreamingMesh {
+    source: Arc<dyn TriangleSource>,
+    obj2world: Mat4,
+    normals2world: Mat3,
+    /// Triangle ids of the source, the ones of a leaf are contiguous.
+    order: Vec<u32>,
+    leaves: Vec<Range<usize>>,
+    /// The root is the last node.
+    nodes: Vec<TreeNode>,
+    mtl: Arc<dyn Material>,
+    cache: Vec<Mutex<LeafCache>>,
+}
+
+impl StreamingMesh {
+    pub fn new(
+        source: Arc<dyn TriangleSource>,
+        obj2world: Mat4,
+        mtl: Arc<dyn Material>,
+        leaf_budget: usize,
+    ) -> StreamingMesh {
+        assert!(
+            source.triangle_count() > 0,
+            "Streaming mesh without triangles"
+        );
+
+        //
+        // one pass over the triangles for their bounds, nothing else is kept
+        let bounds = (0..source.triangle_count())
+            .map(|i| {
+                source
+                    .triangle(i)
+                    .iter()
+                    .fold(Aabb::default(), |mut bbox, vtx| {
+                        bbox.add_point(math::mat4::transform_point(&obj2world, vtx.pos));
+                        bbox
+                    })
+            })
+            .collect::<Vec<_>>();
+
+        let mut order = (0..bounds.len() as u32).collect::<Vec<_>>();
+        let mut nodes = Vec::new();
+        let mut leaves = Vec::new();
+        Self::build_tree(&bounds, &mut order, 0, &mut nodes, &mut leaves);
+
+        StreamingMesh {
+            source,
+            obj2world,
+            normals2world: Mat3::normal_matrix(&obj2world),
+            order,
+            leaves,
+            nodes,
+            mtl,
+            cache: Self::make_cache(leaf_budget.max(1)),
+        }
+    }
+
+    /// Streams the triangles of a binary glTF model from the mapped file, see `new` and
+    /// `MappedGltfTriangles`.
+    pub fn from_file<P: AsRef<std::path::Path>>(
+        p: P,
+        obj2world: Mat4,
+        mtl: Arc<dyn Material>,
+        leaf_budget: usize,
+    ) -> StreamingMesh {
+        let triangles = MappedGltfTriangles::open(&p)
+            .unwrap_or_else(|e| panic!("Failed to map mesh {} : {}", p.as_ref().display(), e));
+
+        Self::new(Arc::new(triangles), obj2world, mtl, leaf_budget)
+    }
+
+    //
+    // at most the budget over all the shards, at least a leaf per shard
+    fn make_cache(budget: usize) -> Vec<Mutex<LeafCache>> {
+        let shards = CACHE_SHARDS.min(budget);
+        (0..shards)
+            .map(|_| {
+                Mutex::new(LeafCache {
+                    budget: budget / shards,
+                    leaves: HashMap::new(),
+                    recency: VecDeque::new(),
+                    stats: StreamingStats::default(),
+                })
+            })
+            .collect()
+    }
+
+    pub fn streaming_stats(&self) -> StreamingStats {
+        self.cache
+            .iter()
+            .map(|shard| shard.lock().unwrap().stats)
+            .fold(StreamingStats::default(), |total, stats| StreamingStats {
+                loads: total.loads + stats.loads,
+                resident: total.resident + stats.resident,
+                peak_resident: total.peak_resident + stats.peak_resident,
+            })
+    }
+
+    //
+    // median split on the longest axis of the centroids' box, returns the subtree's node
+    fn build_tree(
+        bounds: &[Aabb],
+        order: &mut [u32],
+        offset: usize,
+        nodes: &mut Vec<TreeNode>,
+        leaves: &mut Vec<Range<usize>>,
+    ) -> usize {
+        let bbox = order.iter().fold(Aabb::default(), |bbox, &tri| {
+            merge_aabbs(&bbox, &bounds[tri as usize])
+        });
+
+        if order.len() <= LEAF_TRIANGLES {
+            leaves.push(offset..offset + order.len());
+            nodes.push(TreeNode::Leaf {
+                bbox: bbox.padded(AABB_MIN_EXTENT),
+                leaf: leaves.len() - 1,
+            });
+            return nodes.len() - 1;
+        }
+
+        let centroids = order.iter().fold(Aabb::default(), |mut cbox, &tri| {
+            cbox.add_point(bounds[tri as usize].center());
+            cbox
+        });
+        let extent = centroids.max - centroids.min;
+        let axis = (0..3)
+            .max_by(|&a, &b| extent[a].total_cmp(&extent[b]))
+            .unwrap_or(0);
+
+        let mid = order.len() / 2;
+        order.select_nth_unstable_by(mid, |&a, &b| {
+            bounds[a as usize].center()[axis].total_cmp(&bounds[b as usize].center()[axis])
+        });
+        let (left, right) = order.split_at_mut(mid);
+        let children = [
+            Self::build_tree(bounds, left, offset, nodes, leaves),
+            Self::build_tree(bounds, right, offset + mid, nodes, leaves),
+        ];
+
+        nodes.push(TreeNode::Interior {
+            bbox: bbox.padded(AABB_MIN_EXTENT),
+            children,
+        });
+        nodes.len() - 1
+    }
+
+    fn decoded_leaf(&self, leaf: usize) -> DecodedLeaf {
+        let shard = &self.cache[leaf % self.cache.len()];
+        if let Some(triangles) = shard.lock().unwrap().get(leaf) {
+            return triangles;
+        }
+
+        //
+        // decoded without holding the lock
+        let triangles = Arc::new(
+            self.order[self.leaves[leaf].clone()]
+                .iter()
+                .map(|&tri| {
+                    self.source
+                        .triangle(tri as usize)
+                        .map(|vtx| GeometryVertex {
+                            pos: math::mat4::transform_point(&self.obj2world, vtx.pos),
+                            normal: math::vec3::normalize(self.normals2world * vtx.normal),
+                            ..vtx
+                        })
+                })
+                .collect::<Vec<_>>(),
+        );
+        shard.lock().unwrap().insert(leaf, triangles.clone());
+        triangles
+    }
+}
+
+impl Hittable for StreamingMesh {
+    fn bounding_box(&self, _time0: Real, _time1: Real) -> Option<Aabb> {
+        match self.nodes.last() {
+            Some(TreeNode::Interior { bbox, .. }) | Some(TreeNode::Leaf { bbox, .. }) => {
+                Some(*bbox)
+            }
+            None => None,
+        }
+    }
+
+    fn name(&self) -> Option<&str> {
+        Some("streaming mesh")
+    }
+
+    fn hit(&self, r: &Ray, t_min: Real, t_max: Real) -> Option<HitRecord> {
+        self.hit_counted(r, t_min, t_max, &mut HitStats::default())
+    }
+
+    fn hit_counted(
+        &self,
+        r: &Ray,
+        t_min: Real,
+        t_max: Real,
+        stats: &mut HitStats,
+    ) -> Option<HitRecord> {
+        let mut closest: Option<HitRecord> = None;
+        let mut stack = [0usize; MAX_DEPTH];
+        stack[0] = self.nodes.len() - 1;
+        let mut stack_size = 1;
+
+        while stack_size > 0 {
+            stack_size -= 1;
+            let node = stack[stack_size];
+            stats.node_visits += 1;
+            let t_max = closest.as_ref().map_or(t_max, |hit| hit.t);
+
+            match &self.nodes[node] {
+                TreeNode::Interior { bbox, children } => {
+                    if bbox.hit(r, t_min, t_max) {
+                        stack[stack_size..stack_size + 2].copy_from_slice(children);
+                        stack_size += 2;
+                    }
+                }
+                TreeNode::Leaf { bbox, leaf } => {
+                    if !bbox.hit(r, t_min, t_max) {
+                        continue;
+                    }
+
+                    closest = self
+                        .decoded_leaf(*leaf)
+                        .iter()
+                        .fold(closest, |closest, tri| {
+                            stats.primitive_tests += 1;
+                            let t_max = closest.as_ref().map_or(t_max, |hit: &HitRecord| hit.t);
+                            intersect_triangle(
+                                [&tri[0], &tri[1], &tri[2]],
+                                r,
+                                t_min,
+                                t_max,
+                                Shading::Smooth,
+                                false,
+                                &self.mtl,
+                            )
+                            .or(closest)
+                        });
+                }
+            }
+        }
+
+        closest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        geometry_import::GeometryNode,
+        lambertian::Lambertian,
+        triangle_mesh::TriangleMesh,
+        types::{random_real, Vec3, C_INFINITY, C_ONE, C_ZERO},
+    };
+
+    //
+    // wavy grid of n x n quads in the xz plane, normals pointing up
+    fn wavy_grid(n: usize) -> IndexedTriangles {
+        let vertices = (0..=n)
+            .flat_map(|z| (0..=n).map(move |x| (x, z)))
+            .map(|(x, z)| {
+                let (x, z) = (x as Real / n as Real, z as Real / n as Real);
+                GeometryVertex {
+                    pos: Vec3::new(x, 0.1 as Real * (8 as Real * x).sin() * z, z),
+                    normal: Vec3::new(C_ZERO, C_ONE, C_ZERO),
+                    ..Default::default()
+                }
+            })
+            .collect();
+        let indices = (0..n)
+            .flat_map(|z| (0..n).map(move |x| (x, z)))
+            .flat_map(|(x, z)| {
+                let i = (z * (n + 1) + x) as u32;
+                let row = (n + 1) as u32;
+                [i, i + row, i + 1, i + 1, i + row, i + row + 1]
+            })
+            .collect();
+
+        IndexedTriangles { vertices, indices }
+    }
+
+    #[test]
+    fn test_streamed_mesh_hits_like_the_resident_one_within_the_leaf_budget() {
+        const BUDGET: usize = 3;
+        let grid = wavy_grid(40);
+        let mtl: Arc<dyn Material> = Arc::new(Lambertian::new((0.5, 0.5, 0.5)));
+        let obj2world = Mat4::translate(Vec3::new(-0.5 as Real, C_ZERO, -0.5 as Real))
+            * Mat4::uniform_scale(2 as Real);
+
+        let resident = TriangleMesh::from_vertices(
+            &grid.vertices,
+            &[GeometryNode {
+                indices: grid.indices.clone(),
+                ..Default::default()
+            }],
+            obj2world,
+            mtl.clone(),
+        );
+        let streamed = StreamingMesh::new(Arc::new(grid), obj2world, mtl, BUDGET);
+        let leaf_count = streamed.leaves.len();
+        assert!(leaf_count > 10 * BUDGET);
+
+        //
+        // rays from above to random points all over the grid
+        (0..2000).for_each(|_| {
+            let target = Vec3::new(
+                -0.4 as Real + 2.8 as Real * random_real(),
+                C_ZERO,
+                -0.4 as Real + 2.8 as Real * random_real(),
+            );
+            let origin = Vec3::new(0.5 as Real, 3 as Real, 0.5 as Real);
+            let r = Ray::new(origin, target - origin, C_ZERO);
+
+            let expected = resident.hit(&r, 0.001 as Real, C_INFINITY);
+            let hit = streamed.hit(&r, 0.001 as Real, C_INFINITY);
+            assert_eq!(expected.is_some(), hit.is_some());
+            if let (Some(expected), Some(hit)) = (expected, hit) {
+                assert!((expected.t - hit.t).abs() < 1.0e-5);
+                assert!(math::vec3::length(expected.normal - hit.normal) < 1.0e-4);
+                assert_eq!(expected.front_face, hit.front_face);
+            }
+
+            assert!(streamed.streaming_stats().resident <= BUDGET);
+        });
+
+        let stats = streamed.streaming_stats();
+        assert!(stats.peak_resident <= BUDGET);
+        assert!(stats.loads > leaf_count, "{:?}", stats);
+    }
+
+    #[test]
+    fn test_mapped_gltf_decodes_the_imported_triangles() {
+        use crate::geometry_import::ImportedGeometry;
+
+        const MODEL: &str = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../data/models/teapot/teapot.glb"
+        );
+        let geometry = ImportedGeometry::import_from_file(&MODEL).unwrap();
+        let mapped = MappedGltfTriangles::open(MODEL).unwrap();
+        assert_eq!(mapped.triangle_count() * 3, geometry.indices().len());
+
+        //
+        // the same triangles, in the same order, as the fully decoded import
+        (0..mapped.triangle_count()).step_by(7).for_each(|i| {
+            let decoded = mapped.triangle(i);
+            let idx = &geometry.indices()[i * 3..i * 3 + 3];
+            (0..3).for_each(|k| {
+                let expected = &geometry.vertices()[idx[k] as usize];
+                assert!(math::vec3::length(decoded[k].pos - expected.pos) < 1.0e-4);
+                assert!(math::vec3::length(decoded[k].normal - expected.normal) < 1.0e-4);
+                assert!((decoded[k].uv.x - expected.uv.x).abs() < 1.0e-5);
+                assert!((decoded[k].uv.y - expected.uv.y).abs() < 1.0e-5);
+            });
+        });
+
+        //
+        // and the mesh streamed from the file hits like the resident one
+        let mtl: Arc<dyn Material> = Arc::new(Lambertian::new((0.5, 0.5, 0.5)));
+        let identity = math::mat4::consts::identity();
+        let resident = TriangleMesh::from_vertices(
+            geometry.vertices(),
+            geometry.nodes(),
+            identity,
+            mtl.clone(),
+        );
+        let streamed = StreamingMesh::from_file(MODEL, identity, mtl, 4);
+        let bbox = resident.bounding_box(C_ZERO, C_ZERO).unwrap();
+        let radius = math::vec3::length(bbox.max - bbox.min);
+
+        (0..500).for_each(|_| {
+            let origin = bbox.center() + radius * crate::types::random_unit_vector();
+            let target = bbox.min + (bbox.max - bbox.min) * random_real();
+            let r = Ray::new(origin, target - origin, C_ZERO);
+
+            let expected = resident.hit(&r, 0.001 as Real, C_INFINITY);
+            let hit = streamed.hit(&r, 0.001 as Real, C_INFINITY);
+            assert_eq!(expected.is_some(), hit.is_some());
+            if let (Some(expected), Some(hit)) = (expected, hit) {
+                assert!((expected.t - hit.t).abs() < 1.0e-4 * expected.t.max(C_ONE));
+            }
+        });
+        assert!(streamed.streaming_stats().peak_resident <= 4);
+    }
+}
@@ -0,0 +1,273 @@
+use std::collections::HashMap;
+
+use crate::{
+    geometry_import::{GeometryNode, GeometryVertex},
+    types::{Real, Vec3, C_ONE, C_TWO_PI, C_ZERO},
+};
+
+/// Refines the triangles of the nodes with `levels` steps of Loop subdivision, every step
+/// splits a triangle into 4 and moves the vertices towards the smooth limit surface. The
+/// vertex normals are recomputed from the refined faces.
+///
+/// Vertices at the same position are one vertex of the surface (imported meshes split them
+/// on uv seams and hard edges), the seams keep their split attributes but not the hard
+/// edges. Edges of only one triangle are boundaries, they stay put as curves.
+pub fn loop_subdivide(
+    vertices: &[GeometryVertex],
+    nodes: &[GeometryNode],
+    levels: u32,
+) -> (Vec<GeometryVertex>, Vec<GeometryNode>) {
+    let (mut vertices, mut nodes) = (0..levels).fold(
+        (vertices.to_vec(), nodes.to_vec()),
+        |(vertices, nodes), _| subdivide_once(&vertices, &nodes),
+    );
+
+    smooth_normals(&mut vertices, &nodes);
+
+    //
+    // as if the nodes' indices were concatenated into one buffer
+    let mut start = 0;
+    nodes.iter_mut().for_each(|node| {
+        node.index_range = start..start + node.indices.len();
+        start = node.index_range.end;
+    });
+
+    (vertices, nodes)
+}
+
+//
+// id of the surface vertex (all the vertices at the same position) for every vertex
+fn weld_by_position(vertices: &[GeometryVertex]) -> (Vec<usize>, Vec<Vec3>) {
+    let mut welded = HashMap::new();
+    let mut positions = Vec::new();
+
+    let ids = vertices
+        .iter()
+        .map(|vtx| {
+            let key = [
+                vtx.pos.x.to_bits(),
+                vtx.pos.y.to_bits(),
+                vtx.pos.z.to_bits(),
+            ];
+            *welded.entry(key).or_insert_with(|| {
+                positions.push(vtx.pos);
+                positions.len() - 1
+            })
+        })
+        .collect();
+
+    (ids, positions)
+}
+
+fn edge_key(a: usize, b: usize) -> (usize, usize) {
+    (a.min(b), a.max(b))
+}
+
+fn subdivide_once(
+    vertices: &[GeometryVertex],
+    nodes: &[GeometryNode],
+) -> (Vec<GeometryVertex>, Vec<GeometryNode>) {
+    let (ids, positions) = weld_by_position(vertices);
+    let triangles = || {
+        nodes
+            .iter()
+            .flat_map(|node| node.indices.chunks(3))
+            .map(|idx| [idx[0] as usize, idx[1] as usize, idx[2] as usize])
+    };
+
+    //
+    // vertices opposite to every edge of the welded surface
+    let mut opposite = HashMap::<(usize, usize), Vec<usize>>::new();
+    triangles().for_each(|tri| {
+        (0..3).for_each(|e| {
+            let (a, b, c) = (ids[tri[e]], ids[tri[(e + 1) % 3]], ids[tri[(e + 2) % 3]]);
+            opposite.entry(edge_key(a, b)).or_default().push(c);
+        });
+    });
+
+    let mut neighbours = vec![Vec::new(); positions.len()];
+    let mut boundary_neighbours = vec![Vec::new(); positions.len()];
+    opposite.iter().for_each(|(&(a, b), opp)| {
+        neighbours[a].push(b);
+        neighbours[b].push(a);
+        if opp.len() == 1 {
+            boundary_neighbours[a].push(b);
+            boundary_neighbours[b].push(a);
+        }
+    });
+
+    //
+    // even (existing) vertices, Loop's weights for the interior ones and the cubic B-spline
+    // ones along the boundaries (corners of the boundary don't move)
+    let even = (0..positions.len())
+        .map(|v| {
+            let p = positions[v];
+            match boundary_neighbours[v].as_slice() {
+                [] => {
+                    let k = neighbours[v].len() as Real;
+                    if k < 3 as Real {
+                        return p;
+                    }
+                    let w = 0.375 as Real + 0.25 as Real * (C_TWO_PI / k).cos();
+                    let beta = (0.625 as Real - w * w) / k;
+                    let sum = neighbours[v]
+                        .iter()
+                        .fold(Vec3::broadcast(C_ZERO), |sum, &n| sum + positions[n]);
+                    (C_ONE - k * beta) * p + beta * sum
+                }
+                [b0, b1] => 0.75 as Real * p + 0.125 as Real * (positions[*b0] + positions[*b1]),
+                _ => p,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let odd = |a: usize, b: usize| match opposite[&edge_key(a, b)].as_slice() {
+        [c, d] => {
+            0.375 as Real * (positions[a] + positions[b])
+                + 0.125 as Real * (positions[*c] + positions[*d])
+        }
+        _ => 0.5 as Real * (positions[a] + positions[b]),
+    };
+
+    let mut refined = vertices
+        .iter()
+        .zip(ids.iter())
+        .map(|(vtx, &id)| GeometryVertex {
+            pos: even[id],
+            ..*vtx
+        })
+        .collect::<Vec<_>>();
+
+    //
+    // one new vertex per edge of the unwelded mesh, the attributes are split along the
+    // seams like the ones of the edge's ends
+    let mut edge_vertices = HashMap::<(usize, usize), u32>::new();
+    let mut edge_vertex = |a: usize, b: usize| -> u32 {
+        *edge_vertices.entry(edge_key(a, b)).or_insert_with(|| {
+            let (va, vb) = (&vertices[a], &vertices[b]);
+            refined.push(GeometryVertex {
+                pos: odd(ids[a], ids[b]),
+                uv: (va.uv + vb.uv) * 0.5 as Real,
+                color: (va.color + vb.color) * 0.5 as Real,
+                tangent: (va.tangent + vb.tangent) * 0.5 as Real,
+                ..*va
+            });
+            (refined.len() - 1) as u32
+        })
+    };
+
+    let nodes = nodes
+        .iter()
+        .map(|node| {
+            let indices = node
+                .indices
+                .chunks(3)
+                .flat_map(|idx| {
+                    let [a, b, c] = [idx[0], idx[1], idx[2]];
+                    let ab = edge_vertex(a as usize, b as usize);
+                    let bc = edge_vertex(b as usize, c as usize);
+                    let ca = edge_vertex(c as usize, a as usize);
+                    [a, ab, ca, b, bc, ab, c, ca, bc, ab, bc, ca]
+                })
+                .collect();
+
+            GeometryNode {
+                indices,
+                ..node.clone()
+            }
+        })
+        .collect();
+
+    (refined, nodes)
+}
+
+//
+// area weighted face normals, summed over all the vertices at the same position
+fn smooth_normals(vertices: &mut [GeometryVertex], nodes: &[GeometryNode]) {
+    use math::vec3::{cross, normalize};
+
+    let (ids, positions) = weld_by_position(vertices);
+    let mut normals = vec![Vec3::broadcast(C_ZERO); positions.len()];
+
+    nodes
+        .iter()
+        .flat_map(|node| node.indices.chunks(3))
+        .for_each(|idx| {
+            let [p0, p1, p2] = [idx[0], idx[1], idx[2]].map(|i| vertices[i as usize].pos);
+            let n = cross(p1 - p0, p2 - p0);
+            idx.iter().for_each(|&i| normals[ids[i as usize]] += n);
+        });
+
+    vertices
+        .iter_mut()
+        .zip(ids.iter())
+        .filter(|(_, &id)| !math::vec3::is_near_zero(normals[id]))
+        .for_each(|(vtx, &id)| vtx.normal = normalize(normals[id]));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use math::vec3::{cross, dot, length, normalize};
+
+    #[test]
+    fn test_tetrahedron_subdivides_into_4x_triangles_towards_the_limit_surface() {
+        //
+        // regular tetrahedron around the origin, counter clockwise seen from outside
+        let corners = [
+            Vec3::new(1 as Real, 1 as Real, 1 as Real),
+            Vec3::new(1 as Real, -1 as Real, -1 as Real),
+            Vec3::new(-1 as Real, 1 as Real, -1 as Real),
+            Vec3::new(-1 as Real, -1 as Real, 1 as Real),
+        ];
+        let vertices = corners.map(|pos| GeometryVertex {
+            pos,
+            normal: normalize(pos),
+            ..Default::default()
+        });
+        let nodes = [GeometryNode {
+            indices: vec![0, 1, 2, 0, 3, 1, 0, 2, 3, 1, 3, 2],
+            ..Default::default()
+        }];
+
+        let (refined, refined_nodes) = loop_subdivide(&vertices, &nodes, 1);
+        assert_eq!(refined_nodes[0].indices.len(), 4 * nodes[0].indices.len());
+        assert_eq!(refined_nodes[0].index_range, 0..48);
+        assert_eq!(refined.len(), 4 + 6);
+
+        let parallel = |a: Vec3, b: Vec3| {
+            length(cross(normalize(a), normalize(b))) < 1.0e-5 && dot(a, b) > C_ZERO
+        };
+
+        //
+        // by symmetry the corners move straight towards the center, to 1/4 of the way
+        // with Loop's weights for valence 3, and the edge points are on the rays through
+        // the edges' midpoints
+        refined[..4]
+            .iter()
+            .zip(corners.iter())
+            .for_each(|(vtx, &p)| {
+                assert!(parallel(vtx.pos, p));
+                assert!((length(vtx.pos) - length(p) * 0.25 as Real).abs() < 1.0e-5);
+            });
+        refined[4..].iter().for_each(|vtx| {
+            let on_edge =
+                (0..4).any(|a| (a + 1..4).any(|b| parallel(vtx.pos, corners[a] + corners[b])));
+            assert!(on_edge, "{:?}", vtx.pos);
+        });
+
+        //
+        // the refined faces keep the winding, the normals point outward
+        refined_nodes[0].indices.chunks(3).for_each(|idx| {
+            let [p0, p1, p2] = [idx[0], idx[1], idx[2]].map(|i| refined[i as usize].pos);
+            assert!(dot(cross(p1 - p0, p2 - p0), p0 + p1 + p2) > C_ZERO);
+        });
+        refined.iter().for_each(|vtx| {
+            assert!((length(vtx.normal) - C_ONE).abs() < 1.0e-5);
+            assert!(dot(vtx.normal, vtx.pos) > C_ZERO);
+        });
+
+        let (_, twice) = loop_subdivide(&vertices, &nodes, 2);
+        assert_eq!(twice[0].indices.len(), 16 * nodes[0].indices.len());
+    }
+}
@@ -0,0 +1,195 @@
+use math::vec3::length;
+
+use crate::hittable::HitRecord;
+use crate::material::{Material, ScatterRecord};
+use crate::onb::Onb;
+use crate::types::{random_cosine_direction, random_real, random_unit_vector, Color, Ray, Real};
+
+/// Scattering events allowed inside the object, longer walks are absorbed.
+const MAX_WALK_STEPS: u32 = 32;
+
+/// Cheap subsurface scattering (wax, skin, marble) for closed objects. Light enters the
+/// surface diffusely, random walks inside with a mean free path of `radius` and leaves
+/// the object somewhere near the entry point, tinted by `albedo`.
+///
+/// Works like a `ConstantMedium` bounded by the object's own surface, so the front faces
+/// of the object must point outwards.
+pub struct SubsurfaceMaterial {
+    pub albedo: Color,
+    pub radius: Real,
+}
+
+impl SubsurfaceMaterial {
+    pub fn new<T: Into<Color>>(albedo: T, radius: Real) -> SubsurfaceMaterial {
+        SubsurfaceMaterial {
+            albedo: albedo.into(),
+            radius,
+        }
+    }
+}
+
+impl Material for SubsurfaceMaterial {
+    fn scatter(&self, ray: &Ray, hit_record: &HitRecord) -> Option<ScatterRecord> {
+        if hit_record.front_face {
+            //
+            // entering, diffuse transmission into the surface, the walk starts
+            let uvw = Onb::from(-hit_record.normal);
+
+            return Some(ScatterRecord::SpecularRec {
+                ray: Ray::new(
+                    hit_record.p,
+                    uvw.local_from_vec(random_cosine_direction()),
+                    ray.time,
+                ),
+                attenuation: Color::broadcast(1 as Real),
+            });
+        }
+
+        //
+        // the ray went from its origin to the surface inside the object, check if
+        // it got scattered before reaching the surface
+        let ray_length = length(ray.direction);
+        let free_path = -self.radius * random_real().ln();
+
+        if free_path >= hit_record.t * ray_length {
+            //
+            // leaves the object
+            return Some(ScatterRecord::SpecularRec {
+                ray: Ray::new(hit_record.p, ray.direction, ray.time),
+                attenuation: self.albedo,
+            });
+        }
+
+        let walk_steps = hit_record.walk_steps + 1;
+        if walk_steps > MAX_WALK_STEPS {
+            return None;
+        }
+
+        Some(ScatterRecord::WalkRec {
+            ray: Ray::new(
+                ray.at(free_path / ray_length),
+                random_unit_vector(),
+                ray.time,
+            ),
+            attenuation: Color::broadcast(1 as Real),
+            walk_steps,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flip_face::FlipFace;
+    use crate::hittable::Hittable;
+    use crate::hittable_list::HittableList;
+    use crate::lambertian::Lambertian;
+    use crate::rectangles::XZRect;
+    use crate::types::{Point, Vec3};
+    use std::sync::Arc;
+
+    //
+    // slab between y = -0.1 and y = 0, front faces pointing out
+    fn slab(mtl: Arc<dyn Material>) -> HittableList {
+        let face = |k: Real| XZRect {
+            x0: -10 as Real,
+            x1: 10 as Real,
+            z0: -10 as Real,
+            z1: 10 as Real,
+            k,
+            mtl: mtl.clone(),
+        };
+
+        let mut world = HittableList::new();
+        world.add(Arc::new(face(0 as Real)));
+        world.add(Arc::new(FlipFace {
+            obj: Arc::new(face(-0.1 as Real)),
+        }));
+        world
+    }
+
+    //
+    // fraction of the light coming from above that leaves through the bottom of the slab
+    fn transmitted(world: &HittableList) -> Real {
+        const PATHS: u32 = 2000;
+
+        let through = (0..PATHS)
+            .filter(|_| {
+                let mut ray = Ray::new(
+                    Point::new(0 as Real, 1 as Real, 0 as Real),
+                    Vec3::new(0.1 as Real, -1 as Real, 0 as Real),
+                    0 as Real,
+                );
+                let mut walk_steps = 0;
+
+                for _ in 0..64 {
+                    let rec = match world.hit(&ray, 0.0001 as Real, Real::MAX) {
+                        Some(rec) => HitRecord { walk_steps, ..rec },
+                        None => return ray.direction.y < 0 as Real && ray.origin.y < 0 as Real,
+                    };
+
+                    (ray, walk_steps) = match rec.mtl.scatter(&ray, &rec) {
+                        Some(ScatterRecord::SpecularRec { ray, .. }) => (ray, 0),
+                        Some(ScatterRecord::WalkRec {
+                            ray, walk_steps, ..
+                        }) => (ray, walk_steps),
+                        Some(ScatterRecord::PdfRec { pdf, .. }) => {
+                            (Ray::new(rec.p, pdf.generate(), ray.time), 0)
+                        }
+                        None => return false,
+                    };
+                }
+                false
+            })
+            .count();
+
+        through as Real / PATHS as Real
+    }
+
+    #[test]
+    fn test_thin_slab_transmits_light() {
+        let albedo = (0.8 as Real, 0.8 as Real, 0.8 as Real);
+
+        let sss = transmitted(&slab(Arc::new(SubsurfaceMaterial::new(albedo, 0.05))));
+        let opaque = transmitted(&slab(Arc::new(Lambertian::new(albedo))));
+
+        assert_eq!(opaque, 0 as Real);
+        assert!(sss > 0.2 as Real, "transmitted {}", sss);
+    }
+
+    #[test]
+    fn test_the_walk_is_counted_on_its_hits() {
+        let mtl = Arc::new(SubsurfaceMaterial::new((0.8, 0.8, 0.8), 0.05));
+
+        //
+        // a ray of the walk reaching the top of the object from inside, far away so it
+        // scatters on the way
+        let scatter = |walk_steps: u32| {
+            let ray = Ray::new(
+                Point::new(0 as Real, -0.05 as Real, 0 as Real),
+                Vec3::new(0 as Real, 1 as Real, 0 as Real),
+                0 as Real,
+            );
+            let rec = HitRecord {
+                walk_steps,
+                ..HitRecord::new(
+                    ray.at(1000 as Real),
+                    Vec3::new(0 as Real, 1 as Real, 0 as Real),
+                    &ray,
+                    1000 as Real,
+                    mtl.clone(),
+                    0 as Real,
+                    0 as Real,
+                )
+            };
+            assert!(!rec.front_face);
+            mtl.scatter(&ray, &rec)
+        };
+
+        match scatter(3) {
+            Some(ScatterRecord::WalkRec { walk_steps, .. }) => assert_eq!(walk_steps, 4),
+            _ => panic!("the walk should go on"),
+        }
+        assert!(scatter(MAX_WALK_STEPS).is_none());
+    }
+}
@@ -0,0 +1,116 @@
+use crate::hittable::HitRecord;
+use crate::material::{Material, ScatterRecord};
+use crate::types::{Color, Ray, Real};
+
+/// Zero thickness glass sheet (windows, soap bubbles). Light is either reflected or passes
+/// through without changing direction, there is no refraction since both interfaces of the
+/// sheet are at the same place. Meant for rectangles and other open surfaces.
+#[derive(Copy, Clone, Debug)]
+pub struct ThinDielectric {
+    pub refraction_index: Real,
+}
+
+impl ThinDielectric {
+    pub fn new(refraction_index: Real) -> ThinDielectric {
+        ThinDielectric { refraction_index }
+    }
+
+    /// Reflectance of the sheet, summing the light bouncing between the two interfaces.
+    pub fn reflectance(&self, cos_theta: Real) -> Real {
+        let r = crate::types::schlick(cos_theta, self.refraction_index);
+        2 as Real * r / (1 as Real + r)
+    }
+}
+
+impl Material for ThinDielectric {
+    fn scatter(&self, ray: &Ray, hit_record: &HitRecord) -> Option<ScatterRecord> {
+        use crate::types::random_real;
+        use math::vec3::{dot, normalize};
+
+        let uv = normalize(ray.direction);
+        let cos_theta = dot(-uv, hit_record.normal).abs().min(1 as Real);
+
+        let scattered = if random_real() < self.reflectance(cos_theta) {
+            ray.reflect_ray(hit_record.p, hit_record.normal)
+        } else {
+            //
+            // the bending at the two interfaces cancels out, a refraction with a ratio of 1
+            ray.refract_ray(hit_record.p, hit_record.normal, 1 as Real)?
+        };
+
+        Some(ScatterRecord::SpecularRec {
+            ray: scattered,
+            attenuation: Color::broadcast(1 as Real),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dielectric::Dielectric;
+    use crate::hittable::Hittable;
+    use crate::rectangles::XZRect;
+    use crate::types::{Point, Vec3};
+    use std::sync::Arc;
+
+    fn scatter_through_sheet(mtl: Arc<dyn Material>) -> (Vec3, Vec<Vec3>) {
+        let sheet = XZRect {
+            x0: -1 as Real,
+            x1: 1 as Real,
+            z0: -1 as Real,
+            z1: 1 as Real,
+            k: 0 as Real,
+            mtl,
+        };
+
+        let r = Ray::new(
+            Point::new(-1 as Real, 1 as Real, 0 as Real),
+            Vec3::new(1 as Real, -1 as Real, 0 as Real),
+            0 as Real,
+        );
+        let hit = sheet.hit(&r, 0.001, Real::MAX).unwrap();
+
+        let directions = (0..2000)
+            .map(|_| match hit.mtl.scatter(&r, &hit) {
+                Some(ScatterRecord::SpecularRec { ray, .. }) => {
+                    math::vec3::normalize(ray.direction)
+                }
+                _ => panic!("glass should always scatter specularly"),
+            })
+            .collect();
+
+        (math::vec3::normalize(r.direction), directions)
+    }
+
+    #[test]
+    fn test_thin_sheet_transmits_straight_through() {
+        let (incoming, directions) = scatter_through_sheet(Arc::new(ThinDielectric::new(1.5)));
+
+        let straight = directions
+            .iter()
+            .filter(|d| math::vec3::length(**d - incoming) < 1.0E-5)
+            .count();
+        let reflected = directions.iter().filter(|d| d.y > 0 as Real).count();
+
+        //
+        // every ray either goes straight through or is reflected, reflection is the minority
+        assert_eq!(straight + reflected, directions.len());
+        assert!(reflected > 0);
+        assert!(reflected * 5 < directions.len());
+    }
+
+    #[test]
+    fn test_thick_dielectric_bends_the_ray() {
+        let (incoming, directions) = scatter_through_sheet(Arc::new(Dielectric::new(1.5)));
+
+        let transmitted = directions
+            .iter()
+            .filter(|d| d.y < 0 as Real)
+            .collect::<Vec<_>>();
+        assert!(!transmitted.is_empty());
+        assert!(transmitted
+            .iter()
+            .all(|d| math::vec3::length(**d - incoming) > 0.1 as Real));
+    }
+}
@@ -0,0 +1,195 @@
+use crate::types::{Color, Real, Vec3};
+
+/// What the tone curve is applied to.
+#[derive(Copy, Clone, Debug, serde::Serialize, serde::Deserialize, Eq, PartialEq)]
+pub enum ToneCurveMode {
+    /// Remaps the luminance, the channels are scaled by the same factor so hues are kept.
+    Luminance,
+    /// Remaps each channel independently.
+    PerChannel,
+}
+
+/// Tone curve as it appears in the config: (input, output) control points in [0, 1],
+/// joined by straight segments. Levels below the first/above the last point map to the
+/// output of that point.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct ToneCurveConfig {
+    pub mode: ToneCurveMode,
+    pub points: Vec<(Real, Real)>,
+}
+
+impl ToneCurveConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        match self
+            .points
+            .iter()
+            .find(|(x, y)| !x.is_finite() || !y.is_finite())
+        {
+            Some((x, y)) => Err(format!(
+                "tone_curve has a point at ({}, {}), the points must be finite numbers",
+                x, y
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Lookup table built from a `ToneCurveConfig`, applied to the displayable colors
+/// (after gamma correction), before they are quantized to 8 bits. The table keeps the
+/// output levels as floats, so it can be applied to the linear images as well.
+pub struct ToneCurve {
+    mode: ToneCurveMode,
+    lut: Vec<Real>,
+}
+
+impl ToneCurve {
+    const LUT_SIZE: usize = 256;
+
+    pub fn new(cfg: &ToneCurveConfig) -> ToneCurve {
+        if cfg.points.is_empty() {
+            return Self::identity();
+        }
+
+        let mut points = cfg.points.clone();
+        points.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        //
+        // output of the straight segment over x, the ends are extended flat
+        let curve = |x: Real| {
+            let i = points.partition_point(|&(px, _)| px < x);
+            let y = match (points.get(i.wrapping_sub(1)), points.get(i)) {
+                (Some(&(x0, y0)), Some(&(x1, y1))) if x1 > x0 => {
+                    y0 + (y1 - y0) * (x - x0) / (x1 - x0)
+                }
+                (_, Some(&(_, y))) | (Some(&(_, y)), None) => y,
+                (None, None) => x,
+            };
+            y.max(0 as Real).min(1 as Real)
+        };
+
+        ToneCurve {
+            mode: cfg.mode,
+            lut: (0..Self::LUT_SIZE)
+                .map(|i| curve(i as Real / (Self::LUT_SIZE - 1) as Real))
+                .collect(),
+        }
+    }
+
+    pub fn identity() -> ToneCurve {
+        Self::new(&ToneCurveConfig {
+            mode: ToneCurveMode::PerChannel,
+            points: vec![(0 as Real, 0 as Real), (1 as Real, 1 as Real)],
+        })
+    }
+
+    //
+    // linear interpolation between the table entries
+    fn remap(&self, x: Real) -> Real {
+        let pos = x.max(0 as Real).min(1 as Real) * (Self::LUT_SIZE - 1) as Real;
+        let i = (pos.floor() as usize).min(Self::LUT_SIZE - 2);
+        let f = pos - i as Real;
+
+        let (a, b) = (self.lut[i], self.lut[i + 1]);
+        a + (b - a) * f
+    }
+
+    pub fn apply(&self, c: Color) -> Color {
+        match self.mode {
+            ToneCurveMode::PerChannel => Color {
+                r: self.remap(c.r),
+                g: self.remap(c.g),
+                b: self.remap(c.b),
+                ..c
+            },
+            ToneCurveMode::Luminance => {
+                let l = math::vec3::luminance(Vec3::new(c.r, c.g, c.b));
+                if l <= 0 as Real {
+                    return c;
+                }
+
+                let scale = self.remap(l) / l;
+                Color {
+                    r: (c.r * scale).min(1 as Real),
+                    g: (c.g * scale).min(1 as Real),
+                    b: (c.b * scale).min(1 as Real),
+                    ..c
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identity_and_contrast_curves() {
+        let quantize = |x: Real| (x * 255 as Real) as u8;
+        let levels = (0..255)
+            .map(|i| (i as Real + 0.5 as Real) / 255 as Real)
+            .collect::<Vec<_>>();
+
+        let identity = ToneCurve::identity();
+        levels.iter().for_each(|&x| {
+            let c = identity.apply(Color::new(x, 1 as Real - x, 0.5 as Real));
+            assert_eq!(quantize(c.r), quantize(x));
+            assert_eq!(quantize(c.g), quantize(1 as Real - x));
+        });
+
+        let mut contrast_cfg = ToneCurveConfig {
+            mode: ToneCurveMode::PerChannel,
+            points: vec![(0.0, 0.0), (0.25, 0.15), (0.75, 0.85), (1.0, 1.0)],
+        };
+        let contrast = ToneCurve::new(&contrast_cfg);
+        let remapped = levels
+            .iter()
+            .map(|&x| contrast.apply(Color::broadcast(x)).r)
+            .collect::<Vec<_>>();
+
+        assert!(remapped.windows(2).all(|w| w[0] <= w[1]));
+        assert!(contrast.apply(Color::broadcast(0.2)).r < 0.2 as Real);
+        assert!(contrast.apply(Color::broadcast(0.8)).r > 0.8 as Real);
+
+        //
+        // luminance mode keeps the ratio between the channels
+        contrast_cfg.mode = ToneCurveMode::Luminance;
+        let contrast_lum = ToneCurve::new(&contrast_cfg);
+        let c = contrast_lum.apply(Color::new(0.2, 0.1, 0.05));
+        assert!(c.g < 0.1 as Real);
+        assert!((c.r / c.g - 2 as Real).abs() < 1.0e-4);
+    }
+
+    #[test]
+    fn test_curve_levels_are_not_quantized() {
+        //
+        // a gentle curve, the output levels between two 8 bit steps must survive
+        let curve = ToneCurve::new(&ToneCurveConfig {
+            mode: ToneCurveMode::PerChannel,
+            points: vec![(0.0, 0.0), (1.0, 0.01)],
+        });
+        [0.1 as Real, 0.3, 0.5, 0.9].iter().for_each(|&x| {
+            let y = curve.apply(Color::broadcast(x)).r;
+            assert!((y - 0.01 as Real * x).abs() < 1.0e-6, "{} {}", x, y);
+        });
+
+        let identity = ToneCurve::identity();
+        assert!((identity.apply(Color::broadcast(0.3)).r - 0.3 as Real).abs() < 1.0e-6);
+    }
+
+    #[test]
+    fn test_curve_points_must_be_finite() {
+        let cfg = |x: Real, y: Real| ToneCurveConfig {
+            mode: ToneCurveMode::Luminance,
+            points: vec![(0 as Real, 0 as Real), (x, y)],
+        };
+
+        assert!(cfg(1 as Real, 1 as Real).validate().is_ok());
+        [(Real::NAN, 1 as Real), (1 as Real, Real::INFINITY)]
+            .iter()
+            .for_each(|&(x, y)| {
+                let e = cfg(x, y).validate().err().unwrap();
+                assert!(e.contains("tone_curve"), "{}", e);
+            });
+    }
+}
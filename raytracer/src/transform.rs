@@ -2,13 +2,35 @@ use std::sync::Arc;
 
 use crate::{
     aabb3::Aabb,
-    hittable::{HitRecord, Hittable},
-    types::{degrees_to_radians, Mat4, Point, Ray, Real, Vec3, Vec4},
+    hittable::{next_instance_id, HitRecord, HitStats, Hittable, InstanceId, PrimitiveId},
+    types::{degrees_to_radians, Mat3, Mat4, Point, Ray, Real, Vec3, Vec4},
 };
 
 pub struct Translate {
     pub obj: Arc<dyn Hittable>,
     pub offset: Vec3,
+    instance: InstanceId,
+}
+
+impl Translate {
+    pub fn new(obj: Arc<dyn Hittable>, offset: Vec3) -> Translate {
+        Translate {
+            obj,
+            offset,
+            instance: next_instance_id(),
+        }
+    }
+
+    fn object_ray(&self, r: &Ray) -> Ray {
+        Ray::new(r.origin - self.offset, r.direction, r.time)
+    }
+
+    fn world_hit(&self, hit_data: HitRecord) -> HitRecord {
+        HitRecord {
+            p: hit_data.p + self.offset,
+            ..hit_data.instanced(self.instance)
+        }
+    }
 }
 
 impl Hittable for Translate {
@@ -25,24 +47,44 @@ impl Hittable for Translate {
             })
     }
 
+    fn bounding_sphere(&self) -> Option<(Point, Real)> {
+        self.obj
+            .bounding_sphere()
+            .map(|(center, radius)| (center + self.offset, radius))
+    }
+
     fn hit(
         &self,
         r: &crate::types::Ray,
         t_min: crate::types::Real,
         t_max: crate::types::Real,
     ) -> Option<crate::hittable::HitRecord> {
-        let translated_ray = Ray::new(r.origin - self.offset, r.direction, r.time);
-        self.obj.hit(&translated_ray, t_min, t_max).map(|hit_data| {
-            HitRecord::new(
-                hit_data.p + self.offset,
-                hit_data.normal,
-                &translated_ray,
-                hit_data.t,
-                hit_data.mtl,
-                hit_data.u,
-                hit_data.v,
-            )
-        })
+        self.hit_skipping(r, t_min, t_max, None)
+    }
+
+    fn hit_skipping(
+        &self,
+        r: &Ray,
+        t_min: Real,
+        t_max: Real,
+        skip: Option<PrimitiveId>,
+    ) -> Option<HitRecord> {
+        let skip = skip.map(|id| id.uninstanced(self.instance));
+        self.obj
+            .hit_skipping(&self.object_ray(r), t_min, t_max, skip)
+            .map(|hit_data| self.world_hit(hit_data))
+    }
+
+    fn hit_counted(
+        &self,
+        r: &Ray,
+        t_min: Real,
+        t_max: Real,
+        stats: &mut HitStats,
+    ) -> Option<HitRecord> {
+        self.obj
+            .hit_counted(&self.object_ray(r), t_min, t_max, stats)
+            .map(|hit_data| self.world_hit(hit_data))
     }
 
     fn pdf_value(&self, o: Point, v: Vec3) -> Real {
@@ -52,6 +94,17 @@ impl Hittable for Translate {
     fn random(&self, v: Vec3) -> Vec3 {
         self.obj.random(v)
     }
+
+    fn node_boxes(&self, depth: u32, boxes: &mut Vec<Aabb>) {
+        let first = boxes.len();
+        self.obj.node_boxes(depth, boxes);
+        boxes[first..].iter_mut().for_each(|bbox| {
+            *bbox = Aabb {
+                min: bbox.min + self.offset,
+                max: bbox.max + self.offset,
+            }
+        });
+    }
 }
 
 pub struct RotateY {
@@ -59,6 +112,7 @@ pub struct RotateY {
     sin_theta: Real,
     cos_theta: Real,
     bbox: Aabb,
+    instance: InstanceId,
 }
 
 impl RotateY {
@@ -70,6 +124,18 @@ impl RotateY {
             .bounding_box(0 as Real, 1 as Real)
             .expect("Object does not have a bounding box ...");
 
+        Self {
+            obj,
+            sin_theta,
+            cos_theta,
+            bbox: Self::world_box(sin_theta, cos_theta, &bbox),
+            instance: next_instance_id(),
+        }
+    }
+
+    //
+    // box enclosing the rotated corners of the object space box
+    fn world_box(sin_theta: Real, cos_theta: Real, bbox: &Aabb) -> Aabb {
         let mut min = Point::broadcast(std::f32::MAX as Real);
         let mut max = Point::broadcast(std::f32::MIN as Real);
 
@@ -99,21 +165,10 @@ impl RotateY {
             }
         }
 
-        Self {
-            obj,
-            sin_theta,
-            cos_theta,
-            bbox: Aabb { min, max },
-        }
+        Aabb { min, max }
     }
-}
 
-impl Hittable for RotateY {
-    fn bounding_box(&self, _time0: Real, _time1: Real) -> Option<Aabb> {
-        Some(self.bbox)
-    }
-
-    fn hit(&self, r: &Ray, t_min: Real, t_max: Real) -> Option<HitRecord> {
+    fn object_ray(&self, r: &Ray) -> Ray {
         let mut origin = r.origin;
         let mut direction = r.direction;
 
@@ -123,26 +178,84 @@ impl Hittable for RotateY {
         direction[0] = self.cos_theta * r.direction[0] - self.sin_theta * r.direction[2];
         direction[2] = self.sin_theta * r.direction[0] + self.cos_theta * r.direction[2];
 
-        let rotated_r = Ray::new(origin, direction, r.time);
+        Ray::new(origin, direction, r.time)
+    }
 
-        self.obj.hit(&rotated_r, t_min, t_max).map(|hitrec| {
-            let mut p = hitrec.p;
-            let mut n = hitrec.normal;
+    fn world_hit(&self, hitrec: HitRecord) -> HitRecord {
+        let mut p = hitrec.p;
+        let mut n = hitrec.normal;
 
-            p[0] = self.cos_theta * hitrec.p[0] + self.sin_theta * hitrec.p[2];
-            p[2] = -self.sin_theta * hitrec.p[0] + self.cos_theta * hitrec.p[2];
+        p[0] = self.cos_theta * hitrec.p[0] + self.sin_theta * hitrec.p[2];
+        p[2] = -self.sin_theta * hitrec.p[0] + self.cos_theta * hitrec.p[2];
 
-            n[0] = self.cos_theta * hitrec.normal[0] + self.sin_theta * hitrec.normal[2];
-            n[2] = -self.sin_theta * hitrec.normal[0] + self.cos_theta * hitrec.normal[2];
+        n[0] = self.cos_theta * hitrec.normal[0] + self.sin_theta * hitrec.normal[2];
+        n[2] = -self.sin_theta * hitrec.normal[0] + self.cos_theta * hitrec.normal[2];
 
-            HitRecord {
-                p,
-                normal: n,
-                ..hitrec
-            }
+        let tangent = hitrec.tangent.map(|tan| {
+            Vec3::new(
+                self.cos_theta * tan[0] + self.sin_theta * tan[2],
+                tan[1],
+                -self.sin_theta * tan[0] + self.cos_theta * tan[2],
+            )
+        });
+
+        HitRecord {
+            p,
+            normal: n,
+            tangent,
+            ..hitrec.instanced(self.instance)
+        }
+    }
+}
+
+impl Hittable for RotateY {
+    fn bounding_box(&self, _time0: Real, _time1: Real) -> Option<Aabb> {
+        Some(self.bbox)
+    }
+
+    //
+    // rotating doesn't change the radius, unlike the bounding box that grows
+    fn bounding_sphere(&self) -> Option<(Point, Real)> {
+        self.obj.bounding_sphere().map(|(c, radius)| {
+            let center = Point::new(
+                self.cos_theta * c.x + self.sin_theta * c.z,
+                c.y,
+                -self.sin_theta * c.x + self.cos_theta * c.z,
+            );
+            (center, radius)
         })
     }
 
+    fn hit(&self, r: &Ray, t_min: Real, t_max: Real) -> Option<HitRecord> {
+        self.hit_skipping(r, t_min, t_max, None)
+    }
+
+    fn hit_skipping(
+        &self,
+        r: &Ray,
+        t_min: Real,
+        t_max: Real,
+        skip: Option<PrimitiveId>,
+    ) -> Option<HitRecord> {
+        let skip = skip.map(|id| id.uninstanced(self.instance));
+
+        self.obj
+            .hit_skipping(&self.object_ray(r), t_min, t_max, skip)
+            .map(|hitrec| self.world_hit(hitrec))
+    }
+
+    fn hit_counted(
+        &self,
+        r: &Ray,
+        t_min: Real,
+        t_max: Real,
+        stats: &mut HitStats,
+    ) -> Option<HitRecord> {
+        self.obj
+            .hit_counted(&self.object_ray(r), t_min, t_max, stats)
+            .map(|hitrec| self.world_hit(hitrec))
+    }
+
     fn pdf_value(&self, o: Point, v: Vec3) -> Real {
         self.obj.pdf_value(o, v)
     }
@@ -150,13 +263,22 @@ impl Hittable for RotateY {
     fn random(&self, v: Vec3) -> Vec3 {
         self.obj.random(v)
     }
+
+    fn node_boxes(&self, depth: u32, boxes: &mut Vec<Aabb>) {
+        let first = boxes.len();
+        self.obj.node_boxes(depth, boxes);
+        boxes[first..].iter_mut().for_each(|bbox| {
+            *bbox = Self::world_box(self.sin_theta, self.cos_theta, bbox);
+        });
+    }
 }
 
 pub struct Transform {
     obj2world: Mat4,
     world2object: Mat4,
-    normal2world: Mat4,
+    normal2world: Mat3,
     obj: Arc<dyn Hittable>,
+    instance: InstanceId,
 }
 
 impl Transform {
@@ -168,8 +290,29 @@ impl Transform {
         Transform {
             obj2world,
             world2object,
-            normal2world: mat4::adjoint(&world2object).transpose(),
+            normal2world: Mat3::normal_matrix(&obj2world),
             obj,
+            instance: next_instance_id(),
+        }
+    }
+
+    //
+    // hit data of the object space hit in world space
+    fn world_hit(&self, hit: HitRecord) -> HitRecord {
+        use math::mat4;
+        use math::vec3::normalize;
+
+        let p_world = (self.obj2world * Vec4::from_vec3(&hit.p, 1 as Real)).xyz();
+        let n_world = self.normal2world * hit.normal;
+        let tangent = hit
+            .tangent
+            .map(|tan| normalize(mat4::transform_vector(&self.obj2world, tan)));
+
+        HitRecord {
+            p: p_world,
+            normal: normalize(n_world),
+            tangent,
+            ..hit.instanced(self.instance)
         }
     }
 }
@@ -182,32 +325,314 @@ impl Hittable for Transform {
     }
 
     fn hit(&self, r: &Ray, t_min: Real, t_max: Real) -> Option<HitRecord> {
+        self.hit_skipping(r, t_min, t_max, None)
+    }
+
+    fn hit_skipping(
+        &self,
+        r: &Ray,
+        t_min: Real,
+        t_max: Real,
+        skip: Option<PrimitiveId>,
+    ) -> Option<HitRecord> {
         use math::ray::transform;
-        use math::vec3::normalize;
 
         //
         // transform ray to object local space and perform hit testing there
         let transformed_ray = transform(&self.world2object, r);
+        let skip = skip.map(|id| id.uninstanced(self.instance));
 
-        self.obj.hit(&transformed_ray, t_min, t_max).map(|hit| {
-            //
-            // transform hit data to world space
-            let p_world = (self.obj2world * Vec4::from_vec3(&hit.p, 1 as Real)).xyz();
-            let n_world = (self.normal2world * Vec4::from_vec3(&hit.normal, 0 as Real)).xyz();
-
-            HitRecord {
-                p: p_world,
-                normal: normalize(n_world),
-                ..hit
-            }
-        })
+        self.obj
+            .hit_skipping(&transformed_ray, t_min, t_max, skip)
+            .map(|hit| self.world_hit(hit))
+    }
+
+    fn hit_counted(
+        &self,
+        r: &Ray,
+        t_min: Real,
+        t_max: Real,
+        stats: &mut HitStats,
+    ) -> Option<HitRecord> {
+        use math::ray::transform;
+
+        self.obj
+            .hit_counted(&transform(&self.world2object, r), t_min, t_max, stats)
+            .map(|hit| self.world_hit(hit))
     }
 
+    //
+    // sampled in object space, exact for rigid motions and uniform scaling
     fn pdf_value(&self, o: Point, v: Vec3) -> Real {
-        self.obj.pdf_value(o, v)
+        use math::mat4;
+
+        self.obj.pdf_value(
+            mat4::transform_point(&self.world2object, o),
+            mat4::transform_vector(&self.world2object, v),
+        )
     }
 
-    fn random(&self, v: Vec3) -> Vec3 {
-        self.obj.random(v)
+    fn random(&self, o: Vec3) -> Vec3 {
+        use math::mat4;
+
+        mat4::transform_vector(
+            &self.obj2world,
+            self.obj
+                .random(mat4::transform_point(&self.world2object, o)),
+        )
+    }
+
+    fn faces(&self, origin: Point) -> Option<bool> {
+        self.obj
+            .faces(math::mat4::transform_point(&self.world2object, origin))
+    }
+
+    fn node_boxes(&self, depth: u32, boxes: &mut Vec<Aabb>) {
+        let first = boxes.len();
+        self.obj.node_boxes(depth, boxes);
+        boxes[first..].iter_mut().for_each(|bbox| {
+            *bbox = crate::aabb3::transform(&self.obj2world, bbox);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        block::Block,
+        checker_texture::CheckerTexture,
+        lambertian::Lambertian,
+        types::{rand_vec3_range, random_rotation_matrix, random_unit_vector, C_INFINITY, C_ZERO},
+    };
+
+    #[test]
+    fn test_copies_of_a_shared_primitive_only_skip_themselves() {
+        use crate::{hittable_list::HittableList, objects::sphere::Sphere};
+
+        //
+        // two copies of the same sphere, at x = 0 and x = 3
+        let sphere: Arc<dyn Hittable> = Arc::new(Sphere::new(
+            Point::broadcast(0 as Real),
+            1 as Real,
+            Arc::new(Lambertian::new((0.5, 0.5, 0.5))),
+        ));
+        let mut copies = HittableList::new();
+        copies.add(Arc::new(Translate::new(
+            sphere.clone(),
+            Vec3::broadcast(0 as Real),
+        )));
+        copies.add(Arc::new(Transform::new(
+            Mat4::translate(Vec3::new(3 as Real, 0 as Real, 0 as Real)),
+            sphere,
+        )));
+        let copies: Arc<dyn Hittable> = Arc::new(copies);
+        //
+        // and the pair placed again, like a prefab
+        let nested = Transform::new(
+            Mat4::translate(Vec3::new(0 as Real, 0 as Real, 0 as Real)),
+            copies.clone(),
+        );
+
+        [copies.as_ref(), &nested as &dyn Hittable]
+            .iter()
+            .for_each(|world| {
+                //
+                // hit the first copy from the gap between the two, then bounce back toward
+                // the second one from the hit point
+                let dir = Vec3::new(1 as Real, 0 as Real, 0 as Real);
+                let first = world
+                    .hit(
+                        &Ray::new(
+                            Point::new(1.5 as Real, 0 as Real, 0 as Real),
+                            -dir,
+                            0 as Real,
+                        ),
+                        0.001 as Real,
+                        C_INFINITY,
+                    )
+                    .unwrap();
+                assert!((first.p.x - 1 as Real).abs() < 1.0e-4);
+
+                let skip = first.skip_for(dir);
+                assert!(skip.is_some());
+                let second = world
+                    .hit_skipping(
+                        &Ray::new(first.p, dir, 0 as Real),
+                        0 as Real,
+                        C_INFINITY,
+                        skip,
+                    )
+                    .expect("the other copy was skipped");
+                assert!((second.p.x - 2 as Real).abs() < 1.0e-4, "{:?}", second.p);
+                assert_ne!(second.primitive, first.primitive);
+            });
+    }
+
+    #[test]
+    fn test_wrappers_count_the_hits_of_the_wrapped_bvh() {
+        use crate::{bvh::BvhNode, flip_face::FlipFace, objects::sphere::Sphere};
+
+        let mtl = Arc::new(Lambertian::new((0.5, 0.5, 0.5)));
+        let mut spheres = (0..16)
+            .map(|i| {
+                Arc::new(Sphere::new(
+                    Point::new(
+                        (i % 4) as Real * 3 as Real,
+                        (i / 4) as Real * 3 as Real,
+                        C_ZERO,
+                    ),
+                    1 as Real,
+                    mtl.clone(),
+                )) as Arc<dyn Hittable>
+            })
+            .collect::<Vec<_>>();
+        let bvh = BvhNode::new(&mut spheres, C_ZERO, 1 as Real);
+
+        //
+        // wrappers that leave the ray as it is, they count exactly like the bvh
+        let wrappers: [Arc<dyn Hittable>; 4] = [
+            Arc::new(Translate::new(bvh.clone(), Vec3::broadcast(C_ZERO))),
+            Arc::new(RotateY::new(bvh.clone(), C_ZERO)),
+            Arc::new(Transform::new(math::mat4::consts::identity(), bvh.clone())),
+            Arc::new(FlipFace { obj: bvh.clone() }),
+        ];
+
+        let r = Ray::new(
+            Point::new(3 as Real, 3 as Real, 10 as Real),
+            Vec3::new(C_ZERO, C_ZERO, -1 as Real),
+            C_ZERO,
+        );
+        let mut expected = HitStats::default();
+        bvh.hit_counted(&r, 0.001 as Real, C_INFINITY, &mut expected)
+            .unwrap();
+        assert!(expected.node_visits > 0);
+
+        wrappers.iter().for_each(|wrapper| {
+            let mut stats = HitStats::default();
+            assert!(wrapper
+                .hit_counted(&r, 0.001 as Real, C_INFINITY, &mut stats)
+                .is_some());
+            assert_eq!(stats, expected);
+        });
+    }
+
+    #[test]
+    fn test_wrappers_place_the_node_boxes_of_the_wrapped_bvh() {
+        use crate::{
+            bvh::BvhNode, clip_box::ClipBox, flip_face::FlipFace, objects::sphere::Sphere,
+        };
+
+        let mtl = Arc::new(Lambertian::new((0.5, 0.5, 0.5)));
+        let mut spheres = (0..8)
+            .map(|i| {
+                Arc::new(Sphere::new(
+                    Point::new(i as Real * 3 as Real, C_ZERO, C_ZERO),
+                    1 as Real,
+                    mtl.clone(),
+                )) as Arc<dyn Hittable>
+            })
+            .collect::<Vec<_>>();
+        let bvh = BvhNode::new(&mut spheres, C_ZERO, 1 as Real);
+
+        let boxes = |obj: &dyn Hittable| {
+            let mut boxes = Vec::new();
+            obj.node_boxes(2, &mut boxes);
+            boxes
+        };
+        let bvh_boxes = boxes(bvh.as_ref());
+        assert_eq!(bvh_boxes.len(), 7);
+
+        //
+        // the wrappers draw the same nodes, where the wrapped bvh is in the world
+        let offset = Vec3::new(1 as Real, 2 as Real, 3 as Real);
+        let placed: [(Arc<dyn Hittable>, Box<dyn Fn(&Aabb) -> Aabb>); 5] = [
+            (
+                Arc::new(Translate::new(bvh.clone(), offset)),
+                Box::new(move |b: &Aabb| Aabb::new(b.min + offset, b.max + offset)),
+            ),
+            (
+                Arc::new(RotateY::new(bvh.clone(), 90 as Real)),
+                Box::new(|b: &Aabb| {
+                    Aabb::new(
+                        Vec3::new(b.min.z, b.min.y, -b.max.x),
+                        Vec3::new(b.max.z, b.max.y, -b.min.x),
+                    )
+                }),
+            ),
+            (
+                Arc::new(Transform::new(Mat4::translate(offset), bvh.clone())),
+                Box::new(move |b: &Aabb| Aabb::new(b.min + offset, b.max + offset)),
+            ),
+            (
+                Arc::new(FlipFace { obj: bvh.clone() }),
+                Box::new(|b: &Aabb| *b),
+            ),
+            (
+                Arc::new(ClipBox::new(
+                    bvh.clone(),
+                    Aabb::new(Vec3::broadcast(-1 as Real), Vec3::broadcast(1 as Real)),
+                )),
+                Box::new(|b: &Aabb| *b),
+            ),
+        ];
+
+        placed.iter().for_each(|(wrapper, place)| {
+            let wrapped = boxes(wrapper.as_ref());
+            assert_eq!(wrapped.len(), bvh_boxes.len());
+            wrapped.iter().zip(bvh_boxes.iter()).for_each(|(w, b)| {
+                let expected = place(b);
+                assert!(math::vec3::length(w.min - expected.min) < 1.0e-4);
+                assert!(math::vec3::length(w.max - expected.max) < 1.0e-4);
+            });
+        });
+    }
+
+    #[test]
+    fn test_hits_on_a_rotated_block_are_inside_its_bounding_box() {
+        let mtl = Arc::new(Lambertian::from_texture(Arc::new(
+            CheckerTexture::from_colors((0.9, 0.9, 0.9), (0.1, 0.1, 0.1), 10 as Real),
+        )));
+        let half = Vec3::new(1 as Real, 0.25 as Real, 2 as Real);
+
+        (0..20).for_each(|_| {
+            let obj2world = Mat4::translate(Vec3::new(3 as Real, -1 as Real, 2 as Real))
+                * random_rotation_matrix()
+                * Mat4::non_uniform_scale(half * 2 as Real);
+            let block = Transform::new(obj2world, Arc::new(Block::unit_cube(mtl.clone())));
+            let bbox = block.bounding_box(0 as Real, 1 as Real).unwrap();
+
+            //
+            // brute force, the rotated half extents are the sums of the absolute values of
+            // the matrix rows times the box's half extents
+            let center = math::mat4::transform_point(&obj2world, Vec3::broadcast(0 as Real));
+            (0..3).for_each(|a| {
+                let extent = (0..3)
+                    .map(|c| (obj2world.as_slice()[a * 4 + c] * 0.5 as Real).abs())
+                    .sum::<Real>();
+                assert!((bbox.min[a] - (center[a] - extent)).abs() < 1.0e-4);
+                assert!((bbox.max[a] - (center[a] + extent)).abs() < 1.0e-4);
+            });
+
+            //
+            // rays from all around at the block, no hit may be outside of the box
+            let padded = Aabb::new(
+                bbox.min - Vec3::broadcast(1.0e-4),
+                bbox.max + Vec3::broadcast(1.0e-4),
+            );
+            let hits = (0..500)
+                .filter_map(|_| {
+                    let origin = center + 10 as Real * random_unit_vector();
+                    let target = center + rand_vec3_range(-2 as Real, 2 as Real);
+                    block.hit(
+                        &Ray::new(origin, target - origin, 0 as Real),
+                        0.001 as Real,
+                        C_INFINITY,
+                    )
+                })
+                .inspect(|hit| assert!(padded.contains(hit.p), "{:?} {:?}", hit.p, bbox))
+                .count();
+            assert!(hits > 50, "{}", hits);
+        });
     }
 }
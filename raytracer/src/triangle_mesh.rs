@@ -5,13 +5,25 @@ use num::Zero;
 use crate::{
     aabb3::Aabb,
     geometry_import::{self, GeometryNode, GeometryVertex, ImportedGeometry},
-    hittable::{HitRecord, Hittable},
+    hittable::{next_primitive_id, HitRecord, HitStats, Hittable, PrimitiveId},
     image_texture::ImageTexture,
     lambertian::Lambertian,
     material::Material,
-    types::{Mat4, Ray, Real, C_ONE, C_ZERO},
+    types::{Color, Mat3, Mat4, Ray, Real, Vec3, C_ONE, C_ZERO},
 };
 
+const AABB_MIN_EXTENT: Real = 1.0E-4;
+
+/// Normals used for shading the triangles of a mesh.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum Shading {
+    /// Vertex normals interpolated across the triangle.
+    #[default]
+    Smooth,
+    /// The geometric normal of the triangle, for a faceted (low poly) look.
+    Flat,
+}
+
 pub struct TriangleMesh {
     obj2world: Mat4,
     world2obj: Mat4,
@@ -20,13 +32,20 @@ pub struct TriangleMesh {
     aabb: Aabb,
     materials: Arc<Vec<Arc<dyn Material>>>,
     mtl: Arc<dyn Material>,
+    /// White diffuse material the vertex colors tint, when the mesh is shaded with them.
+    vertex_color_mtl: Option<Arc<dyn Material>>,
+    shading: Shading,
+    /// The triangles are the parts of the id, see `triangle_id`.
+    id: PrimitiveId,
 }
 
 impl TriangleMesh {
+    /// Imports the mesh from a file, see `new` for `subdivisions`.
     pub fn from_file<P: AsRef<std::path::Path>>(
         p: P,
         obj2world: Mat4,
         mtl: Arc<dyn Material>,
+        subdivisions: u32,
     ) -> TriangleMesh {
         let geometry = ImportedGeometry::import_from_file(&p)
             .expect(&format!("Failed to import mesh : {}", p.as_ref().display()));
@@ -38,28 +57,85 @@ impl TriangleMesh {
             geometry.nodes().len()
         );
 
-        Self::new(&geometry, obj2world, mtl)
+        Self::new(&geometry, obj2world, mtl, subdivisions)
     }
+
+    /// Mesh of the imported geometry, refined with `subdivisions` levels of Loop
+    /// subdivision first (0 keeps the triangles as they are), for smooth low poly models.
     pub fn new(
         imported_geometry: &ImportedGeometry,
         obj2world: Mat4,
         mtl: Arc<dyn Material>,
+        subdivisions: u32,
+    ) -> Self {
+        let mesh = if subdivisions > 0 {
+            let (vertices, nodes) = crate::subdivision::loop_subdivide(
+                imported_geometry.vertices(),
+                imported_geometry.nodes(),
+                subdivisions,
+            );
+            Self::from_vertices(&vertices, &nodes, obj2world, mtl)
+        } else {
+            Self::from_vertices(
+                imported_geometry.vertices(),
+                imported_geometry.nodes(),
+                obj2world,
+                mtl,
+            )
+        };
+
+        eprintln!("AABB {:?}", mesh.aabb);
+        mesh.nodes.iter().for_each(|n| {
+            eprintln!("node {} aabb {:?}", n.name, n.aabb);
+        });
+
+        let (img_width, img_height, copy_src) = imported_geometry.pbr_base_color_images();
+        let materials = Arc::new(
+            copy_src
+                .iter()
+                .map(|copy_img| {
+                    let tex = ImageTexture::from_pixels(img_width, img_height, unsafe {
+                        std::slice::from_raw_parts(copy_img.src, copy_img.bytes)
+                    })
+                    .with_srgb(true);
+
+                    Arc::new(Lambertian::from_texture(Arc::new(tex))) as Arc<dyn Material>
+                })
+                .collect::<Vec<_>>(),
+        );
+
+        TriangleMesh { materials, ..mesh }
+    }
+
+    /// Builds a mesh from procedurally generated geometry (no PBR materials).
+    pub fn from_vertices(
+        vertices: &[GeometryVertex],
+        nodes: &[GeometryNode],
+        obj2world: Mat4,
+        mtl: Arc<dyn Material>,
     ) -> Self {
+        nodes.iter().for_each(|node| {
+            assert!(
+                node.has_valid_triangles(vertices.len()),
+                "Node {} has indices that are not whole triangles of the {} vertices",
+                node.name,
+                vertices.len()
+            );
+        });
+
         let world2obj = math::mat4::invert(&obj2world);
-        let normals2world = world2obj.transpose();
+        let normals2world = Mat3::normal_matrix(&obj2world);
 
         let mut aabb = Aabb::default();
 
-        let vertices = imported_geometry
-            .vertices()
+        let vertices = vertices
             .iter()
             .map(|vtx| {
                 //
                 // transforming rays to object space is expensive for a mesh
                 // so transform all position and normals to world space and recompute the AABB
                 let pos = math::mat4::transform_point(&obj2world, vtx.pos);
-                let normal =
-                    math::vec3::normalize(math::mat4::transform_vector(&normals2world, vtx.normal));
+                let normal = math::vec3::normalize(normals2world * vtx.normal);
 
                 aabb.add_point(pos);
 
@@ -71,8 +147,7 @@ impl TriangleMesh {
             })
             .collect::<Vec<_>>();
 
-        let nodes = imported_geometry
-            .nodes()
+        let nodes = nodes
             .iter()
             .filter(|node| !node.indices.is_empty())
             .map(|node| {
@@ -80,6 +155,7 @@ impl TriangleMesh {
                     bbox.add_point(vertices[idx as usize].pos);
                     bbox
                 });
+                let aabb = aabb.padded(AABB_MIN_EXTENT);
 
                 GeometryNode {
                     aabb,
@@ -88,36 +164,193 @@ impl TriangleMesh {
             })
             .collect::<Vec<_>>();
 
-        eprintln!("AABB {:?}", aabb);
-        nodes.iter().for_each(|n| {
-            eprintln!("node {} aabb {:?}", n.name, n.aabb);
-        });
-
-        let (img_width, img_height, copy_src) = imported_geometry.pbr_base_color_images();
-        let materials = Arc::new(
-            copy_src
-                .iter()
-                .map(|copy_img| {
-                    let tex = ImageTexture::from_pixels(img_width, img_height, unsafe {
-                        std::slice::from_raw_parts(copy_img.src, copy_img.bytes)
-                    });
-
-                    Arc::new(Lambertian::from_texture(Arc::new(tex))) as Arc<dyn Material>
-                })
-                .collect::<Vec<_>>(),
-        );
-
         TriangleMesh {
             vertices,
             nodes,
-            aabb,
+            aabb: aabb.padded(AABB_MIN_EXTENT),
             mtl,
             obj2world,
             world2obj,
-            materials,
+            materials: Arc::new(Vec::new()),
+            vertex_color_mtl: None,
+            shading: Shading::Smooth,
+            id: next_primitive_id(),
+        }
+    }
+
+    /// Shade the mesh as diffuse, with the interpolated vertex colors as albedo
+    /// (for vertex painted models), instead of using the mesh material.
+    pub fn with_vertex_colors(self, vertex_colors: bool) -> Self {
+        TriangleMesh {
+            vertex_color_mtl: vertex_colors
+                .then(|| Arc::new(Lambertian::new((C_ONE, C_ONE, C_ONE))) as Arc<dyn Material>),
+            ..self
         }
     }
 
+    pub fn with_shading(self, shading: Shading) -> Self {
+        TriangleMesh { shading, ..self }
+    }
+
+    /// Runs `orient_winding` on the mesh, for imported models exported with mixed winding.
+    /// Off by default, open meshes and meshes that face inward on purpose would get flipped.
+    pub fn with_oriented_winding(mut self, orient: bool) -> Self {
+        if orient {
+            self.orient_winding();
+        }
+        self
+    }
+
+    /// Makes the triangle winding consistent across shared edges (flood fill over the
+    /// edge adjacency), then orients every connected piece outward from its centroid.
+    /// Vertex normals that disagree with the resulting face normals get flipped, so back
+    /// face culling no longer drops front faces of meshes exported with mixed winding.
+    pub fn orient_winding(&mut self) {
+        use math::vec3::{cross, dot};
+        use std::collections::{HashMap, VecDeque};
+
+        //
+        // weld vertices by position, imported meshes duplicate vertices for seams/flat shading
+        let mut welded = HashMap::new();
+        let vertex_ids = self
+            .vertices
+            .iter()
+            .map(|vtx| {
+                let key = [
+                    vtx.pos.x.to_bits(),
+                    vtx.pos.y.to_bits(),
+                    vtx.pos.z.to_bits(),
+                ];
+                let next_id = welded.len();
+                *welded.entry(key).or_insert(next_id)
+            })
+            .collect::<Vec<_>>();
+
+        //
+        // (node, index of first vertex)
+        let triangles = self
+            .nodes
+            .iter()
+            .enumerate()
+            .flat_map(|(node_idx, node)| {
+                (0..node.indices.len() / 3).map(move |tri| (node_idx, tri * 3))
+            })
+            .collect::<Vec<_>>();
+
+        let tri_indices = |nodes: &[GeometryNode], tri: (usize, usize)| -> [u32; 3] {
+            let idx = &nodes[tri.0].indices[tri.1..tri.1 + 3];
+            [idx[0], idx[1], idx[2]]
+        };
+
+        let mut edges = HashMap::<(usize, usize), Vec<usize>>::new();
+        triangles.iter().enumerate().for_each(|(tri_id, &tri)| {
+            let idx = tri_indices(&self.nodes, tri);
+            (0..3).for_each(|e| {
+                let a = vertex_ids[idx[e] as usize];
+                let b = vertex_ids[idx[(e + 1) % 3] as usize];
+                edges.entry((a.min(b), a.max(b))).or_default().push(tri_id);
+            });
+        });
+
+        //
+        // true if the triangle traverses the directed edge a -> b
+        let has_directed_edge = |idx: [u32; 3], a: usize, b: usize| {
+            (0..3).any(|e| {
+                vertex_ids[idx[e] as usize] == a && vertex_ids[idx[(e + 1) % 3] as usize] == b
+            })
+        };
+
+        let mut visited = vec![false; triangles.len()];
+        let mut flipped = vec![false; triangles.len()];
+
+        for seed in 0..triangles.len() {
+            if visited[seed] {
+                continue;
+            }
+
+            visited[seed] = true;
+            let mut component = vec![seed];
+            let mut queue = VecDeque::from([seed]);
+
+            while let Some(tri_id) = queue.pop_front() {
+                let mut idx = tri_indices(&self.nodes, triangles[tri_id]);
+                if flipped[tri_id] {
+                    idx.swap(1, 2);
+                }
+
+                for e in 0..3 {
+                    let a = vertex_ids[idx[e] as usize];
+                    let b = vertex_ids[idx[(e + 1) % 3] as usize];
+
+                    for &n in edges[&(a.min(b), a.max(b))].iter() {
+                        if visited[n] {
+                            continue;
+                        }
+
+                        //
+                        // consistent neighbours traverse the shared edge in opposite directions
+                        visited[n] = true;
+                        flipped[n] =
+                            has_directed_edge(tri_indices(&self.nodes, triangles[n]), a, b);
+                        component.push(n);
+                        queue.push_back(n);
+                    }
+                }
+            }
+
+            //
+            // signed volume relative to the centroid, negative means the piece faces inward
+            let corners = |tri_id: usize| {
+                let mut idx = tri_indices(&self.nodes, triangles[tri_id]);
+                if flipped[tri_id] {
+                    idx.swap(1, 2);
+                }
+                idx.map(|i| self.vertices[i as usize].pos)
+            };
+
+            let centroid = component
+                .iter()
+                .flat_map(|&tri_id| corners(tri_id))
+                .fold(Vec3::broadcast(C_ZERO), |sum, p| sum + p)
+                / (component.len() * 3) as Real;
+
+            let volume = component.iter().fold(C_ZERO, |vol, &tri_id| {
+                let [p0, p1, p2] = corners(tri_id);
+                vol + dot(p0 - centroid, cross(p1 - centroid, p2 - centroid))
+            });
+
+            if volume < C_ZERO {
+                component
+                    .iter()
+                    .for_each(|&tri_id| flipped[tri_id] = !flipped[tri_id]);
+            }
+        }
+
+        triangles
+            .iter()
+            .zip(flipped.iter())
+            .filter(|(_, &flip)| flip)
+            .for_each(|(&(node_idx, offset), _)| {
+                self.nodes[node_idx].indices.swap(offset + 1, offset + 2);
+            });
+
+        //
+        // flip vertex normals pointing away from the faces that use them
+        let mut face_normals = vec![Vec3::broadcast(C_ZERO); self.vertices.len()];
+        triangles.iter().for_each(|&tri| {
+            let idx = tri_indices(&self.nodes, tri);
+            let [p0, p1, p2] = idx.map(|i| self.vertices[i as usize].pos);
+            let n = cross(p1 - p0, p2 - p0);
+            idx.iter().for_each(|&i| face_normals[i as usize] += n);
+        });
+
+        self.vertices
+            .iter_mut()
+            .zip(face_normals.iter())
+            .filter(|(vtx, &n)| dot(vtx.normal, n) < C_ZERO)
+            .for_each(|(vtx, _)| vtx.normal = -vtx.normal);
+    }
+
     fn ray_triangle_intersect(
         &self,
         idx: &[u32],
@@ -171,75 +404,171 @@ impl TriangleMesh {
 
         let normal = normalize(u * p1.normal + v * p2.normal + w * p3.normal);
 
-        //
-        // cull back-faces
-        if dot(r.direction, normal) > C_ZERO {
-            return None;
-        }
-
         let mtl = self.mtl.clone();
         //self.materials[p1.pbr_buf_id as usize].clone();
         Some(HitRecord::new(r.at(t), normal, r, t, mtl, uv.x, uv.y))
     }
 
-    fn ray_triangle_intersect_test(
+    fn triangle_id(&self, node: usize, triangle: usize) -> PrimitiveId {
+        self.id.part(((node as u64) << 32) | triangle as u64)
+    }
+
+    //
+    // closest hit over the triangles of the nodes, but the `skip` one
+    fn closest_hit(
         &self,
-        idx: &[u32],
         r: &Ray,
         t_min: Real,
         t_max: Real,
+        stats: &mut HitStats,
+        skip: Option<PrimitiveId>,
     ) -> Option<HitRecord> {
-        //
-        // Physically based rendering, section 3.6.2, pg 140
-        let p1 = &self.vertices[idx[0] as usize];
-        let p2 = &self.vertices[idx[1] as usize];
-        let p3 = &self.vertices[idx[2] as usize];
-
-        use math::vec3::{cross, dot, normalize};
+        stats.node_visits += 1;
 
-        let e1 = p2.pos - p1.pos;
-        let e2 = p3.pos - p1.pos;
-        let s1 = cross(r.direction, e2);
-        let div = dot(s1, e1);
+        if self.aabb.hit(r, t_min, t_max) {
+            self.nodes
+                .iter()
+                .enumerate()
+                .filter_map(|(node_idx, node)| {
+                    stats.node_visits += 1;
 
-        if div.is_zero() {
-            return None;
+                    if node.aabb.hit(r, t_min, t_max) {
+                        //
+                        // the closest triangle, back faces are hit too so the first one
+                        // found can be behind the front one
+                        node.indices.chunks(3).enumerate().fold(
+                            None,
+                            |closest, (triangle, idx_range)| {
+                                let id = self.triangle_id(node_idx, triangle);
+                                if skip == Some(id) {
+                                    return closest;
+                                }
+
+                                stats.primitive_tests += 1;
+                                let t_max = closest.as_ref().map_or(t_max, |hit: &HitRecord| hit.t);
+                                self.ray_triangle_intersect_test(idx_range, r, t_min, t_max)
+                                    .map(|hit| hit.with_primitive(id))
+                                    .or(closest)
+                            },
+                        )
+                    } else {
+                        None
+                    }
+                })
+                .reduce(|hit0, hit1| if hit0.t < hit1.t { hit0 } else { hit1 })
+        } else {
+            None
         }
+    }
 
-        let inv_div = div.recip();
-
-        let d = r.origin - p1.pos;
-        let b1 = dot(d, s1) * inv_div;
-
-        if b1 < C_ZERO || b1 > C_ONE {
-            return None;
-        }
+    fn ray_triangle_intersect_test(
+        &self,
+        idx: &[u32],
+        r: &Ray,
+        t_min: Real,
+        t_max: Real,
+    ) -> Option<HitRecord> {
+        intersect_triangle(
+            [
+                &self.vertices[idx[0] as usize],
+                &self.vertices[idx[1] as usize],
+                &self.vertices[idx[2] as usize],
+            ],
+            r,
+            t_min,
+            t_max,
+            self.shading,
+            self.vertex_color_mtl.is_some(),
+            self.vertex_color_mtl.as_ref().unwrap_or(&self.mtl),
+        )
+    }
+}
 
-        let s2 = cross(d, e1);
-        let b2 = dot(r.direction, s2) * inv_div;
+/// Hit of the ray with the triangle of the (world space) vertices, shaded like the
+/// triangles of a `TriangleMesh`. With `vertex_colors` the hit carries the interpolated
+/// vertex color, for `mtl` to tint with.
+pub(crate) fn intersect_triangle(
+    [p1, p2, p3]: [&GeometryVertex; 3],
+    r: &Ray,
+    t_min: Real,
+    t_max: Real,
+    shading: Shading,
+    vertex_colors: bool,
+    mtl: &Arc<dyn Material>,
+) -> Option<HitRecord> {
+    //
+    // Physically based rendering, section 3.6.2, pg 140
+    use math::vec3::{cross, dot, normalize};
+
+    let e1 = p2.pos - p1.pos;
+    let e2 = p3.pos - p1.pos;
+    let s1 = cross(r.direction, e2);
+    let div = dot(s1, e1);
+
+    if div.is_zero() {
+        return None;
+    }
 
-        if b2 < C_ZERO || (b1 + b2) > C_ONE {
-            return None;
-        }
+    let inv_div = div.recip();
 
-        let t = dot(e2, s2) * inv_div;
-        if t < t_min || t > t_max {
-            return None;
-        }
+    let d = r.origin - p1.pos;
+    let b1 = dot(d, s1) * inv_div;
 
-        let b0 = C_ONE - b1 - b2;
-        let n = normalize(b0 * p1.normal + b1 * p2.normal + b2 * p3.normal);
-        if dot(r.direction, n) > C_ZERO {
-            return None;
-        }
+    if b1 < C_ZERO || b1 > C_ONE {
+        return None;
+    }
 
-        let uvs = b0 * p1.uv + b1 * p2.uv + b2 * p3.uv;
+    let s2 = cross(d, e1);
+    let b2 = dot(r.direction, s2) * inv_div;
 
-        // let mtl = self.materials[p1.pbr_buf_id as usize].clone();
-        let mtl = self.mtl.clone();
+    if b2 < C_ZERO || (b1 + b2) > C_ONE {
+        return None;
+    }
 
-        Some(HitRecord::new(r.at(t), n, r, t, mtl, uvs.x, uvs.y))
+    let t = dot(e2, s2) * inv_div;
+    if t < t_min || t > t_max {
+        return None;
     }
+
+    //
+    // the winding decides which side is the front, the vertex normals only shade
+    let geometric_normal = normalize(cross(e1, e2));
+
+    let b0 = C_ONE - b1 - b2;
+    let n = match shading {
+        Shading::Smooth => {
+            //
+            // vertex normals pointing away from the face (bad exports) are flipped to
+            // its side, they would shade the front as a back face
+            let n = normalize(b0 * p1.normal + b1 * p2.normal + b2 * p3.normal);
+            if dot(n, geometric_normal) < C_ZERO {
+                -n
+            } else {
+                n
+            }
+        }
+        Shading::Flat => geometric_normal,
+    };
+
+    let uvs = b0 * p1.uv + b1 * p2.uv + b2 * p3.uv;
+
+    // let mtl = self.materials[p1.pbr_buf_id as usize].clone();
+    let hit = HitRecord::builder(r.at(t), n, r, t, mtl.clone()).uv(uvs.x, uvs.y);
+    let hit = if vertex_colors {
+        let c = p1.color * b0 + p2.color * b1 + p3.color * b2;
+        hit.vertex_color(Color::new(c.x, c.y, c.z))
+    } else {
+        hit
+    };
+
+    //
+    // only imported meshes that came with tangents have them
+    let tangent = b0 * p1.tangent.xyz() + b1 * p2.tangent.xyz() + b2 * p3.tangent.xyz();
+    Some(if math::vec3::is_near_zero(tangent) {
+        hit.build()
+    } else {
+        hit.tangent(normalize(tangent)).build()
+    })
 }
 
 impl Hittable for TriangleMesh {
@@ -247,22 +576,399 @@ impl Hittable for TriangleMesh {
         Some(self.aabb)
     }
 
+    fn name(&self) -> Option<&str> {
+        Some("triangle mesh")
+    }
+
     fn hit(&self, r: &Ray, t_min: Real, t_max: Real) -> Option<HitRecord> {
-        if self.aabb.hit(r, t_min, t_max) {
-            self.nodes
-                .iter()
-                .filter_map(|node| {
-                    if node.aabb.hit(r, t_min, t_max) {
-                        node.indices.chunks(3).find_map(|idx_range| {
-                            self.ray_triangle_intersect_test(idx_range, r, t_min, t_max)
+        self.closest_hit(r, t_min, t_max, &mut HitStats::default(), None)
+    }
+
+    fn hit_skipping(
+        &self,
+        r: &Ray,
+        t_min: Real,
+        t_max: Real,
+        skip: Option<PrimitiveId>,
+    ) -> Option<HitRecord> {
+        self.closest_hit(r, t_min, t_max, &mut HitStats::default(), skip)
+    }
+
+    fn hit_counted(
+        &self,
+        r: &Ray,
+        t_min: Real,
+        t_max: Real,
+        stats: &mut HitStats,
+    ) -> Option<HitRecord> {
+        self.closest_hit(r, t_min, t_max, stats, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //
+    // Octahedron with one vertex per face corner and flat normals, like an exporter
+    // would write it. Face `flip` gets the opposite winding and inward normals.
+    fn octahedron(flip: usize) -> (Vec<GeometryVertex>, Vec<GeometryNode>) {
+        let axis = |a: usize, s: Real| {
+            let mut v = Vec3::broadcast(C_ZERO);
+            v[a] = s;
+            v
+        };
+
+        let mut vertices = Vec::new();
+        [1 as Real, -1 as Real].iter().for_each(|&sx| {
+            [1 as Real, -1 as Real].iter().for_each(|&sy| {
+                [1 as Real, -1 as Real].iter().for_each(|&sz| {
+                    let mut corners = [axis(0, sx), axis(1, sy), axis(2, sz)];
+                    //
+                    // counter clockwise seen from outside
+                    if sx * sy * sz < C_ZERO {
+                        corners.swap(1, 2);
+                    }
+                    if vertices.len() / 3 == flip {
+                        corners.swap(1, 2);
+                    }
+
+                    let n = math::vec3::normalize(math::vec3::cross(
+                        corners[1] - corners[0],
+                        corners[2] - corners[0],
+                    ));
+                    corners.iter().for_each(|&pos| {
+                        vertices.push(GeometryVertex {
+                            pos,
+                            normal: n,
+                            ..Default::default()
                         })
+                    });
+                });
+            });
+        });
+
+        let nodes = vec![GeometryNode {
+            indices: (0..vertices.len() as u32).collect(),
+            ..Default::default()
+        }];
+
+        (vertices, nodes)
+    }
+
+    fn face_faces_outward(mesh: &TriangleMesh, face: usize) -> bool {
+        use math::vec3::{cross, dot};
+        let idx = &mesh.nodes[0].indices[face * 3..face * 3 + 3];
+        let [p0, p1, p2] = [idx[0], idx[1], idx[2]].map(|i| mesh.vertices[i as usize].pos);
+        dot(cross(p1 - p0, p2 - p0), p0 + p1 + p2) > C_ZERO
+    }
+
+    #[test]
+    fn test_orient_winding_fixes_flipped_triangle() {
+        const FLIPPED: usize = 5;
+        let (vertices, nodes) = octahedron(FLIPPED);
+        let mesh = TriangleMesh::from_vertices(
+            &vertices,
+            &nodes,
+            math::mat4::consts::identity(),
+            Arc::new(Lambertian::new((0.5 as Real, 0.5 as Real, 0.5 as Real))),
+        );
+
+        //
+        // ray aimed at the center of the flipped face, from outside
+        let target = {
+            let idx = &mesh.nodes[0].indices[FLIPPED * 3..FLIPPED * 3 + 3];
+            idx.iter().fold(Vec3::broadcast(C_ZERO), |c, &i| {
+                c + mesh.vertices[i as usize].pos
+            }) / 3 as Real
+        };
+        let r = Ray::new(target * 4 as Real, -target, C_ZERO);
+
+        assert!(!face_faces_outward(&mesh, FLIPPED));
+        assert!(!mesh.hit(&r, 0.001, Real::MAX).unwrap().front_face);
+
+        //
+        // the winding is left as imported unless asked for
+        let mut mesh = mesh.with_oriented_winding(false);
+        assert!(!face_faces_outward(&mesh, FLIPPED));
+
+        mesh.orient_winding();
+
+        (0..8).for_each(|face| assert!(face_faces_outward(&mesh, face)));
+        let hit = mesh.hit(&r, 0.001, Real::MAX).unwrap();
+        assert!(hit.front_face);
+        assert!((hit.t - 3 as Real).abs() < 1.0E-4);
+    }
+
+    #[test]
+    fn test_vertex_colors_interpolated_at_centroid() {
+        use crate::material::ScatterRecord;
+        use crate::types::Vec4;
+
+        let vertices = [
+            (
+                Vec3::new(-1 as Real, C_ZERO, C_ZERO),
+                Vec4::new(C_ONE, C_ZERO, C_ZERO, C_ONE),
+            ),
+            (
+                Vec3::new(1 as Real, C_ZERO, C_ZERO),
+                Vec4::new(C_ZERO, C_ONE, C_ZERO, C_ONE),
+            ),
+            (
+                Vec3::new(C_ZERO, 1 as Real, C_ZERO),
+                Vec4::new(C_ZERO, C_ZERO, C_ONE, C_ONE),
+            ),
+        ]
+        .map(|(pos, color)| GeometryVertex {
+            pos,
+            normal: Vec3::new(C_ZERO, C_ZERO, C_ONE),
+            color,
+            ..Default::default()
+        });
+        let nodes = [GeometryNode {
+            indices: vec![0, 1, 2],
+            ..Default::default()
+        }];
+
+        let mesh = TriangleMesh::from_vertices(
+            &vertices,
+            &nodes,
+            math::mat4::consts::identity(),
+            Arc::new(Lambertian::new((0.5 as Real, 0.5 as Real, 0.5 as Real))),
+        )
+        .with_vertex_colors(true);
+
+        let centroid = Vec3::new(C_ZERO, 1 as Real / 3 as Real, C_ZERO);
+        let r = Ray::new(
+            centroid + Vec3::new(C_ZERO, C_ZERO, 2 as Real),
+            Vec3::new(C_ZERO, C_ZERO, -1 as Real),
+            C_ZERO,
+        );
+        let hit = mesh.hit(&r, 0.001, Real::MAX).unwrap();
+
+        match hit.mtl.scatter(&r, &hit).unwrap() {
+            ScatterRecord::PdfRec { attenuation, .. } => {
+                [attenuation.r, attenuation.g, attenuation.b]
+                    .iter()
+                    .for_each(|&c| assert!((c - 1 as Real / 3 as Real).abs() < 1.0e-5));
+            }
+            _ => panic!("Vertex colored mesh should scatter diffusely"),
+        }
+
+        //
+        // the color comes with the hit, every hit shares the mesh's material
+        let other = mesh
+            .hit(
+                &Ray::new(
+                    Vec3::new(-0.5 as Real, 0.25 as Real, 2 as Real),
+                    Vec3::new(C_ZERO, C_ZERO, -1 as Real),
+                    C_ZERO,
+                ),
+                0.001,
+                Real::MAX,
+            )
+            .unwrap();
+        assert!(Arc::ptr_eq(&hit.mtl, &other.mtl));
+        assert_ne!(hit.vertex_color, other.vertex_color);
+    }
+
+    #[test]
+    fn test_flat_shading_gives_facets() {
+        use math::vec3::{dot, length, normalize};
+
+        //
+        // octahedron with shared vertices and normals pointing away from the center,
+        // the coarsest sphere
+        let vertices = [
+            Vec3::new(C_ONE, C_ZERO, C_ZERO),
+            Vec3::new(-C_ONE, C_ZERO, C_ZERO),
+            Vec3::new(C_ZERO, C_ONE, C_ZERO),
+            Vec3::new(C_ZERO, -C_ONE, C_ZERO),
+            Vec3::new(C_ZERO, C_ZERO, C_ONE),
+            Vec3::new(C_ZERO, C_ZERO, -C_ONE),
+        ]
+        .map(|pos| GeometryVertex {
+            pos,
+            normal: pos,
+            ..Default::default()
+        });
+        let mut indices = Vec::new();
+        (0..2u32).for_each(|x| {
+            (0..2u32).for_each(|y| {
+                (0..2u32).for_each(|z| {
+                    let (a, b, c) = (x, 2 + y, 4 + z);
+                    //
+                    // counter clockwise seen from outside
+                    if (x + y + z) % 2 == 0 {
+                        indices.extend([a, b, c]);
                     } else {
-                        None
+                        indices.extend([a, c, b]);
                     }
                 })
-                .reduce(|hit0, hit1| if hit0.t < hit1.t { hit0 } else { hit1 })
-        } else {
-            None
-        }
+            })
+        });
+        let nodes = [GeometryNode {
+            indices,
+            ..Default::default()
+        }];
+
+        let mesh = |shading: Shading| {
+            TriangleMesh::from_vertices(
+                &vertices,
+                &nodes,
+                math::mat4::consts::identity(),
+                Arc::new(Lambertian::new((0.5 as Real, 0.5 as Real, 0.5 as Real))),
+            )
+            .with_shading(shading)
+        };
+        //
+        // normal at a point inside the face in the +x +y +z octant, hit from outside
+        let normal_at = |mesh: &TriangleMesh, p: Vec3| {
+            let r = Ray::new(p * 3 as Real, -p, C_ZERO);
+            mesh.hit(&r, 0.001, Real::MAX).unwrap().normal
+        };
+        let interior = [
+            Vec3::new(0.6, 0.2, 0.2),
+            Vec3::new(0.2, 0.6, 0.2),
+            Vec3::new(0.2, 0.2, 0.6),
+        ];
+        let face_normal = normalize(Vec3::broadcast(C_ONE));
+
+        let flat = mesh(Shading::Flat);
+        interior.iter().for_each(|&p| {
+            assert!(length(normal_at(&flat, p) - face_normal) < 1.0e-5);
+        });
+
+        //
+        // smooth normals lean towards the closest corner
+        let smooth = mesh(Shading::Smooth);
+        let normals = interior.map(|p| normal_at(&smooth, p));
+        normals.iter().for_each(|&n| {
+            assert!(dot(n, face_normal) < 1 as Real - 1.0e-2);
+        });
+        assert!(dot(normals[0], normals[1]) < 1 as Real - 1.0e-2);
+        (0..3).for_each(|a| assert!(normals[a][a] > normals[(a + 1) % 3][a]));
+    }
+
+    #[test]
+    fn test_backward_vertex_normals_are_flipped_to_the_face() {
+        use math::vec3::dot;
+
+        //
+        // counter clockwise seen from +z, the vertex normals point to -z and lean outwards
+        let vertices = [
+            Vec3::new(-1 as Real, C_ZERO, C_ZERO),
+            Vec3::new(1 as Real, C_ZERO, C_ZERO),
+            Vec3::new(C_ZERO, 1 as Real, C_ZERO),
+        ]
+        .map(|pos| GeometryVertex {
+            pos,
+            normal: math::vec3::normalize(pos * 0.5 as Real - Vec3::new(C_ZERO, C_ZERO, C_ONE)),
+            ..Default::default()
+        });
+        let nodes = [GeometryNode {
+            indices: vec![0, 1, 2],
+            ..Default::default()
+        }];
+        let mesh = TriangleMesh::from_vertices(
+            &vertices,
+            &nodes,
+            math::mat4::consts::identity(),
+            Arc::new(Lambertian::new((0.5 as Real, 0.5 as Real, 0.5 as Real))),
+        );
+
+        let target = Vec3::new(0.2 as Real, 0.3 as Real, C_ZERO);
+        let down = Vec3::new(C_ZERO, C_ZERO, -1 as Real);
+        let hit = mesh
+            .hit(
+                &Ray::new(target + Vec3::new(C_ZERO, C_ZERO, 2 as Real), down, C_ZERO),
+                0.001,
+                Real::MAX,
+            )
+            .unwrap();
+
+        assert!(hit.front_face);
+        assert!(dot(hit.normal, down) < C_ZERO);
+        //
+        // still interpolated, only flipped
+        assert!(hit.normal.z < 1 as Real - 1.0e-3);
+
+        //
+        // the back of the face is a back face, whatever the vertex normals say
+        let back = mesh
+            .hit(
+                &Ray::new(target - Vec3::new(C_ZERO, C_ZERO, 2 as Real), -down, C_ZERO),
+                0.001,
+                Real::MAX,
+            )
+            .unwrap();
+        assert!(!back.front_face);
+        assert!(dot(back.normal, -down) < C_ZERO);
+    }
+
+    #[test]
+    fn test_imported_mesh_hits_the_front_most_triangle_of_every_node() {
+        use crate::types::{random_real, random_unit_vector};
+
+        let geometry = ImportedGeometry::import_from_file(&concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../data/models/teapot/teapot.glb"
+        ))
+        .unwrap();
+        let mtl: Arc<dyn Material> =
+            Arc::new(Lambertian::new((0.5 as Real, 0.5 as Real, 0.5 as Real)));
+        let mesh = TriangleMesh::from_vertices(
+            geometry.vertices(),
+            geometry.nodes(),
+            math::mat4::consts::identity(),
+            mtl.clone(),
+        );
+
+        //
+        // every triangle of every node, from the node's range of the shared index buffer
+        let triangles = geometry
+            .nodes()
+            .iter()
+            .flat_map(|node| geometry.indices()[node.index_range.clone()].chunks(3))
+            .map(|idx| [idx[0], idx[1], idx[2]].map(|i| &geometry.vertices()[i as usize]))
+            .collect::<Vec<_>>();
+        assert_eq!(triangles.len() * 3, geometry.indices().len());
+
+        let bbox = mesh.bounding_box(C_ZERO, C_ZERO).unwrap();
+        let center = bbox.center();
+        let radius = math::vec3::length(bbox.max - bbox.min);
+
+        //
+        // rays from all around at points inside the box, the mesh's hit must be the
+        // closest of all the triangles the ray goes through
+        let mut through_several = 0;
+        (0..200).for_each(|_| {
+            let jitter =
+                Vec3::new(random_real(), random_real(), random_real()) - Vec3::broadcast(0.5);
+            let target = center + jitter * (bbox.max - bbox.min) * 0.5 as Real;
+            let origin = center + random_unit_vector() * radius;
+            let r = Ray::new(origin, target - origin, C_ZERO);
+
+            let hits = triangles
+                .iter()
+                .filter_map(|&tri| {
+                    intersect_triangle(tri, &r, 0.001, Real::MAX, Shading::Smooth, false, &mtl)
+                })
+                .map(|hit| hit.t)
+                .collect::<Vec<_>>();
+            if hits.len() > 1 {
+                through_several += 1;
+            }
+
+            let nearest = hits.iter().cloned().reduce(Real::min);
+            let hit = mesh.hit(&r, 0.001, Real::MAX).map(|hit| hit.t);
+            match (nearest, hit) {
+                (Some(nearest), Some(t)) => assert!((nearest - t).abs() < 1.0e-4 * nearest),
+                (None, None) => {}
+                _ => panic!("{:?} {:?}", nearest, hit),
+            }
+        });
+
+        assert!(through_several > 50, "{}", through_several);
     }
 }
@@ -37,21 +37,84 @@ pub type Vec2 = math::vec2::TVec2<Real>;
 pub type Vec3 = math::vec3::TVec3<Real>;
 pub type Vec4 = math::vec4::TVec4<Real>;
 pub type Ray = math::ray::TRay<Real>;
+pub type RayDifferentials = math::ray::TRayDifferentials<Real>;
 pub type Point = Vec3;
 pub type Color = math::colors::RGBAColorF32;
+pub type Mat3 = math::mat3::Mat3<Real>;
 pub type Mat4 = math::mat4::Mat4<Real>;
 
 pub fn degrees_to_radians(degrees: Real) -> Real {
     (degrees * C_PI) / 180 as Real
 }
 
+thread_local! {
+    //
+    // generator of the pixel currently traced on this thread, None draws from the
+    // thread's entropy seeded generator
+    static SEEDED_RNG: std::cell::RefCell<Option<rand::rngs::StdRng>> =
+        const { std::cell::RefCell::new(None) };
+}
+
+fn with_rng<T>(f: impl FnOnce(&mut dyn rand::RngCore) -> T) -> T {
+    SEEDED_RNG.with(|rng| match rng.borrow_mut().as_mut() {
+        Some(rng) => f(rng),
+        None => f(&mut rand::thread_rng()),
+    })
+}
+
+/// Puts back the generator the calling thread drew from before `seed_random` when
+/// dropped, so a seeded render leaves nothing behind on the workers that traced it.
+#[must_use]
+pub struct SeededRandom {
+    previous: Option<rand::rngs::StdRng>,
+}
+
+impl Drop for SeededRandom {
+    fn drop(&mut self) {
+        SEEDED_RNG.with(|rng| *rng.borrow_mut() = self.previous.take());
+    }
+}
+
+/// Makes the random functions of this module draw from a generator seeded with `seed`
+/// on the calling thread, None from the unseeded one, until the returned guard is dropped.
+pub fn seed_random(seed: Option<u64>) -> SeededRandom {
+    use rand::SeedableRng;
+    SEEDED_RNG.with(|rng| SeededRandom {
+        previous: std::mem::replace(
+            &mut *rng.borrow_mut(),
+            seed.map(rand::rngs::StdRng::seed_from_u64),
+        ),
+    })
+}
+
+/// Seed of the random stream of pixel (x, y) in a render seeded with `seed`, for the frame
+/// starting at `time`. Neighbouring pixels and frames get unrelated seeds (splitmix64
+/// finalizer over the packed coordinates), so the noise has no structure however the
+/// pixels are spread over the workers and does not stand still in animations.
+pub fn pixel_seed(x: i32, y: i32, time: Real, seed: u64) -> u64 {
+    let mix = |mut z: u64| {
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    };
+
+    let coords = ((x as u32 as u64) << 32) | y as u32 as u64;
+    let frame = mix(seed.wrapping_add(0x9e3779b97f4a7c15)) ^ time.to_bits() as u64;
+    mix(mix(frame) ^ coords)
+}
+
 pub fn random_real() -> Real {
-    rand::thread_rng().gen_range(0.0, 1.0) as Real
+    with_rng(|rng| rng.gen_range(0.0, 1.0) as Real)
 }
 
 pub fn random_real_range(min: Real, max: Real) -> Real {
-    use rand::prelude::*;
-    thread_rng().gen_range(min, max)
+    with_rng(|rng| rng.gen_range(min, max))
+}
+
+/// Shuffles `values` with the same generator as the random functions of this module.
+pub fn random_shuffle<T>(values: &mut [T]) {
+    use rand::seq::SliceRandom;
+    with_rng(|rng| values.shuffle(rng))
 }
 
 pub fn clamp(x: Real, min: Real, max: Real) -> Real {
@@ -104,7 +167,7 @@ pub fn random_in_unit_sphere() -> Vec3 {
 }
 
 pub fn random_int(min: i32, max: i32) -> i32 {
-    rand::thread_rng().gen_range(min, max + 1)
+    with_rng(|rng| rng.gen_range(min, max + 1))
 }
 
 pub fn random_unit_vector() -> Vec3 {
@@ -91,11 +91,11 @@ impl UiBackend {
 
         let vs_code = include_str!("../../data/shaders/ui.vert");
         let vs = create_shader_program_from_string(vs_code, rendering::ShaderType::Vertex)
-            .expect("Failed to create UI vertex shader");
+            .unwrap_or_else(|e| panic!("Failed to create UI vertex shader: {}", e));
 
         let fs_code = include_str!("../../data/shaders/ui.frag");
         let fs = create_shader_program_from_string(fs_code, rendering::ShaderType::Fragment)
-            .expect("Failed to create UI fragment shader");
+            .unwrap_or_else(|e| panic!("Failed to create UI fragment shader: {}", e));
 
         let pipeline = UniquePipeline::new(unsafe {
             let mut pipeline = 0u32;
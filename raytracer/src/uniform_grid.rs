@@ -0,0 +1,355 @@
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    aabb3::Aabb,
+    bvh::{BvhNode, FlatBvh},
+    hittable::{HitRecord, HitStats, Hittable, PrimitiveId},
+    hittable_list::HittableList,
+    types::{Ray, Real},
+};
+
+/// Acceleration structure built over large groups of objects in the scenes.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Accelerator {
+    #[default]
+    Bvh,
+    /// The BVH flattened into an array, cheaper to traverse.
+    FlatBvh,
+    /// Better than the BVH for many small, uniformly distributed objects.
+    UniformGrid,
+}
+
+impl Accelerator {
+    pub fn build(
+        self,
+        objects: &mut [Arc<dyn Hittable>],
+        time0: Real,
+        time1: Real,
+    ) -> Arc<dyn Hittable> {
+        match self {
+            Accelerator::Bvh => BvhNode::new(objects, time0, time1),
+            Accelerator::FlatBvh => FlatBvh::build_world(objects, time0, time1),
+            Accelerator::UniformGrid => UniformGrid::build_world(objects, time0, time1),
+        }
+    }
+}
+
+/// Objects binned into the voxels of a regular grid over their bounds, rays walk the
+/// voxels they pass through front to back (3D DDA) and stop at the first voxel that
+/// contains a hit.
+pub struct UniformGrid {
+    bbox: Aabb,
+    dims: [usize; 3],
+    cell_size: [Real; 3],
+    objects: Vec<Arc<dyn Hittable>>,
+    /// Indices into `objects`, for every voxel. Objects are in all the voxels their box overlaps.
+    cells: Vec<Vec<u32>>,
+}
+
+impl UniformGrid {
+    /// Objects per voxel the resolution is picked for.
+    const OBJECTS_PER_CELL: Real = 3 as Real;
+    const MAX_CELLS_PER_AXIS: usize = 128;
+
+    /// Builds a grid over the objects. Like the BVH, objects without a bounding box are
+    /// kept in a list next to the grid.
+    pub fn build_world(l: &mut [Arc<dyn Hittable>], time0: Real, time1: Real) -> Arc<dyn Hittable> {
+        let (bounded, unbounded): (Vec<_>, Vec<_>) = l
+            .iter()
+            .cloned()
+            .partition(|obj| obj.bounding_box(time0, time1).is_some());
+
+        if unbounded.is_empty() && !bounded.is_empty() {
+            return Arc::new(Self::build(bounded, time0, time1));
+        }
+
+        let mut world = HittableList::new();
+        if !bounded.is_empty() {
+            world.add(Arc::new(Self::build(bounded, time0, time1)));
+        }
+        unbounded.into_iter().for_each(|obj| world.add(obj));
+
+        Arc::new(world)
+    }
+
+    fn build(objects: Vec<Arc<dyn Hittable>>, time0: Real, time1: Real) -> UniformGrid {
+        let boxes = objects
+            .iter()
+            .map(|obj| obj.bounding_box(time0, time1).unwrap())
+            .collect::<Vec<_>>();
+
+        //
+        // padded so that voxels of flat groups still have some thickness
+        let bbox = boxes
+            .iter()
+            .skip(1)
+            .fold(boxes[0], |acc, b| crate::aabb3::merge_aabbs(&acc, b))
+            .padded(1.0E-3 as Real);
+
+        //
+        // voxels about as wide as they are deep, sized for OBJECTS_PER_CELL objects each
+        let extent = bbox.max - bbox.min;
+        let volume = extent.x * extent.y * extent.z;
+        let cells_per_unit =
+            (objects.len() as Real / Self::OBJECTS_PER_CELL / volume).powf(1 as Real / 3 as Real);
+
+        let mut dims = [1usize; 3];
+        let mut cell_size = [0 as Real; 3];
+        (0..3).for_each(|a| {
+            dims[a] =
+                ((extent[a] * cells_per_unit).round() as usize).clamp(1, Self::MAX_CELLS_PER_AXIS);
+            cell_size[a] = extent[a] / dims[a] as Real;
+        });
+
+        let mut grid = UniformGrid {
+            bbox,
+            dims,
+            cell_size,
+            objects,
+            cells: vec![Vec::new(); dims[0] * dims[1] * dims[2]],
+        };
+
+        boxes.iter().enumerate().for_each(|(idx, b)| {
+            let lo = grid.voxel(b.min);
+            let hi = grid.voxel(b.max);
+
+            (lo[2]..=hi[2]).for_each(|z| {
+                (lo[1]..=hi[1]).for_each(|y| {
+                    (lo[0]..=hi[0]).for_each(|x| {
+                        let cell = grid.cell_index([x, y, z]);
+                        grid.cells[cell].push(idx as u32);
+                    })
+                })
+            })
+        });
+
+        grid
+    }
+
+    fn voxel(&self, p: crate::types::Point) -> [usize; 3] {
+        let mut v = [0usize; 3];
+        (0..3).for_each(|a| {
+            let i = ((p[a] - self.bbox.min[a]) / self.cell_size[a]).floor();
+            v[a] = (i.max(0 as Real) as usize).min(self.dims[a] - 1);
+        });
+        v
+    }
+
+    fn cell_index(&self, v: [usize; 3]) -> usize {
+        v[0] + self.dims[0] * (v[1] + self.dims[1] * v[2])
+    }
+
+    //
+    // parametric range of the ray inside the grid bounds
+    fn bounds_interval(&self, r: &Ray, t_min: Real, t_max: Real) -> Option<(Real, Real)> {
+        (0..3).try_fold((t_min, t_max), |(t0, t1), a| {
+            let inv_d = 1 as Real / r.direction[a];
+            let ta = (self.bbox.min[a] - r.origin[a]) * inv_d;
+            let tb = (self.bbox.max[a] - r.origin[a]) * inv_d;
+
+            let t0 = t0.max(ta.min(tb));
+            let t1 = t1.min(ta.max(tb));
+
+            if t1 < t0 {
+                None
+            } else {
+                Some((t0, t1))
+            }
+        })
+    }
+
+    //
+    // hit_object tests an object for the [t_min, t_max] interval passed to it,
+    // visit_cell is called for every voxel walked through
+    fn closest_hit<F, V>(
+        &self,
+        r: &Ray,
+        t_min: Real,
+        t_max: Real,
+        mut hit_object: F,
+        mut visit_cell: V,
+    ) -> Option<HitRecord>
+    where
+        F: FnMut(&dyn Hittable, Real, Real) -> Option<HitRecord>,
+        V: FnMut(),
+    {
+        let (t_enter, t_exit) = self.bounds_interval(r, t_min, t_max)?;
+
+        let mut voxel = self.voxel(r.at(t_enter));
+        let mut step = [0isize; 3];
+        let mut t_delta = [Real::INFINITY; 3];
+        let mut t_next = [Real::INFINITY; 3];
+
+        (0..3).for_each(|a| {
+            let d = r.direction[a];
+            if d > 0 as Real {
+                step[a] = 1;
+                t_delta[a] = self.cell_size[a] / d;
+                let boundary = self.bbox.min[a] + (voxel[a] + 1) as Real * self.cell_size[a];
+                t_next[a] = (boundary - r.origin[a]) / d;
+            } else if d < 0 as Real {
+                step[a] = -1;
+                t_delta[a] = -self.cell_size[a] / d;
+                let boundary = self.bbox.min[a] + voxel[a] as Real * self.cell_size[a];
+                t_next[a] = (boundary - r.origin[a]) / d;
+            }
+        });
+
+        let mut closest: Option<HitRecord> = None;
+        loop {
+            visit_cell();
+
+            let t_closest = closest.as_ref().map_or(t_max, |hit| hit.t);
+            let cell_hit = self.cells[self.cell_index(voxel)]
+                .iter()
+                .filter_map(|&idx| {
+                    hit_object(self.objects[idx as usize].as_ref(), t_min, t_closest)
+                })
+                .reduce(|a, b| if a.t < b.t { a } else { b });
+            if cell_hit.is_some() {
+                closest = cell_hit;
+            }
+
+            //
+            // objects span several voxels, a hit beyond this voxel could still be behind
+            // an object in the next one
+            let axis = (0..3)
+                .min_by(|&a, &b| t_next[a].partial_cmp(&t_next[b]).unwrap())
+                .unwrap();
+            let t_cell_exit = t_next[axis];
+
+            if closest.as_ref().map_or(false, |hit| hit.t <= t_cell_exit) || t_cell_exit > t_exit {
+                return closest;
+            }
+
+            let next = voxel[axis] as isize + step[axis];
+            if next < 0 || next >= self.dims[axis] as isize {
+                return closest;
+            }
+
+            voxel[axis] = next as usize;
+            t_next[axis] += t_delta[axis];
+        }
+    }
+}
+
+impl Hittable for UniformGrid {
+    fn hit(&self, r: &Ray, t_min: Real, t_max: Real) -> Option<HitRecord> {
+        self.hit_counted(r, t_min, t_max, &mut HitStats::default())
+    }
+
+    fn hit_counted(
+        &self,
+        r: &Ray,
+        t_min: Real,
+        t_max: Real,
+        stats: &mut HitStats,
+    ) -> Option<HitRecord> {
+        //
+        // voxels walked count as node visits
+        let visits = std::cell::Cell::new(0);
+        let hit = self.closest_hit(
+            r,
+            t_min,
+            t_max,
+            |obj, t0, t1| obj.hit_counted(r, t0, t1, stats),
+            || visits.set(visits.get() + 1),
+        );
+        stats.node_visits += visits.get();
+        hit
+    }
+
+    fn hit_skipping(
+        &self,
+        r: &Ray,
+        t_min: Real,
+        t_max: Real,
+        skip: Option<PrimitiveId>,
+    ) -> Option<HitRecord> {
+        self.closest_hit(
+            r,
+            t_min,
+            t_max,
+            |obj, t0, t1| obj.hit_skipping(r, t0, t1, skip),
+            || {},
+        )
+    }
+
+    fn name(&self) -> Option<&str> {
+        Some("uniform grid")
+    }
+
+    fn bounding_box(&self, _time0: Real, _time1: Real) -> Option<Aabb> {
+        Some(self.bbox)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lambertian::Lambertian;
+    use crate::objects::sphere::Sphere;
+    use crate::types::{random_real_range, random_unit_vector, Point, Vec3};
+
+    #[test]
+    fn test_grid_matches_bvh() {
+        let mtl = Arc::new(Lambertian::new((0.5, 0.5, 0.5)));
+        let mut objects = (0..1000)
+            .map(|_| {
+                let center = Point::new(
+                    random_real_range(0 as Real, 10 as Real),
+                    random_real_range(0 as Real, 10 as Real),
+                    random_real_range(0 as Real, 10 as Real),
+                );
+                Arc::new(Sphere::new(
+                    center,
+                    random_real_range(0.2, 0.6),
+                    mtl.clone(),
+                )) as Arc<dyn Hittable>
+            })
+            .collect::<Vec<_>>();
+
+        let grid = UniformGrid::build_world(&mut objects, 0 as Real, 1 as Real);
+        let bvh = BvhNode::new(&mut objects, 0 as Real, 1 as Real);
+
+        let center = Point::broadcast(5 as Real);
+        let mut hits = 0;
+        (0..2000).for_each(|i| {
+            //
+            // from outside, aimed into the field, and from inside it
+            let r = if i % 2 == 0 {
+                let origin = center + random_unit_vector() * 30 as Real;
+                let target =
+                    center + random_unit_vector() * random_real_range(0 as Real, 5 as Real);
+                Ray::new(origin, target - origin, 0 as Real)
+            } else {
+                let origin =
+                    center + random_unit_vector() * random_real_range(0 as Real, 5 as Real);
+                Ray::new(origin, random_unit_vector(), 0 as Real)
+            };
+
+            match (
+                grid.hit(&r, 0.001 as Real, Real::MAX),
+                bvh.hit(&r, 0.001 as Real, Real::MAX),
+            ) {
+                (Some(g), Some(b)) => {
+                    hits += 1;
+                    assert!((g.t - b.t).abs() < 1.0e-4, "grid {} bvh {}", g.t, b.t);
+                }
+                (g, b) => assert_eq!(g.is_some(), b.is_some()),
+            }
+        });
+        assert!(hits > 500);
+
+        let mut stats = HitStats::default();
+        let r = Ray::new(
+            Point::broadcast(-5 as Real),
+            Vec3::broadcast(1 as Real),
+            0 as Real,
+        );
+        grid.hit_counted(&r, 0.001 as Real, Real::MAX, &mut stats);
+        assert!(stats.node_visits > 0);
+    }
+}
@@ -0,0 +1,143 @@
+use crate::{
+    aabb3::Aabb,
+    perlin::PerlinNoise,
+    types::{Point, Real},
+};
+
+/// Scalar field defined over space, sampled at world positions (e.g. the density of a
+/// participating medium).
+pub trait VolumeTexture: Send + Sync {
+    fn value(&self, p: Point) -> Real;
+
+    /// Upper bound of the values over all of space.
+    fn max_value(&self) -> Real;
+}
+
+/// Values stored on a regular 3D grid spanning `bounds`, sampled with trilinear
+/// interpolation. Points outside the bounds get the value of the closest grid point.
+pub struct GridVolume {
+    bounds: Aabb,
+    dims: [usize; 3],
+    values: Vec<Real>,
+}
+
+impl GridVolume {
+    /// `values` are laid out x first, then y, then z.
+    pub fn new(bounds: Aabb, dims: [usize; 3], values: Vec<Real>) -> GridVolume {
+        assert!(
+            dims.iter().all(|&d| d > 0),
+            "Grid dimensions must be non zero"
+        );
+        assert_eq!(
+            values.len(),
+            dims[0] * dims[1] * dims[2],
+            "Grid value count does not match the dimensions"
+        );
+
+        GridVolume {
+            bounds,
+            dims,
+            values,
+        }
+    }
+
+    fn at(&self, x: usize, y: usize, z: usize) -> Real {
+        self.values[x + self.dims[0] * (y + self.dims[1] * z)]
+    }
+}
+
+impl VolumeTexture for GridVolume {
+    fn value(&self, p: Point) -> Real {
+        //
+        // grid coordinates, lower corner of the cell and the position inside it
+        let cell = |a: usize| {
+            let extent = self.bounds.max[a] - self.bounds.min[a];
+            let last = (self.dims[a] - 1) as Real;
+            let g = if extent > 0 as Real {
+                ((p[a] - self.bounds.min[a]) / extent * last)
+                    .max(0 as Real)
+                    .min(last)
+            } else {
+                0 as Real
+            };
+
+            let i = (g.floor() as usize).min(self.dims[a].saturating_sub(2));
+            let i1 = (i + 1).min(self.dims[a] - 1);
+            (i, i1, g - i as Real)
+        };
+
+        let (x0, x1, fx) = cell(0);
+        let (y0, y1, fy) = cell(1);
+        let (z0, z1, fz) = cell(2);
+
+        let lerp = |a: Real, b: Real, t: Real| a + (b - a) * t;
+        let plane = |z: usize| {
+            lerp(
+                lerp(self.at(x0, y0, z), self.at(x1, y0, z), fx),
+                lerp(self.at(x0, y1, z), self.at(x1, y1, z), fx),
+                fy,
+            )
+        };
+
+        lerp(plane(z0), plane(z1), fz)
+    }
+
+    fn max_value(&self) -> Real {
+        //
+        // interpolation can't go past the grid values
+        self.values.iter().copied().fold(Real::MIN, Real::max)
+    }
+}
+
+/// Procedural volume from Perlin noise, values in [0, max_value].
+pub struct NoiseVolume {
+    perlin: PerlinNoise,
+    scale: Real,
+    max_value: Real,
+}
+
+impl NoiseVolume {
+    pub fn new(scale: Real, max_value: Real) -> NoiseVolume {
+        NoiseVolume {
+            perlin: PerlinNoise::new(),
+            scale,
+            max_value,
+        }
+    }
+}
+
+impl VolumeTexture for NoiseVolume {
+    fn value(&self, p: Point) -> Real {
+        let n = self.perlin.noise(p * self.scale);
+        self.max_value
+            * (0.5 as Real * (1 as Real + n))
+                .max(0 as Real)
+                .min(1 as Real)
+    }
+
+    fn max_value(&self) -> Real {
+        self.max_value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grid_trilinear_sampling() {
+        let grid = GridVolume::new(
+            Aabb::new(Point::broadcast(0 as Real), Point::broadcast(1 as Real)),
+            [2, 2, 2],
+            vec![0.0, 1.0, 0.0, 1.0, 2.0, 3.0, 2.0, 3.0],
+        );
+
+        assert_eq!(grid.value(Point::broadcast(0 as Real)), 0 as Real);
+        assert_eq!(grid.value(Point::broadcast(1 as Real)), 3 as Real);
+        assert!((grid.value(Point::broadcast(0.5 as Real)) - 1.5 as Real).abs() < 1.0e-6);
+        assert!((grid.value(Point::new(0.25, 0.0, 0.5)) - 1.25 as Real).abs() < 1.0e-6);
+        //
+        // clamped outside of the bounds
+        assert_eq!(grid.value(Point::new(-5.0, -5.0, 5.0)), 2 as Real);
+    }
+}
@@ -0,0 +1,103 @@
+use raytracer::{display_color, quantize_colors, render, tone_curve::ToneCurve, RaytracerConfig};
+
+const TINY_CONFIG: &str = "(
+    active_scene: TwoSpheres,
+    default_params: (
+        workers: 1,
+        worker_block_pixels: 8,
+        aspect_ratio: 2.0,
+        image_width: 32,
+        samples_per_pixel: 4,
+        max_ray_depth: 4,
+        vertical_fov: 20.0,
+        look_from: (13.0, 2.0, 3.0),
+        look_at: (0.0, 0.0, 0.0),
+        world_up: (0.0, 1.0, 0.0),
+        aperture: 0.0,
+        focus_dist: 10.0,
+        shuffle_workblocks: false,
+        background: (0.7, 0.8, 1.0),
+        projection: Perspective,
+        psi_max: 90.0,
+        lambda_max: 180.0,
+    ),
+    defined_scenes: [(TwoSpheres, None)],
+)";
+
+#[test]
+fn test_render_tiny_config() {
+    let config: RaytracerConfig = ron::de::from_str(TINY_CONFIG).expect("Invalid test config");
+    let result = render(config, true).unwrap();
+
+    assert_eq!((result.width, result.height), (32, 16));
+    assert_eq!(result.pixels.len(), 32 * 16);
+    //
+    // not clamped, but the gamma correction keeps them positive
+    assert!(result
+        .pixels
+        .iter()
+        .all(|c| [c.r, c.g, c.b].iter().all(|v| v.is_finite() && *v >= 0.0)));
+
+    //
+    // the sky is visible around the spheres, the spheres are darker than it
+    let brightness = |c: &raytracer::types::Color| c.r + c.g + c.b;
+    let brightest = result.pixels.iter().map(brightness).fold(0.0, f32::max);
+    let darkest = result.pixels.iter().map(brightness).fold(3.0, f32::min);
+    assert!(brightest > 1.5);
+    assert!(darkest < brightest);
+
+    let stats = result.stats.expect("Statistics were asked for");
+    assert!(stats.total_rays >= 32 * 16 * 4);
+    assert_eq!(stats.block_times.len(), 1);
+}
+
+#[test]
+fn test_linear_and_display_images_from_one_render() {
+    let mut config: RaytracerConfig = ron::de::from_str(TINY_CONFIG).expect("Invalid test config");
+    config.tone_curve =
+        ron::de::from_str("Some((mode: PerChannel, points: [(0.0, 0.1), (0.5, 0.4), (1.0, 0.9)]))")
+            .expect("Invalid tone curve");
+    let curve = ToneCurve::new(config.tone_curve.as_ref().unwrap());
+    let result = render(config, false).unwrap();
+    assert!(result.stats.is_none());
+
+    let dir = std::env::temp_dir().join(format!("raytracer_dump_both_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let (exr_file, png_file) = result.save_linear_and_display(dir.join("tiny"));
+    assert_eq!(exr_file, dir.join("tiny.exr"));
+    assert_eq!(png_file, dir.join("tiny.png"));
+
+    //
+    // the EXR keeps the linear colors as they are
+    let exr = image::open(&exr_file).unwrap().into_rgb32f();
+    assert_eq!(exr.dimensions(), (result.width, result.height));
+    let exr_colors = exr
+        .pixels()
+        .map(|px| raytracer::types::Color::new(px[0], px[1], px[2]))
+        .collect::<Vec<_>>();
+    exr_colors
+        .iter()
+        .zip(result.linear_pixels.iter())
+        .for_each(|(a, b)| assert_eq!((a.r, a.g, a.b), (b.r, b.g, b.b)));
+
+    //
+    // the PNG is the EXR's colors, gamma corrected, tone mapped and quantized
+    let display = exr_colors
+        .iter()
+        .map(|&c| display_color(c, Some(&curve)))
+        .collect::<Vec<_>>();
+    let png = image::open(&png_file).unwrap().into_rgb8();
+    assert_eq!(
+        png.into_raw(),
+        quantize_colors(&display, result.color_clamp)
+    );
+
+    //
+    // and the curve changed them
+    assert!(display
+        .iter()
+        .zip(exr_colors.iter())
+        .any(|(d, l)| (d.r - display_color(*l, None).r).abs() > 0.01));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
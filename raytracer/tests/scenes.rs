@@ -0,0 +1,161 @@
+use raytracer::{
+    hittable::Hittable,
+    render,
+    types::{Point, Ray, Vec3},
+    uniform_grid::Accelerator,
+    RaytracerConfig, RaytracerState, Scene,
+};
+
+//
+// the scenes load their assets relative to the repository root
+fn in_repository_root() {
+    std::env::set_current_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/.."))
+        .expect("Failed to change to the repository root");
+}
+
+#[test]
+fn test_mesh_scene_has_mesh_and_light() {
+    in_repository_root();
+    let (world, lights) = RaytracerState::build_scene(Scene::MeshTest, Accelerator::Bvh);
+
+    assert!(world.iter().any(|obj| obj.name() == Some("triangle mesh")));
+    assert!(lights.objects_len() > 0);
+
+    //
+    // looking up from above the teapot, at the light
+    let origin = Point::new(0.0, 30.0, 0.0);
+    let up = Vec3::new(0.0, 1.0, 0.0);
+    let r = Ray::new(origin, up, 0.0);
+    let hit = world.hit(&r, 0.001, f32::MAX).expect("Light not found");
+    let emitted = hit.mtl.emitted(&r, &hit, hit.u, hit.v, hit.p);
+    assert!(emitted.r > 0.0 && emitted.g > 0.0 && emitted.b > 0.0);
+
+    assert!(lights.pdf_value(origin, up) > 0.0);
+
+    //
+    // the teapot is lit
+    let config: RaytracerConfig = ron::de::from_str(
+        "(
+            active_scene: MeshTest,
+            default_params: (
+                workers: 1,
+                worker_block_pixels: 8,
+                aspect_ratio: 1.0,
+                image_width: 8,
+                samples_per_pixel: 4,
+                max_ray_depth: 4,
+                vertical_fov: 20.0,
+                look_from: (0.0, 25.0, 20.0),
+                look_at: (0.0, 0.0, 0.0),
+                world_up: (0.0, 1.0, 0.0),
+                aperture: 0.0,
+                focus_dist: 10.0,
+                shuffle_workblocks: false,
+                background: (0.0, 0.0, 0.0),
+                projection: Perspective,
+                psi_max: 90.0,
+                lambda_max: 180.0,
+            ),
+            defined_scenes: [(MeshTest, None)],
+        )",
+    )
+    .expect("Invalid test config");
+    let result = render(config, false).unwrap();
+    let lit = result
+        .pixels
+        .iter()
+        .filter(|c| c.r + c.g + c.b > 0.0)
+        .count();
+    assert!(lit > result.pixels.len() / 2);
+}
+
+#[test]
+fn test_scene_file_replaces_the_active_scene() {
+    in_repository_root();
+    let config: RaytracerConfig = ron::de::from_str(
+        "(
+            active_scene: TwoSpheres,
+            default_params: (
+                workers: 1,
+                worker_block_pixels: 8,
+                aspect_ratio: 1.0,
+                image_width: 16,
+                samples_per_pixel: 4,
+                max_ray_depth: 4,
+                vertical_fov: 90.0,
+                look_from: (13.0, 2.0, 3.0),
+                look_at: (0.0, 0.0, 0.0),
+                world_up: (0.0, 1.0, 0.0),
+                aperture: 0.0,
+                focus_dist: 10.0,
+                shuffle_workblocks: false,
+                background: (0.7, 0.8, 1.0),
+                projection: Perspective,
+                psi_max: 90.0,
+                lambda_max: 180.0,
+            ),
+            defined_scenes: [(TwoSpheres, None)],
+            scene_file: Some(\"data/scenes/cornell_box.ron\"),
+        )",
+    )
+    .expect("Invalid test config");
+
+    let (params, world, lights) = RaytracerState::build_active_scene(&config).unwrap();
+    assert_eq!(params.look_from, [278.0, 278.0, -800.0]);
+    assert_eq!(params.vertical_fov, 40.0);
+    assert_eq!(params.image_width, 16);
+    assert_eq!(lights.objects_len(), 2);
+
+    //
+    // straight up from the floor of the box, at the light
+    let r = Ray::new(Point::new(278.0, 1.0, 280.0), Vec3::new(0.0, 1.0, 0.0), 0.0);
+    let hit = world.hit(&r, 0.001, f32::MAX).expect("Light not found");
+    assert_eq!(hit.mtl.emitted(&r, &hit, hit.u, hit.v, hit.p).r, 15.0);
+
+    //
+    // the camera of the file looks into the box, the background of the file is black
+    let result = render(config, false).unwrap();
+    assert!(!result
+        .pixels
+        .iter()
+        .any(|c| c.r == 0.7 && c.g == 0.8 && c.b == 1.0));
+    assert!(result.pixels.iter().any(|c| c.r + c.g + c.b > 0.0));
+}
+
+#[test]
+fn test_bad_scene_file_is_reported() {
+    in_repository_root();
+    let config: RaytracerConfig = ron::de::from_str(
+        "(
+            active_scene: TwoSpheres,
+            default_params: (
+                workers: 1,
+                worker_block_pixels: 8,
+                aspect_ratio: 1.0,
+                image_width: 16,
+                samples_per_pixel: 4,
+                max_ray_depth: 4,
+                vertical_fov: 90.0,
+                look_from: (13.0, 2.0, 3.0),
+                look_at: (0.0, 0.0, 0.0),
+                world_up: (0.0, 1.0, 0.0),
+                aperture: 0.0,
+                focus_dist: 10.0,
+                shuffle_workblocks: false,
+                background: (0.7, 0.8, 1.0),
+                projection: Perspective,
+                psi_max: 90.0,
+                lambda_max: 180.0,
+            ),
+            defined_scenes: [(TwoSpheres, None)],
+            scene_file: Some(\"data/scenes/no_such_scene.ron\"),
+        )",
+    )
+    .expect("Invalid test config");
+
+    let err = RaytracerState::build_active_scene(&config)
+        .err()
+        .expect("A missing scene file must be an error");
+    assert!(err.contains("no_such_scene.ron"), "{}", err);
+    assert!(render(config, false).is_err());
+}
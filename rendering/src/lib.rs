@@ -1,9 +1,10 @@
 mod renderer_gl;
 
 pub use self::renderer_gl::{
-    create_shader_program_from_string, gl, BufferAccess, OpenGLStateSnapshot, PipelineBuilder,
-    SamplerBuilder, ShaderProgramBuilder, ShaderType, UniqueBuffer, UniqueBufferMapping,
-    UniquePipeline, UniqueSampler, UniqueShaderProgram, UniqueTexture, UniqueVertexArray,
+    create_shader_program_from_string, format_shader_error, gl, BufferAccess, OpenGLStateSnapshot,
+    PipelineBuilder, SamplerBuilder, ShaderProgramBuilder, ShaderType, UniqueBuffer,
+    UniqueBufferMapping, UniquePipeline, UniqueSampler, UniqueShaderProgram, UniqueTexture,
+    UniqueVertexArray,
 };
 
 // pub use self::renderer_gl::gl;
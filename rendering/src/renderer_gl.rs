@@ -170,6 +170,26 @@ pub enum ShaderType {
     Fragment,
 }
 
+/// Builds the error message for a shader that failed to compile or link, from the
+/// (possibly NUL terminated) GL info log.
+pub fn format_shader_error(prog_type: ShaderType, info_log: &[u8]) -> String {
+    let log_len = info_log
+        .iter()
+        .position(|&c| c == 0)
+        .unwrap_or(info_log.len());
+    let log = String::from_utf8_lossy(&info_log[..log_len]);
+    let log = log.trim();
+
+    if log.is_empty() {
+        format!(
+            "{:?} shader failed to compile, no compile log is available",
+            prog_type
+        )
+    } else {
+        format!("{:?} shader failed to compile:\n{}", prog_type, log)
+    }
+}
+
 pub fn create_shader_program_from_string(
     s: &str,
     prog_type: ShaderType,
@@ -178,14 +198,14 @@ pub fn create_shader_program_from_string(
         .map_err(|_| String::from("failed to convert source code to C-string"))?;
 
     let x = [src_code.as_ptr()];
-    let prog_type = match prog_type {
+    let gl_prog_type = match prog_type {
         ShaderType::Vertex => gl::VERTEX_SHADER,
         ShaderType::Fragment => gl::FRAGMENT_SHADER,
         ShaderType::Geometry => gl::GEOMETRY_SHADER,
     };
 
     let prg =
-        UniqueShaderProgram::new(unsafe { gl::CreateShaderProgramv(prog_type, 1, x.as_ptr()) })
+        UniqueShaderProgram::new(unsafe { gl::CreateShaderProgramv(gl_prog_type, 1, x.as_ptr()) })
             .ok_or_else(|| "glCreateShaderProgramv() failed".to_string())?;
 
     let linked_successfully = (|| {
@@ -200,7 +220,12 @@ pub fn create_shader_program_from_string(
         return Ok(prg);
     }
 
-    let mut info_log_buff: Vec<u8> = vec![0; 1024];
+    let mut info_log_len = 0i32;
+    unsafe {
+        gl::GetProgramiv(*prg, gl::INFO_LOG_LENGTH, &mut info_log_len);
+    }
+
+    let mut info_log_buff: Vec<u8> = vec![0; info_log_len.max(1) as usize];
     let mut info_log_size = 0i32;
     unsafe {
         gl::GetProgramInfoLog(
@@ -211,13 +236,8 @@ pub fn create_shader_program_from_string(
         );
     }
 
-    if info_log_size > 0 {
-        info_log_buff[info_log_size as usize] = 0;
-        return Err(String::from_utf8(info_log_buff)
-            .unwrap_or("Failed to display compiler error".to_string()));
-    }
-
-    Err("Error but no compile log is available".to_string())
+    info_log_buff.truncate(info_log_size.max(0) as usize);
+    Err(format_shader_error(prog_type, &info_log_buff))
 }
 
 /// Stores a snapshot of the OpenGL state machine at some point in time.
@@ -541,3 +561,25 @@ impl<'a> ShaderProgramBuilder<'a> {
         Err("Error but no compile log is available".to_string())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shader_error_contains_log() {
+        let log = b"0:12(3): error: `colour' undeclared\0\0\0";
+        let err = format_shader_error(ShaderType::Fragment, log);
+
+        assert!(err.contains("Fragment"));
+        assert!(err.contains("0:12(3): error: `colour' undeclared"));
+        assert!(!err.contains('\0'));
+    }
+
+    #[test]
+    fn test_shader_error_without_log() {
+        let err = format_shader_error(ShaderType::Vertex, &[]);
+        assert!(err.contains("Vertex"));
+        assert!(err.contains("no compile log"));
+    }
+}